@@ -0,0 +1,230 @@
+//! On-disk cache of search results.
+//!
+//! Results are stored as JSON, one file per (package manager, query) pair,
+//! under the user's cache directory. An entry is valid for the configured
+//! time-to-live, measured from the modification time of its file.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+
+use crate::{Package, PackageManager};
+
+/// Default time-to-live of cached search results.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Cache of search results keyed by package manager and query.
+#[derive(Debug, Clone)]
+pub struct SearchCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl SearchCache {
+    /// Create a cache that stores its entries in `dir`.
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// Create a cache in the default cache directory of this user e.g.
+    /// `~/.cache/mpm/search` on Linux.
+    pub fn try_default(ttl: Duration) -> anyhow::Result<Self> {
+        Ok(Self::new(Self::default_dir()?, ttl))
+    }
+
+    /// Default directory of the search cache.
+    pub fn default_dir() -> anyhow::Result<PathBuf> {
+        let dirs = directories::ProjectDirs::from("", "", "mpm")
+            .context("failed to determine the cache directory")?;
+        Ok(dirs.cache_dir().join("search"))
+    }
+
+    /// Directory the entries are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of the cache entry of `query` for package manager `manager`.
+    ///
+    /// The query is hex encoded so that any query maps to a valid file name.
+    pub fn entry_path(&self, manager: &str, query: &str) -> PathBuf {
+        let key: String = query.bytes().map(|b| format!("{b:02x}")).collect();
+        self.dir.join(format!("{manager}-{key}.json"))
+    }
+
+    /// Cached results of `query` if present and not expired.
+    pub fn get(&self, manager: &str, query: &str) -> Option<Vec<Package>> {
+        let path = self.entry_path(manager, query);
+        let age = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())?;
+        if age > self.ttl {
+            tracing::debug!("Cache entry {path:?} expired");
+            return None;
+        }
+        let content = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(pkgs) => Some(pkgs),
+            Err(e) => {
+                tracing::warn!("Ignoring corrupt cache entry {path:?}: {e}");
+                None
+            }
+        }
+    }
+
+    /// Store the results of `query`.
+    pub fn put(&self, manager: &str, query: &str, pkgs: &[Package]) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create cache directory {:?}", self.dir))?;
+        let path = self.entry_path(manager, query);
+        std::fs::write(&path, serde_json::to_string(pkgs)?)
+            .with_context(|| format!("failed to write cache entry {path:?}"))?;
+        Ok(())
+    }
+
+    /// Search using the package manager, answering from the cache when
+    /// possible. Failed searches are not cached, see
+    /// [``PackageManager::try_search``].
+    pub fn search<P: PackageManager>(&self, mpm: &P, query: &str) -> anyhow::Result<Vec<Package>> {
+        let manager = mpm.pkg_manager_name();
+        if let Some(pkgs) = self.get(&manager, query) {
+            tracing::debug!("Using cached search results for {query:?}");
+            return Ok(pkgs);
+        }
+        let pkgs = mpm.try_search(query)?;
+        if let Err(e) = self.put(&manager, query, &pkgs) {
+            tracing::warn!("Failed to cache search results: {e}");
+        }
+        Ok(pkgs)
+    }
+
+    /// Remove all cached entries.
+    pub fn clear(&self) -> anyhow::Result<()> {
+        if self.dir.exists() {
+            std::fs::remove_dir_all(&self.dir)
+                .with_context(|| format!("failed to remove {:?}", self.dir))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        fmt::Display,
+        process::{Command, Output},
+    };
+
+    use super::*;
+    use crate::{common::synthetic_exit_status, Cmd, PackageManagerCommands, PkgFormat};
+
+    /// Package manager that counts how often it runs a command.
+    #[derive(Debug, Default)]
+    struct CountingManager {
+        calls: Cell<usize>,
+        /// Whether its commands fail, e.g. because the database is locked.
+        failing: bool,
+    }
+
+    impl Display for CountingManager {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("counting")
+        }
+    }
+
+    impl PackageManagerCommands for CountingManager {
+        fn cmd(&self) -> Command {
+            Command::new("")
+        }
+        fn get_cmds(&self, _: Cmd, _: Option<&Package>) -> Vec<String> {
+            vec![]
+        }
+        fn exec_cmds(&self, _: &[String]) -> Output {
+            self.calls.set(self.calls.get() + 1);
+            if self.failing {
+                return Output {
+                    status: synthetic_exit_status(false),
+                    stdout: vec![],
+                    stderr: b"E: Could not get lock /var/lib/dpkg/lock-frontend\n".to_vec(),
+                };
+            }
+            Output {
+                status: synthetic_exit_status(true),
+                stdout: b"foo+1.0\n".to_vec(),
+                stderr: vec![],
+            }
+        }
+    }
+
+    impl PackageManager for CountingManager {
+        fn pkg_delimiter(&self) -> char {
+            '+'
+        }
+        fn pkg_manager_name(&self) -> String {
+            "counting".to_string()
+        }
+        fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn test_search_within_ttl_uses_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = SearchCache::new(dir.path(), DEFAULT_TTL);
+        let mpm = CountingManager::default();
+
+        let first = cache.search(&mpm, "foo").unwrap();
+        assert_eq!(mpm.calls.get(), 1);
+        assert!(cache.entry_path("counting", "foo").is_file());
+
+        let second = cache.search(&mpm, "foo").unwrap();
+        assert_eq!(mpm.calls.get(), 1, "second search must not spawn");
+        assert_eq!(first, second);
+
+        // a different query is a different entry
+        cache.search(&mpm, "bar").unwrap();
+        assert_eq!(mpm.calls.get(), 2);
+
+        cache.clear().unwrap();
+        cache.search(&mpm, "foo").unwrap();
+        assert_eq!(mpm.calls.get(), 3);
+    }
+
+    #[test]
+    fn test_failed_search_is_not_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = SearchCache::new(dir.path(), DEFAULT_TTL);
+        let mpm = CountingManager {
+            failing: true,
+            ..Default::default()
+        };
+
+        let err = cache.search(&mpm, "foo").unwrap_err();
+        assert!(err.to_string().contains("Could not get lock"), "{err}");
+        assert!(!cache.entry_path("counting", "foo").exists());
+
+        // the next search runs again rather than finding nothing
+        assert!(cache.search(&mpm, "foo").is_err());
+        assert_eq!(mpm.calls.get(), 2);
+    }
+
+    #[test]
+    fn test_expired_entry_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = SearchCache::new(dir.path(), Duration::ZERO);
+        cache
+            .put("counting", "foo", &[Package::from("foo")])
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(cache.get("counting", "foo"), None);
+    }
+}
@@ -0,0 +1,3771 @@
+mod render;
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::Context;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use strum::IntoEnumIterator;
+
+pub use self::render::OutputFormat;
+use self::render::{print_outcome, print_plan};
+use crate::{
+    cache::SearchCache,
+    config::{alias_package, resolve_manager_for, Config},
+    error::Error,
+    history::{History, HistoryEntry, RollbackReport},
+    print::{Column, SortKey},
+    snapshot::{RestoreReport, Snapshots},
+    AvailablePackageManager, Cmd, DependencyGraph, ManagerPriority, MetaPackageManager, Package,
+    PackageKind, PackageManager, PackageManagerCommands, PackageMetadata, Repository, Unsupported,
+};
+
+#[derive(Parser)]
+#[command(
+    author,
+    version,
+    about = "A meta package manager.",
+    long_about = "A meta package manager for interfacing with multiple distro and platform specific package managers."
+)]
+
+/// Cli for PackageManager.
+///
+/// It is public because other tools can use this interface to pass the command
+/// line args.
+pub struct Cli {
+    #[command(subcommand)]
+    command: MpmPackageManagerCommands,
+
+    /// Optionally specify a package manager that you want to use. If not given,
+    /// mpm will search for default package manager on this system, in the
+    /// order of MPM_MANAGER_PRIORITY (e.g. `brew,apt,-flatpak`) or the
+    /// `priority` of the config.
+    #[arg(long, short)]
+    manager: Option<crate::common::AvailablePackageManager>,
+
+    // Set interactive mode: the package manager asks its own questions
+    // instead of mpm answering them with yes.
+    #[arg(long, short, default_value_t = false)]
+    interactive: bool,
+
+    /// Show the commands and ask before changing the installed packages.
+    #[arg(long, default_value_t = false, conflicts_with = "yes")]
+    confirm: bool,
+
+    /// Do not ask for confirmation, even if the configuration enables
+    /// `confirm`.
+    #[arg(long, short, default_value_t = false)]
+    yes: bool,
+
+    /// Format of the output: tables and plain text, or JSON, YAML or CSV for
+    /// scripts.
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t,
+        value_name = "FORMAT"
+    )]
+    output: OutputFormat,
+
+    /// Set output to be in json format, same as `--output json`.
+    #[arg(
+        long,
+        global = true,
+        default_value_t = false,
+        conflicts_with = "output"
+    )]
+    json: bool,
+
+    /// Comma separated columns to show in package tables and JSON, e.g.
+    /// `name,version,manager`.
+    #[arg(long, global = true, value_enum, value_delimiter = ',')]
+    columns: Option<Vec<Column>>,
+
+    /// Print without colors, also when stdout is a terminal. Colors are off
+    /// anyway when it is not, or when NO_COLOR is set.
+    #[arg(long, global = true, default_value_t = false)]
+    no_color: bool,
+
+    /// Sort package tables and lists by this field, then by name.
+    #[arg(long, global = true, value_enum, value_name = "FIELD")]
+    sort: Option<SortKey>,
+
+    /// Path to the configuration file. Defaults to `mpm.toml` in the user's
+    /// config directory.
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Connect to mirrors over IPv4 only. Works around hanging connections on
+    /// networks with broken IPv6 (apt, dnf, yum and zypper).
+    #[arg(long = "ipv4", default_value_t = false)]
+    force_ipv4: bool,
+
+    /// Allow downloading packages over unencrypted `http` and `ftp` URLs.
+    /// Only `https` and `file` URLs are allowed otherwise.
+    #[arg(long, default_value_t = false)]
+    allow_insecure_url: bool,
+
+    /// Only show what install, uninstall, update, sync, clean, autoremove and
+    /// `snapshot restore` would do: the package manager simulates the changes
+    /// where it can (apt, dnf, yum, zypper), otherwise the commands are
+    /// printed instead of run. `clean` reports how much space would be freed.
+    #[arg(long, global = true, default_value_t = false)]
+    dry_run: bool,
+
+    /// Operate on the named environment of package managers that manage
+    /// environments, i.e. conda, also for packages routed to them. Fails
+    /// for a selected package manager without environments.
+    #[arg(long, value_name = "NAME")]
+    env: Option<String>,
+
+    /// Also write a structured log of the operations to this file.
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// How often to rotate the log file.
+    #[arg(long, value_enum, default_value_t, requires = "log_file")]
+    log_rotation: crate::logging::LogRotation,
+}
+
+impl Cli {
+    /// File to write the operation log to, if any.
+    pub fn log_file(&self) -> Option<&std::path::Path> {
+        self.log_file.as_deref()
+    }
+
+    /// How often to rotate the log file.
+    pub fn log_rotation(&self) -> crate::logging::LogRotation {
+        self.log_rotation
+    }
+
+    /// Whether colors were turned off with `--no-color`.
+    pub fn no_color(&self) -> bool {
+        self.no_color
+    }
+
+    /// Format to print the outcome in, JSON with `--json`.
+    pub fn output_format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else {
+            self.output
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MpmPackageManagerCommands {
+    #[command(about = "List supported package managers and display their availability")]
+    Managers {
+        /// Install default package manager if not found e.g. choco on Windows
+        /// and homebrew on osx.
+        #[arg(long, default_value_t = false)]
+        install_default: bool,
+    },
+
+    #[command(about = "Search for a given sub-string and list matching packages")]
+    Search {
+        string: String,
+
+        /// Reuse results of an identical recent search from the on-disk cache.
+        #[arg(long, default_value_t = false)]
+        cache: bool,
+
+        /// How long cached search results stay valid, in seconds.
+        #[arg(long, default_value_t = crate::cache::DEFAULT_TTL.as_secs(), requires = "cache")]
+        cache_ttl: u64,
+
+        /// Mark the results that are already installed.
+        #[arg(long, default_value_t = false)]
+        show_installed: bool,
+
+        /// Only show the packages named exactly like the query, ignoring
+        /// case.
+        #[arg(long, default_value_t = false)]
+        exact: bool,
+
+        /// Only show the packages that are already installed.
+        #[arg(long, default_value_t = false)]
+        installed: bool,
+
+        /// Stop searching once this many packages were found, with `--all`
+        /// per package manager.
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Search with all available package managers at once, e.g. to find
+        /// out whether a tool is packaged by brew or flatpak.
+        #[arg(long, default_value_t = false, conflicts_with = "cache")]
+        all: bool,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+
+        /// Pick packages to install from the results. Needs a terminal.
+        #[cfg(feature = "cli")]
+        #[arg(long, default_value_t = false, conflicts_with = "all")]
+        pick: bool,
+    },
+
+    #[command(about = "List all packages that are installed")]
+    List {
+        #[arg(long, short)]
+        all: bool,
+
+        /// Print the packages grouped by package manager in this format.
+        /// Deprecated in favor of `mpm export`, whose lockfile has a
+        /// documented schema and can be installed again. `-o` is accepted
+        /// as it was before `--output` became global.
+        #[arg(short, long, short_alias = 'o', value_enum)]
+        file_format: Option<FileFormat>,
+
+        /// Print a separate table per package manager, e.g. with `--all`.
+        #[arg(long, value_enum, conflicts_with = "file_format")]
+        group_by: Option<GroupBy>,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+
+    #[command(
+        about = "Install the given package(s)",
+        long_about = "Install the given package(s).\nIf a specific version of the package is desired, it can be specified using the format <package_name>@<version>.\nNote: version information is optional."
+    )]
+    Install {
+        /// Packages to install, optionally as `<manager>:<name>` (e.g.
+        /// `brew:wget`) to install them with another package manager.
+        #[arg(required_unless_present_any = ["input_file", "locked"])]
+        packages: Vec<String>,
+
+        #[arg(short, long, required_unless_present_any = ["packages", "locked"])]
+        input_file: Option<PathBuf>,
+
+        /// Install exactly the versions recorded in a lockfile, `mpm.lock`
+        /// unless another one is given as `--locked=<PATH>`, see `mpm lock`.
+        /// Fails if a version is unavailable.
+        #[arg(
+            long,
+            value_name = "PATH",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = crate::lockfile::DEFAULT_PATH,
+            conflicts_with_all = ["packages", "input_file"]
+        )]
+        locked: Option<PathBuf>,
+
+        /// Verify the signature of package files and URLs before installing
+        /// them (e.g. `rpm --checksig`).
+        #[arg(long, default_value_t = false)]
+        verify_signature: bool,
+
+        /// Expected SHA-256 checksum of the package file or URL, like a
+        /// `#sha256=<checksum>` URL fragment. Downloads that don't match are
+        /// not installed.
+        #[arg(long, value_name = "SHA256", conflicts_with_all = ["input_file", "locked"])]
+        checksum: Option<String>,
+
+        /// URL of a detached GPG signature of the package file or URL, like a
+        /// `#signature=<url>` URL fragment. It must be made by a key that is
+        /// fully trusted in the keyring of root, or of the user for package
+        /// managers that run without sudo (e.g. brew).
+        #[arg(long, value_name = "URL", conflicts_with_all = ["input_file", "locked"])]
+        signature: Option<url::Url>,
+
+        /// Install either all of the packages or none of them. Package
+        /// managers with transactions (e.g. dnf, zypper) install them in a
+        /// single transaction, others remove the already installed ones when
+        /// one fails.
+        #[arg(long, default_value_t = false)]
+        transactional: bool,
+
+        /// Skip packages whose requested version is already installed and
+        /// intact, and reinstall those whose files are missing or corrupt.
+        #[arg(long, default_value_t = false, conflicts_with = "transactional")]
+        if_missing: bool,
+
+        /// Resume an interrupted install from an input file: skip the
+        /// packages that are already installed and try all remaining ones
+        /// even if some fail.
+        #[arg(long, default_value_t = false, requires = "input_file")]
+        resume: bool,
+
+        /// Package parameters, e.g. "/InstallDir:C:\foo" (choco only).
+        #[arg(long, allow_hyphen_values = true)]
+        params: Option<String>,
+
+        /// Arguments for the native installer (choco only).
+        #[arg(long, allow_hyphen_values = true)]
+        install_arguments: Option<String>,
+
+        /// Ask before installing a package that downloads or occupies more
+        /// than this many megabytes (apt, dnf, yum). Without `--interactive`
+        /// such packages are refused.
+        #[arg(long, value_name = "MB")]
+        max_size: Option<u64>,
+
+        /// Show the packages that would be installed, upgraded and removed,
+        /// including dependencies, before installing (apt, dnf, yum). With
+        /// `--interactive` the install has to be confirmed.
+        #[arg(long, default_value_t = false)]
+        plan: bool,
+
+        /// Print a standalone shell script with the native commands that
+        /// install the packages instead of installing them.
+        #[arg(long, default_value_t = false, conflicts_with_all = ["input_file", "locked"])]
+        emit_script: bool,
+
+        /// Install patterns, patches or products (zypper), or apps or runtimes
+        /// (flatpak) instead of packages.
+        #[arg(long, value_enum, conflicts_with_all = ["input_file", "locked"])]
+        kind: Option<PackageKind>,
+
+        /// Install from this remote, e.g. flathub (flatpak only).
+        #[arg(long)]
+        remote: Option<String>,
+    },
+
+    #[command(
+        about = "Uninstall the given package(s)",
+        long_about = "Uninstall the given package(s).\nIf a specific version of the package is desired, it can be specified using the format <package_name>@<version>.\nNote: version information is optional."
+    )]
+    Uninstall {
+        /// Packages to uninstall, optionally as `<manager>:<name>` (e.g.
+        /// `brew:wget`) to uninstall them with another package manager.
+        #[clap(required = true)]
+        packages: Vec<String>,
+
+        /// Uninstall even if a package is protected by the package manager
+        /// (e.g. essential packages).
+        #[arg(long, default_value_t = false)]
+        force: bool,
+
+        /// Uninstall patterns or products (zypper), or apps or runtimes
+        /// (flatpak) instead of packages.
+        #[arg(long, value_enum)]
+        kind: Option<PackageKind>,
+    },
+
+    #[command(about = "Add, list or remove the third-party repositories of the package manager")]
+    Repo {
+        #[command(subcommand)]
+        command: RepoCommands,
+    },
+
+    #[command(
+        about = "Updates the cached package repository data",
+        long_about = "Sync the cached package repository data.\nNote: this behavior might not be consistent among package managers; when sync is not supported, the package manager might simply update itself."
+    )]
+    Sync {
+        /// Sync with all available package managers at once and print
+        /// whether each of them succeeded.
+        #[arg(long, default_value_t = false)]
+        all: bool,
+    },
+
+    #[command(about = "Update/upgrade the given package(s) or (--)all of them")]
+    #[group(required = true)]
+    Update {
+        packages: Vec<String>,
+        #[arg(long, short)]
+        all: bool,
+
+        /// With --all, show the number of upgraded packages instead of the
+        /// package manager's output (apt only).
+        #[arg(long, requires = "all")]
+        progress: bool,
+
+        /// With --all, only download the updates to install them later with
+        /// --apply-downloaded (apt, dnf, yum).
+        #[arg(long, requires = "all", conflicts_with_all = ["progress", "apply_downloaded"])]
+        download_only: bool,
+
+        /// With --all, install the updates downloaded with --download-only
+        /// without downloading anything.
+        #[arg(long, requires = "all", conflicts_with = "progress")]
+        apply_downloaded: bool,
+
+        /// Update patterns, patches or products (zypper), or apps or runtimes
+        /// (flatpak) instead of packages.
+        #[arg(long, value_enum, requires = "packages")]
+        kind: Option<PackageKind>,
+    },
+
+    #[command(about = "List all of the packages that can be updated")]
+    Outdated {
+        #[arg(long, short)]
+        all: bool,
+    },
+
+    #[command(
+        about = "List the patches needed by the installed packages (zypper)",
+        long_about = "List the patches needed by the installed packages, or with --all every patch of the repositories (zypper).\nNeeded patches are installed with `mpm install --kind patch <patch>` or `mpm update --all`."
+    )]
+    Patches {
+        /// List the patches that are not needed as well.
+        #[arg(long, short)]
+        all: bool,
+    },
+
+    #[command(
+        about = "List packages that are installed by more than one package manager",
+        long_about = "Cross-reference the installed packages of all available package managers and list packages installed by more than one of them.\nUpdating such a package through one package manager leaves the other copy stale."
+    )]
+    Conflicts,
+
+    #[command(
+        about = "List packages available in the repositories",
+        long_about = "List packages available in the repositories, optionally only those whose name starts with the given prefix (apt, brew, dnf, yum)."
+    )]
+    ListAvailable { prefix: Option<String> },
+
+    #[command(
+        about = "Show the dependency tree of a package",
+        long_about = "Show the dependencies of a package as an indented tree, or as an adjacency list with --json (apt, brew, dnf, yum).\ndnf and yum only report the direct dependencies."
+    )]
+    Deps { package: String },
+
+    #[command(
+        about = "Show the metadata of a package",
+        long_about = "Show the description, homepage, license, installed size and the installed and candidate versions of a package (apk, apt, brew, choco, dnf, yum)."
+    )]
+    Info { package: String },
+
+    #[command(
+        about = "List the packages that depend on the given package",
+        long_about = "List the packages that depend on the given package, e.g. before removing a library (apt, brew, dnf, yum)."
+    )]
+    Rdeps { package: String },
+
+    #[command(
+        about = "Show the package that owns a file",
+        long_about = "Show the package that installed the given file, e.g. `mpm owns /usr/bin/wget` (apt, brew, dnf, yum, zypper).\nFails if no package owns the file."
+    )]
+    Owns { path: PathBuf },
+
+    #[command(
+        about = "Remove downloaded packages and cached metadata",
+        long_about = "Remove the packages and metadata the package manager downloaded, e.g. `apt clean` or `dnf clean all` (apt, brew, choco, dnf, yum, zypper).\nWith --dry-run only report how much space would be freed."
+    )]
+    Clean,
+
+    #[command(
+        about = "Remove dependencies that are no longer needed",
+        long_about = "Remove packages that were installed as dependencies and are no longer needed by any installed package (apt, brew, dnf, flatpak, yum, zypper).\nzypper removes the packages listed by `zypper packages --unneeded`, flatpak removes unused runtimes."
+    )]
+    Autoremove,
+
+    #[command(
+        about = "Hold a package at its installed version",
+        long_about = "Hold a package at its installed version, so that neither updating it nor `update --all` changes it until it is unpinned (apt, brew, choco, dnf, yum).\nWithout a package, list the pinned packages. dnf and yum use the versionlock plugin."
+    )]
+    Pin { package: Option<String> },
+
+    #[command(about = "Release a package held with pin")]
+    Unpin { package: String },
+
+    #[command(
+        about = "Show the operations mpm ran",
+        long_about = "Show the install, uninstall, update, autoremove, pin and unpin operations mpm ran, with their packages and exit code.\nWith --native, merge in the history of the package manager itself (dnf, zypper)."
+    )]
+    History {
+        /// Include the package manager's own transaction history
+        #[arg(long)]
+        native: bool,
+    },
+
+    #[command(
+        about = "Revert an operation from the history",
+        long_about = "Revert the operation with the given id from `mpm history`, by default the last one of the package manager: installed packages are uninstalled, uninstalled ones installed again and pins are undone.\nUpdates and autoremove are reverted by undoing the dnf or yum transaction recorded with them. What could not be reverted is reported."
+    )]
+    Rollback { id: Option<usize> },
+
+    #[command(
+        about = "List the files installed by a package",
+        long_about = "List the files installed by a package, one per line or as a JSON array with --json (apt, brew, dnf, yum, zypper)."
+    )]
+    Files { package: String },
+
+    #[command(
+        about = "Recover a broken package database",
+        long_about = "Recover a broken package database, e.g. after an interrupted operation left packages half-configured.\nRuns `dpkg --configure -a` and `apt-get install -f` for apt, and `check` for dnf and yum."
+    )]
+    Fix,
+
+    #[command(
+        about = "Check installed packages for modified or missing files",
+        long_about = "Check an installed package, or all of them with --all, for modified or missing files and report them grouped by package.\nRuns `dpkg --verify` for a package and `debsums -s` for all packages with apt, and `rpm -V` and `rpm -Va` with dnf, yum and zypper, which may take a while. brew only reports missing files. Fails if any file is modified or missing."
+    )]
+    Verify {
+        /// Package to verify.
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        package: Option<String>,
+
+        /// Verify all installed packages.
+        #[arg(long, default_value_t = false)]
+        all: bool,
+    },
+
+    #[command(about = "Manage mpm's on-disk cache")]
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+
+    #[command(
+        about = "Record the exact versions of the installed packages in a lockfile",
+        long_about = "Record the exact versions of the packages installed by the package manager in a lockfile, which `mpm install --locked` installs again."
+    )]
+    Lock {
+        /// Path of the lockfile. `--output` is the global output format.
+        #[arg(short = 'o', long, default_value = crate::lockfile::DEFAULT_PATH)]
+        lockfile: PathBuf,
+    },
+
+    #[command(
+        about = "Export the installed packages of all package managers to a lockfile",
+        long_about = "Export the exact versions of the packages installed by all available package managers, grouped by package manager, together with the schema version and the platform (OS, architecture, mpm version) to a lockfile.\nThe lockfile is TOML, or JSON if the path ends in `.json`, and can be installed again with `mpm install --locked`. See the `lockfile` module documentation for the schema."
+    )]
+    Export {
+        /// Path of the lockfile. `--output` is the global output format.
+        #[arg(short = 'o', long, default_value = crate::lockfile::DEFAULT_PATH)]
+        lockfile: PathBuf,
+    },
+
+    #[command(
+        about = "Compare the installed packages with a lockfile",
+        long_about = "Compare the installed packages with a lockfile written by `mpm lock` or `mpm export` and report missing, extra and version-mismatched packages. Only the package managers in the lockfile are checked.\nExits with a non-zero code if the installed packages differ, e.g. to catch drift of a golden image in CI."
+    )]
+    Check {
+        /// Path of the lockfile.
+        #[arg(default_value = crate::lockfile::DEFAULT_PATH)]
+        lockfile: PathBuf,
+    },
+
+    #[command(
+        about = "Save or restore the installed packages of all package managers",
+        long_about = "Save the installed packages of all available package managers with their versions as a named snapshot, and later restore it: missing packages and other versions are installed, packages that are not in the snapshot are uninstalled.\nRestoring only touches the package managers in the snapshot. Use --dry-run to see the changes first."
+    )]
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
+
+    #[command(
+        about = "Print the native command(s) a command would run without running it",
+        long_about = "Print the native command(s) a command would run without running it, with a short explanation of their flags, e.g. `mpm explain install firefox`.\nSupports install, uninstall, update, list, search, sync, outdated and clean."
+    )]
+    Explain {
+        #[command(subcommand)]
+        command: Box<MpmPackageManagerCommands>,
+    },
+
+    #[command(
+        about = "Read or set configuration options of the package manager (apt, dnf, yum)",
+        long_about = "Read or set configuration options of the package manager, e.g. `mpm config set max_parallel_downloads 10`.\ndnf and yum options are read from and written to the [main] section of dnf.conf and yum.conf; apt options are read with `apt-config` and written to /etc/apt/apt.conf.d/99mpm."
+    )]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
+    #[command(about = "List or switch the mirrors packages are downloaded from (apt, dnf, yum)")]
+    Mirrors {
+        #[command(subcommand)]
+        command: MirrorCommands,
+    },
+
+    #[command(
+        about = "Write man pages for mpm and all of its subcommands",
+        long_about = "Write man pages generated from the command line definitions for mpm and all of its subcommands, e.g. `mpm.1` and `mpm-install.1`, to a directory. Works without a package manager."
+    )]
+    Manpages {
+        /// Directory to write the man pages to, created if missing.
+        #[arg(long, value_name = "DIR", default_value = ".")]
+        out_dir: PathBuf,
+    },
+
+    #[command(
+        about = "Run the package manager with the given arguments",
+        long_about = "Run the package manager with the given arguments as they are, e.g. `mpm run --manager apt -- show curl`, to use features mpm does not cover. The package manager runs in the terminal, elevated with sudo if it needs to be."
+    )]
+    Run {
+        /// Arguments passed to the package manager.
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
+    #[command(
+        about = "Diagnose problems of the package managers and the system",
+        long_about = "Check the version of every available package manager, or only the one given with --manager, whether another process holds its lock, whether its repositories are reachable and whether the directories it installs programs into are on PATH, and whether mpm can elevate with sudo. Fails if any check found an error."
+    )]
+    Doctor,
+
+    #[command(
+        about = "Update mpm to its latest release",
+        long_about = "Download the latest release of mpm for this platform from GitHub, verify its SHA-256 checksum, and the signature of the checksums if the release signs them, and replace the running executable with it. Refuses to replace an mpm installed by a package manager. Set GITHUB_TOKEN to avoid the rate limits of the GitHub API."
+    )]
+    SelfUpdate {
+        /// Only check whether a newer release is available, as with
+        /// `--dry-run`.
+        #[arg(long, default_value_t = false)]
+        check: bool,
+    },
+
+    #[command(
+        about = "Run an mpm command on a schedule",
+        long_about = "Run an mpm command on a schedule, e.g. `mpm schedule --daily \"update --all\"`, as a systemd service and timer on Linux, a launchd daemon on macOS or a Task Scheduler task on Windows. The job runs as root and answers yes to all questions."
+    )]
+    Schedule {
+        /// mpm command to run with its arguments, as one or several words.
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+
+        /// Run at the start of every hour.
+        #[arg(long, default_value_t = false, group = "interval")]
+        hourly: bool,
+
+        /// Run every day at 03:00 (default).
+        #[arg(long, default_value_t = false, group = "interval")]
+        daily: bool,
+
+        /// Run every Monday at 03:00.
+        #[arg(long, default_value_t = false, group = "interval")]
+        weekly: bool,
+
+        /// Name of the job, by default `mpm-<command>`, e.g. `mpm-update`.
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Print the job's files, or the command creating the task on
+        /// Windows, instead of installing it.
+        #[arg(long, default_value_t = false)]
+        emit: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommands {
+    #[command(about = "Remove all cached search results")]
+    Clear,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SnapshotCommands {
+    #[command(
+        about = "Save the installed packages under a name, replacing a snapshot of the same name"
+    )]
+    Save { name: String },
+
+    #[command(about = "Install and uninstall packages to match a snapshot")]
+    Restore { name: String },
+
+    #[command(about = "List the saved snapshots")]
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+    #[command(about = "Print the value of an option")]
+    Get { key: String },
+
+    #[command(about = "Set an option")]
+    Set { key: String, value: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RepoCommands {
+    #[command(
+        about = "Add the provided third-party repo location to the package manager",
+        long_about = "Provide a repo in the form of a URL or package manager specific repo format to add it to the list of repositories of the package manager"
+    )]
+    Add { repo: Vec<String> },
+
+    #[command(
+        about = "List the repositories of the package manager (apt, dnf, yum, zypper, brew, flatpak, choco)",
+        long_about = "List the repositories of the package manager, including disabled ones: the sources of apt, `dnf repolist`, `zypper lr`, the taps of brew, the remotes of flatpak and the sources of choco. apt repositories are named by their URI."
+    )]
+    List,
+
+    #[command(
+        about = "Remove a repository by the name `mpm repo list` shows",
+        long_about = "Remove a repository by the name `mpm repo list` shows. apt sources and dnf and yum repositories are removed from their files, which are deleted once they define no other repository."
+    )]
+    Remove { name: String },
+
+    /// `mpm repo <repo>` from before `repo add`, kept working but hidden.
+    #[command(external_subcommand)]
+    AddPositional(Vec<String>),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MirrorCommands {
+    #[command(about = "List the configured mirrors")]
+    List,
+
+    #[command(
+        about = "Switch the primary mirror",
+        long_about = "Switch the primary mirror, i.e. the host most repositories are downloaded from, to the scheme, host and port of the given URL. The paths of the repositories are kept."
+    )]
+    Set { url: url::Url },
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum FileFormat {
+    Toml,
+    Json,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum GroupBy {
+    Manager,
+}
+
+/// Options of the commands that list packages, e.g. `list` and `search`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct FilterArgs {
+    /// Only keep the packages whose name or version matches this regular
+    /// expression, e.g. `^lib`. It is matched against the packages mpm parsed
+    /// from the output of the package manager, so it works the same with
+    /// every package manager, and before `--limit` is applied.
+    #[arg(long, value_name = "REGEX")]
+    filter: Option<regex::Regex>,
+
+    /// Match `--filter` against the package names only.
+    #[arg(long, default_value_t = false, requires = "filter")]
+    name_only: bool,
+}
+
+impl FilterArgs {
+    /// Whether packages are filtered at all.
+    fn is_set(&self) -> bool {
+        self.filter.is_some()
+    }
+
+    /// Keep the packages whose name, or unless `--name-only` version,
+    /// matches `--filter`.
+    fn apply(&self, pkgs: &mut Vec<Package>) {
+        if let Some(filter) = &self.filter {
+            pkgs.retain(|pkg| {
+                filter.is_match(pkg.name())
+                    || (!self.name_only && pkg.version().is_some_and(|v| filter.is_match(v)))
+            });
+        }
+    }
+}
+
+/// Outcome of a successfully executed [`Cli`] command.
+///
+/// Returned by [`execute_with_outcome`] so that other tools can consume the
+/// results programmatically instead of parsing mpm's output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecuteOutcome {
+    /// Supported package managers were listed.
+    Managers,
+    /// Packages matching the search query.
+    Search {
+        query: String,
+        packages: Vec<Package>,
+    },
+    /// Installed packages and the format they were requested in.
+    List {
+        packages: Vec<Package>,
+        file_format: Option<FileFormat>,
+        group_by: Option<GroupBy>,
+    },
+    /// Packages that were installed.
+    Installed(Vec<String>),
+    /// Packages that were uninstalled.
+    Uninstalled(Vec<String>),
+    /// Packages that were updated.
+    Updated(Vec<String>),
+    /// All packages were updated.
+    UpdatedAll,
+    /// Updates were downloaded to be installed later.
+    UpdatesDownloaded,
+    /// Repositories that were added.
+    RepoAdded(Vec<String>),
+    /// Repositories of the package manager.
+    Repos(Vec<Repository>),
+    /// The repository with this name was removed.
+    RepoRemoved(String),
+    /// Package repositories were synced.
+    Synced,
+    /// Whether each package manager synced its repositories, with `--all`.
+    SyncedAll(Vec<(String, bool)>),
+    /// Packages that can be updated.
+    Outdated(Vec<Package>),
+    /// Packages installed by more than one package manager.
+    Conflicts(Vec<(String, Vec<AvailablePackageManager>)>),
+    /// The on-disk cache was cleared.
+    CacheCleared,
+    /// The package database was fixed.
+    Fixed,
+    /// Modified or missing files of installed packages.
+    Verified(Vec<(Package, Vec<PathBuf>)>),
+    /// Packages depending on a package.
+    ReverseDependencies(Vec<Package>),
+    /// Metadata of a package.
+    Info(PackageMetadata),
+    /// Package owning a file.
+    Owner(Package),
+    /// Files installed by a package.
+    Files(Vec<PathBuf>),
+    /// The package manager's cache was cleaned.
+    Cleaned,
+    /// Bytes that cleaning the cache would free.
+    Cleanable(u64),
+    /// Unneeded dependencies were removed.
+    Autoremoved,
+    /// Pinned packages.
+    Pins(Vec<Package>),
+    /// The package was pinned.
+    Pinned(String),
+    /// The package was unpinned.
+    Unpinned(String),
+    /// Operations that changed the installed packages, oldest first.
+    History(Vec<HistoryEntry>),
+    /// An operation from the history was reverted.
+    RolledBack(RollbackReport),
+    /// Dependencies of a package.
+    Dependencies(DependencyGraph),
+    /// Patches of the package manager.
+    Patches(Vec<Package>),
+    /// Packages available in the repositories.
+    Available(Vec<Package>),
+    /// Value of a configuration option, `None` if it is not set.
+    OptionValue { key: String, value: Option<String> },
+    /// A configuration option was set.
+    OptionSet { key: String, value: String },
+    /// Configured mirrors.
+    Mirrors(Vec<url::Url>),
+    /// The primary mirror was switched to this URL.
+    MirrorSet(url::Url),
+    /// A lockfile was written to this path.
+    Locked(PathBuf),
+    /// How the installed packages differ from a lockfile.
+    Checked(crate::lockfile::Drift),
+    /// A snapshot was saved under this name.
+    SnapshotSaved(String),
+    /// Names of the saved snapshots.
+    Snapshots(Vec<String>),
+    /// Changes restoring a snapshot made, or would make with `dry_run`.
+    SnapshotRestored {
+        report: RestoreReport,
+        dry_run: bool,
+    },
+    /// Man pages that were written.
+    Manpages(Vec<PathBuf>),
+    /// Findings of `mpm doctor`.
+    Diagnosed(Vec<crate::doctor::Check>),
+    /// Latest release of mpm, and whether it was installed.
+    SelfUpdated(crate::self_update::SelfUpdate),
+    /// Files, or the Task Scheduler task, of the job that was installed.
+    Scheduled(Vec<PathBuf>),
+    /// The package manager ran with the arguments of `mpm run`.
+    Ran,
+    /// Native commands, program first, that a command would run.
+    Explained(Vec<Vec<String>>),
+    /// Shell script reproducing a command.
+    Script(String),
+}
+
+impl MpmPackageManagerCommands {
+    /// Commands that modify the system and therefore need elevated
+    /// privileges.
+    fn requires_sudo(&self) -> bool {
+        #[cfg(feature = "cli")]
+        if let MpmPackageManagerCommands::Search { pick: true, .. } = self {
+            return true;
+        }
+        matches!(
+            self,
+            MpmPackageManagerCommands::Install {
+                emit_script: false,
+                ..
+            } | MpmPackageManagerCommands::Uninstall { .. }
+                | MpmPackageManagerCommands::Update { .. }
+                | MpmPackageManagerCommands::Repo {
+                    command: RepoCommands::Add { .. }
+                        | RepoCommands::AddPositional(_)
+                        | RepoCommands::Remove { .. }
+                }
+                // `sync --all` elevates per package manager, see `sync_all`
+                | MpmPackageManagerCommands::Sync { all: false }
+                | MpmPackageManagerCommands::Fix
+                | MpmPackageManagerCommands::Verify { .. }
+                | MpmPackageManagerCommands::Snapshot {
+                    command: SnapshotCommands::Restore { .. }
+                }
+                | MpmPackageManagerCommands::Mirrors {
+                    command: MirrorCommands::Set { .. }
+                }
+                | MpmPackageManagerCommands::Config {
+                    command: ConfigCommands::Set { .. }
+                }
+        )
+    }
+
+    /// Operation and packages recorded in the history for commands that
+    /// change the installed packages, see [`crate::history`].
+    fn history_operation(&self) -> Option<(&'static str, Vec<String>)> {
+        match self {
+            MpmPackageManagerCommands::Install {
+                emit_script: false,
+                packages,
+                ..
+            } => Some(("install", packages.clone())),
+            MpmPackageManagerCommands::Uninstall { packages, .. } => {
+                Some(("uninstall", packages.clone()))
+            }
+            // downloading changes no installed package
+            MpmPackageManagerCommands::Update {
+                all: true,
+                download_only: true,
+                ..
+            } => None,
+            MpmPackageManagerCommands::Update { packages, .. } => {
+                Some(("update", packages.clone()))
+            }
+            MpmPackageManagerCommands::Autoremove => Some(("autoremove", vec![])),
+            MpmPackageManagerCommands::Pin {
+                package: Some(package),
+            } => Some(("pin", vec![package.clone()])),
+            MpmPackageManagerCommands::Unpin { package } => Some(("unpin", vec![package.clone()])),
+            _ => None,
+        }
+    }
+
+    /// Commands that change the system and thus must not run with
+    /// `--dry-run` unless they support it.
+    fn modifies_system(&self) -> bool {
+        self.requires_sudo()
+            || self.history_operation().is_some()
+            || matches!(
+                self,
+                MpmPackageManagerCommands::Clean | MpmPackageManagerCommands::Rollback { .. }
+            )
+    }
+
+    /// Commands whose changes all go through the package manager's commands
+    /// and thus can be simulated with `--dry-run`.
+    fn supports_dry_run(&self) -> bool {
+        matches!(
+            self,
+            MpmPackageManagerCommands::Install { .. }
+                | MpmPackageManagerCommands::Uninstall { .. }
+                | MpmPackageManagerCommands::Update { .. }
+                | MpmPackageManagerCommands::Sync { .. }
+                | MpmPackageManagerCommands::Clean
+                | MpmPackageManagerCommands::Autoremove
+                | MpmPackageManagerCommands::Snapshot { .. }
+                | MpmPackageManagerCommands::Run { .. }
+        )
+    }
+
+    /// Kind of the packages given to the command, see [`PackageKind`].
+    fn kind(&self) -> Option<PackageKind> {
+        match self {
+            MpmPackageManagerCommands::Install { kind, .. }
+            | MpmPackageManagerCommands::Uninstall { kind, .. }
+            | MpmPackageManagerCommands::Update { kind, .. } => *kind,
+            _ => None,
+        }
+    }
+}
+
+/// Keeps the packages named `query`, ignoring case, if `exact`, and those
+/// marked as installed (see [`PackageManager::mark_installed`]) if
+/// `installed`.
+fn retain_matches(pkgs: &mut Vec<Package>, query: &str, exact: bool, installed: bool) {
+    pkgs.retain(|p| {
+        (!exact || p.name().eq_ignore_ascii_case(query))
+            && (!installed || p.installed() == Some(true))
+    });
+}
+
+/// Keeps the first `limit` packages of each package manager.
+fn truncate_per_manager(pkgs: &mut Vec<Package>, limit: usize) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    pkgs.retain(|pkg| {
+        let count = counts.entry(pkg.package_manager().to_string()).or_default();
+        *count += 1;
+        *count <= limit
+    });
+}
+
+/// Parse a package given on the command line as a package of `kind`.
+fn package_of_kind(pkg: &str, kind: Option<PackageKind>) -> anyhow::Result<Package> {
+    let mut pkg = Package::from_str(pkg)?;
+    if let Some(kind) = kind {
+        pkg.set_kind(kind);
+    }
+    Ok(pkg)
+}
+
+/// Function that handles the parsed CLI arguments in one place
+pub fn execute(args: Cli) -> anyhow::Result<()> {
+    let format = args.output_format();
+    let columns = args.columns.clone();
+    let sort = args.sort;
+    let outcome = execute_with_outcome(args)?;
+    print_outcome(&outcome, format, columns.as_deref(), sort)?;
+    match &outcome {
+        ExecuteOutcome::Checked(drift) => anyhow::ensure!(
+            drift.is_empty(),
+            "The installed packages differ from the lockfile"
+        ),
+        ExecuteOutcome::Verified(modified) => {
+            anyhow::ensure!(modified.is_empty(), "Found modified or missing files")
+        }
+        ExecuteOutcome::Diagnosed(checks) => anyhow::ensure!(
+            checks
+                .iter()
+                .all(|c| c.status != crate::doctor::Status::Error),
+            "mpm doctor found problems"
+        ),
+        ExecuteOutcome::RolledBack(RollbackReport { failed, .. })
+        | ExecuteOutcome::SnapshotRestored {
+            report: RestoreReport { failed, .. },
+            ..
+        } if !failed.is_empty() => {
+            return Err(Error::PartialFailure(format!("Failed to {}", failed.join(", "))).into());
+        }
+        ExecuteOutcome::SyncedAll(synced) if synced.iter().any(|(_, ok)| !ok) => {
+            let failed: Vec<&str> = synced
+                .iter()
+                .filter(|(_, ok)| !ok)
+                .map(|(m, _)| m.as_str())
+                .collect();
+            let msg = format!("Failed to sync {}", failed.join(", "));
+            return Err(Error::PartialFailure(msg).into());
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Execute the parsed CLI arguments and return what happened without
+/// printing it.
+pub fn execute_with_outcome(args: Cli) -> anyhow::Result<ExecuteOutcome> {
+    // needs no package manager, e.g. when packaging mpm
+    if let MpmPackageManagerCommands::Manpages { out_dir } = &args.command {
+        return Ok(ExecuteOutcome::Manpages(write_manpages(out_dir)?));
+    }
+    if let MpmPackageManagerCommands::SelfUpdate { check } = &args.command {
+        let update = crate::self_update::self_update(*check || args.dry_run)?;
+        return Ok(ExecuteOutcome::SelfUpdated(update));
+    }
+    // the job selects its package manager when it runs
+    if let MpmPackageManagerCommands::Schedule { .. } = &args.command {
+        return schedule(&args);
+    }
+    let config = Config::load(args.config.as_deref())?;
+    // reports unavailable package managers instead of failing
+    if let MpmPackageManagerCommands::Doctor = &args.command {
+        let checks = crate::doctor::diagnose(args.manager.clone().or(config.manager.clone()));
+        return Ok(ExecuteOutcome::Diagnosed(checks));
+    }
+    let mut mpm = if let Some(manager) = args.manager.clone().or(config.manager.clone()) {
+        crate::MetaPackageManager::new_if_available(manager)?
+    } else {
+        let priority = match ManagerPriority::from_env()? {
+            Some(priority) => priority,
+            None => config.priority.clone(),
+        };
+        crate::MetaPackageManager::new_default_with(&priority)?
+    };
+    if let (Some(backend), MetaPackageManager::Apt(apt)) = (config.apt_backend, &mut mpm) {
+        apt.set_backend(backend);
+    }
+    if args.force_ipv4 {
+        mpm.set_force_ipv4(true)?;
+    }
+    crate::common::set_allow_insecure_urls(args.allow_insecure_url);
+    crate::common::set_dry_run(args.dry_run);
+    crate::common::set_assume_yes(!args.interactive);
+    #[cfg(feature = "cli")]
+    if !args.output_format().is_structured() {
+        crate::common::set_download_reporter(Some(download_progress_bar()));
+    }
+    if args.dry_run && args.command.modifies_system() && !args.command.supports_dry_run() {
+        anyhow::bail!("--dry-run is not supported for this command");
+    }
+    let routed = matches!(
+        args.command,
+        MpmPackageManagerCommands::Install { .. } | MpmPackageManagerCommands::Uninstall { .. }
+    );
+    let rules =
+        args.manager.is_none() && matches!(args.command, MpmPackageManagerCommands::Install { .. });
+    apply_env(&mut mpm, &args, &config, rules)?;
+    if let MpmPackageManagerCommands::Install {
+        params,
+        install_arguments,
+        ..
+    } = &args.command
+    {
+        if params.is_some() || install_arguments.is_some() {
+            mpm.set_choco_params(params.clone(), install_arguments.clone())?;
+        }
+    }
+    if let MpmPackageManagerCommands::Install {
+        remote: Some(remote),
+        ..
+    } = &args.command
+    {
+        mpm.set_remote(remote.clone())?;
+    }
+    if let Some(kind) = args.command.kind() {
+        if !mpm.supports_kind(kind) {
+            return Err(
+                Unsupported::new(mpm.pkg_manager_name(), "packages of another kind").into(),
+            );
+        }
+    }
+
+    // packages routed to other package managers must not be installed as
+    // root, e.g. with brew, so then only the commands that need it are
+    // elevated (see `PackageManagerCommands::elevated`)
+    let single_manager = match args.command.history_operation() {
+        Some((_, packages)) if routed => history_groups(&mpm, packages, &config, rules)
+            .iter()
+            .all(|(manager, _)| *manager == mpm.pkg_manager_name()),
+        _ => true,
+    };
+    // elevate to root only for specific commands
+    if args.command.requires_sudo() && mpm.needs_sudo() && !args.dry_run && single_manager {
+        sudo()?;
+    }
+
+    // asked after elevating, which restarts mpm
+    let confirm_first = (args.confirm || config.confirm) && !args.yes && !args.dry_run;
+    if confirm_first && args.command.modifies_system() {
+        confirm_command(&mpm, &args.command)?;
+    }
+
+    tracing::info!(command = ?args.command, manager = %mpm, "Executing command");
+
+    // simulated operations changed nothing
+    let recorded = args
+        .command
+        .history_operation()
+        .filter(|_| !args.dry_run)
+        .map(|(operation, packages)| {
+            let mut groups = if routed {
+                history_groups(&mpm, packages, &config, rules)
+            } else {
+                vec![(mpm.pkg_manager_name(), packages)]
+            };
+            // the removed versions are only known beforehand
+            if operation == "uninstall" {
+                for (manager, packages) in &mut groups {
+                    *packages =
+                        with_installed_versions(&mpm, manager, std::mem::take(packages), &config);
+                }
+            }
+            (operation, groups)
+        });
+    let outcome = execute_with_manager(&mpm, args, &config);
+    if let Some((operation, groups)) = recorded {
+        let exit_code = outcome
+            .as_ref()
+            .map_or_else(crate::error::exit_code, |_| crate::error::EXIT_SUCCESS);
+        for (manager, mut packages) in groups {
+            if operation != "uninstall" {
+                packages = with_installed_versions(&mpm, &manager, packages, &config);
+            }
+            let mut entry = HistoryEntry::new(manager, operation, packages, exit_code);
+            // rollback undoes this transaction, not whatever ran last
+            if exit_code == crate::error::EXIT_SUCCESS {
+                entry.transaction = if entry.manager == mpm.pkg_manager_name() {
+                    mpm.last_transaction()
+                } else {
+                    manager_named(&entry.manager).and_then(|m| m.last_transaction())
+                };
+            }
+            if let Err(e) = History::try_default().and_then(|history| history.record(&entry)) {
+                tracing::warn!("Failed to record the operation in the history: {e}");
+            }
+        }
+    }
+    outcome
+}
+
+/// `packages` of a history entry grouped by the package manager they are
+/// installed or uninstalled with, see [`target_manager`]. Package files and
+/// packages without a `manager:` prefix or matching rule belong to `mpm`.
+fn history_groups<P: PackageManager>(
+    mpm: &P,
+    packages: Vec<String>,
+    config: &Config,
+    rules: bool,
+) -> Vec<(String, Vec<String>)> {
+    let mut groups: Vec<(String, Vec<String>)> = vec![(mpm.pkg_manager_name(), vec![])];
+    for arg in packages {
+        let manager = Package::from_str(&arg)
+            .ok()
+            .filter(|_| !Path::new(&arg).is_file())
+            .and_then(|pkg| target_manager(&arg, &pkg, config, rules))
+            .map_or_else(|| mpm.pkg_manager_name(), |m| m.to_string().to_lowercase());
+        match groups.iter_mut().find(|(m, _)| *m == manager) {
+            Some((_, pkgs)) => pkgs.push(arg),
+            None => groups.push((manager, vec![arg])),
+        }
+    }
+    // keep an entry for operations on all packages, e.g. `update --all`
+    if groups.len() > 1 && groups[0].1.is_empty() {
+        groups.remove(0);
+    }
+    groups
+}
+
+/// Scope `mpm` to the environment of `--env`, see
+/// [`MetaPackageManager::set_env`]. Package managers without environments
+/// fail unless some of the packages are routed to one that has them (see
+/// [`in_env`]), with the config rules if `rules`.
+fn apply_env(
+    mpm: &mut MetaPackageManager,
+    args: &Cli,
+    config: &Config,
+    rules: bool,
+) -> anyhow::Result<()> {
+    let Some(env) = &args.env else {
+        return Ok(());
+    };
+    let Err(e) = mpm.set_env(env.clone()) else {
+        return Ok(());
+    };
+    let packages = match &args.command {
+        MpmPackageManagerCommands::Install { packages, .. }
+        | MpmPackageManagerCommands::Uninstall { packages, .. } => packages.clone(),
+        _ => return Err(e),
+    };
+    let conda = AvailablePackageManager::Conda.to_string().to_lowercase();
+    if history_groups(&*mpm, packages, config, rules)
+        .iter()
+        .any(|(manager, _)| *manager == conda)
+    {
+        Ok(())
+    } else {
+        Err(e)
+    }
+}
+
+/// The available package manager named `manager`, e.g. `brew`.
+fn manager_named(manager: &str) -> Option<MetaPackageManager> {
+    let manager = manager.parse::<AvailablePackageManager>().ok()?;
+    MetaPackageManager::new_if_available(manager).ok()
+}
+
+/// `packages` with the version `manager` has installed, e.g. `htop@3.3.0`,
+/// keeping those it has not installed as they were given. `mpm` is asked if
+/// it is `manager`.
+fn with_installed_versions<P: PackageManager>(
+    mpm: &P,
+    manager: &str,
+    packages: Vec<String>,
+    config: &Config,
+) -> Vec<String> {
+    if packages.is_empty() {
+        return packages;
+    }
+    let installed = if manager == mpm.pkg_manager_name() {
+        mpm.list_installed()
+    } else {
+        match manager_named(manager) {
+            Some(m) => m.list_installed(),
+            None => return packages,
+        }
+    };
+    packages
+        .into_iter()
+        .map(|arg| {
+            let name = match Package::from_str(&arg) {
+                Ok(pkg) if !Path::new(&arg).is_file() => {
+                    alias_package(pkg, manager, config).name().to_string()
+                }
+                _ => return arg,
+            };
+            installed
+                .iter()
+                .find(|p| p.name() == name)
+                .and_then(|p| Some(format!("{name}@{}", p.version()?)))
+                .unwrap_or(arg)
+        })
+        .collect()
+}
+
+/// Execute the parsed CLI arguments using the given package manager and the
+/// configuration loaded from `--config`.
+fn execute_with_manager<P: PackageManager>(
+    mpm: &P,
+    args: Cli,
+    config: &Config,
+) -> anyhow::Result<ExecuteOutcome> {
+    let outcome = match args.command {
+        MpmPackageManagerCommands::Managers { install_default } => {
+            if install_default {
+                if let Err(e) = install_default_manager() {
+                    eprintln!("Failed to install default package manager: {e}");
+                }
+            }
+            ExecuteOutcome::Managers
+        }
+        MpmPackageManagerCommands::Search {
+            string,
+            cache,
+            cache_ttl,
+            show_installed,
+            exact,
+            installed,
+            limit,
+            all,
+            filter,
+            #[cfg(feature = "cli")]
+            pick,
+        } => {
+            // the limit applies to the packages left after filtering, so the
+            // search itself can only be limited without filters
+            let search_limit = limit.filter(|_| !filter.is_set() && !installed && !exact);
+            if all {
+                let mut packages = search_all(&string, search_limit, show_installed || installed);
+                retain_matches(&mut packages, &string, exact, installed);
+                filter.apply(&mut packages);
+                if let Some(limit) = limit {
+                    truncate_per_manager(&mut packages, limit);
+                }
+                return Ok(ExecuteOutcome::Search {
+                    query: string,
+                    packages,
+                });
+            }
+            let mut pkgs = match (cache, search_limit) {
+                (true, _) => SearchCache::try_default(Duration::from_secs(cache_ttl))?
+                    .search(mpm, &string)?,
+                (false, Some(limit)) => mpm.search_limited(&string, limit)?,
+                (false, None) if exact => mpm.search_exact(&string),
+                (false, None) => mpm.search(&string),
+            };
+            if show_installed || installed {
+                mpm.mark_installed(&mut pkgs);
+            }
+            retain_matches(&mut pkgs, &string, exact, installed);
+            filter.apply(&mut pkgs);
+            pkgs.truncate(limit.unwrap_or(usize::MAX));
+            #[cfg(feature = "cli")]
+            if pick {
+                let installed = pick_and_install(mpm, &pkgs, args.interactive)?;
+                return Ok(ExecuteOutcome::Installed(installed));
+            }
+            ExecuteOutcome::Search {
+                query: string,
+                packages: pkgs,
+            }
+        }
+        MpmPackageManagerCommands::List {
+            all,
+            file_format,
+            group_by,
+            filter,
+        } => {
+            let mut packages = if all {
+                list_all_installed()
+            } else {
+                mpm.list_installed()
+            };
+            filter.apply(&mut packages);
+            if matches!(file_format, Some(FileFormat::Toml | FileFormat::Json)) {
+                tracing::warn!(
+                    "`list --file-format` is deprecated, `mpm export` writes a lockfile that \
+                     `mpm install --locked` installs again"
+                );
+            }
+            ExecuteOutcome::List {
+                packages,
+                file_format,
+                group_by,
+            }
+        }
+        MpmPackageManagerCommands::Install {
+            packages,
+            input_file,
+            locked,
+            verify_signature,
+            checksum,
+            signature,
+            transactional,
+            if_missing,
+            resume,
+            max_size,
+            plan,
+            emit_script,
+            kind,
+            ..
+        } => {
+            if let Some(input) = input_file {
+                let file_type = get_file_type(&input);
+                let installed = install_from_file(
+                    &input,
+                    file_type,
+                    config,
+                    args.interactive,
+                    transactional,
+                    resume,
+                )?;
+                return Ok(ExecuteOutcome::Installed(installed));
+            }
+            if let Some(lockfile) = locked {
+                let installed = crate::lockfile::install_locked(&lockfile, args.interactive)?;
+                return Ok(ExecuteOutcome::Installed(installed));
+            }
+
+            anyhow::ensure!(
+                packages.len() == 1 || (checksum.is_none() && signature.is_none()),
+                "--checksum and --signature verify a single package"
+            );
+            // verified package files stay in a directory only mpm can write
+            // to until they are installed
+            let mut verified_dir = None;
+            let mut pkgs = vec![];
+            for pkg in &packages {
+                let pkg_path = PathBuf::from(pkg);
+                let mut package = if pkg_path.is_file() {
+                    ensure_installable_file(mpm, &pkg_path, std::env::consts::ARCH)?;
+                    Package::from(&pkg_path)
+                } else {
+                    package_of_kind(pkg, kind)?
+                };
+                if let Some(checksum) = &checksum {
+                    package.set_sha256(checksum);
+                }
+                if let Some(signature) = &signature {
+                    package.set_signature(signature.clone());
+                }
+                verify_package(&mut package, &mut verified_dir)?;
+                if verify_signature {
+                    ensure_valid_signature(mpm, &mut package)?;
+                }
+                pkgs.push(package);
+            }
+
+            if emit_script {
+                let route = args.manager.is_none();
+                let cmds =
+                    install_commands(mpm, &packages, pkgs, config, route, args.env.as_deref());
+                return Ok(ExecuteOutcome::Script(crate::common::to_shell_script(
+                    &cmds,
+                )));
+            }
+
+            // install packages with the package manager of their `manager:`
+            // prefix, or else the one preferred by the config rules unless a
+            // package manager was given explicitly
+            let mut routed: Vec<(AvailablePackageManager, Vec<Package>)> = vec![];
+            let mut rest = vec![];
+            for (arg, pkg) in packages.iter().zip(pkgs) {
+                match target_manager(arg, &pkg, config, args.manager.is_none()) {
+                    Some(m) if m.to_string().to_lowercase() != mpm.pkg_manager_name() => {
+                        match routed.iter_mut().find(|(r, _)| *r == m) {
+                            Some((_, pkgs)) => pkgs.push(pkg),
+                            None => routed.push((m, vec![pkg])),
+                        }
+                    }
+                    _ => rest.push(pkg),
+                }
+            }
+            let routed = routed
+                .into_iter()
+                .map(|(m, pkgs)| -> anyhow::Result<_> {
+                    let m = in_env(
+                        MetaPackageManager::new_if_available(m)?,
+                        args.env.as_deref(),
+                    );
+                    let pkgs: Vec<Package> = pkgs
+                        .into_iter()
+                        .map(|pkg| alias_package(pkg, &m.pkg_manager_name(), config))
+                        .collect();
+                    Ok((m, pkgs))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let pkgs: Vec<Package> = rest
+                .into_iter()
+                .map(|pkg| alias_package(pkg, &mpm.pkg_manager_name(), config))
+                .collect();
+
+            // every check covers the packages of all package managers before
+            // any of them is installed
+            if plan {
+                for (m, pkgs) in &routed {
+                    for package in pkgs {
+                        print_plan(&m.install_plan(package)?, args.output_format())?;
+                    }
+                }
+                for package in &pkgs {
+                    print_plan(&mpm.install_plan(package)?, args.output_format())?;
+                }
+                if args.interactive {
+                    anyhow::ensure!(confirm("Continue?")?, "Installation aborted");
+                }
+            }
+
+            if let Some(max_size) = max_size {
+                for (m, pkgs) in &routed {
+                    for package in pkgs {
+                        ensure_size_within(m, package, max_size, args.interactive)?;
+                    }
+                }
+                for package in &pkgs {
+                    ensure_size_within(mpm, package, max_size, args.interactive)?;
+                }
+            }
+
+            if transactional {
+                install_transactional_routed(mpm, &pkgs, &routed)?;
+            } else if if_missing {
+                for (m, pkgs) in &routed {
+                    for package in pkgs {
+                        let action = m.install_if_missing(package, args.interactive)?;
+                        tracing::info!("{}: {action:?}", package.name());
+                    }
+                }
+                for package in &pkgs {
+                    let action = mpm.install_if_missing(package, args.interactive)?;
+                    tracing::info!("{}: {action:?}", package.name());
+                }
+            } else {
+                for (m, pkgs) in &routed {
+                    if !m.install_many(pkgs, args.interactive).success() {
+                        return Err(install_error(m, pkgs));
+                    }
+                }
+                if !pkgs.is_empty() && !mpm.install_many(&pkgs, args.interactive).success() {
+                    return Err(install_error(mpm, &pkgs));
+                }
+            }
+            ExecuteOutcome::Installed(packages)
+        }
+        MpmPackageManagerCommands::Uninstall {
+            packages,
+            force,
+            kind,
+        } => {
+            for pkg in &packages {
+                let package = package_of_kind(pkg, kind)?;
+                // uninstall with the package manager of a `manager:` prefix
+                match target_manager(pkg, &package, config, false) {
+                    Some(m) if m.to_string().to_lowercase() != mpm.pkg_manager_name() => {
+                        let m = in_env(
+                            MetaPackageManager::new_if_available(m)?,
+                            args.env.as_deref(),
+                        );
+                        uninstall_package(&m, pkg, package, config, args.interactive, force)?;
+                    }
+                    _ => uninstall_package(mpm, pkg, package, config, args.interactive, force)?,
+                }
+            }
+            ExecuteOutcome::Uninstalled(packages)
+        }
+
+        MpmPackageManagerCommands::Update {
+            packages,
+            all,
+            progress,
+            download_only,
+            apply_downloaded,
+            kind,
+        } => {
+            if all && download_only {
+                let s = mpm.download_updates()?;
+                anyhow::ensure!(s.success(), "Failed to download updates");
+                ExecuteOutcome::UpdatesDownloaded
+            } else if all {
+                let s = if apply_downloaded {
+                    mpm.apply_downloaded_updates()?
+                } else if progress {
+                    let s = mpm.update_all_with_progress(|done, total| {
+                        eprint!("\r{done}/{total} upgraded");
+                    });
+                    eprintln!();
+                    s
+                } else {
+                    mpm.update_all(args.interactive)
+                };
+                anyhow::ensure!(s.success(), "Failed to update all packages");
+                ExecuteOutcome::UpdatedAll
+            } else {
+                for pkg in &packages {
+                    let s = mpm.update(package_of_kind(pkg, kind)?, args.interactive);
+                    anyhow::ensure!(s.success(), "Failed to update pacakge {pkg}");
+                }
+                ExecuteOutcome::Updated(packages)
+            }
+        }
+        MpmPackageManagerCommands::Repo { command } => match command {
+            RepoCommands::Add { repo } => {
+                mpm.add_repo(&repo)?;
+                ExecuteOutcome::RepoAdded(repo)
+            }
+            RepoCommands::AddPositional(repo) => {
+                tracing::warn!("`mpm repo <repo>` is deprecated, use `mpm repo add <repo>`");
+                mpm.add_repo(&repo)?;
+                ExecuteOutcome::RepoAdded(repo)
+            }
+            RepoCommands::List => ExecuteOutcome::Repos(mpm.list_repos()?),
+            RepoCommands::Remove { name } => {
+                mpm.remove_repo(&name)?;
+                ExecuteOutcome::RepoRemoved(name)
+            }
+        },
+        MpmPackageManagerCommands::Sync { all: true } => ExecuteOutcome::SyncedAll(sync_all()?),
+        MpmPackageManagerCommands::Sync { all: false } => {
+            let s = mpm.sync();
+            anyhow::ensure!(s.success(), "Failed to sync repositories");
+            ExecuteOutcome::Synced
+        }
+        MpmPackageManagerCommands::Outdated { all } => {
+            let pkgs = if all {
+                list_all_outdated()
+            } else {
+                mpm.list_outdated()
+            };
+            ExecuteOutcome::Outdated(pkgs)
+        }
+        MpmPackageManagerCommands::Patches { all } => {
+            ExecuteOutcome::Patches(mpm.list_patches(all)?)
+        }
+        MpmPackageManagerCommands::Conflicts => {
+            ExecuteOutcome::Conflicts(crate::managers::find_cross_manager_duplicates())
+        }
+        MpmPackageManagerCommands::ListAvailable { prefix } => {
+            let prefix = prefix.unwrap_or_default();
+            let mut pkgs = vec![];
+            mpm.list_available_with(|pkg| {
+                if pkg.name().starts_with(&prefix) {
+                    pkgs.push(pkg);
+                }
+            })?;
+            ExecuteOutcome::Available(pkgs)
+        }
+        MpmPackageManagerCommands::Deps { package } => {
+            ExecuteOutcome::Dependencies(mpm.dependencies(&Package::from_str(&package)?)?)
+        }
+        MpmPackageManagerCommands::Info { package } => {
+            ExecuteOutcome::Info(mpm.info(&Package::from_str(&package)?)?)
+        }
+        MpmPackageManagerCommands::Rdeps { package } => ExecuteOutcome::ReverseDependencies(
+            mpm.reverse_dependencies(&Package::from_str(&package)?)?,
+        ),
+        MpmPackageManagerCommands::Owns { path } => {
+            // `dpkg -S` treats relative paths as patterns
+            let path = std::env::current_dir()?.join(path);
+            ExecuteOutcome::Owner(mpm.owner(&path)?)
+        }
+        MpmPackageManagerCommands::Files { package } => {
+            ExecuteOutcome::Files(mpm.files(&Package::from_str(&package)?)?)
+        }
+        MpmPackageManagerCommands::Clean if args.dry_run => {
+            let dirs = mpm.cache_dirs();
+            if dirs.is_empty() {
+                return Err(
+                    Unsupported::new(mpm.pkg_manager_name(), "estimating the cache size").into(),
+                );
+            }
+            ExecuteOutcome::Cleanable(dirs.iter().map(crate::common::dir_size).sum())
+        }
+        MpmPackageManagerCommands::Clean => {
+            anyhow::ensure!(mpm.clean()?.success(), "Failed to clean the cache");
+            ExecuteOutcome::Cleaned
+        }
+        MpmPackageManagerCommands::Autoremove => {
+            let s = mpm.autoremove(args.interactive)?;
+            anyhow::ensure!(s.success(), "Failed to remove unneeded packages");
+            ExecuteOutcome::Autoremoved
+        }
+        MpmPackageManagerCommands::Pin { package: None } => {
+            ExecuteOutcome::Pins(mpm.list_pinned()?)
+        }
+        MpmPackageManagerCommands::Pin {
+            package: Some(package),
+        } => {
+            mpm.pin(&Package::from_str(&package)?)?;
+            ExecuteOutcome::Pinned(package)
+        }
+        MpmPackageManagerCommands::Unpin { package } => {
+            mpm.unpin(&Package::from_str(&package)?)?;
+            ExecuteOutcome::Unpinned(package)
+        }
+        MpmPackageManagerCommands::History { native } => {
+            let entries = History::try_default()?.entries()?;
+            if native {
+                ExecuteOutcome::History(crate::history::merge(entries, mpm.history()?))
+            } else {
+                ExecuteOutcome::History(entries)
+            }
+        }
+        MpmPackageManagerCommands::Rollback { id } => {
+            ExecuteOutcome::RolledBack(rollback(mpm, id, args.interactive)?)
+        }
+        MpmPackageManagerCommands::Fix => {
+            let s = mpm.fix_broken()?;
+            anyhow::ensure!(s.success(), "Failed to fix broken packages");
+            ExecuteOutcome::Fixed
+        }
+        MpmPackageManagerCommands::Verify {
+            package: Some(package),
+            ..
+        } => {
+            let pkg = Package::from_str(&package)?;
+            let files = mpm.verify_files(&pkg)?;
+            if files.is_empty() {
+                ExecuteOutcome::Verified(vec![])
+            } else {
+                ExecuteOutcome::Verified(vec![(pkg, files)])
+            }
+        }
+        MpmPackageManagerCommands::Verify { package: None, .. } => {
+            ExecuteOutcome::Verified(mpm.verify_all()?)
+        }
+        MpmPackageManagerCommands::Cache { command } => match command {
+            CacheCommands::Clear => {
+                SearchCache::try_default(crate::cache::DEFAULT_TTL)?.clear()?;
+                ExecuteOutcome::CacheCleared
+            }
+        },
+        MpmPackageManagerCommands::Lock { lockfile } => {
+            crate::lockfile::generate_lockfile(mpm).write(&lockfile)?;
+            ExecuteOutcome::Locked(lockfile)
+        }
+        MpmPackageManagerCommands::Export { lockfile } => {
+            crate::lockfile::Lockfile::export(list_all_installed()).write(&lockfile)?;
+            ExecuteOutcome::Locked(lockfile)
+        }
+        MpmPackageManagerCommands::Check { lockfile } => {
+            ExecuteOutcome::Checked(crate::lockfile::check(&lockfile)?)
+        }
+        MpmPackageManagerCommands::Snapshot { command } => {
+            let snapshots = Snapshots::try_default()?;
+            match command {
+                SnapshotCommands::Save { name } => {
+                    let snapshot = crate::lockfile::Lockfile::from_packages(list_all_installed());
+                    snapshots.save(&name, &snapshot)?;
+                    ExecuteOutcome::SnapshotSaved(name)
+                }
+                SnapshotCommands::Restore { name } => {
+                    let snapshot = snapshots.load(&name)?;
+                    let report =
+                        crate::snapshot::restore(&snapshot, args.dry_run, args.interactive)?;
+                    ExecuteOutcome::SnapshotRestored {
+                        report,
+                        dry_run: args.dry_run,
+                    }
+                }
+                SnapshotCommands::List => ExecuteOutcome::Snapshots(snapshots.list()),
+            }
+        }
+        MpmPackageManagerCommands::Explain { command } => {
+            ExecuteOutcome::Explained(explain(mpm, &command)?)
+        }
+        MpmPackageManagerCommands::Manpages { .. }
+        | MpmPackageManagerCommands::Doctor
+        | MpmPackageManagerCommands::SelfUpdate { .. }
+        | MpmPackageManagerCommands::Schedule { .. } => {
+            unreachable!("handled by execute_with_outcome before selecting a package manager")
+        }
+        MpmPackageManagerCommands::Run { args: run_args } => {
+            let status = if crate::common::is_dry_run() {
+                crate::common::exec_dry_run(mpm.cmd(), &run_args, None, |s, _| s.success())
+            } else {
+                mpm.exec_cmds_spawn(&run_args).wait()?
+            };
+            anyhow::ensure!(
+                status.success(),
+                "{} failed with {status}",
+                mpm.pkg_manager_name()
+            );
+            ExecuteOutcome::Ran
+        }
+        MpmPackageManagerCommands::Config { command } => match command {
+            ConfigCommands::Get { key } => {
+                let value = mpm.get_option(&key)?;
+                ExecuteOutcome::OptionValue { key, value }
+            }
+            ConfigCommands::Set { key, value } => {
+                mpm.set_option(&key, &value)?;
+                ExecuteOutcome::OptionSet { key, value }
+            }
+        },
+        MpmPackageManagerCommands::Mirrors { command } => match command {
+            MirrorCommands::List => ExecuteOutcome::Mirrors(mpm.list_mirrors()?),
+            MirrorCommands::Set { url } => {
+                mpm.set_mirror(&url)?;
+                ExecuteOutcome::MirrorSet(url)
+            }
+        },
+    };
+
+    Ok(outcome)
+}
+
+/// Native commands, program first, that `command` runs. Nothing is executed.
+fn explain<P: PackageManager>(
+    mpm: &P,
+    command: &MpmPackageManagerCommands,
+) -> anyhow::Result<Vec<Vec<String>>> {
+    let with_program = |cmd: Cmd, args: Vec<String>| -> Vec<String> {
+        let program = mpm.cmd_for(cmd).unwrap_or_else(|| mpm.cmd());
+        std::iter::once(program.get_program().to_string_lossy().into_owned())
+            .chain(args)
+            .collect()
+    };
+    let per_pkg = |cmd: Cmd, pkgs: &[String]| -> anyhow::Result<Vec<Vec<String>>> {
+        anyhow::ensure!(!pkgs.is_empty(), "explain needs the package names");
+        pkgs.iter()
+            .map(|p| {
+                let path = PathBuf::from(p);
+                let mut pkg = if path.is_file() {
+                    Package::from(&path)
+                } else {
+                    Package::from_str(p)?
+                };
+                // reformatting may download packages from URLs
+                let fmt = match pkg.url() {
+                    Some(_) => p.clone(),
+                    None => mpm.reformat_for_command(&mut pkg),
+                };
+                Ok(with_program(cmd, mpm.consolidated(cmd, Some(&pkg), &[fmt])))
+            })
+            .collect()
+    };
+    let without_pkgs = |cmd: Cmd| vec![with_program(cmd, mpm.consolidated::<&str>(cmd, None, &[]))];
+    let cmds = match command {
+        MpmPackageManagerCommands::Install { packages, .. } => per_pkg(Cmd::Install, packages)?,
+        MpmPackageManagerCommands::Uninstall { packages, .. } => per_pkg(Cmd::Uninstall, packages)?,
+        MpmPackageManagerCommands::Update { all: true, .. } => without_pkgs(Cmd::UpdateAll),
+        MpmPackageManagerCommands::Update { packages, .. } => per_pkg(Cmd::Update, packages)?,
+        MpmPackageManagerCommands::List { .. } => without_pkgs(Cmd::List),
+        MpmPackageManagerCommands::Search { string, .. } => {
+            let args = mpm.consolidated(Cmd::Search, None, &[mpm.search_query(string)]);
+            vec![with_program(Cmd::Search, args)]
+        }
+        MpmPackageManagerCommands::Sync { .. } => without_pkgs(Cmd::Sync),
+        MpmPackageManagerCommands::Outdated { .. } => without_pkgs(Cmd::Outdated),
+        MpmPackageManagerCommands::Clean => without_pkgs(Cmd::Clean),
+        _ => anyhow::bail!(
+            "explain supports install, uninstall, update, list, search, sync, outdated and clean"
+        ),
+    };
+    Ok(cmds)
+}
+
+/// Installs the job of `mpm schedule`, or returns its files as a script with
+/// `--emit` or `--dry-run`.
+fn schedule(args: &Cli) -> anyhow::Result<ExecuteOutcome> {
+    let job = schedule_job(args)?;
+    if matches!(
+        args.command,
+        MpmPackageManagerCommands::Schedule { emit: true, .. }
+    ) || args.dry_run
+    {
+        return Ok(ExecuteOutcome::Script(job.emit(std::env::consts::OS)?));
+    }
+    sudo()?;
+    Ok(ExecuteOutcome::Scheduled(job.install()?))
+}
+
+/// The job of `mpm schedule`, running the command with the global options
+/// mpm was given, e.g. `--config`.
+fn schedule_job(args: &Cli) -> anyhow::Result<crate::schedule::Job> {
+    use crate::schedule::{Interval, Job};
+
+    let MpmPackageManagerCommands::Schedule {
+        command,
+        hourly,
+        weekly,
+        name,
+        ..
+    } = &args.command
+    else {
+        anyhow::bail!("not a schedule command");
+    };
+    let interval = match (hourly, weekly) {
+        (true, _) => Interval::Hourly,
+        (_, true) => Interval::Weekly,
+        _ => Interval::Daily,
+    };
+    let words: Vec<String> = command
+        .iter()
+        .flat_map(|c| c.split_whitespace())
+        .map(String::from)
+        .collect();
+    let name = match name {
+        Some(name) => name.clone(),
+        None => format!("mpm-{}", words.first().context("missing command")?),
+    };
+    let mut mpm_args = global_args(args)?;
+    mpm_args.extend(words);
+    let exe = std::env::current_exe().context("failed to locate the running mpm")?;
+    Ok(Job::new(&name, &exe, &mpm_args, interval))
+}
+
+/// Global options of `args` to pass on to a job, with `--yes` since it must
+/// not wait for an answer. Paths are made absolute, as the job runs in
+/// another directory.
+fn global_args(args: &Cli) -> anyhow::Result<Vec<String>> {
+    let absolute = |path: &Path| -> anyhow::Result<String> {
+        Ok(std::env::current_dir()?.join(path).display().to_string())
+    };
+    let mut mpm_args = vec!["--yes".to_string()];
+    if let Some(manager) = &args.manager {
+        mpm_args.extend(["--manager".to_string(), manager.to_string().to_lowercase()]);
+    }
+    if let Some(config) = &args.config {
+        mpm_args.extend(["--config".to_string(), absolute(config)?]);
+    }
+    if let Some(env) = &args.env {
+        mpm_args.extend(["--env".to_string(), env.clone()]);
+    }
+    if args.force_ipv4 {
+        mpm_args.push("--ipv4".to_string());
+    }
+    if args.allow_insecure_url {
+        mpm_args.push("--allow-insecure-url".to_string());
+    }
+    if let Some(log_file) = &args.log_file {
+        mpm_args.extend(["--log-file".to_string(), absolute(log_file)?]);
+        mpm_args.extend(["--log-rotation".to_string(), arg_value(&args.log_rotation)]);
+    }
+    if args.output_format() != OutputFormat::Table {
+        mpm_args.extend(["--output".to_string(), arg_value(&args.output_format())]);
+    }
+    if args.no_color {
+        mpm_args.push("--no-color".to_string());
+    }
+    if let Some(columns) = &args.columns {
+        let columns: Vec<String> = columns.iter().map(arg_value).collect();
+        mpm_args.extend(["--columns".to_string(), columns.join(",")]);
+    }
+    if let Some(sort) = &args.sort {
+        mpm_args.extend(["--sort".to_string(), arg_value(sort)]);
+    }
+    Ok(mpm_args)
+}
+
+/// How `value` is written on the command line.
+fn arg_value<T: clap::ValueEnum>(value: &T) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Uninstalls `package`, given as `arg` on the command line, with `mpm`,
+/// translating its name with the config aliases.
+fn uninstall_package<P: PackageManager>(
+    mpm: &P,
+    arg: &str,
+    package: Package,
+    config: &Config,
+    interactive: bool,
+    force: bool,
+) -> anyhow::Result<()> {
+    let package = alias_package(package, &mpm.pkg_manager_name(), config);
+    let name = package.name().to_string();
+    let s = mpm.try_uninstall(package, interactive, force)?;
+    if !s.success() {
+        let msg = format!("Failed to uninstall package {arg}");
+        if crate::traits::installed_names(mpm).contains(&name) {
+            anyhow::bail!(msg);
+        }
+        return Err(anyhow::Error::from(Error::PackageNotFound(name)).context(msg));
+    }
+    Ok(())
+}
+
+/// `mpm` scoped to the environment `env` of `--env` if it manages
+/// environments, for packages routed to another package manager than the
+/// selected one, which may not.
+fn in_env(mut mpm: MetaPackageManager, env: Option<&str>) -> MetaPackageManager {
+    if let (Some(env), MetaPackageManager::Conda(conda)) = (env, &mut mpm) {
+        conda.set_env(env.to_string());
+    }
+    mpm
+}
+
+/// Install `pkgs` with `mpm` and the `routed` packages with their package
+/// managers, either all of them or none: every package manager rolls back its
+/// own packages (see [`PackageManager::install_transactional`]), and the
+/// packages the previous ones installed are rolled back here.
+fn install_transactional_routed<P: PackageManager>(
+    mpm: &P,
+    pkgs: &[Package],
+    routed: &[(MetaPackageManager, Vec<Package>)],
+) -> anyhow::Result<()> {
+    let mut installed = vec![];
+    for (m, pkgs) in routed {
+        let before = crate::traits::installed_names(m);
+        if let Err(e) = m.install_transactional(pkgs) {
+            for (m, pkgs, before) in &installed {
+                crate::traits::rollback_install(*m, pkgs, before);
+            }
+            return Err(e);
+        }
+        installed.push((m, pkgs, before));
+    }
+    if pkgs.is_empty() {
+        return Ok(());
+    }
+    if let Err(e) = mpm.install_transactional(pkgs) {
+        for (m, pkgs, before) in &installed {
+            crate::traits::rollback_install(*m, pkgs, before);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Package manager to use for `pkg`, given as `arg` on the command line,
+/// instead of the selected one: the one of a `manager:` prefix of `arg` (see
+/// [`crate::common::manager_prefix`]), else the one the config rules prefer
+/// (see [`resolve_manager_for`]) if `rules`. The package manager of
+/// `manager@name` is ignored as it always was.
+fn target_manager(
+    arg: &str,
+    pkg: &Package,
+    config: &Config,
+    rules: bool,
+) -> Option<AvailablePackageManager> {
+    crate::common::manager_prefix(arg)
+        .map(|(manager, _)| manager)
+        .or_else(|| {
+            rules
+                .then(|| resolve_manager_for(pkg.name(), config))
+                .flatten()
+        })
+}
+
+/// Native commands, program first, that install `pkgs`, given as `args` on
+/// the command line: a single command per package manager, using the package
+/// manager of a `manager:` prefix or, if `route`, the one the config rules
+/// prefer (see [`target_manager`]). Package names are translated with the
+/// config aliases. Package URLs are not downloaded. Routed packages are
+/// installed in the environment `env` where it applies, see [`in_env`].
+fn install_commands<P: PackageManager>(
+    mpm: &P,
+    args: &[String],
+    pkgs: Vec<Package>,
+    config: &Config,
+    route: bool,
+    env: Option<&str>,
+) -> Vec<Vec<String>> {
+    let mut routed: Vec<(AvailablePackageManager, Vec<Package>)> = vec![];
+    let mut rest = vec![];
+    for (arg, pkg) in args.iter().zip(pkgs) {
+        match target_manager(arg, &pkg, config, route) {
+            Some(m) if m.to_string().to_lowercase() != mpm.pkg_manager_name() => {
+                let pkg = alias_package(pkg, &m.to_string().to_lowercase(), config);
+                match routed.iter_mut().find(|(r, _)| *r == m) {
+                    Some((_, pkgs)) => pkgs.push(pkg),
+                    None => routed.push((m, vec![pkg])),
+                }
+            }
+            _ => rest.push(alias_package(pkg, &mpm.pkg_manager_name(), config)),
+        }
+    }
+
+    let mut cmds: Vec<Vec<String>> = routed
+        .into_iter()
+        .flat_map(|(m, pkgs)| install_command(&in_env(MetaPackageManager::new(m), env), &pkgs))
+        .collect();
+    cmds.extend(install_command(mpm, &rest));
+    cmds
+}
+
+/// Native commands, program first, that install all of `pkgs` with as few
+/// invocations of `mpm` as possible, see [`crate::traits::consolidated_pkgs`].
+fn install_command<P: PackageManager>(mpm: &P, pkgs: &[Package]) -> Vec<Vec<String>> {
+    let program = mpm.cmd_for(Cmd::Install).unwrap_or_else(|| mpm.cmd());
+    let program = program.get_program().to_string_lossy().into_owned();
+    crate::traits::consolidated_pkgs(mpm, Cmd::Install, pkgs, |pkg| match pkg.url() {
+        // reformatting would download the package
+        Some(url) => url.to_string(),
+        None => mpm.reformat_for_command(pkg),
+    })
+    .into_iter()
+    .map(|cmds| std::iter::once(program.clone()).chain(cmds).collect())
+    .collect()
+}
+
+/// Short explanations of the native flags mpm passes, see
+/// [`explain_flag`].
+const FLAG_EXPLANATIONS: &[(&str, &str)] = &[
+    ("--yes", "answer yes to all prompts"),
+    ("-y", "answer yes to all prompts"),
+    ("-n", "do not ask any questions"),
+    (
+        "--only-upgrade",
+        "only upgrade packages that are already installed",
+    ),
+    ("--installed", "only list installed packages"),
+    ("-i", "only list installed packages"),
+    ("--upgradable", "only list packages that can be upgraded"),
+    ("--limit-output", "print machine readable output"),
+    ("-q", "print no progress information"),
+    ("--no-refresh", "do not refresh the repositories first"),
+    ("--xmlout", "print XML output"),
+    ("--if-not-exists", "do nothing if the remote already exists"),
+    ("--ipv4", "connect over IPv4 only"),
+    ("--setopt=ip_resolve=4", "connect to mirrors over IPv4 only"),
+    (
+        "Acquire::ForceIPv4=true",
+        "connect to mirrors over IPv4 only",
+    ),
+    ("-o", "set the configuration option that follows"),
+    ("--params", "package parameters"),
+    ("--install-arguments", "arguments for the native installer"),
+];
+
+/// Explanation of a native flag, looking up flags with a value like
+/// `--params=...` by their name.
+fn explain_flag(flag: &str) -> Option<&'static str> {
+    let lookup = |f: &str| {
+        FLAG_EXPLANATIONS
+            .iter()
+            .find(|(name, _)| *name == f)
+            .map(|(_, text)| *text)
+    };
+    lookup(flag).or_else(|| lookup(flag.split_once('=')?.0))
+}
+
+/// Print commands the way they would be typed in a shell, followed by an
+/// explanation of their flags.
+fn print_explained(cmds: &[Vec<String>]) {
+    let mut flags: Vec<(&str, &str)> = vec![];
+    for cmd in cmds {
+        let line: Vec<String> = cmd
+            .iter()
+            .map(|arg| {
+                if arg.contains(char::is_whitespace) {
+                    format!("'{arg}'")
+                } else {
+                    arg.clone()
+                }
+            })
+            .collect();
+        println!("{}", line.join(" "));
+        for arg in cmd.iter().skip(1) {
+            if let Some(text) = explain_flag(arg) {
+                if !flags.iter().any(|(f, _)| f == arg) {
+                    flags.push((arg, text));
+                }
+            }
+        }
+    }
+    let width = flags.iter().map(|(f, _)| f.len()).max().unwrap_or(0);
+    for (flag, text) in flags {
+        println!("  {flag:width$}  {text}");
+    }
+}
+
+/// Verify the checksum and signature of `pkg`, if it has any, with a copy in
+/// the private directory `dir`, created when first needed, that the package
+/// then points to, see [`Package::make_verified_copy`]. The directory has to
+/// be kept until the package is installed.
+fn verify_package(pkg: &mut Package, dir: &mut Option<tempfile::TempDir>) -> anyhow::Result<()> {
+    if !pkg.needs_verification() {
+        return Ok(());
+    }
+    anyhow::ensure!(
+        pkg.url().is_some(),
+        "Checksums and signatures can only be verified for package files and URLs, not {}",
+        pkg.name()
+    );
+    let dir = match dir {
+        Some(dir) => dir,
+        None => dir.insert(tempfile::Builder::new().prefix("mpm-").tempdir()?),
+    };
+    pkg.make_verified_copy(dir.path())?;
+    Ok(())
+}
+
+/// Verify the signature of a package file, downloading it first if the
+/// package is a remote URL.
+fn ensure_valid_signature<P: PackageManager>(mpm: &P, pkg: &mut Package) -> anyhow::Result<()> {
+    let path = match pkg.url() {
+        Some(url) if url.scheme() == "file" => url
+            .to_file_path()
+            .map_err(|_| anyhow::anyhow!("invalid file URL {url}"))?,
+        Some(_) => pkg.make_available_on_disk(None, false)?,
+        None => anyhow::bail!(
+            "Signatures can only be verified for package files and URLs, not {}",
+            pkg.name()
+        ),
+    };
+    anyhow::ensure!(
+        mpm.verify_package_signature(&path)?,
+        "Signature verification of {path:?} failed"
+    );
+    tracing::info!("Signature of {path:?} is valid");
+    Ok(())
+}
+
+/// Refuse to install a package file that `mpm` cannot install on a host with
+/// the architecture `arch`, named like [`std::env::consts::ARCH`].
+fn ensure_installable_file<P: PackageManager>(
+    mpm: &P,
+    path: &std::path::Path,
+    arch: &str,
+) -> anyhow::Result<()> {
+    // package managers without package files decide for themselves
+    if mpm.supported_pkg_formats().is_empty() {
+        return Ok(());
+    }
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    anyhow::ensure!(
+        mpm.supported_pkg_formats_for(arch)
+            .iter()
+            .any(|fmt| name.ends_with(&format!(".{}", fmt.file_extention()))),
+        "{} cannot install {path:?} on {arch}",
+        mpm.pkg_manager_name()
+    );
+    if let Some(file_arch) = crate::common::pkg_file_arch(path) {
+        anyhow::ensure!(
+            file_arch == arch,
+            "{path:?} is built for {file_arch}, not {arch}"
+        );
+    }
+    Ok(())
+}
+
+/// Revert the history entry with the given id, or the last one of `mpm`, see
+/// [`crate::history::rollback_target`], and record the rollback.
+fn rollback<P: PackageManager>(
+    mpm: &P,
+    id: Option<usize>,
+    interactive: bool,
+) -> anyhow::Result<RollbackReport> {
+    let history = History::try_default()?;
+    let entries = history.entries()?;
+    let manager = mpm.pkg_manager_name();
+    let entry = crate::history::rollback_target(&entries, &manager, id)?;
+    let id = entry.id.context("history entry without id")?;
+    let mut report = RollbackReport {
+        id,
+        ..Default::default()
+    };
+    let mut outcome = |change: String, result: anyhow::Result<()>| match result {
+        Ok(()) => report.reverted.push(change),
+        Err(e) => report.failed.push(format!("{change}: {e}")),
+    };
+    let status = |s: std::process::ExitStatus| {
+        anyhow::ensure!(s.success(), "{s}");
+        Ok(())
+    };
+    for name in &entry.packages {
+        let pkg = match Package::from_str(name) {
+            Ok(pkg) => pkg,
+            Err(e) => {
+                outcome(format!("revert {name}"), Err(e));
+                continue;
+            }
+        };
+        match entry.operation.as_str() {
+            // whichever version is installed now
+            "install" => outcome(
+                format!("uninstall {name}"),
+                status(mpm.uninstall(
+                    Package::new(pkg.name(), pkg.package_manager().to_string(), None),
+                    interactive,
+                )),
+            ),
+            "uninstall" => outcome(
+                format!("install {name}"),
+                status(mpm.install(pkg, interactive)),
+            ),
+            "pin" => outcome(format!("unpin {name}"), mpm.unpin(&pkg)),
+            "unpin" => outcome(format!("pin {name}"), mpm.pin(&pkg)),
+            _ => (),
+        }
+    }
+    // the previous versions are only known to the package manager
+    if matches!(entry.operation.as_str(), "update" | "autoremove") {
+        let change = format!("undo {}", entry.operation);
+        match &entry.transaction {
+            Some(transaction) => outcome(
+                change,
+                mpm.undo_transaction(transaction, interactive)
+                    .and_then(status),
+            ),
+            None => outcome(
+                change,
+                Err(anyhow::anyhow!(
+                    "{manager} recorded no transaction that could be undone"
+                )),
+            ),
+        }
+    }
+
+    let exit_code = if report.failed.is_empty() { 0 } else { 1 };
+    let entry = HistoryEntry::new(
+        manager,
+        crate::history::ROLLBACK,
+        vec![id.to_string()],
+        exit_code,
+    );
+    if let Err(e) = history.record(&entry) {
+        tracing::warn!("Failed to record the rollback in the history: {e}");
+    }
+    Ok(report)
+}
+
+/// Refuse to install a package whose estimated size exceeds `max_size`
+/// megabytes, unless the user confirms it in interactive mode.
+fn ensure_size_within<P: PackageManager>(
+    mpm: &P,
+    pkg: &Package,
+    max_size: u64,
+    interactive: bool,
+) -> anyhow::Result<()> {
+    let Some(size) = mpm.estimate_operation_size(pkg) else {
+        tracing::warn!("Could not estimate the size of {}", pkg.name());
+        return Ok(());
+    };
+    if size <= max_size.saturating_mul(1_000_000) {
+        return Ok(());
+    }
+    let mb = size as f64 / 1e6;
+    let msg = format!("{} needs {mb:.1} MB, more than {max_size} MB", pkg.name());
+    anyhow::ensure!(interactive, "{msg}");
+    anyhow::ensure!(confirm(&format!("{msg}. Continue?"))?, "{msg}");
+    Ok(())
+}
+
+/// Write a man page for mpm and each of its subcommands to `out_dir` and
+/// return their paths. Pages of subcommands are named after the command path,
+/// e.g. `mpm-cache-clear.1`.
+fn write_manpages(out_dir: &std::path::Path) -> anyhow::Result<Vec<PathBuf>> {
+    fn write(
+        cmd: &clap::Command,
+        out_dir: &std::path::Path,
+        written: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        let name = cmd.get_display_name().unwrap_or(cmd.get_name());
+        let path = out_dir.join(format!("{name}.1"));
+        let mut page = vec![];
+        clap_mangen::Man::new(cmd.clone()).render(&mut page)?;
+        std::fs::write(&path, page).with_context(|| format!("failed to write {path:?}"))?;
+        written.push(path);
+        for sub in cmd.get_subcommands().filter(|sub| sub.get_name() != "help") {
+            write(sub, out_dir, written)?;
+        }
+        Ok(())
+    }
+
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("failed to create directory {out_dir:?}"))?;
+    let mut cmd = Cli::command();
+    // sets the display names and propagates global arguments to subcommands
+    cmd.build();
+    let mut written = vec![];
+    write(&cmd, out_dir, &mut written)?;
+    Ok(written)
+}
+
+/// Progress bar on stderr for packages downloaded from URLs, with the ETA if
+/// the size is known. Hidden if stderr is not a terminal.
+#[cfg(feature = "cli")]
+fn download_progress_bar() -> crate::common::DownloadReporter {
+    use indicatif::{ProgressBar, ProgressStyle};
+
+    let current: std::sync::Mutex<Option<ProgressBar>> = std::sync::Mutex::new(None);
+    std::sync::Arc::new(move |progress: &crate::common::DownloadProgress| {
+        let Ok(mut current) = current.lock() else {
+            return;
+        };
+        // a failed download leaves its bar behind
+        if progress.downloaded == 0 {
+            if let Some(stale) = current.take() {
+                stale.abandon();
+            }
+        }
+        let bar = current.get_or_insert_with(|| {
+            let (bar, template) = match progress.total {
+                Some(total) => (
+                    ProgressBar::new(total),
+                    "{msg} [{bar:30}] {bytes}/{total_bytes} {bytes_per_sec}, ETA {eta}",
+                ),
+                None => (
+                    ProgressBar::new_spinner(),
+                    "{msg} {spinner} {bytes} {bytes_per_sec}",
+                ),
+            };
+            if let Ok(style) = ProgressStyle::with_template(template) {
+                bar.set_style(style.progress_chars("=> "));
+            }
+            let name = progress
+                .url
+                .path_segments()
+                .and_then(|segments| segments.last())
+                .unwrap_or_default();
+            bar.set_message(name.to_string());
+            bar
+        });
+        bar.set_position(progress.downloaded);
+        if progress.done {
+            bar.finish();
+            *current = None;
+        }
+    })
+}
+
+/// Let the user pick packages to install from the search results `pkgs`.
+#[cfg(feature = "cli")]
+fn pick_and_install<P: PackageManager>(
+    mpm: &P,
+    pkgs: &[Package],
+    interactive: bool,
+) -> anyhow::Result<Vec<String>> {
+    use std::io::IsTerminal;
+
+    anyhow::ensure!(std::io::stdout().is_terminal(), "--pick needs a terminal");
+    if pkgs.is_empty() {
+        return Ok(vec![]);
+    }
+    let items: Vec<String> = pkgs
+        .iter()
+        .map(|p| format!("{} {}", p.name(), p.version().unwrap_or_default()))
+        .collect();
+    let selected = dialoguer::MultiSelect::new()
+        .with_prompt("Select packages to install")
+        .items(&items)
+        .interact()?;
+    install_selected(mpm, pkgs, &selected, interactive)
+}
+
+/// Install the latest version of the packages at the `selected` indices of
+/// `pkgs` together, like `mpm install`, and return their names.
+#[cfg(feature = "cli")]
+fn install_selected<P: PackageManager>(
+    mpm: &P,
+    pkgs: &[Package],
+    selected: &[usize],
+    interactive: bool,
+) -> anyhow::Result<Vec<String>> {
+    let names = selected
+        .iter()
+        .map(|&i| {
+            pkgs.get(i)
+                .map(|pkg| pkg.name().to_string())
+                .ok_or_else(|| anyhow::anyhow!("no search result at index {i}"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let selected = names
+        .iter()
+        .map(|name| Package::from_str(name))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if !mpm.install_many(&selected, interactive).success() {
+        return Err(install_error(mpm, &selected));
+    }
+    Ok(names)
+}
+
+/// Show the native commands `command` runs, if known, and ask whether to run
+/// them.
+fn confirm_command<P: PackageManager>(
+    mpm: &P,
+    command: &MpmPackageManagerCommands,
+) -> anyhow::Result<()> {
+    match explain(mpm, command) {
+        Ok(cmds) => {
+            println!("mpm will run:");
+            for cmd in &cmds {
+                let line: Vec<_> = cmd.iter().map(|arg| crate::shell_quote(arg)).collect();
+                println!("  {}", line.join(" "));
+            }
+        }
+        Err(_) => println!("mpm will change the packages installed with {mpm}"),
+    }
+    anyhow::ensure!(confirm("Continue?")?, "Aborted");
+    Ok(())
+}
+
+/// Ask a yes/no question on the terminal. Anything but `y` or `yes` is a no.
+fn confirm(question: &str) -> anyhow::Result<bool> {
+    use std::io::Write;
+
+    print!("{question} [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn list_all_packages<F>(package_lister: F) -> Vec<Package>
+where
+    F: Fn(&MetaPackageManager) -> Vec<Package>,
+{
+    let mut all_packages = HashSet::new();
+    // Nala lists the packages of apt
+    for pm in AvailablePackageManager::iter().filter(|m| *m != AvailablePackageManager::Nala) {
+        let mpm = MetaPackageManager::new(pm.clone());
+        if mpm.is_available() {
+            let packages = package_lister(&mpm);
+            all_packages.extend(packages);
+        }
+    }
+    all_packages.into_iter().collect()
+}
+
+/// Sync the repositories of all of the available package managers in
+/// parallel, see [`sync_each`]. The commands of those that need it are run
+/// with `sudo`, see [`PackageManagerCommands::elevated`].
+fn sync_all() -> anyhow::Result<Vec<(String, bool)>> {
+    // Nala syncs the repositories of apt and would wait for its lock
+    let managers: Vec<MetaPackageManager> = AvailablePackageManager::iter()
+        .filter(|m| *m != AvailablePackageManager::Nala)
+        .map(MetaPackageManager::new)
+        .filter(|mpm| mpm.is_available())
+        .collect();
+    Ok(sync_each(&managers))
+}
+
+/// Sync the repositories of each of `managers` in a thread of its own and
+/// return whether each of them succeeded, in the order of `managers`. The
+/// output of each package manager is printed once it is done, in that order.
+fn sync_each<P: PackageManager + Sync>(managers: &[P]) -> Vec<(String, bool)> {
+    std::thread::scope(|scope| {
+        let syncs: Vec<_> = managers
+            .iter()
+            .map(|mpm| scope.spawn(move || crate::common::capture_output(|| mpm.sync().success())))
+            .collect();
+        syncs
+            .into_iter()
+            .zip(managers)
+            .map(|(sync, mpm)| {
+                let success = match sync.join() {
+                    Ok((success, output)) => {
+                        for line in output {
+                            println!("{line}");
+                        }
+                        success
+                    }
+                    Err(_) => false,
+                };
+                (mpm.pkg_manager_name(), success)
+            })
+            .collect()
+    })
+}
+
+/// Search `query` with all of the available package managers in parallel,
+/// see [`search_each`].
+fn search_all(query: &str, limit: Option<usize>, show_installed: bool) -> Vec<Package> {
+    // Nala searches the packages of apt
+    let managers: Vec<MetaPackageManager> = AvailablePackageManager::iter()
+        .filter(|m| *m != AvailablePackageManager::Nala)
+        .map(MetaPackageManager::new)
+        .filter(|mpm| mpm.is_available())
+        .collect();
+    search_each(&managers, query, limit, show_installed)
+}
+
+/// Search `query` with each of `managers` in a thread of its own, with at
+/// most `limit` results per package manager, and merge the results in the
+/// order of `managers`. Package managers that fail to search are skipped.
+fn search_each<P: PackageManager + Sync>(
+    managers: &[P],
+    query: &str,
+    limit: Option<usize>,
+    show_installed: bool,
+) -> Vec<Package> {
+    std::thread::scope(|scope| {
+        let searches: Vec<_> = managers
+            .iter()
+            .map(|mpm| {
+                scope.spawn(move || {
+                    let mut pkgs = match limit {
+                        Some(limit) => mpm.search_limited(query, limit).unwrap_or_else(|e| {
+                            tracing::warn!("{} failed to search: {e}", mpm.pkg_manager_name());
+                            vec![]
+                        }),
+                        None => mpm.search(query),
+                    };
+                    if show_installed {
+                        mpm.mark_installed(&mut pkgs);
+                    }
+                    pkgs
+                })
+            })
+            .collect();
+        searches
+            .into_iter()
+            .zip(managers)
+            .flat_map(|(search, mpm)| {
+                search.join().unwrap_or_else(|_| {
+                    tracing::warn!("{} failed to search", mpm.pkg_manager_name());
+                    vec![]
+                })
+            })
+            .collect()
+    })
+}
+
+/// List all of the installed packages from all of the available package
+/// managers
+fn list_all_installed() -> Vec<Package> {
+    list_all_packages(|mpm| mpm.list_installed())
+}
+
+/// List all of the outdated packages from all of the available package
+/// managers
+fn list_all_outdated() -> Vec<Package> {
+    list_all_packages(|mpm| mpm.list_outdated())
+}
+
+/// Get the input file format
+fn get_file_type(file: &PathBuf) -> FileFormat {
+    match file.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => FileFormat::Json,
+        Some("toml") => FileFormat::Toml,
+        _ => FileFormat::None,
+    }
+}
+
+/// Install a list of packages from a given input file and return their names
+///
+/// With `transactional`, the packages of each package manager are installed
+/// with [``PackageManager::install_transactional``].
+fn install_from_file(
+    input_file: &PathBuf,
+    file_type: FileFormat,
+    config: &Config,
+    interactive: bool,
+    transactional: bool,
+    resume: bool,
+) -> anyhow::Result<Vec<String>> {
+    type PackageMap = HashMap<String, HashMap<String, String>>;
+
+    let file_contents = std::fs::read_to_string(input_file)?;
+
+    let parsed: PackageMap = match file_type {
+        FileFormat::Json => serde_json::from_str(&file_contents)?,
+        FileFormat::Toml => toml::from_str(&file_contents)?,
+        FileFormat::None => {
+            anyhow::bail!("{input_file:?} is neither a .json nor a .toml file")
+        }
+    };
+    // unknown package managers fail before anything is installed
+    let parsed = parsed
+        .into_iter()
+        .map(|(package_manager, packages)| -> anyhow::Result<_> {
+            let pm = package_manager
+                .parse::<AvailablePackageManager>()
+                .map_err(|_| {
+                    anyhow::anyhow!("unknown package manager {package_manager:?} in {input_file:?}")
+                })?;
+            Ok((pm, packages))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut installed = vec![];
+    for (pm, packages) in parsed {
+        let mpm = MetaPackageManager::new(pm);
+        if !mpm.is_available() {
+            continue;
+        }
+        let mut names: Vec<String> = packages.into_keys().collect();
+        names.sort();
+
+        if transactional {
+            if resume {
+                let before = crate::traits::installed_names(&mpm);
+                names.retain(|name| !before.contains(name));
+            }
+            let mut verified_dir = None;
+            let pkgs = names
+                .iter()
+                .cloned()
+                .map(|name| {
+                    let mut pkg = Package::try_from(name)?;
+                    verify_package(&mut pkg, &mut verified_dir)?;
+                    Ok(alias_package(pkg, &mpm.pkg_manager_name(), config))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            mpm.install_transactional(&pkgs)?;
+            installed.extend(names);
+            continue;
+        }
+
+        installed.extend(install_manifest_packages(
+            &mpm,
+            names,
+            config,
+            interactive,
+            resume,
+        )?);
+    }
+
+    Ok(installed)
+}
+
+/// Install the packages `names` of an input file with `mpm` and return the
+/// installed ones.
+///
+/// With `resume`, packages that are already installed are skipped, and a
+/// failing package does not stop the remaining ones from being installed.
+/// The packages that failed are reported at the end. Names are translated with
+/// the config aliases.
+fn install_manifest_packages<P: PackageManager>(
+    mpm: &P,
+    names: Vec<String>,
+    config: &Config,
+    interactive: bool,
+    resume: bool,
+) -> anyhow::Result<Vec<String>> {
+    let before = if resume {
+        crate::traits::installed_names(mpm)
+    } else {
+        HashSet::new()
+    };
+    let mut installed = vec![];
+    let mut failed = vec![];
+    let mut verified_dir = None;
+    for name in names {
+        let mut pkg = Package::try_from(name.clone())?;
+        verify_package(&mut pkg, &mut verified_dir)?;
+        let pkg = alias_package(pkg, &mpm.pkg_manager_name(), config);
+        if before.contains(pkg.name()) {
+            tracing::info!("Skipping {name}: already installed");
+            continue;
+        }
+        if mpm.install(pkg, interactive).success() {
+            installed.push(name);
+        } else if resume {
+            tracing::error!("Failed to install {name}");
+            failed.push(name);
+        } else {
+            anyhow::bail!("Failed to install {name}");
+        }
+    }
+    if !failed.is_empty() {
+        return Err(Error::PartialFailure(format!(
+            "Failed to install {}; run again with --resume to retry them",
+            failed.join(", ")
+        ))
+        .into());
+    }
+    Ok(installed)
+}
+
+/// Error for a failed install of `pkgs`, caused by
+/// [`Error::PermissionDenied`] if the package manager said so, or by
+/// [`Error::PackageNotFound`] if it did not know a package and `mpm` finds
+/// no package of the name of one of them.
+fn install_error<P: PackageManager>(mpm: &P, pkgs: &[Package]) -> anyhow::Error {
+    let names: Vec<&str> = pkgs.iter().map(Package::name).collect();
+    let msg = format!("Failed to install {}", names.join(", "));
+    let stderr = crate::common::last_stderr();
+    if let Some(reason) = crate::error::permission_denied(&stderr) {
+        return anyhow::Error::from(Error::PermissionDenied(reason.to_string())).context(msg);
+    }
+    if !crate::error::not_found(&stderr) {
+        return anyhow::anyhow!(msg);
+    }
+    // package files and URLs are not in the repositories
+    let missing = pkgs.iter().find(|pkg| {
+        pkg.url().is_none()
+            && !mpm
+                .search(pkg.name())
+                .iter()
+                .any(|p| p.name() == pkg.name())
+    });
+    match missing {
+        Some(pkg) => {
+            anyhow::Error::from(Error::PackageNotFound(pkg.name().to_string())).context(msg)
+        }
+        None => anyhow::anyhow!(msg),
+    }
+}
+
+/// elevates to sudo
+fn sudo() -> anyhow::Result<()> {
+    #[cfg(target_os = "linux")]
+    if let Err(e) = sudo::with_env(&["CARGO_", "RUST_LOG"]) {
+        return Err(Error::PermissionDenied(format!("failed to elevate to sudo: {e}")).into());
+    }
+    Ok(())
+}
+
+/// install default package manager.
+#[cfg(windows)]
+fn install_default_manager() -> anyhow::Result<()> {
+    println!("Installing choco package manager...");
+    let script = "Set-ExecutionPolicy Bypass -Scope Process -Force; [System.Net.ServicePointManager]::SecurityProtocol = [System.Net.ServicePointManager]::SecurityProtocol -bor 3072; iex ((New-Object System.Net.WebClient).DownloadString('https://community.chocolatey.org/install.ps1'))";
+    let status = run_script_rs::run_script(script, false)?;
+    anyhow::ensure!(status.success(), "Command failed to install choco");
+    tracing::info!("{status:?}");
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn install_default_manager() -> anyhow::Result<()> {
+    println!("Installing homebrew on this system...");
+    let script = "curl -fsSL https://raw.githubusercontent.com/Homebrew/install/HEAD/install.sh";
+    let status = run_script_rs::run_script(script, false)?;
+    anyhow::ensure!(status.success(), "Command failed to install homebrew");
+    tracing::info!("{status:?}");
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn install_default_manager() -> anyhow::Result<()> {
+    println!("This command does nothing on linux.");
+    Ok(())
+}
+
+#[cfg(target_os = "android")]
+fn install_default_manager() -> anyhow::Result<()> {
+    println!("This command does nothing on android.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::{Command, Output};
+
+    use super::*;
+    use crate::{Cmd, PkgFormat};
+
+    /// Package manager that answers `list` with `installed` and every other
+    /// command with `stdout`.
+    #[derive(Debug)]
+    struct MockManager {
+        stdout: &'static str,
+        installed: &'static str,
+    }
+
+    impl std::fmt::Display for MockManager {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("mock")
+        }
+    }
+
+    impl PackageManagerCommands for MockManager {
+        fn cmd(&self) -> Command {
+            Command::new("")
+        }
+        fn get_cmds(&self, cmd: Cmd, _: Option<&Package>) -> Vec<String> {
+            vec![format!("{cmd:?}")]
+        }
+        fn exec_cmds(&self, cmds: &[String]) -> Output {
+            let stdout = match cmds.first().map(String::as_str) {
+                Some("List") => self.installed,
+                _ => self.stdout,
+            };
+            Output {
+                status: crate::common::synthetic_exit_status(true),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: vec![],
+            }
+        }
+        fn exec_cmds_status<S>(&self, _: &[S], _: Option<bool>) -> std::process::ExitStatus
+        where
+            S: AsRef<str> + std::fmt::Debug + AsRef<std::ffi::OsStr>,
+        {
+            crate::common::synthetic_exit_status(true)
+        }
+    }
+
+    impl PackageManager for MockManager {
+        fn pkg_delimiter(&self) -> char {
+            '+'
+        }
+        fn pkg_manager_name(&self) -> String {
+            "mock".to_string()
+        }
+        fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+            vec![]
+        }
+    }
+
+    /// Package manager that records the commands it runs and answers `list`
+    /// with `installed`.
+    #[derive(Debug, Default)]
+    struct RecordingManager {
+        installed: &'static str,
+        cmds: std::cell::RefCell<Vec<Vec<String>>>,
+    }
+
+    impl std::fmt::Display for RecordingManager {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("recording")
+        }
+    }
+
+    impl PackageManagerCommands for RecordingManager {
+        fn cmd(&self) -> Command {
+            Command::new("")
+        }
+        fn get_cmds(&self, cmd: Cmd, _: Option<&Package>) -> Vec<String> {
+            vec![format!("{cmd:?}")]
+        }
+        fn exec_cmds(&self, _: &[String]) -> Output {
+            Output {
+                status: crate::common::synthetic_exit_status(true),
+                stdout: self.installed.as_bytes().to_vec(),
+                stderr: vec![],
+            }
+        }
+        fn exec_cmds_status<S>(&self, cmds: &[S], _: Option<bool>) -> std::process::ExitStatus
+        where
+            S: AsRef<str> + std::fmt::Debug + AsRef<std::ffi::OsStr>,
+        {
+            let cmds: Vec<String> = cmds
+                .iter()
+                .map(|s| AsRef::<str>::as_ref(s).to_string())
+                .collect();
+            // packages named `broken` fail to install
+            let success = !cmds.iter().any(|c| c == "broken");
+            self.cmds.borrow_mut().push(cmds);
+            crate::common::synthetic_exit_status(success)
+        }
+    }
+
+    impl PackageManager for RecordingManager {
+        fn pkg_delimiter(&self) -> char {
+            '+'
+        }
+        fn pkg_manager_name(&self) -> String {
+            "recording".to_string()
+        }
+        fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+            vec![]
+        }
+        fn download_updates(&self) -> anyhow::Result<std::process::ExitStatus> {
+            Ok(self.exec_cmds_status(&["DownloadUpdates"], None))
+        }
+        fn apply_downloaded_updates(&self) -> anyhow::Result<std::process::ExitStatus> {
+            Ok(self.exec_cmds_status(&["ApplyDownloadedUpdates"], None))
+        }
+    }
+
+    #[test]
+    fn test_history_operation() {
+        let args = Cli::parse_from(["mpm", "install", "htop", "tmux@3.4"]);
+        assert_eq!(
+            args.command.history_operation(),
+            Some(("install", vec!["htop".to_string(), "tmux@3.4".to_string()]))
+        );
+        let args = Cli::parse_from(["mpm", "install", "--emit-script", "htop"]);
+        assert_eq!(args.command.history_operation(), None);
+        let args = Cli::parse_from(["mpm", "pin"]);
+        assert_eq!(args.command.history_operation(), None);
+        let args = Cli::parse_from(["mpm", "update", "--all"]);
+        assert_eq!(args.command.history_operation(), Some(("update", vec![])));
+        let args = Cli::parse_from(["mpm", "update", "--all", "--download-only"]);
+        assert_eq!(args.command.history_operation(), None);
+    }
+
+    #[test]
+    fn test_update_downloaded() {
+        let mpm = RecordingManager::default();
+        let args = Cli::parse_from(["mpm", "update", "--all", "--download-only"]);
+        assert_eq!(
+            execute_with_manager(&mpm, args, &Config::default()).unwrap(),
+            ExecuteOutcome::UpdatesDownloaded
+        );
+        assert_eq!(mpm.cmds.take(), [["DownloadUpdates"]]);
+
+        let args = Cli::parse_from(["mpm", "update", "--all", "--apply-downloaded"]);
+        assert_eq!(
+            execute_with_manager(&mpm, args, &Config::default()).unwrap(),
+            ExecuteOutcome::UpdatedAll
+        );
+        assert_eq!(mpm.cmds.take(), [["ApplyDownloadedUpdates"]]);
+
+        assert!(Cli::try_parse_from(["mpm", "update", "--download-only"]).is_err());
+        assert!(Cli::try_parse_from([
+            "mpm",
+            "update",
+            "--all",
+            "--download-only",
+            "--apply-downloaded"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_history_groups() {
+        let mpm = RecordingManager {
+            installed: "htop+3.3.0\n",
+            ..Default::default()
+        };
+        let packages = vec!["htop".to_string(), "brew:wget".into(), "tmux".into()];
+        let groups = history_groups(&mpm, packages, &Config::default(), false);
+        assert_eq!(
+            groups,
+            [
+                ("recording".to_string(), vec!["htop".into(), "tmux".into()]),
+                ("brew".to_string(), vec!["brew:wget".into()])
+            ]
+        );
+        assert_eq!(
+            history_groups(&mpm, vec![], &Config::default(), false),
+            [("recording".to_string(), vec![])]
+        );
+        // only the `manager:` prefix selects another package manager
+        assert_eq!(
+            history_groups(
+                &mpm,
+                vec!["brew@wget@1.0".into()],
+                &Config::default(),
+                false
+            ),
+            [("recording".to_string(), vec!["brew@wget@1.0".into()])]
+        );
+
+        // packages that are not installed keep the given name
+        let packages = vec!["htop".to_string(), "tmux@3.4".into()];
+        assert_eq!(
+            with_installed_versions(&mpm, "recording", packages, &Config::default()),
+            ["htop@3.3.0", "tmux@3.4"]
+        );
+    }
+
+    #[test]
+    fn test_write_manpages() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = write_manpages(dir.path()).unwrap();
+        for name in ["mpm.1", "mpm-install.1", "mpm-cache-clear.1"] {
+            assert!(written.contains(&dir.path().join(name)), "{name} missing");
+        }
+        assert!(!written.contains(&dir.path().join("mpm-help.1")));
+        let page = std::fs::read_to_string(dir.path().join("mpm-install.1")).unwrap();
+        assert!(page.contains(".TH"), "{page}");
+    }
+
+    #[test]
+    fn test_dry_run_support() {
+        let args = Cli::parse_from(["mpm", "--dry-run", "install", "htop"]);
+        assert!(args.dry_run);
+        assert!(args.command.supports_dry_run());
+        let args = Cli::parse_from(["mpm", "--dry-run", "pin", "htop"]);
+        assert!(args.command.modifies_system() && !args.command.supports_dry_run());
+        let args = Cli::parse_from(["mpm", "--dry-run", "list"]);
+        assert!(!args.command.modifies_system());
+        let args = Cli::parse_from(["mpm", "repo", "list"]);
+        assert!(!args.command.modifies_system());
+        let args = Cli::parse_from(["mpm", "repo", "remove", "flathub"]);
+        assert!(args.command.modifies_system() && !args.command.supports_dry_run());
+        // `mpm repo <repo>` from before `repo add`
+        let args = Cli::parse_from(["mpm", "repo", "ppa:foo/bar", "ppa:foo/baz"]);
+        assert!(args.command.modifies_system());
+        assert!(matches!(
+            args.command,
+            MpmPackageManagerCommands::Repo {
+                command: RepoCommands::AddPositional(repo)
+            } if repo == ["ppa:foo/bar", "ppa:foo/baz"]
+        ));
+        // the same flag after the subcommand
+        let args = Cli::parse_from(["mpm", "clean", "--dry-run"]);
+        assert!(args.dry_run && args.command.supports_dry_run());
+        let args = Cli::parse_from(["mpm", "snapshot", "restore", "base", "--dry-run"]);
+        assert!(args.dry_run && args.command.supports_dry_run());
+    }
+
+    #[test]
+    fn test_verify_args() {
+        let args = Cli::parse_from(["mpm", "verify", "openssh-server"]);
+        assert!(matches!(
+            args.command,
+            MpmPackageManagerCommands::Verify {
+                package: Some(_),
+                all: false
+            }
+        ));
+        let args = Cli::parse_from(["mpm", "verify", "--all"]);
+        assert!(matches!(
+            args.command,
+            MpmPackageManagerCommands::Verify {
+                package: None,
+                all: true
+            }
+        ));
+        assert!(Cli::try_parse_from(["mpm", "verify"]).is_err());
+        assert!(Cli::try_parse_from(["mpm", "verify", "--all", "bash"]).is_err());
+    }
+
+    #[test]
+    fn test_checksum_args() {
+        let args = Cli::parse_from([
+            "mpm",
+            "install",
+            "https://example.com/hello.deb",
+            "--checksum",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            "--signature",
+            "https://example.com/hello.deb.asc",
+        ]);
+        match args.command {
+            MpmPackageManagerCommands::Install {
+                checksum,
+                signature,
+                ..
+            } => {
+                assert!(checksum.unwrap().starts_with("ba7816bf"));
+                assert_eq!(signature.unwrap().path(), "/hello.deb.asc");
+            }
+            _ => panic!("expected install"),
+        }
+        assert!(
+            Cli::try_parse_from(["mpm", "install", "hello.deb", "--signature", "not-a-url"])
+                .is_err()
+        );
+        assert!(Cli::try_parse_from([
+            "mpm",
+            "install",
+            "--input-file",
+            "pkgs.txt",
+            "--checksum",
+            "ba78"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_locked() {
+        let locked = |args: &[&str]| match Cli::parse_from(args).command {
+            MpmPackageManagerCommands::Install { locked, .. } => locked,
+            _ => panic!("expected install"),
+        };
+        assert_eq!(
+            locked(&["mpm", "install", "--locked"]),
+            Some(PathBuf::from(crate::lockfile::DEFAULT_PATH))
+        );
+        assert_eq!(
+            locked(&["mpm", "install", "--locked=dev.lock"]),
+            Some(PathBuf::from("dev.lock"))
+        );
+        // a package is not taken for the lockfile
+        assert!(Cli::try_parse_from(["mpm", "install", "--locked", "wget"]).is_err());
+    }
+
+    #[test]
+    fn test_output_format() {
+        let args = Cli::parse_from(["mpm", "list"]);
+        assert_eq!(args.output_format(), OutputFormat::Table);
+        let args = Cli::parse_from(["mpm", "--output", "yaml", "list", "--file-format", "toml"]);
+        assert_eq!(args.output_format(), OutputFormat::Yaml);
+        assert!(matches!(
+            args.command,
+            MpmPackageManagerCommands::List {
+                file_format: Some(FileFormat::Toml),
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["mpm", "list", "--output", "toml"]).is_err());
+        let args = Cli::parse_from(["mpm", "--json", "search", "foo"]);
+        assert_eq!(args.output_format(), OutputFormat::Json);
+        assert!(Cli::try_parse_from(["mpm", "--json", "--output", "csv", "list"]).is_err());
+        // the output format is accepted after the subcommand too
+        let args = Cli::parse_from(["mpm", "list", "--output", "json"]);
+        assert_eq!(args.output_format(), OutputFormat::Json);
+        let args = Cli::parse_from(["mpm", "search", "foo", "--json"]);
+        assert_eq!(args.output_format(), OutputFormat::Json);
+        // `-o` of the file format from before `--output` became global
+        let args = Cli::parse_from(["mpm", "list", "-o", "toml"]);
+        assert_eq!(args.output_format(), OutputFormat::Table);
+        assert!(matches!(
+            args.command,
+            MpmPackageManagerCommands::List {
+                file_format: Some(FileFormat::Toml),
+                ..
+            }
+        ));
+        // `-o` of the lockfile is not the output format
+        let args = Cli::parse_from(["mpm", "lock", "-o", "dev.lock", "--output", "json"]);
+        assert_eq!(args.output_format(), OutputFormat::Json);
+        assert!(matches!(
+            args.command,
+            MpmPackageManagerCommands::Lock { lockfile } if lockfile == PathBuf::from("dev.lock")
+        ));
+    }
+
+    #[test]
+    fn test_run_args() {
+        let args = Cli::parse_from(["mpm", "-m", "apt", "run", "--", "show", "-a", "curl"]);
+        let MpmPackageManagerCommands::Run { args: run_args } = args.command else {
+            panic!("expected run");
+        };
+        assert_eq!(run_args, ["show", "-a", "curl"]);
+        // arguments after the first one are passed as they are
+        let args = Cli::parse_from(["mpm", "run", "list", "--installed"]);
+        let MpmPackageManagerCommands::Run { args: run_args } = args.command else {
+            panic!("expected run");
+        };
+        assert_eq!(run_args, ["list", "--installed"]);
+        assert!(Cli::try_parse_from(["mpm", "run"]).is_err());
+    }
+
+    #[test]
+    fn test_search_outcome() {
+        let mock = MockManager {
+            stdout: "foo+1.0\nfoobar+2.0\n",
+            installed: "",
+        };
+        let args = Cli::parse_from(["mpm", "search", "foo"]);
+        let outcome = execute_with_manager(&mock, args, &Config::default()).unwrap();
+        assert_eq!(
+            outcome,
+            ExecuteOutcome::Search {
+                query: "foo".to_string(),
+                packages: vec![
+                    Package::new("foo", "mock".to_string(), Some("1.0")),
+                    Package::new("foobar", "mock".to_string(), Some("2.0")),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_emit_script() {
+        let apt = crate::managers::apt::AdvancedPackageTool::default();
+        let args = Cli::parse_from(["mpm", "install", "--emit-script", "foo", "bar"]);
+        assert!(!args.command.requires_sudo());
+        let ExecuteOutcome::Script(script) =
+            execute_with_manager(&apt, args, &Config::default()).unwrap()
+        else {
+            panic!("expected a script");
+        };
+        assert!(script.starts_with("#!/usr/bin/env bash\n"));
+        assert!(script.contains("\nset -euo pipefail\n"));
+        assert!(
+            script.ends_with("\napt install --yes foo bar\n"),
+            "{script}"
+        );
+    }
+
+    #[test]
+    fn test_emit_script_config() {
+        let apt = crate::managers::apt::AdvancedPackageTool::default();
+        let config: Config =
+            toml::from_str("[aliases.build-essentials]\napt = \"build-essential\"\n").unwrap();
+        let args = Cli::parse_from([
+            "mpm",
+            "install",
+            "--emit-script",
+            "build-essentials",
+            "curl",
+        ]);
+        let ExecuteOutcome::Script(script) = execute_with_manager(&apt, args, &config).unwrap()
+        else {
+            panic!("expected a script");
+        };
+        assert!(
+            script.ends_with("\napt install --yes build-essential curl\n"),
+            "{script}"
+        );
+    }
+
+    #[test]
+    fn test_emit_script_manager_prefix() {
+        let apt = crate::managers::apt::AdvancedPackageTool::default();
+        // the prefix selects the package manager even with --manager
+        let args = Cli::parse_from([
+            "mpm",
+            "--manager",
+            "apt",
+            "install",
+            "--emit-script",
+            "brew:wget",
+            "curl",
+            "apt:htop",
+        ]);
+        let ExecuteOutcome::Script(script) =
+            execute_with_manager(&apt, args, &Config::default()).unwrap()
+        else {
+            panic!("expected a script");
+        };
+        assert!(script.contains("\nbrew install wget\n"), "{script}");
+        assert!(
+            script.ends_with("\napt install --yes curl htop\n"),
+            "{script}"
+        );
+    }
+
+    #[test]
+    fn test_emit_script_nix_flake_ref() {
+        let nix = crate::managers::nix::Nix;
+        // `github:` is a flake reference rather than the GitHub releases prefix
+        let args = Cli::parse_from([
+            "mpm",
+            "--manager",
+            "nix",
+            "install",
+            "--emit-script",
+            "github:owner/repo#tool",
+            "nix:github:owner/other#tool",
+        ]);
+        let ExecuteOutcome::Script(script) =
+            execute_with_manager(&nix, args, &Config::default()).unwrap()
+        else {
+            panic!("expected a script");
+        };
+        assert!(
+            script.ends_with(" profile install github:owner/repo#tool github:owner/other#tool\n"),
+            "{script}"
+        );
+    }
+
+    #[test]
+    fn test_apply_env() {
+        let apt = || MetaPackageManager::Apt(crate::managers::apt::AdvancedPackageTool::default());
+        let config = Config::default();
+        // apt has no environments
+        let args = Cli::parse_from(["mpm", "--env", "analysis", "install", "curl"]);
+        let err = apply_env(&mut apt(), &args, &config, false).unwrap_err();
+        assert!(err.downcast_ref::<Unsupported>().is_some(), "{err}");
+        // unless a package is routed to conda
+        let args = Cli::parse_from(["mpm", "--env", "analysis", "install", "conda:numpy", "curl"]);
+        assert!(apply_env(&mut apt(), &args, &config, false).is_ok());
+        let args = Cli::parse_from(["mpm", "--env", "analysis", "uninstall", "conda:numpy"]);
+        assert!(apply_env(&mut apt(), &args, &config, false).is_ok());
+        let args = Cli::parse_from(["mpm", "--env", "analysis", "list"]);
+        assert!(apply_env(&mut apt(), &args, &config, false).is_err());
+    }
+
+    #[test]
+    fn test_emit_script_env() {
+        let apt = crate::managers::apt::AdvancedPackageTool::default();
+        let args = Cli::parse_from([
+            "mpm",
+            "--env",
+            "analysis",
+            "install",
+            "--emit-script",
+            "conda:numpy",
+            "curl",
+        ]);
+        let ExecuteOutcome::Script(script) =
+            execute_with_manager(&apt, args, &Config::default()).unwrap()
+        else {
+            panic!("expected a script");
+        };
+        // only conda has environments
+        assert!(
+            script.contains(" install --yes --name analysis numpy\n"),
+            "{script}"
+        );
+        assert!(script.ends_with("\napt install --yes curl\n"), "{script}");
+    }
+
+    #[test]
+    fn test_explain() {
+        let apt = crate::managers::apt::AdvancedPackageTool::default();
+        let args = Cli::parse_from(["mpm", "explain", "install", "firefox"]);
+        assert_eq!(
+            execute_with_manager(&apt, args, &Config::default()).unwrap(),
+            ExecuteOutcome::Explained(vec![vec![
+                "apt".to_string(),
+                "install".to_string(),
+                "--yes".to_string(),
+                "firefox".to_string()
+            ]])
+        );
+        assert_eq!(explain_flag("--yes"), Some("answer yes to all prompts"));
+        assert_eq!(
+            explain_flag("--params='/InstallDir:C:\\foo'"),
+            Some("package parameters")
+        );
+        assert_eq!(explain_flag("firefox"), None);
+
+        let args = Cli::parse_from(["mpm", "explain", "conflicts"]);
+        assert!(execute_with_manager(&apt, args, &Config::default()).is_err());
+    }
+
+    #[test]
+    fn test_manager_macports() {
+        let args = Cli::try_parse_from(["mpm", "--manager", "macports", "list"]).unwrap();
+        assert!(matches!(
+            args.manager,
+            Some(crate::common::AvailablePackageManager::MacPorts)
+        ));
+    }
+
+    #[test]
+    fn test_search_no_match_message() {
+        let mock = MockManager {
+            stdout: "",
+            installed: "",
+        };
+        let args = Cli::parse_from(["mpm", "search", "nonexistent"]);
+        let ExecuteOutcome::Search { query, packages } =
+            execute_with_manager(&mock, args, &Config::default()).unwrap()
+        else {
+            panic!("expected search outcome");
+        };
+        assert!(packages.is_empty());
+        assert_eq!(
+            render::no_match_message(&query, &packages, OutputFormat::Table).as_deref(),
+            Some("No packages found matching 'nonexistent'")
+        );
+        assert_eq!(
+            render::no_match_message(&query, &packages, OutputFormat::Json).as_deref(),
+            Some("[]")
+        );
+
+        let found = [Package::from("foo")];
+        assert_eq!(
+            render::no_match_message("foo", &found, OutputFormat::Table),
+            None
+        );
+    }
+
+    #[test]
+    fn test_search_show_installed() {
+        let mock = MockManager {
+            stdout: "foo+1.0\nfoobar+2.0\n",
+            installed: "foobar+2.0\n",
+        };
+        let args = Cli::parse_from(["mpm", "search", "--show-installed", "foo"]);
+        let ExecuteOutcome::Search { packages: pkgs, .. } =
+            execute_with_manager(&mock, args, &Config::default()).unwrap()
+        else {
+            panic!("expected search outcome");
+        };
+        let installed: Vec<_> = pkgs.iter().map(|p| (p.name(), p.installed())).collect();
+        assert_eq!(installed, [("foo", Some(false)), ("foobar", Some(true))]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_schedule_job() {
+        let args = Cli::parse_from([
+            "mpm",
+            "--manager",
+            "apt",
+            "--config",
+            "/etc/mpm/mpm.toml",
+            "--ipv4",
+            "--log-file",
+            "/var/log/mpm.log",
+            "--log-rotation",
+            "daily",
+            "--json",
+            "schedule",
+            "--weekly",
+            "update --all",
+        ]);
+        let job = schedule_job(&args).unwrap();
+        assert_eq!(job.name, "mpm-update");
+        assert_eq!(
+            job.command[1..],
+            [
+                "--yes",
+                "--manager",
+                "apt",
+                "--config",
+                "/etc/mpm/mpm.toml",
+                "--ipv4",
+                "--log-file",
+                "/var/log/mpm.log",
+                "--log-rotation",
+                "daily",
+                "--output",
+                "json",
+                "update",
+                "--all",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_schedule_emit() {
+        let args = Cli::parse_from([
+            "mpm",
+            "--manager",
+            "apt",
+            "schedule",
+            "--weekly",
+            "--emit",
+            "update --all",
+        ]);
+        let ExecuteOutcome::Script(script) = schedule(&args).unwrap() else {
+            panic!("expected the job's files");
+        };
+        assert!(script.contains("mpm-update"), "{script}");
+        assert!(script.contains("--yes"), "{script}");
+        assert!(script.contains("apt"), "{script}");
+
+        let args = ["mpm", "schedule", "--daily", "--weekly", "sync"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_doctor() {
+        let mock = MockManager {
+            stdout: "",
+            installed: "",
+        };
+        let checks = crate::doctor::check_manager(&mock);
+        // the mock has no program to print its version
+        let found: Vec<_> = checks
+            .iter()
+            .map(|c| (c.subject.as_str(), c.check.as_str(), c.status))
+            .collect();
+        assert_eq!(found, [("mock", "version", crate::doctor::Status::Warning)]);
+        assert!(checks[0].hint.is_some());
+    }
+
+    #[test]
+    fn test_filter() {
+        let mock = MockManager {
+            stdout: "libfoo+1.0\nfoo+lib2\nbar+2.0\n",
+            installed: "libfoo+1.0\nfoo+lib2\nbar+2.0\n",
+        };
+        let names = |args: Cli| {
+            let pkgs = match execute_with_manager(&mock, args, &Config::default()).unwrap() {
+                ExecuteOutcome::List { packages, .. } => packages,
+                ExecuteOutcome::Search { packages, .. } => packages,
+                outcome => panic!("unexpected outcome {outcome:?}"),
+            };
+            pkgs.iter()
+                .map(|p| p.name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let args = Cli::parse_from(["mpm", "list", "--filter", "^lib"]);
+        assert_eq!(names(args), ["libfoo", "foo"]);
+        let args = Cli::parse_from(["mpm", "list", "--filter", "^lib", "--name-only"]);
+        assert_eq!(names(args), ["libfoo"]);
+        let args = Cli::parse_from(["mpm", "search", "o", "--filter", "o$"]);
+        assert_eq!(names(args), ["libfoo", "foo"]);
+        // the limit counts the packages left after filtering
+        let args = Cli::parse_from(["mpm", "search", "o", "--filter", "^foo", "--limit", "1"]);
+        assert_eq!(names(args), ["foo"]);
+        // and so does the limit with --exact
+        let args = Cli::parse_from(["mpm", "search", "foo", "--exact", "--limit", "1"]);
+        assert_eq!(names(args), ["foo"]);
+
+        assert!(Cli::try_parse_from(["mpm", "list", "--filter", "("]).is_err());
+        assert!(Cli::try_parse_from(["mpm", "list", "--name-only"]).is_err());
+    }
+
+    #[test]
+    fn test_sync_each() {
+        let managers = [
+            MockManager {
+                stdout: "",
+                installed: "",
+            },
+            MockManager {
+                stdout: "",
+                installed: "",
+            },
+        ];
+        assert_eq!(
+            sync_each(&managers),
+            [("mock".to_string(), true), ("mock".to_string(), true)]
+        );
+        let args = Cli::parse_from(["mpm", "sync", "--all"]);
+        assert!(matches!(
+            args.command,
+            MpmPackageManagerCommands::Sync { all: true }
+        ));
+    }
+
+    #[test]
+    fn test_search_exact() {
+        let mock = MockManager {
+            stdout: "foo+1.0\nFoo+1.1\nfoobar+2.0\n",
+            installed: "foobar+2.0\n",
+        };
+        let names = |pkgs: &[Package]| {
+            pkgs.iter()
+                .map(|p| p.name().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&mock.search_exact("foo")), ["foo", "Foo"]);
+
+        let mut pkgs = mock.search("foo");
+        mock.mark_installed(&mut pkgs);
+        retain_matches(&mut pkgs, "foo", false, true);
+        assert_eq!(names(&pkgs), ["foobar"]);
+        retain_matches(&mut pkgs, "foo", true, true);
+        assert!(pkgs.is_empty());
+    }
+
+    #[test]
+    fn test_search_each() {
+        let managers = [
+            MockManager {
+                stdout: "foo+1.0\nfoobar+2.0\n",
+                installed: "foobar+2.0\n",
+            },
+            MockManager {
+                stdout: "foo+1.1\n",
+                installed: "",
+            },
+        ];
+        let pkgs = search_each(&managers, "foo", None, true);
+        let found: Vec<_> = pkgs
+            .iter()
+            .map(|p| (p.name(), p.version(), p.installed()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("foo", Some("1.0"), Some(false)),
+                ("foobar", Some("2.0"), Some(true)),
+                ("foo", Some("1.1"), Some(false)),
+            ]
+        );
+        let args = Cli::parse_from(["mpm", "search", "--all", "foo"]);
+        assert!(matches!(
+            args.command,
+            MpmPackageManagerCommands::Search { all: true, .. }
+        ));
+        assert!(Cli::try_parse_from(["mpm", "search", "--all", "--cache", "foo"]).is_err());
+    }
+
+    #[test]
+    fn test_ensure_installable_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, "").unwrap();
+            path
+        };
+        let apt = crate::managers::apt::AdvancedPackageTool::default();
+        assert!(ensure_installable_file(&apt, &file("hello_2.10-3_amd64.deb"), "x86_64").is_ok());
+        assert!(ensure_installable_file(&apt, &file("tzdata_2024a-1_all.deb"), "aarch64").is_ok());
+        let err =
+            ensure_installable_file(&apt, &file("hello_2.10-3_arm64.deb"), "x86_64").unwrap_err();
+        assert!(err.to_string().contains("built for aarch64"), "{err}");
+        assert!(ensure_installable_file(&apt, &file("hello.rpm"), "x86_64").is_err());
+
+        let choco = crate::managers::choco::Chocolatey::default();
+        assert!(ensure_installable_file(&choco, &file("setup.exe"), "x86_64").is_ok());
+        assert!(ensure_installable_file(&choco, &file("setup.exe"), "aarch64").is_err());
+        assert!(ensure_installable_file(&choco, &file("setup.msi"), "aarch64").is_ok());
+    }
+
+    #[test]
+    fn test_install_manifest_resume() {
+        let names = || ["bar", "broken", "foo", "qux"].map(String::from).to_vec();
+        let mpm = RecordingManager {
+            installed: "bar+1.0\nfoo+2.0\n",
+            ..Default::default()
+        };
+        let err =
+            install_manifest_packages(&mpm, names(), &Config::default(), false, true).unwrap_err();
+        assert!(
+            err.to_string().contains("Failed to install broken;"),
+            "{err}"
+        );
+        assert_eq!(
+            crate::error::exit_code(&err),
+            crate::error::EXIT_PARTIAL_FAILURE
+        );
+        // already installed packages are skipped, and `qux` is still
+        // installed after `broken` failed
+        assert_eq!(mpm.cmds.take(), [["Install", "broken"], ["Install", "qux"]]);
+
+        let err =
+            install_manifest_packages(&mpm, names(), &Config::default(), false, false).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to install broken");
+        assert_eq!(mpm.cmds.take(), [["Install", "bar"], ["Install", "broken"]]);
+    }
+
+    #[test]
+    fn test_install_many() {
+        let mpm = RecordingManager::default();
+        let pkgs = [Package::from("foo"), Package::from("bar")];
+        assert!(mpm.install_many(&pkgs, false).success());
+        assert_eq!(mpm.cmds.take(), [["Install", "foo", "bar"]]);
+        assert!(mpm.install_many(&[], false).success());
+        assert!(mpm.cmds.take().is_empty());
+
+        let pkgs = [
+            Package::from("foo"),
+            Package::from("broken"),
+            Package::from("bar"),
+        ];
+        assert!(!crate::traits::install_each(&mpm, &pkgs, false).success());
+        assert_eq!(mpm.cmds.take(), [["Install", "foo"], ["Install", "broken"]]);
+    }
+
+    #[test]
+    fn test_install_transactional() {
+        let mpm = RecordingManager::default();
+        let pkgs = [Package::from("foo"), Package::from("bar")];
+        mpm.install_transactional(&pkgs).unwrap();
+        assert_eq!(mpm.cmds.take(), [["Install", "foo", "bar"]]);
+
+        let pkgs = [Package::from("foo"), Package::from("broken")];
+        let err = mpm.install_transactional(&pkgs).unwrap_err();
+        assert!(err.to_string().contains("rolled back"), "{err}");
+        // nothing was installed, so nothing is uninstalled
+        assert_eq!(mpm.cmds.take(), [["Install", "foo", "broken"]]);
+    }
+
+    #[test]
+    fn test_install_manifest_aliases() {
+        let mpm = RecordingManager::default();
+        let config: Config =
+            toml::from_str("[aliases.build-essentials]\nrecording = \"build-essential\"\n")
+                .unwrap();
+        let names = ["build-essentials", "curl"].map(String::from).to_vec();
+        let installed = install_manifest_packages(&mpm, names, &config, false, false).unwrap();
+        // the names of the input file are reported
+        assert_eq!(installed, ["build-essentials", "curl"]);
+        assert_eq!(
+            mpm.cmds.take(),
+            [["Install", "build-essential"], ["Install", "curl"]]
+        );
+    }
+
+    #[test]
+    fn test_install_from_file_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::default();
+        let path = dir.path().join("packages.toml");
+        std::fs::write(&path, "[aptt]\ncurl = \"\"\n").unwrap();
+        let err = install_from_file(&path, get_file_type(&path), &config, false, false, false)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("unknown package manager \"aptt\""),
+            "{err}"
+        );
+
+        let path = dir.path().join("packages.yaml");
+        std::fs::write(&path, "apt:\n  curl: ''\n").unwrap();
+        assert_eq!(get_file_type(&path), FileFormat::None);
+        assert!(install_from_file(&path, FileFormat::None, &config, false, false, false).is_err());
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn test_install_selected() {
+        let mpm = RecordingManager::default();
+        let pkgs = [("foo", "1.0"), ("bar", "2.0"), ("baz", "3.0")]
+            .map(|(name, version)| Package::new(name, mpm.pkg_manager_name(), Some(version)));
+        assert!(install_selected(&mpm, &pkgs, &[3], false).is_err());
+
+        let installed = install_selected(&mpm, &pkgs, &[2, 0], false).unwrap();
+        assert_eq!(installed, ["baz", "foo"]);
+        assert_eq!(mpm.cmds.take(), [["Install", "baz", "foo"]]);
+
+        // malformed names fail rather than panic
+        let pkgs = [Package::new("a@b@c@d", mpm.pkg_manager_name(), None)];
+        assert!(install_selected(&mpm, &pkgs, &[0], false).is_err());
+        assert!(mpm.cmds.take().is_empty());
+    }
+}
@@ -0,0 +1,848 @@
+//! Output of the commands in the format chosen with `--output`: tables and
+//! plain text for people, or JSON, YAML or CSV for scripts.
+//!
+//! Every command renders its [`ExecuteOutcome`] here, so that all formats
+//! work the same way for all of them. CSV has one row per package, map entry
+//! or list item; nested values are written as compact JSON in their cell.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+};
+
+use clap::ValueEnum;
+use indexmap::IndexMap;
+
+use super::{print_explained, ExecuteOutcome, FileFormat, GroupBy};
+use crate::{
+    history::{HistoryEntry, RollbackReport},
+    print::{Column, SortKey},
+    snapshot::RestoreReport,
+    AvailablePackageManager, DependencyGraph, InstallPlan, Package, PackageMetadata,
+};
+
+/// Format of the output of a command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Tables and plain text.
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    Csv,
+}
+
+impl OutputFormat {
+    /// Whether the output is meant for other programs rather than people.
+    pub fn is_structured(self) -> bool {
+        self != OutputFormat::Table
+    }
+}
+
+/// Serialize `value` in the structured `format`. Tables are rendered as
+/// JSON.
+pub fn to_string<T: serde::Serialize + ?Sized>(
+    value: &T,
+    format: OutputFormat,
+) -> anyhow::Result<String> {
+    Ok(match format {
+        OutputFormat::Table | OutputFormat::Json => serde_json::to_string_pretty(value)?,
+        OutputFormat::Yaml => serde_yaml::to_string(value)?.trim_end().to_string(),
+        OutputFormat::Csv => to_csv(&serde_json::to_value(value)?),
+    })
+}
+
+/// Like [`to_string`], but single values are written as compact JSON, as
+/// they were before `--output` existed.
+fn value_to_string<T: serde::Serialize + ?Sized>(
+    value: &T,
+    format: OutputFormat,
+) -> anyhow::Result<String> {
+    match format {
+        OutputFormat::Table | OutputFormat::Json => Ok(serde_json::to_string(value)?),
+        format => to_string(value, format),
+    }
+}
+
+/// Render `value` as CSV. An array of objects becomes a header of all keys
+/// and a row per object, other arrays a row per item, and an object a
+/// `key,value` row per entry.
+fn to_csv(value: &serde_json::Value) -> String {
+    use serde_json::Value;
+
+    let rows: Vec<Vec<String>> = match value {
+        Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object) => {
+            let mut keys: Vec<&str> = vec![];
+            for item in items.iter().filter_map(Value::as_object) {
+                for key in item.keys() {
+                    if !keys.contains(&key.as_str()) {
+                        keys.push(key);
+                    }
+                }
+            }
+            let mut rows = vec![keys.iter().map(|k| k.to_string()).collect()];
+            rows.extend(items.iter().map(|item| {
+                keys.iter()
+                    .map(|k| item.get(k).map(csv_cell).unwrap_or_default())
+                    .collect()
+            }));
+            rows
+        }
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::Array(cells) => cells.iter().map(csv_cell).collect(),
+                item => vec![csv_cell(item)],
+            })
+            .collect(),
+        Value::Object(entries) => entries
+            .iter()
+            .map(|(key, value)| vec![key.clone(), csv_cell(value)])
+            .collect(),
+        value => vec![vec![csv_cell(value)]],
+    };
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .map(|c| csv_quote(c))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Text of a CSV cell: strings as they are, nothing for `null`, and compact
+/// JSON for other values.
+fn csv_cell(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Quote a CSV cell if it contains a separator, quote or line break.
+fn csv_quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Print the outcome of a command in `format`. Package tables show only
+/// `columns` if given, sorted by `sort` if given.
+pub(super) fn print_outcome(
+    outcome: &ExecuteOutcome,
+    format: OutputFormat,
+    columns: Option<&[Column]>,
+    sort: Option<SortKey>,
+) -> anyhow::Result<()> {
+    match outcome {
+        ExecuteOutcome::Managers => match format {
+            OutputFormat::Table => crate::print::print_managers(),
+            format => println!("{}", to_string(&crate::print::managers(), format)?),
+        },
+        ExecuteOutcome::Search { query, packages } => {
+            match no_match_message(query, packages, format) {
+                Some(msg) => println!("{msg}"),
+                None => print_pkgs(packages, format, columns, sort)?,
+            }
+        }
+        ExecuteOutcome::Outdated(pkgs)
+        | ExecuteOutcome::ReverseDependencies(pkgs)
+        | ExecuteOutcome::Patches(pkgs)
+        | ExecuteOutcome::Pins(pkgs)
+        | ExecuteOutcome::Available(pkgs) => print_pkgs(pkgs, format, columns, sort)?,
+        ExecuteOutcome::List {
+            packages,
+            file_format,
+            group_by,
+        } => match (file_format, group_by) {
+            (Some(FileFormat::Toml), _) => pkgs_to_format(packages, FileFormat::Toml)?,
+            (Some(FileFormat::Json), _) => pkgs_to_format(packages, FileFormat::Json)?,
+            (Some(FileFormat::None), _) => (),
+            (None, Some(GroupBy::Manager)) => {
+                print_pkgs_by_manager(packages, format, columns, sort)?
+            }
+            (None, None) => print_pkgs(packages, format, columns, sort)?,
+        },
+        ExecuteOutcome::Conflicts(conflicts) => print_conflicts(conflicts, format)?,
+        ExecuteOutcome::SyncedAll(synced) => print_synced(synced, format)?,
+        ExecuteOutcome::Repos(repos) => print_repos(repos, format)?,
+        ExecuteOutcome::Explained(cmds) => {
+            if format.is_structured() {
+                println!("{}", to_string(cmds, format)?);
+            } else {
+                print_explained(cmds);
+            }
+        }
+        ExecuteOutcome::OptionValue { key, value } => match value {
+            value if format.is_structured() => println!("{}", value_to_string(value, format)?),
+            Some(value) => println!("{value}"),
+            None => println!("{key} is not set"),
+        },
+        ExecuteOutcome::Script(script) => {
+            if format.is_structured() {
+                println!("{}", value_to_string(script, format)?);
+            } else {
+                print!("{script}");
+            }
+        }
+        ExecuteOutcome::Verified(modified) => print_modified(modified, format)?,
+        ExecuteOutcome::Info(info) => print_info(info, format)?,
+        ExecuteOutcome::History(entries) => print_history(entries, format)?,
+        ExecuteOutcome::RolledBack(report) => print_rollback(report, format)?,
+        ExecuteOutcome::Checked(drift) => print_drift(drift, format)?,
+        ExecuteOutcome::SnapshotRestored { report, dry_run } => {
+            print_restore(report, *dry_run, format)?
+        }
+        ExecuteOutcome::Snapshots(names) => {
+            if format.is_structured() {
+                println!("{}", to_string(names, format)?);
+            } else {
+                names.iter().for_each(|n| println!("{n}"));
+            }
+        }
+        ExecuteOutcome::Owner(pkg) => print_pkgs(std::slice::from_ref(pkg), format, columns, None)?,
+        ExecuteOutcome::Dependencies(graph) => {
+            if format.is_structured() {
+                println!("{}", to_string(graph, format)?);
+            } else {
+                dependency_tree(graph).iter().for_each(|l| println!("{l}"));
+            }
+        }
+        ExecuteOutcome::Cleanable(size) => {
+            if format.is_structured() {
+                println!("{size}");
+            } else {
+                println!("Cleaning would free {:.1} MB", *size as f64 / 1e6);
+            }
+        }
+        ExecuteOutcome::Diagnosed(checks) => print_diagnosis(checks, format)?,
+        ExecuteOutcome::SelfUpdated(update) => {
+            if format.is_structured() {
+                println!("{}", to_string(update, format)?);
+            } else if update.updated {
+                println!("Updated mpm from {} to {}", update.current, update.latest);
+            } else if update.is_available() {
+                println!(
+                    "mpm {} is available, {} is installed",
+                    update.latest, update.current
+                );
+            } else {
+                println!("mpm {} is up to date", update.current);
+            }
+        }
+        ExecuteOutcome::Files(files)
+        | ExecuteOutcome::Manpages(files)
+        | ExecuteOutcome::Scheduled(files) => {
+            if format.is_structured() {
+                println!("{}", to_string(files, format)?);
+            } else {
+                files.iter().for_each(|f| println!("{}", f.display()));
+            }
+        }
+        ExecuteOutcome::Mirrors(mirrors) => {
+            if format.is_structured() {
+                println!("{}", to_string(mirrors, format)?);
+            } else {
+                mirrors.iter().for_each(|m| println!("{m}"));
+            }
+        }
+        ExecuteOutcome::Installed(_)
+        | ExecuteOutcome::Uninstalled(_)
+        | ExecuteOutcome::Updated(_)
+        | ExecuteOutcome::UpdatedAll
+        | ExecuteOutcome::UpdatesDownloaded
+        | ExecuteOutcome::RepoAdded(_)
+        | ExecuteOutcome::RepoRemoved(_)
+        | ExecuteOutcome::Synced
+        | ExecuteOutcome::CacheCleared
+        | ExecuteOutcome::Cleaned
+        | ExecuteOutcome::Autoremoved
+        | ExecuteOutcome::Pinned(_)
+        | ExecuteOutcome::Unpinned(_)
+        | ExecuteOutcome::Fixed
+        | ExecuteOutcome::MirrorSet(_)
+        | ExecuteOutcome::OptionSet { .. }
+        | ExecuteOutcome::SnapshotSaved(_)
+        | ExecuteOutcome::Ran
+        | ExecuteOutcome::Locked(_) => (),
+    }
+    Ok(())
+}
+
+/// Print modified files grouped by package.
+fn print_modified(
+    modified: &[(Package, Vec<PathBuf>)],
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    if format.is_structured() {
+        let modified: Vec<_> = modified
+            .iter()
+            .map(|(pkg, files)| serde_json::json!({ "package": pkg.name(), "files": files }))
+            .collect();
+        println!("{}", to_string(&modified, format)?);
+    } else if modified.is_empty() {
+        println!("No modified files found");
+    } else {
+        for (pkg, files) in modified {
+            println!("{}:", pkg.name());
+            files.iter().for_each(|f| println!("  {}", f.display()));
+        }
+    }
+    Ok(())
+}
+
+/// Lines of the dependency tree of `graph`, indented by depth. Packages that
+/// were already shown are marked with `(*)` and not expanded again.
+fn dependency_tree(graph: &DependencyGraph) -> Vec<String> {
+    fn walk<'a>(
+        graph: &'a DependencyGraph,
+        pkg: &'a str,
+        prefix: &str,
+        seen: &mut HashSet<&'a str>,
+        lines: &mut Vec<String>,
+    ) {
+        let deps = graph.dependencies_of(pkg);
+        for (i, dep) in deps.iter().enumerate() {
+            let last = i + 1 == deps.len();
+            let branch = if last { "└── " } else { "├── " };
+            let expand = seen.insert(dep.as_str());
+            let mark = if expand || graph.dependencies_of(dep).is_empty() {
+                ""
+            } else {
+                " (*)"
+            };
+            lines.push(format!("{prefix}{branch}{dep}{mark}"));
+            if expand {
+                let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+                walk(graph, dep, &prefix, seen, lines);
+            }
+        }
+    }
+
+    let mut lines = vec![graph.root.clone()];
+    let mut seen = HashSet::from([graph.root.as_str()]);
+    walk(graph, &graph.root, "", &mut seen, &mut lines);
+    lines
+}
+
+/// Print the metadata of a package as a table of the reported fields.
+fn print_info(info: &PackageMetadata, format: OutputFormat) -> anyhow::Result<()> {
+    if format.is_structured() {
+        println!("{}", to_string(info, format)?);
+        return Ok(());
+    }
+    let size = info.size.map(|size| format!("{:.1} MB", size as f64 / 1e6));
+    let mut builder = tabled::builder::Builder::default();
+    builder.push_record(["name", info.name.as_str()]);
+    for (key, value) in [
+        ("description", &info.description),
+        ("homepage", &info.homepage),
+        ("license", &info.license),
+        ("installed size", &size),
+        ("installed version", &info.installed_version),
+        ("candidate version", &info.candidate_version),
+    ] {
+        if let Some(value) = value {
+            builder.push_record([key, value.as_str()]);
+        }
+    }
+    crate::print::print_table(builder.build());
+    Ok(())
+}
+
+/// Print the history as a table. Native entries have no id and exit code and
+/// show `-` instead.
+fn print_history(entries: &[HistoryEntry], format: OutputFormat) -> anyhow::Result<()> {
+    if format.is_structured() {
+        println!("{}", to_string(entries, format)?);
+    } else if entries.is_empty() {
+        println!("No operations recorded");
+    } else {
+        let mut builder = tabled::builder::Builder::default();
+        builder.push_record([
+            "id",
+            "time",
+            "manager",
+            "operation",
+            "packages",
+            "exit code",
+        ]);
+        for entry in entries {
+            builder.push_record([
+                entry
+                    .id
+                    .map_or_else(|| "-".to_string(), |id| id.to_string()),
+                entry.time.clone(),
+                entry.manager.clone(),
+                entry.operation.clone(),
+                entry.packages.join(" "),
+                entry
+                    .exit_code
+                    .map_or_else(|| "-".to_string(), |code| code.to_string()),
+            ]);
+        }
+        crate::print::print_table(builder.build());
+    }
+    Ok(())
+}
+
+/// Print what a rollback reverted and what it could not.
+fn print_rollback(report: &RollbackReport, format: OutputFormat) -> anyhow::Result<()> {
+    if format.is_structured() {
+        println!("{}", to_string(report, format)?);
+        return Ok(());
+    }
+    println!("Rolled back operation {}", report.id);
+    report.reverted.iter().for_each(|r| println!("  {r}"));
+    if !report.failed.is_empty() {
+        println!("Could not revert:");
+        report.failed.iter().for_each(|f| println!("  {f}"));
+    }
+    Ok(())
+}
+
+/// Print the findings of `mpm doctor`, with what to do about the problems.
+fn print_diagnosis(checks: &[crate::doctor::Check], format: OutputFormat) -> anyhow::Result<()> {
+    use colored::Colorize;
+
+    use crate::doctor::Status;
+
+    if format.is_structured() {
+        println!("{}", to_string(checks, format)?);
+        return Ok(());
+    }
+    for check in checks {
+        let status = match check.status {
+            Status::Ok => "ok".green(),
+            Status::Warning => "warning".yellow(),
+            Status::Error => "error".red(),
+        };
+        println!(
+            "{status:>7} {} {}: {}",
+            check.subject, check.check, check.message
+        );
+        if let Some(hint) = &check.hint {
+            println!("        {hint}");
+        }
+    }
+    Ok(())
+}
+
+/// Print how the installed packages differ from a lockfile.
+fn print_drift(drift: &crate::lockfile::Drift, format: OutputFormat) -> anyhow::Result<()> {
+    if format.is_structured() {
+        println!("{}", to_string(drift, format)?);
+    } else if drift.is_empty() {
+        println!("The installed packages match the lockfile");
+    } else {
+        for pkg in &drift.missing {
+            println!(
+                "Missing {} {} ({})",
+                pkg.name(),
+                pkg.version().unwrap_or_default(),
+                pkg.package_manager()
+            );
+        }
+        for pkg in &drift.extra {
+            println!("Extra {} ({})", pkg.name(), pkg.package_manager());
+        }
+        for m in &drift.mismatched {
+            println!(
+                "Mismatched {} ({}): locked {}, installed {}",
+                m.name, m.manager, m.locked, m.installed
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print the packages restoring a snapshot installs and uninstalls, and the
+/// changes that failed.
+fn print_restore(
+    report: &RestoreReport,
+    dry_run: bool,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    if format.is_structured() {
+        println!("{}", to_string(report, format)?);
+        return Ok(());
+    }
+    if report.diff.is_empty() && report.failed.is_empty() {
+        println!("The installed packages match the snapshot");
+        return Ok(());
+    }
+    let (install, uninstall) = if dry_run {
+        ("Would install", "Would uninstall")
+    } else {
+        ("Installed", "Uninstalled")
+    };
+    for pkg in &report.diff.install {
+        println!(
+            "{install} {} {} ({})",
+            pkg.name(),
+            pkg.version().unwrap_or_default(),
+            pkg.package_manager()
+        );
+    }
+    for pkg in &report.diff.remove {
+        println!("{uninstall} {} ({})", pkg.name(), pkg.package_manager());
+    }
+    if !report.failed.is_empty() {
+        println!("Failed:");
+        report.failed.iter().for_each(|f| println!("  {f}"));
+    }
+    Ok(())
+}
+
+/// Print what an install would change.
+pub(super) fn print_plan(plan: &InstallPlan, format: OutputFormat) -> anyhow::Result<()> {
+    if format.is_structured() {
+        println!("{}", to_string(plan, format)?);
+        return Ok(());
+    }
+    for (action, pkgs) in [
+        ("Install", &plan.to_install),
+        ("Upgrade", &plan.to_upgrade),
+        ("Remove", &plan.to_remove),
+    ] {
+        if !pkgs.is_empty() {
+            let names: Vec<String> = pkgs
+                .iter()
+                .map(|p| match p.version() {
+                    Some(v) => format!("{} ({v})", p.name()),
+                    None => p.name().to_string(),
+                })
+                .collect();
+            println!("{action}: {}", names.join(", "));
+        }
+    }
+    if let Some(size) = plan.download_size {
+        println!("Download size: {:.1} MB", size as f64 / 1e6);
+    }
+    if let Some(size) = plan.installed_size {
+        println!("Installed size: {:.1} MB", size as f64 / 1e6);
+    }
+    Ok(())
+}
+
+/// Message to print instead of an empty table when a search found nothing.
+pub(super) fn no_match_message(
+    query: &str,
+    pkgs: &[Package],
+    format: OutputFormat,
+) -> Option<String> {
+    match (pkgs.is_empty(), format) {
+        (false, _) => None,
+        (true, OutputFormat::Json | OutputFormat::Yaml) => Some("[]".to_string()),
+        (true, OutputFormat::Csv) => Some(String::new()),
+        (true, OutputFormat::Table) => Some(format!("No packages found matching '{query}'")),
+    }
+}
+
+/// `pkgs` sorted by `sort`, if given.
+fn sorted(pkgs: &[Package], sort: Option<SortKey>) -> Cow<'_, [Package]> {
+    match sort {
+        Some(key) => {
+            let mut pkgs = pkgs.to_vec();
+            key.sort(&mut pkgs);
+            Cow::Owned(pkgs)
+        }
+        None => Cow::Borrowed(pkgs),
+    }
+}
+
+/// Print packages
+fn print_pkgs(
+    pkgs: &[Package],
+    format: OutputFormat,
+    columns: Option<&[Column]>,
+    sort: Option<SortKey>,
+) -> anyhow::Result<()> {
+    let pkgs = &*sorted(pkgs, sort);
+    let show_installed = pkgs.iter().any(|p| p.installed().is_some());
+    match (columns, format) {
+        (Some(columns), OutputFormat::Table) => {
+            println!("{}", crate::print::pkgs_table(pkgs, columns))
+        }
+        (Some(columns), format) => println!(
+            "{}",
+            to_string(&crate::print::pkgs_json(pkgs, columns), format)?
+        ),
+        // the grouped format has no room for the installed flag, CSV has a
+        // row per package instead
+        (None, OutputFormat::Json | OutputFormat::Yaml) if !show_installed => {
+            println!("{}", to_string(&versions_by_manager(pkgs), format)?)
+        }
+        (None, OutputFormat::Json | OutputFormat::Yaml | OutputFormat::Csv) => {
+            println!("{}", to_string(pkgs, format)?)
+        }
+        (None, OutputFormat::Table) if show_installed => {
+            let columns = [
+                Column::Name,
+                Column::Manager,
+                Column::Version,
+                Column::Installed,
+            ];
+            println!("{}", crate::print::pkgs_table(pkgs, &columns));
+        }
+        (None, OutputFormat::Table) => println!("{}", tabled::Table::new(pkgs)),
+    }
+    Ok(())
+}
+
+/// Packages grouped by the name of their package manager.
+fn group_by_manager(pkgs: &[Package]) -> BTreeMap<&str, Vec<Package>> {
+    let mut groups: BTreeMap<&str, Vec<Package>> = BTreeMap::new();
+    for pkg in pkgs {
+        groups
+            .entry(pkg.package_manager())
+            .or_default()
+            .push(pkg.clone());
+    }
+    groups
+}
+
+/// Print a table of packages per package manager, or an object mapping the
+/// package managers to their packages. CSV has a row per package instead.
+fn print_pkgs_by_manager(
+    pkgs: &[Package],
+    format: OutputFormat,
+    columns: Option<&[Column]>,
+    sort: Option<SortKey>,
+) -> anyhow::Result<()> {
+    use colored::Colorize;
+
+    if format == OutputFormat::Csv {
+        return print_pkgs(pkgs, format, columns, sort);
+    }
+    let pkgs = &*sorted(pkgs, sort);
+    let groups = group_by_manager(pkgs);
+    if format.is_structured() {
+        let groups: BTreeMap<&str, serde_json::Value> = groups
+            .into_iter()
+            .map(|(manager, pkgs)| {
+                let pkgs = match columns {
+                    Some(columns) => crate::print::pkgs_json(&pkgs, columns),
+                    None => serde_json::to_value(pkgs)?,
+                };
+                Ok((manager, pkgs))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        println!("{}", to_string(&groups, format)?);
+        return Ok(());
+    }
+    for (manager, pkgs) in groups {
+        println!("{}", manager.bold());
+        print_pkgs(&pkgs, format, columns, None)?;
+    }
+    Ok(())
+}
+
+/// Print packages installed by more than one package manager
+fn print_conflicts(
+    conflicts: &[(String, Vec<AvailablePackageManager>)],
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    let rows = conflicts.iter().map(|(name, managers)| {
+        let managers: Vec<String> = managers
+            .iter()
+            .map(|m| m.to_string().to_lowercase())
+            .collect();
+        (name.clone(), managers)
+    });
+
+    if format.is_structured() {
+        let grouped: BTreeMap<String, Vec<String>> = rows.collect();
+        println!("{}", to_string(&grouped, format)?);
+    } else if conflicts.is_empty() {
+        println!("No package is installed by more than one package manager.");
+    } else {
+        let mut builder = tabled::builder::Builder::default();
+        builder.push_record(["name", "package managers"]);
+        for (name, managers) in rows {
+            builder.push_record([name, managers.join(", ")]);
+        }
+        crate::print::print_table(builder.build());
+    }
+    Ok(())
+}
+
+/// Print whether each package manager synced its repositories.
+fn print_synced(synced: &[(String, bool)], format: OutputFormat) -> anyhow::Result<()> {
+    use colored::Colorize;
+
+    if format.is_structured() {
+        let rows: Vec<serde_json::Value> = synced
+            .iter()
+            .map(|(manager, ok)| serde_json::json!({"manager": manager, "synced": ok}))
+            .collect();
+        println!("{}", to_string(&rows, format)?);
+    } else if synced.is_empty() {
+        println!("No package manager is available.");
+    } else {
+        let mut builder = tabled::builder::Builder::default();
+        builder.push_record(["package manager", "status"]);
+        for (manager, ok) in synced {
+            let status = match ok {
+                true => "synced".green().to_string(),
+                false => "failed".red().to_string(),
+            };
+            builder.push_record([manager.clone(), status]);
+        }
+        crate::print::print_table(builder.build());
+    }
+    Ok(())
+}
+
+/// Print the repositories of a package manager with their URL and whether
+/// they are enabled.
+fn print_repos(repos: &[crate::Repository], format: OutputFormat) -> anyhow::Result<()> {
+    if format.is_structured() {
+        println!("{}", to_string(repos, format)?);
+    } else if repos.is_empty() {
+        println!("No repositories are configured.");
+    } else {
+        let mut builder = tabled::builder::Builder::default();
+        builder.push_record(["name", "url", "enabled"]);
+        for repo in repos {
+            builder.push_record([
+                repo.name.clone(),
+                repo.url.clone().unwrap_or_default(),
+                if repo.enabled { "yes" } else { "no" }.to_string(),
+            ]);
+        }
+        crate::print::print_table(builder.build());
+    }
+    Ok(())
+}
+
+/// Versions of the packages by name, grouped by the name of their package
+/// manager. Packages without a version are left out.
+fn versions_by_manager(packages: &[Package]) -> BTreeMap<String, IndexMap<String, String>> {
+    let mut grouped: BTreeMap<String, IndexMap<String, String>> = BTreeMap::new();
+
+    for package in packages {
+        if let Some(version) = &package.version() {
+            grouped
+                .entry(package.package_manager().to_string())
+                .or_default()
+                .insert(package.name().to_string(), version.to_string());
+        }
+    }
+    grouped
+}
+
+/// Convert Package to a JSON or TOML format
+fn pkgs_to_format(packages: &[Package], format: FileFormat) -> anyhow::Result<()> {
+    let grouped = versions_by_manager(packages);
+    let output = match format {
+        FileFormat::Toml => toml::to_string(&grouped)?,
+        FileFormat::Json => serde_json::to_string_pretty(&grouped)?,
+        FileFormat::None => todo!(),
+    };
+
+    println!("{}", output);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_csv() {
+        let pkgs = [
+            Package::new("foo", "apt".to_string(), Some("1.0")),
+            Package::new("bar, baz", "apt".to_string(), None),
+        ];
+        assert_eq!(
+            to_string(&pkgs, OutputFormat::Csv).unwrap(),
+            "name,package_manager,version\nfoo,apt,1.0\n\"bar, baz\",apt,"
+        );
+        assert_eq!(
+            to_string(&[["apt-get", "install", "a \"b\""]], OutputFormat::Csv).unwrap(),
+            "apt-get,install,\"a \"\"b\"\"\""
+        );
+        let map = BTreeMap::from([("curl", vec!["apt", "brew"])]);
+        assert_eq!(
+            to_string(&map, OutputFormat::Csv).unwrap(),
+            "curl,\"[\"\"apt\"\",\"\"brew\"\"]\""
+        );
+    }
+
+    #[test]
+    fn test_to_yaml() {
+        let pkgs = [Package::new("foo", "apt".to_string(), Some("1.0"))];
+        assert_eq!(
+            to_string(&pkgs, OutputFormat::Yaml).unwrap(),
+            "- name: foo\n  package_manager: apt\n  version: '1.0'"
+        );
+    }
+
+    #[test]
+    fn test_dependency_tree() {
+        let mut graph = DependencyGraph::new("wget");
+        graph.add("wget", "libidn2");
+        graph.add("wget", "openssl");
+        graph.add("libidn2", "libunistring");
+        graph.add("openssl", "libidn2");
+        assert_eq!(
+            dependency_tree(&graph),
+            [
+                "wget",
+                "├── libidn2",
+                "│   └── libunistring",
+                "└── openssl",
+                "    └── libidn2 (*)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sorted() {
+        let pkgs = [
+            Package::new("foo", "apt".to_string(), Some("1.10")),
+            Package::new("bar", "apt".to_string(), Some("1.9")),
+        ];
+        assert_eq!(sorted(&pkgs, None)[0].name(), "foo");
+        let by_version = sorted(&pkgs, Some(SortKey::Version));
+        assert_eq!(by_version[0].name(), "bar");
+        assert_eq!(pkgs[0].name(), "foo");
+        assert_eq!(
+            versions_by_manager(&by_version)["apt"]
+                .keys()
+                .collect::<Vec<_>>(),
+            ["bar", "foo"]
+        );
+    }
+
+    #[test]
+    fn test_group_by_manager() {
+        let pkgs = [
+            ("vim", "apt"),
+            ("wget", "brew"),
+            ("curl", "apt"),
+            ("org.gimp.GIMP", "flatpak"),
+        ]
+        .map(|(name, manager)| Package::new(name, manager.to_string(), None));
+        let groups: Vec<(&str, Vec<&str>)> = group_by_manager(&pkgs)
+            .iter()
+            .map(|(manager, pkgs)| (*manager, pkgs.iter().map(Package::name).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                ("apt", vec!["vim", "curl"]),
+                ("brew", vec!["wget"]),
+                ("flatpak", vec!["org.gimp.GIMP"]),
+            ]
+        );
+    }
+}
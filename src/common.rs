@@ -6,6 +6,7 @@ use std::{
     io::{BufRead, BufReader},
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use anyhow::Context;
@@ -26,24 +27,84 @@ pub enum Cmd {
     AddRepo,
     Search,
     Outdated,
+    /// Remove downloaded packages and cached metadata, see
+    /// [``crate::PackageManager::clean``].
+    Clean,
+    /// Remove dependencies that no installed package needs anymore, see
+    /// [``crate::PackageManager::autoremove``].
+    Autoremove,
 }
 
 /// A representation of a package
 ///
 /// This struct contains package's name and version information (optional).
-#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+///
+/// Empty and missing fields are omitted when serialized, and accepted as
+/// either absent or `null` when deserialized.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Package {
     /// name of the package
     name: String,
 
     /// name of the package manager
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     package_manager: String,
 
     /// Untyped version, might be replaced with a strongly typed one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     version: Option<String>,
 
     /// Url of this package. A local package can be passed as "file://" URI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     url: Option<url::Url>,
+
+    /// Whether this package is installed. `None` if unknown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    installed: Option<bool>,
+
+    /// What the package manager installs, `None` for a regular package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kind: Option<PackageKind>,
+
+    /// Expected hex encoded SHA-256 checksum of the file at `url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+
+    /// URL of a detached GPG signature of the file at `url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<url::Url>,
+}
+
+/// Kind of installable, for package managers that install more than
+/// packages, e.g. the patterns, patches and products of zypper, see
+/// [``PackageManager::supports_kind``].
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    clap::ValueEnum,
+    strum::Display,
+    strum::EnumString,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageKind {
+    Package,
+    /// A group of packages for a task, e.g. `devel_basis`.
+    Pattern,
+    /// A fix for installed packages, e.g. `openSUSE-SLE-15.5-2024-123`.
+    Patch,
+    /// A product and its release packages, e.g. `openSUSE`.
+    Product,
+    /// An application, e.g. `org.gnome.Maps` of flatpak.
+    App,
+    /// A runtime applications run on, e.g. `org.gnome.Platform` of flatpak.
+    Runtime,
 }
 
 impl Package {
@@ -54,6 +115,10 @@ impl Package {
             package_manager: pm.to_string(),
             version: version.map(|v| v.to_string()),
             url: None,
+            installed: None,
+            kind: None,
+            sha256: None,
+            signature: None,
         }
     }
 
@@ -62,6 +127,11 @@ impl Package {
         &self.name
     }
 
+    /// Rename the package, e.g. to the name of an alias.
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = name.into();
+    }
+
     /// Name of the package manager
     pub fn package_manager(&self) -> &str {
         &self.package_manager
@@ -92,7 +162,124 @@ impl Package {
         self.url.as_ref()
     }
 
+    /// Whether this package is installed, if known.
+    pub fn installed(&self) -> Option<bool> {
+        self.installed
+    }
+
+    /// Mark this package as installed or not installed.
+    pub fn set_installed(&mut self, installed: bool) {
+        self.installed = Some(installed);
+    }
+
+    /// Kind of this package, `None` for a regular package.
+    pub fn kind(&self) -> Option<PackageKind> {
+        self.kind
+    }
+
+    /// Set the kind of this package, see [``PackageKind``].
+    pub fn set_kind(&mut self, kind: PackageKind) {
+        self.kind = (kind != PackageKind::Package).then_some(kind);
+    }
+
+    /// Expected SHA-256 checksum of the package file, see
+    /// [``Package::set_sha256``].
+    pub fn sha256(&self) -> Option<&str> {
+        self.sha256.as_deref()
+    }
+
+    /// Expect the package file to have the hex encoded SHA-256 `checksum`.
+    /// Also set with a `#sha256=<checksum>` URL fragment.
+    ///
+    /// Checked by [``Package::make_available_on_disk``] and
+    /// [``Package::make_verified_copy``], so hand the verified file to the
+    /// package manager rather than the package.
+    pub fn set_sha256(&mut self, checksum: &str) {
+        self.sha256 = Some(checksum.to_lowercase());
+    }
+
+    /// URL of the detached signature of the package file, see
+    /// [``Package::set_signature``].
+    pub fn signature(&self) -> Option<&url::Url> {
+        self.signature.as_ref()
+    }
+
+    /// Expect the package file to have a valid detached GPG signature at
+    /// `url` made by a fully trusted key, checked like the checksum (see
+    /// [``Package::set_sha256``]). The keyring is that of the user mpm runs
+    /// as, i.e. root's when the package manager needs sudo. Also set with a
+    /// `#signature=<url>` URL fragment.
+    pub fn set_signature(&mut self, url: url::Url) {
+        self.signature = Some(url);
+    }
+
+    /// Whether the package file must be verified before it is installed.
+    pub fn needs_verification(&self) -> bool {
+        self.sha256.is_some() || self.signature.is_some()
+    }
+
+    /// Check the file at `path` against the expected checksum and
+    /// signature, if any.
+    pub fn verify_file(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(expected) = &self.sha256 {
+            let actual = sha256_file(path)?;
+            anyhow::ensure!(
+                actual.eq_ignore_ascii_case(expected),
+                "Checksum of {path:?} is {actual}, expected {expected}"
+            );
+            tracing::info!("Verified the checksum of {path:?}");
+        }
+        if let Some(url) = &self.signature {
+            ensure_allowed_url(url)?;
+            let mut signature = path.as_os_str().to_owned();
+            signature.push(".sig");
+            let signature = PathBuf::from(signature);
+            download_url(url, &signature, true)?;
+            let verified = verify_gpg_signature(&signature, path);
+            let _ = std::fs::remove_file(&signature);
+            verified?;
+            tracing::info!("Verified the signature of {path:?}");
+        }
+        Ok(())
+    }
+
+    /// Download the package file, or copy it if it is a local file, into
+    /// `dir` and verify it there (see [``Package::verify_file``]), pointing
+    /// the package to the verified file. `dir` must only be writable by the
+    /// user running mpm, so that the file can not be swapped after it was
+    /// verified.
+    pub fn make_verified_copy(&mut self, dir: &Path) -> anyhow::Result<PathBuf> {
+        let url = self
+            .url()
+            .context("There is no URL associated with this package")?
+            .clone();
+        let name = url
+            .path_segments()
+            .and_then(|s| s.last())
+            .filter(|name| !name.is_empty())
+            .context("missing filepath in url")?;
+        let path = dir.join(name);
+        if url.scheme() != "file" {
+            return self.make_available_on_disk(Some(&path), true);
+        }
+        let src = url
+            .to_file_path()
+            .map_err(|_| anyhow::anyhow!("invalid file URL {url}"))?;
+        std::fs::copy(&src, &path).with_context(|| format!("failed to copy {src:?}"))?;
+        if let Err(e) = self.verify_file(&path) {
+            let _ = std::fs::remove_file(&path);
+            return Err(e.context(format!("Refusing to install {src:?}")));
+        }
+        self.url = url::Url::from_file_path(&path).ok();
+        Ok(path)
+    }
+
     /// Turn remote url to local file based URI
+    ///
+    /// Downloads that do not match the expected checksum or signature (see
+    /// [``Package::verify_file``]) are deleted and fail. They need an
+    /// `output` in a directory only the user running mpm can write to, see
+    /// [``Package::make_verified_copy``].
     pub fn make_available_on_disk(
         &mut self,
         output: Option<&Path>,
@@ -107,31 +294,67 @@ impl Package {
             url.scheme() != "file",
             "Package already points to local file {url:?}"
         );
+        ensure_allowed_url(url)?;
 
+        let name = url
+            .path_segments()
+            .context("missing path in url")?
+            .last()
+            .context("missing filepath in url")?;
         let pkgpath = match output {
             Some(p) => p.into(),
-            None => std::env::temp_dir().join(
-                url.path_segments()
-                    .context("missing path in url")?
-                    .last()
-                    .context("missing filepath in url")?,
-            ),
+            None => {
+                anyhow::ensure!(
+                    !self.needs_verification(),
+                    "Verified downloads need an output path in a private directory"
+                );
+                std::env::temp_dir().join(name)
+            }
         };
 
-        // download to disk.
-        download_url(url, &pkgpath, force)?;
+        // download to disk, verified files always anew
+        download_url(url, &pkgpath, force || self.needs_verification())?;
 
         anyhow::ensure!(pkgpath.is_file(), "Failed to download {url} -> {pkgpath:?}");
+        if let Err(e) = self.verify_file(&pkgpath) {
+            let _ = std::fs::remove_file(&pkgpath);
+            return Err(e.context(format!("Refusing to install {url}")));
+        }
         self.url = format!("file://{}", pkgpath.display()).parse().ok();
         Ok(pkgpath)
     }
 }
 
+/// Package manager selected by a `<manager>:` prefix of the package `s`,
+/// e.g. brew for `brew:wget`, and the rest of `s`. URLs like `file:///a.deb`
+/// and Nix flake references like `github:owner/repo#tool` have no prefix,
+/// but the latter may be given one, e.g. `nix:github:owner/repo#tool`.
+pub fn manager_prefix(s: &str) -> Option<(AvailablePackageManager, &str)> {
+    let (manager, rest) = s.split_once(':')?;
+    let manager = manager.parse().ok()?;
+    let flake_ref = rest.contains('#') && manager != AvailablePackageManager::Nix;
+    (!rest.is_empty() && !rest.starts_with("//") && !flake_ref).then_some((manager, rest))
+}
+
+/// Parses `<name>`, a URL, `<manager>@<name>[@<version>]`, or
+/// `<manager>:<name>[@<version>]` where the prefix selects the package
+/// manager, e.g. `brew:wget` or `flatpak:org.gimp.GIMP`. URLs without a path
+/// like `github:owner/repo#tool` are names.
 impl std::str::FromStr for Package {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> anyhow::Result<Self> {
-        if let Ok(url) = url::Url::parse(s) {
+        // checked first since `brew:wget` is a valid URL as well
+        if let Some((manager, rest)) = manager_prefix(s) {
+            let manager = manager.to_string().to_lowercase();
+            return match rest.split('@').collect::<Vec<&str>>()[..] {
+                [name, version] => Ok(Package::new(name, manager, Some(version))),
+                [name] => Ok(Package::new(name, manager, None)),
+                _ => Err(anyhow::Error::msg("Invalid package format")),
+            };
+        }
+
+        if let Some(url) = url::Url::parse(s).ok().filter(|u| !u.cannot_be_a_base()) {
             tracing::debug!("Given package is a URL: {url:?}");
             let name = url
                 .path_segments()
@@ -147,11 +370,19 @@ impl std::str::FromStr for Package {
                     }
                 }
             }
+            let signature = match fragments.remove("signature") {
+                Some(sig) => Some(sig.parse().context("invalid signature URL")?),
+                None => None,
+            };
             return Ok(Self {
                 name: name.to_string(),
                 package_manager: "".to_string(),
                 version: fragments.remove("version"),
+                sha256: fragments.remove("sha256").map(|s| s.to_lowercase()),
+                signature,
                 url: Some(url),
+                installed: None,
+                kind: None,
             });
         }
 
@@ -166,12 +397,27 @@ impl std::str::FromStr for Package {
     }
 }
 
+/// # Panics
+/// Panics if `s` is not a valid package, e.g. `a@b@c@d`. Use
+/// [``std::str::FromStr``] or [``TryFrom<String>``] for fallible
+/// construction.
+///
+/// Note: `TryFrom<&str>` can not be implemented alongside this impl since the
+/// standard library already provides it through `From<&str>`.
 impl std::convert::From<&str> for Package {
     fn from(s: &str) -> Self {
         s.parse().expect("invalid format")
     }
 }
 
+impl std::convert::TryFrom<String> for Package {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> anyhow::Result<Self> {
+        s.parse()
+    }
+}
+
 impl std::convert::From<&Path> for Package {
     fn from(p: &Path) -> Self {
         let p = std::fs::canonicalize(p).unwrap_or(p.to_path_buf());
@@ -225,16 +471,43 @@ impl tabled::Tabled for Package {
     strum::EnumIter,
     strum::EnumCount,
     strum::EnumString,
+    serde::Serialize,
+    serde::Deserialize,
 )]
 #[strum(ascii_case_insensitive)]
+#[serde(rename_all = "lowercase")]
 pub enum AvailablePackageManager {
+    Apk,
+    // apt frontend, preferred over apt when it is installed
+    Nala,
     Apt,
+    Aur,
     Brew,
+    Cargo,
     Choco,
+    Conda,
     Dnf,
     Flatpak,
+    Github,
+    Go,
+    #[value(name = "macports")]
+    MacPorts,
+    Msys2,
+    Opkg,
+    Pip,
+    Portage,
+    Scoop,
+    Swupd,
+    Xbps,
     Yum,
     Zypper,
+    // install into a profile of the user, so only picked when no system
+    // package manager is installed, e.g. on NixOS or Guix System
+    Nix,
+    Guix,
+    // comes with Windows, so only picked when no other manager is installed
+    #[value(name = "oneget")]
+    OneGet,
 }
 
 /// Operation type to execute using [``Package::execute_pkg_command``]
@@ -245,30 +518,294 @@ pub enum Operation {
     Update,
 }
 
+/// What [``crate::PackageManager::install_if_missing``] does with a package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallAction {
+    /// The requested version is installed and intact.
+    Skip,
+    /// The requested version is installed but its files are missing or
+    /// corrupt.
+    Reinstall,
+    /// The package or the requested version is not installed.
+    Install,
+}
+
+impl InstallAction {
+    /// Decide what to do with `requested` given the `installed` package of
+    /// the same name, if any. `intact` is only called if the requested
+    /// version is installed.
+    pub fn decide(
+        installed: Option<&Package>,
+        requested: &Package,
+        intact: impl FnOnce() -> bool,
+    ) -> Self {
+        let Some(installed) = installed else {
+            return Self::Install;
+        };
+        let version_matches = match requested.version() {
+            Some(v) => installed.version() == Some(v),
+            None => true,
+        };
+        match (version_matches, version_matches && intact()) {
+            (false, _) => Self::Install,
+            (true, true) => Self::Skip,
+            (true, false) => Self::Reinstall,
+        }
+    }
+}
+
+/// Packages an install would change, including dependencies, as reported by
+/// [``crate::PackageManager::install_plan``].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct InstallPlan {
+    /// Packages that would be newly installed.
+    pub to_install: Vec<Package>,
+    /// Installed packages that would be upgraded to the given version.
+    pub to_upgrade: Vec<Package>,
+    /// Installed packages that would be removed.
+    pub to_remove: Vec<Package>,
+    /// Number of bytes to download, if reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_size: Option<u64>,
+    /// Number of bytes of additional disk space used, if reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installed_size: Option<u64>,
+}
+
+/// Dependencies of a package and of its dependencies, as reported by
+/// [``crate::PackageManager::dependencies``]. Serialized as an adjacency
+/// list.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct DependencyGraph {
+    /// Name of the package the graph starts from.
+    pub root: String,
+    /// Direct dependencies by package name. Packages without dependencies,
+    /// or whose dependencies were not queried, are missing.
+    pub dependencies: std::collections::BTreeMap<String, Vec<String>>,
+}
+
+impl DependencyGraph {
+    /// Empty graph of `root`.
+    pub fn new(root: &str) -> Self {
+        Self {
+            root: root.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Record that `pkg` depends on `dep`, once.
+    pub fn add(&mut self, pkg: &str, dep: &str) {
+        let deps = self.dependencies.entry(pkg.to_string()).or_default();
+        if !deps.iter().any(|d| d == dep) {
+            deps.push(dep.to_string());
+        }
+    }
+
+    /// Direct dependencies of `pkg`.
+    pub fn dependencies_of(&self, pkg: &str) -> &[String] {
+        self.dependencies.get(pkg).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Metadata of a package, as reported by [``crate::PackageManager::info``].
+/// Fields the package manager does not report are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct PackageMetadata {
+    pub name: String,
+    /// One line summary of the package.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Number of bytes the package occupies when installed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Installed version, `None` if the package is not installed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installed_version: Option<String>,
+    /// Version that an install or update would install.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candidate_version: Option<String>,
+}
+
+/// A repository of a package manager, as listed by
+/// [``crate::PackageManager::list_repos``].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Repository {
+    /// Name the repository is removed by, see
+    /// [``crate::PackageManager::remove_repo``]. The URL for repositories
+    /// without a name, like those of apt.
+    pub name: String,
+    /// Where packages are downloaded from, if reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Whether packages are installed from it.
+    pub enabled: bool,
+}
+
+impl Repository {
+    /// An enabled repository.
+    pub fn new(name: &str, url: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.map(String::from),
+            enabled: true,
+        }
+    }
+}
+
 /// Pkg Format.
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub enum PkgFormat {
+    Apk,
     Bottle,
     Exe,
     Msi,
     Rpm,
     Deb,
     Flatpak,
+    Ipk,
 }
 
 impl PkgFormat {
     /// File extension of package.
     pub fn file_extention(&self) -> String {
         match self {
+            Self::Apk => "apk",
             Self::Bottle => "tar.gz",
             Self::Exe => "exe",
             Self::Msi => "msi",
             Self::Rpm => "rpm",
             Self::Deb => "deb",
             Self::Flatpak => "flatpak",
+            Self::Ipk => "ipk",
         }
         .to_string()
     }
+
+    /// Whether packages of this format can be installed on a host with the
+    /// given architecture, named like [`std::env::consts::ARCH`].
+    pub fn is_supported_on(&self, arch: &str) -> bool {
+        match self {
+            // Windows on ARM cannot run most installers
+            Self::Exe => matches!(arch, "x86" | "x86_64"),
+            // Homebrew only builds bottles for these
+            Self::Bottle => matches!(arch, "x86_64" | "aarch64"),
+            Self::Apk | Self::Msi | Self::Rpm | Self::Deb | Self::Flatpak | Self::Ipk => true,
+        }
+    }
+}
+
+/// Architecture a package file was built for, parsed from its file name and
+/// named like [`std::env::consts::ARCH`]. `None` for architecture independent
+/// packages and unknown names.
+///
+/// Understands names like `hello_2.10-3_amd64.deb`,
+/// `hello-2.12.1-5.fc41.aarch64.rpm` and `hello--2.12.arm64_sonoma.bottle.tar.gz`.
+pub fn pkg_file_arch(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    let arch = if let Some(stem) = name.strip_suffix(".deb") {
+        stem.rsplit('_').next()?
+    } else if let Some(stem) = name.strip_suffix(".rpm") {
+        stem.rsplit('.').next()?
+    } else if let Some(stem) = name.strip_suffix(".bottle.tar.gz") {
+        // bottles for Intel Macs are only tagged with the macOS release
+        match stem.rsplit('.').next()?.split_once('_') {
+            Some(("arm64", _)) => "arm64",
+            _ => "x86_64",
+        }
+    } else {
+        return None;
+    };
+    match arch {
+        "amd64" | "x86_64" => Some("x86_64"),
+        "arm64" | "aarch64" => Some("aarch64"),
+        "i386" | "i586" | "i686" => Some("x86"),
+        "armhf" | "armel" | "armv7hl" => Some("arm"),
+        "ppc64el" | "ppc64le" => Some("powerpc64"),
+        "riscv64" => Some("riscv64"),
+        "s390x" => Some("s390x"),
+        // `all`, `noarch`, ...
+        _ => None,
+    }
+}
+
+/// Error returned when a package manager does not support an operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsupported {
+    /// Name of the package manager.
+    pub manager: String,
+    /// Human readable name of the operation.
+    pub operation: &'static str,
+}
+
+impl Unsupported {
+    /// Create a new error for `operation` on `manager`.
+    pub fn new(manager: impl Into<String>, operation: &'static str) -> Self {
+        Self {
+            manager: manager.into(),
+            operation,
+        }
+    }
+}
+
+impl Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is not supported by {}", self.operation, self.manager)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+/// Error returned when no package owns a file, see
+/// [``crate::PackageManager::owner``].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotOwned(pub PathBuf);
+
+impl Display for NotOwned {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is not owned by any package", self.0.display())
+    }
+}
+
+impl std::error::Error for NotOwned {}
+
+/// Progress reported by a package manager while it runs, see
+/// [``crate::PackageManager::parse_progress``].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Progress {
+    /// Number of packages the operation is going to process.
+    Total(usize),
+    /// Started processing a package, e.g. apt's `Unpacking <pkg>`.
+    Started(String),
+    /// Finished processing a package, e.g. apt's `Setting up <pkg>`.
+    Finished(String),
+}
+
+/// Aggregates [`Progress`] events into `(done, total)` counts.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProgressCounter {
+    done: usize,
+    total: usize,
+}
+
+impl ProgressCounter {
+    /// Account for a progress event and return the new `(done, total)` counts
+    /// if they changed.
+    pub fn update(&mut self, progress: Progress) -> Option<(usize, usize)> {
+        match progress {
+            Progress::Total(total) => self.total = total,
+            Progress::Started(_) => return None,
+            Progress::Finished(_) => {
+                self.done += 1;
+                // the total might be unknown or underestimated
+                self.total = self.total.max(self.done);
+            }
+        }
+        Some((self.done, self.total))
+    }
 }
 
 /// Command result is a tuple of ExitStatus, stdout lines
@@ -293,6 +830,21 @@ impl std::fmt::Display for CommandResult {
     }
 }
 
+/// An `ExitStatus` for operations that are not backed by a single process.
+pub(crate) fn synthetic_exit_status(success: bool) -> std::process::ExitStatus {
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        // wait status: the exit code is stored in the second byte.
+        std::process::ExitStatus::from_raw(if success { 0 } else { 1 << 8 })
+    }
+    #[cfg(target_family = "windows")]
+    {
+        use std::os::windows::process::ExitStatusExt;
+        std::process::ExitStatus::from_raw(if success { 0 } else { 1 })
+    }
+}
+
 /// Execute a command and stream its output. Collect response.
 pub fn run_command<S: AsRef<str> + std::convert::AsRef<std::ffi::OsStr>>(
     mut cmd: Command,
@@ -305,37 +857,364 @@ pub fn run_command<S: AsRef<str> + std::convert::AsRef<std::ffi::OsStr>>(
     if interactive == Some(true) {
         print_header();
 
-        let filtered_args: Vec<&S> = args
-            .iter()
-            .filter(|x| AsRef::<str>::as_ref(x) != "-y")
-            .collect();
-
-        let mut child = cmd.args(filtered_args).spawn()?;
+        let mut child = cmd.args(args).spawn()?;
         let ec = child.wait()?;
         return Ok(CommandResult(ec, result));
     }
 
-    let mut child = cmd.args(args).stdout(Stdio::piped()).spawn()?;
+    let ec = run_command_with(cmd, args, |line| {
+        if stream_to_stdout {
+            let captured = CAPTURED_OUTPUT.with(|captured| {
+                let mut captured = captured.borrow_mut();
+                let lines = captured.as_mut()?;
+                lines.push(format!("[MPM] {line}"));
+                Some(())
+            });
+            if captured.is_none() {
+                println!("[MPM] {line}");
+            }
+        } else {
+            tracing::debug!(">> {line}");
+        }
+        result.push(line.to_string());
+    })?;
+    Ok(CommandResult(ec, result))
+}
+
+/// Execute a command and pass each line of its output to `on_line` as soon as
+/// it is printed. Its errors are printed to stderr as they come and kept for
+/// [`last_stderr`].
+pub fn run_command_with<S, F>(
+    mut cmd: Command,
+    args: &[S],
+    mut on_line: F,
+) -> anyhow::Result<std::process::ExitStatus>
+where
+    S: AsRef<std::ffi::OsStr>,
+    F: FnMut(&str),
+{
+    let mut child = cmd
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stderr = child.stderr.take().unwrap();
+    let stderr_reader = std::thread::spawn(move || {
+        BufReader::new(stderr)
+            .lines()
+            .map_while(Result::ok)
+            .inspect(|line| eprintln!("{line}"))
+            .collect::<Vec<_>>()
+    });
     {
         let stdout = child.stdout.as_mut().unwrap();
         let stdout_reader = BufReader::new(stdout);
-        let stdout_lines = stdout_reader.lines();
-
-        for line in stdout_lines.map_while(Result::ok) {
-            if stream_to_stdout {
-                println!("[MPM] {line}");
-            } else {
-                tracing::debug!(">> {line}");
-            }
-            result.push(line);
+        for line in stdout_reader.lines().map_while(Result::ok) {
+            on_line(&line);
         }
     }
     let ec = child.wait()?;
+    let stderr = stderr_reader.join().unwrap_or_default();
+    LAST_STDERR.with(|last| last.replace(stderr));
     tracing::trace!(">>> command response: {}", ec);
-    Ok(CommandResult(ec, result))
+    Ok(ec)
+}
+
+/// Group files by the name of the package they belong to, keeping the order
+/// in which the packages were first seen.
+pub(crate) fn group_by_package<I: IntoIterator<Item = (String, PathBuf)>>(
+    manager: String,
+    files: I,
+) -> Vec<(Package, Vec<PathBuf>)> {
+    let mut groups: Vec<(Package, Vec<PathBuf>)> = vec![];
+    for (name, path) in files {
+        match groups.iter_mut().find(|(pkg, _)| pkg.name() == name) {
+            Some((_, paths)) => paths.push(path),
+            None => groups.push((Package::new(&name, manager.clone(), None), vec![path])),
+        }
+    }
+    groups
+}
+
+/// Parse a list of installed files, one absolute path per line as printed by
+/// `dpkg -L` and `rpm -ql`. Other lines, e.g. `(contains no files)` or
+/// diversion notes, and dpkg's `/.` entry are skipped.
+pub(crate) fn parse_file_list(output: &str) -> Vec<PathBuf> {
+    output
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| l.starts_with('/') && *l != "/.")
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Parse a human readable size like `5,678 kB`, `1.2 M` or `3 MiB` into
+/// bytes.
+///
+/// Units with a `B` suffix (`kB`, `MB`, `GB`) are decimal as printed by apt,
+/// single letters (`k`, `M`, `G`) and `KiB`, `MiB`, `GiB` are binary as
+/// printed by dnf.
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim().replace(',', "");
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let num: f64 = num.parse().ok()?;
+    let factor: u64 = match unit.trim() {
+        "" | "B" => 1,
+        "kB" | "KB" => 1000,
+        "MB" => 1000_u64.pow(2),
+        "GB" => 1000_u64.pow(3),
+        "k" | "K" | "KiB" => 1024,
+        "M" | "MiB" => 1024_u64.pow(2),
+        "G" | "GiB" => 1024_u64.pow(3),
+        _ => return None,
+    };
+    Some((num * factor as f64).round() as u64)
+}
+
+/// Total size in bytes of the files below `dir`, not following symlinks.
+/// Unreadable entries and a missing directory count as empty.
+pub fn dir_size<P: AsRef<Path>>(dir: P) -> u64 {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            Some(if meta.is_dir() {
+                dir_size(entry.path())
+            } else {
+                meta.len()
+            })
+        })
+        .sum()
+}
+
+/// Files in `dir` with the given extension, sorted by name. A missing
+/// directory has no files.
+pub(crate) fn files_with_extension<P: AsRef<Path>>(dir: P, ext: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == ext))
+        .collect();
+    files.sort();
+    files
+}
+
+/// The host that most of the given mirror URLs point to, i.e. the primary
+/// mirror. Ties go to the host seen first.
+pub(crate) fn primary_host<'a, I: IntoIterator<Item = &'a url::Url>>(urls: I) -> Option<String> {
+    let mut counts: Vec<(String, usize)> = vec![];
+    for host in urls.into_iter().filter_map(|u| u.host_str()) {
+        match counts.iter_mut().find(|(h, _)| h == host) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((host.to_string(), 1)),
+        }
+    }
+    // `max_by_key` returns the last maximum, so search from the back
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, n)| *n)
+        .map(|(h, _)| h)
+}
+
+/// `uri` with the scheme, host and port of `mirror`, keeping its path and
+/// query.
+pub(crate) fn with_mirror_origin(uri: &url::Url, mirror: &url::Url) -> String {
+    let mut s = format!("{}{}", mirror.origin().ascii_serialization(), uri.path());
+    if let Some(query) = uri.query() {
+        s.push('?');
+        s.push_str(query);
+    }
+    s
+}
+
+/// Escape regular expression metacharacters so that `s` is matched literally.
+pub fn escape_regex(s: &str) -> String {
+    const META: &[char] = &[
+        '\\', '.', '+', '*', '?', '(', ')', '|', '[', ']', '{', '}', '^', '$',
+    ];
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if META.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Quote `arg` for a POSIX shell if it contains anything but plain
+/// characters.
+pub fn shell_quote(arg: &str) -> Cow<'_, str> {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_.,:/=+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        return Cow::Borrowed(arg);
+    }
+    Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+}
+
+/// A standalone bash script running the given commands, program first, that
+/// stops at the first failing command.
+pub fn to_shell_script(operations: &[Vec<String>]) -> String {
+    let mut script = String::from("#!/usr/bin/env bash\n# Generated by mpm\nset -euo pipefail\n\n");
+    for op in operations {
+        let line: Vec<Cow<'_, str>> = op.iter().map(|arg| shell_quote(arg)).collect();
+        script.push_str(&line.join(" "));
+        script.push('\n');
+    }
+    script
+}
+
+/// URL schemes packages are always downloaded from.
+pub const SECURE_URL_SCHEMES: &[&str] = &["https", "file"];
+
+/// URL schemes packages are only downloaded from after
+/// [`set_allow_insecure_urls`].
+pub const INSECURE_URL_SCHEMES: &[&str] = &["http", "ftp"];
+
+static ALLOW_INSECURE_URLS: AtomicBool = AtomicBool::new(false);
+
+/// Allow downloading packages from [`INSECURE_URL_SCHEMES`], which are
+/// refused by default.
+pub fn set_allow_insecure_urls(allow: bool) {
+    ALLOW_INSECURE_URLS.store(allow, Ordering::Relaxed);
+}
+
+/// Fail unless packages may be downloaded from `url`.
+pub fn ensure_allowed_url(url: &url::Url) -> anyhow::Result<()> {
+    ensure_allowed_url_with(url, ALLOW_INSECURE_URLS.load(Ordering::Relaxed))
+}
+
+/// Like [`ensure_allowed_url`], but allows [`INSECURE_URL_SCHEMES`] only if
+/// `allow_insecure`.
+fn ensure_allowed_url_with(url: &url::Url, allow_insecure: bool) -> anyhow::Result<()> {
+    let scheme = url.scheme();
+    if SECURE_URL_SCHEMES.contains(&scheme) {
+        return Ok(());
+    }
+    anyhow::ensure!(
+        INSECURE_URL_SCHEMES.contains(&scheme),
+        "Refusing to download {url}: unsupported URL scheme {scheme:?}"
+    );
+    anyhow::ensure!(
+        allow_insecure,
+        "Refusing to download {url} over insecure {scheme}, pass --allow-insecure-url to allow it"
+    );
+    Ok(())
+}
+
+static ASSUME_YES: AtomicBool = AtomicBool::new(true);
+
+/// Whether to answer the package manager's confirmation prompts, see
+/// [``crate::PackageManagerCommands::yes_flags``]. Enabled by default.
+pub fn set_assume_yes(assume_yes: bool) {
+    ASSUME_YES.store(assume_yes, Ordering::Relaxed);
+}
+
+/// Whether [`set_assume_yes`] is enabled.
+pub fn assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed)
 }
 
-/// Download this url to the disk.
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Only simulate commands that change the system, see
+/// [``crate::PackageManagerCommands::dry_run_flags``]. Commands without a
+/// native simulation are printed instead of run.
+pub fn set_dry_run(dry_run: bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
+/// Whether [`set_dry_run`] is enabled.
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+thread_local! {
+    static CAPTURED_OUTPUT: std::cell::RefCell<Option<Vec<String>>> =
+        const { std::cell::RefCell::new(None) };
+    static LAST_STDERR: std::cell::RefCell<Vec<String>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// What the last command run with [`run_command_with`] on this thread
+/// printed to stderr, e.g. to tell why a package manager failed.
+pub fn last_stderr() -> Vec<String> {
+    LAST_STDERR.with(|last| last.borrow().clone())
+}
+
+/// Run `f` and return what the commands it ran on this thread printed
+/// instead of printing it, e.g. to print the output of package managers
+/// running in parallel one after the other.
+pub fn capture_output<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+    let outer = CAPTURED_OUTPUT.with(|captured| captured.replace(Some(vec![])));
+    let result = f();
+    let lines = CAPTURED_OUTPUT.with(|captured| captured.replace(outer));
+    (result, lines.unwrap_or_default())
+}
+
+/// Whether `args` contain all of the non-empty `flags`, i.e. the command
+/// only simulates its changes.
+pub(crate) fn is_simulated<S: AsRef<str>>(args: &[S], flags: Option<&[String]>) -> bool {
+    flags.is_some_and(|flags| {
+        !flags.is_empty()
+            && flags
+                .iter()
+                .all(|flag| args.iter().any(|arg| arg.as_ref() == flag))
+    })
+}
+
+/// The command line `cmd` with `args` would run, quoted for a POSIX shell.
+pub(crate) fn command_line<S: AsRef<str>>(cmd: &Command, args: &[S]) -> String {
+    let program = cmd.get_program().to_string_lossy();
+    std::iter::once(shell_quote(&program).into_owned())
+        .chain(
+            args.iter()
+                .map(|arg| shell_quote(arg.as_ref()).into_owned()),
+        )
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Run `cmd` in dry-run mode: as a simulation if `args` contain the
+/// simulation `flags`, or else print it. Whether a simulation succeeded is
+/// decided by `succeeded` from its exit status and output, since e.g. dnf's
+/// `--assumeno` always fails.
+pub(crate) fn exec_dry_run<S, F>(
+    cmd: Command,
+    args: &[S],
+    flags: Option<&[String]>,
+    succeeded: F,
+) -> std::process::ExitStatus
+where
+    S: AsRef<str> + AsRef<std::ffi::OsStr>,
+    F: FnOnce(std::process::ExitStatus, &[String]) -> bool,
+{
+    let strs: Vec<&str> = args.iter().map(AsRef::<str>::as_ref).collect();
+    let line = command_line(&cmd, &strs);
+    if !is_simulated(&strs, flags) {
+        println!("Would run: {line}");
+        return synthetic_exit_status(true);
+    }
+    tracing::debug!("Simulating {line}");
+    // simulations ask nothing, so their output is always captured
+    match run_command(cmd, args, true, None) {
+        Ok(CommandResult(status, output)) => synthetic_exit_status(succeeded(status, &output)),
+        Err(e) => {
+            tracing::warn!("Failed to simulate {line}: {e}");
+            synthetic_exit_status(false)
+        }
+    }
+}
+
+/// Download `url` to `pkgpath`, streaming it to the disk and reporting the
+/// progress to the [`set_download_reporter`] callback.
 pub fn download_url(url: &url::Url, pkgpath: &Path, force: bool) -> anyhow::Result<()> {
     use std::io::Write;
     tracing::debug!("Downloading package from `{url}` (force={force})...");
@@ -343,21 +1222,186 @@ pub fn download_url(url: &url::Url, pkgpath: &Path, force: bool) -> anyhow::Resu
         tracing::info!("{pkgpath:?} already exists. Reusing it since `force=false`.");
         return Ok(());
     }
+    // redirects must not downgrade to a scheme that is not allowed
+    let redirect =
+        reqwest::redirect::Policy::custom(|attempt| match ensure_allowed_url(attempt.url()) {
+            Err(e) => attempt.error(e),
+            Ok(()) if attempt.previous().len() >= 10 => attempt.error("too many redirects"),
+            Ok(()) => attempt.follow(),
+        });
     let resp = reqwest::blocking::Client::builder()
         .timeout(None)
+        .redirect(redirect)
         .build()?
         .get(url.as_str())
         .send()?;
     resp.error_for_status_ref()?;
+    let total = resp.content_length();
 
-    let bytes = resp.bytes()?;
-    tracing::debug!(" ... fetched {} MB.", bytes.len() / 1024 / 1024);
+    // a failed download must not leave a file that is reused later
+    let mut partial = pkgpath.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+    let mut file =
+        std::fs::File::create(&partial).with_context(|| format!("failed to create {partial:?}"))?;
+    let reporter = DOWNLOAD_REPORTER.read().ok().and_then(|r| r.clone());
+    let copied = copy_with_progress(resp, &mut file, url, total, |progress| {
+        if let Some(report) = &reporter {
+            report(progress);
+        }
+    })
+    .and_then(|size| file.flush().map(|()| size));
+    let size = match copied {
+        Ok(size) => size,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e).with_context(|| format!("failed to download {url}"));
+        }
+    };
+    tracing::debug!(" ... fetched {} MB.", size / 1024 / 1024);
+    std::fs::rename(&partial, pkgpath)
+        .with_context(|| format!("failed to move {partial:?} to {pkgpath:?}"))
+}
+
+/// Hex encoded SHA-256 checksum of the file at `path`.
+pub(crate) fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(path).with_context(|| format!("failed to open {path:?}"))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
 
-    let mut buffer = std::fs::File::create(pkgpath)?;
-    buffer.write_all(&bytes)?;
+/// Check the detached GPG `signature` of the file at `path` with `gpg
+/// --verify`, see [`check_gpg_status`].
+fn verify_gpg_signature(signature: &Path, path: &Path) -> anyhow::Result<()> {
+    let out = Command::new("gpg")
+        .args(["--batch", "--status-fd", "1", "--verify"])
+        .arg(signature)
+        .arg(path)
+        .output()
+        .context("failed to run gpg, is it installed?")?;
+    let checked = if out.status.success() {
+        check_gpg_status(&String::from_utf8_lossy(&out.stdout))
+    } else {
+        Err(anyhow::anyhow!(
+            "{}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ))
+    };
+    checked.with_context(|| format!("Signature of {path:?} is invalid"))
+}
+
+/// Check the `--status-fd` output of `gpg --verify`: the signature must be
+/// good (`VALIDSIG`) and made by a fully or ultimately trusted key, not just
+/// by any key in the keyring, which gpg accepts as well.
+pub(crate) fn check_gpg_status(status: &str) -> anyhow::Result<()> {
+    let keywords: Vec<&str> = status
+        .lines()
+        .filter_map(|l| l.strip_prefix("[GNUPG:] "))
+        .filter_map(|l| l.split_whitespace().next())
+        .collect();
+    anyhow::ensure!(
+        keywords.contains(&"VALIDSIG"),
+        "gpg reported no valid signature"
+    );
+    anyhow::ensure!(
+        keywords
+            .iter()
+            .any(|k| matches!(*k, "TRUST_FULLY" | "TRUST_ULTIMATE")),
+        "the signing key is not trusted, trust it with `gpg --edit-key <key> trust`"
+    );
     Ok(())
 }
 
+/// Progress of a package download, see [`set_download_reporter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadProgress {
+    /// URL being downloaded.
+    pub url: url::Url,
+    /// Bytes downloaded so far.
+    pub downloaded: u64,
+    /// Size of the download, if the server sent a `Content-Length`.
+    pub total: Option<u64>,
+    /// Time since the download started.
+    pub elapsed: std::time::Duration,
+    /// Whether the download is complete. Failed downloads are never done.
+    pub done: bool,
+}
+
+impl DownloadProgress {
+    /// Time until the download completes at its average speed so far. `None`
+    /// while the size or the speed is unknown.
+    pub fn eta(&self) -> Option<std::time::Duration> {
+        let total = self.total?;
+        if self.downloaded == 0 || self.elapsed.is_zero() {
+            return None;
+        }
+        let remaining = total.saturating_sub(self.downloaded) as f64;
+        let secs = self.elapsed.as_secs_f64() * remaining / self.downloaded as f64;
+        Some(std::time::Duration::from_secs_f64(secs))
+    }
+}
+
+/// Callback receiving the progress of package downloads.
+pub type DownloadReporter = std::sync::Arc<dyn Fn(&DownloadProgress) + Send + Sync>;
+
+static DOWNLOAD_REPORTER: std::sync::RwLock<Option<DownloadReporter>> =
+    std::sync::RwLock::new(None);
+
+/// Report the progress of the packages downloaded from URLs, e.g. by
+/// [`Package::make_available_on_disk`], to `reporter`. `None` stops
+/// reporting.
+pub fn set_download_reporter(reporter: Option<DownloadReporter>) {
+    if let Ok(mut current) = DOWNLOAD_REPORTER.write() {
+        *current = reporter;
+    }
+}
+
+/// Copy `reader` to `writer` in chunks, calling `on_progress` when the copy
+/// starts, after every chunk and once it is done. Returns the number of bytes
+/// copied.
+fn copy_with_progress<R, W, F>(
+    mut reader: R,
+    writer: &mut W,
+    url: &url::Url,
+    total: Option<u64>,
+    mut on_progress: F,
+) -> std::io::Result<u64>
+where
+    R: std::io::Read,
+    W: std::io::Write,
+    F: FnMut(&DownloadProgress),
+{
+    let start = std::time::Instant::now();
+    let mut progress = DownloadProgress {
+        url: url.clone(),
+        downloaded: 0,
+        total,
+        elapsed: std::time::Duration::ZERO,
+        done: false,
+    };
+    on_progress(&progress);
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n])?;
+        progress.downloaded += n as u64;
+        progress.elapsed = start.elapsed();
+        on_progress(&progress);
+    }
+    progress.done = true;
+    progress.elapsed = start.elapsed();
+    on_progress(&progress);
+    Ok(progress.downloaded)
+}
+
 fn print_header() {
     if let Some((Width(w), _)) = terminal_size() {
         let text = "[MPM interactive]";
@@ -372,3 +1416,343 @@ fn print_header() {
         println!("-------------------[MPM interactive]----------------");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insecure_url_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("hello.deb");
+        // refused before connecting, insecure URLs are not allowed by default
+        let mut pkg = "http://example.com/hello.deb".parse::<Package>().unwrap();
+        let err = pkg.make_available_on_disk(Some(&output), true).unwrap_err();
+        assert!(err.to_string().contains("--allow-insecure-url"), "{err}");
+        assert!(!output.exists());
+        // verified downloads are not left in a shared directory
+        let mut pkg = "https://example.com/hello.deb#sha256=ba7816bf"
+            .parse::<Package>()
+            .unwrap();
+        assert!(pkg.make_available_on_disk(None, true).is_err());
+
+        let url = |s: &str| url::Url::parse(s).unwrap();
+        assert!(ensure_allowed_url_with(&url("https://example.com/hello.deb"), false).is_ok());
+        assert!(ensure_allowed_url_with(&url("file:///tmp/hello.deb"), false).is_ok());
+        assert!(ensure_allowed_url_with(&url("ftp://example.com/hello.deb"), false).is_err());
+        assert!(ensure_allowed_url_with(&url("ftp://example.com/hello.deb"), true).is_ok());
+        assert!(ensure_allowed_url_with(&url("http://example.com/hello.deb"), true).is_ok());
+        assert!(ensure_allowed_url_with(&url("gopher://example.com/hello.deb"), true).is_err());
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mpm");
+        std::fs::write(&path, "abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_verify_file() {
+        let pkg: Package = "https://example.com/hello.deb#version=1.0,sha256=BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD,signature=https://example.com/hello.deb.asc"
+            .parse()
+            .unwrap();
+        assert_eq!(pkg.version(), Some("1.0"));
+        assert_eq!(
+            pkg.sha256(),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert_eq!(
+            pkg.signature().map(url::Url::as_str),
+            Some("https://example.com/hello.deb.asc")
+        );
+        assert!(pkg.needs_verification());
+        assert!("https://example.com/hello.deb#signature=not-a-url"
+            .parse::<Package>()
+            .is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hello.deb");
+        std::fs::write(&path, "abc").unwrap();
+        let mut pkg = Package::from("https://example.com/hello.deb");
+        assert!(!pkg.needs_verification());
+        pkg.set_sha256("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert!(pkg.verify_file(&path).is_ok());
+        std::fs::write(&path, "abd").unwrap();
+        let err = pkg.verify_file(&path).unwrap_err();
+        assert!(err.to_string().contains("expected ba7816bf"), "{err}");
+
+        // a verified copy of a local file
+        let private = tempfile::tempdir().unwrap();
+        std::fs::write(&path, "abc").unwrap();
+        let mut pkg = Package::from(&path);
+        pkg.set_sha256("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        let copy = pkg.make_verified_copy(private.path()).unwrap();
+        assert_eq!(copy, private.path().join("hello.deb"));
+        assert_eq!(pkg.url().unwrap().to_file_path().unwrap(), copy);
+        std::fs::write(&path, "abd").unwrap();
+        let mut pkg = Package::from(&path);
+        pkg.set_sha256("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert!(pkg.make_verified_copy(private.path()).is_err());
+        assert!(!copy.exists());
+    }
+
+    #[test]
+    fn test_check_gpg_status() {
+        let good = "[GNUPG:] NEWSIG\n\
+                    [GNUPG:] GOODSIG 4AEE18F83AFDEB23 Jane <jane@example.com>\n\
+                    [GNUPG:] VALIDSIG 0A1B 2024-11-04 1730700000 0 4 0 1 10 00 0A1B\n";
+        let err = check_gpg_status(good).unwrap_err();
+        assert!(err.to_string().contains("not trusted"), "{err}");
+        assert!(check_gpg_status(&format!("{good}[GNUPG:] TRUST_FULLY 0 pgp\n")).is_ok());
+        assert!(check_gpg_status(&format!("{good}[GNUPG:] TRUST_ULTIMATE 0 pgp\n")).is_ok());
+        assert!(check_gpg_status("[GNUPG:] BADSIG 4AEE18F83AFDEB23 Jane\n").is_err());
+        assert!(check_gpg_status("[GNUPG:] TRUST_FULLY 0 pgp\n").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capture_output() {
+        let (result, lines) =
+            capture_output(|| run_command(Command::new("echo"), &["hello"], true, None).unwrap());
+        assert!(result.0.success());
+        assert_eq!(lines, ["[MPM] hello"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_last_stderr() {
+        let status = run_command_with(
+            Command::new("sh"),
+            &["-c", "echo out; echo err >&2"],
+            |_| {},
+        )
+        .unwrap();
+        assert!(status.success());
+        assert_eq!(last_stderr(), ["err"]);
+    }
+
+    #[test]
+    fn test_parse_file_list() {
+        let output = "/.\n/usr\n/usr/bin/wget\ndiverted by foo to: /usr/bin/wget.real\n";
+        assert_eq!(
+            parse_file_list(output),
+            [PathBuf::from("/usr"), PathBuf::from("/usr/bin/wget")]
+        );
+        assert!(parse_file_list("(contains no files)\n").is_empty());
+    }
+
+    #[test]
+    fn test_dir_size() {
+        let dir = std::env::temp_dir().join(format!("mpm-dir-size-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("a"), [0; 10]).unwrap();
+        std::fs::write(dir.join("nested/b"), [0; 5]).unwrap();
+        assert_eq!(dir_size(&dir), 15);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(dir_size(&dir), 0);
+    }
+
+    #[test]
+    fn test_to_shell_script() {
+        let script = to_shell_script(&[
+            vec!["apt".into(), "install".into(), "--yes".into(), "foo".into()],
+            vec![
+                "choco".into(),
+                "install".into(),
+                "--params='/Dir:C:\\my dir'".into(),
+            ],
+        ]);
+        assert_eq!(
+            script,
+            r#"#!/usr/bin/env bash
+# Generated by mpm
+set -euo pipefail
+
+apt install --yes foo
+choco install '--params='\''/Dir:C:\my dir'\'''
+"#
+        );
+    }
+
+    #[test]
+    fn test_copy_with_progress() {
+        let url: url::Url = "https://example.com/hello.deb".parse().unwrap();
+        let data = vec![7u8; 150 * 1024];
+        let mut out = vec![];
+        let mut events = vec![];
+        let size = copy_with_progress(data.as_slice(), &mut out, &url, Some(150 * 1024), |p| {
+            events.push((p.downloaded, p.done))
+        })
+        .unwrap();
+        assert_eq!(size, 150 * 1024);
+        assert_eq!(out, data);
+        assert_eq!(events.first(), Some(&(0, false)));
+        assert_eq!(events.last(), Some(&(150 * 1024, true)));
+        assert!(events.windows(2).all(|w| w[0].0 <= w[1].0));
+    }
+
+    #[test]
+    fn test_download_eta() {
+        let mut progress = DownloadProgress {
+            url: "https://example.com/hello.deb".parse().unwrap(),
+            downloaded: 25,
+            total: Some(100),
+            elapsed: std::time::Duration::from_secs(10),
+            done: false,
+        };
+        assert_eq!(progress.eta(), Some(std::time::Duration::from_secs(30)));
+        progress.total = None;
+        assert_eq!(progress.eta(), None);
+        progress.total = Some(100);
+        progress.downloaded = 0;
+        assert_eq!(progress.eta(), None);
+    }
+
+    #[test]
+    fn test_dry_run_command_line() {
+        let args = ["install", "--yes", "--simulate", "my pkg"];
+        assert_eq!(
+            command_line(&Command::new("apt-get"), &args),
+            "apt-get install --yes --simulate 'my pkg'"
+        );
+        let simulate = vec!["--simulate".to_string()];
+        assert!(is_simulated(&args, Some(simulate.as_slice())));
+        assert!(!is_simulated(&args[..2], Some(simulate.as_slice())));
+        assert!(!is_simulated(&args, Some(&[][..])));
+        assert!(!is_simulated(&args, None));
+    }
+
+    #[test]
+    fn test_pkg_file_arch() {
+        let arch = |name: &str| pkg_file_arch(Path::new(name));
+        assert_eq!(arch("hello_2.10-3_amd64.deb"), Some("x86_64"));
+        assert_eq!(arch("/tmp/tzdata_2024a-1_all.deb"), None);
+        assert_eq!(arch("hello-2.12.1-5.fc41.aarch64.rpm"), Some("aarch64"));
+        assert_eq!(arch("fonts-1.0-1.noarch.rpm"), None);
+        assert_eq!(
+            arch("hello--2.12.arm64_sonoma.bottle.tar.gz"),
+            Some("aarch64")
+        );
+        assert_eq!(arch("hello--2.12.sonoma.bottle.tar.gz"), Some("x86_64"));
+        assert_eq!(arch("setup.exe"), None);
+    }
+
+    #[test]
+    fn test_package_serialization_omits_empty_fields() {
+        let pkg = Package::new("foo", "".to_string(), None);
+        assert_eq!(serde_json::to_string(&pkg).unwrap(), r#"{"name":"foo"}"#);
+
+        let pkg = Package::new("foo", "apt".to_string(), Some("1.0"));
+        assert_eq!(
+            serde_json::to_string(&pkg).unwrap(),
+            r#"{"name":"foo","package_manager":"apt","version":"1.0"}"#
+        );
+    }
+
+    #[test]
+    fn test_package_deserialization() {
+        let expected = Package::new("foo", "".to_string(), None);
+        let absent: Package = serde_json::from_str(r#"{"name":"foo"}"#).unwrap();
+        assert_eq!(absent, expected);
+
+        let null: Package =
+            serde_json::from_str(r#"{"name":"foo","version":null,"url":null}"#).unwrap();
+        assert_eq!(null, expected);
+
+        let pkg: Package =
+            serde_json::from_str(r#"{"name":"foo","package_manager":"apt","version":"1.0"}"#)
+                .unwrap();
+        assert_eq!(pkg, Package::new("foo", "apt".to_string(), Some("1.0")));
+    }
+
+    #[test]
+    fn test_install_action() {
+        let requested = Package::new("firefox", "apt".to_string(), Some("101"));
+        let same = Package::new("firefox", "apt".to_string(), Some("101"));
+        let older = Package::new("firefox", "apt".to_string(), Some("100"));
+
+        assert_eq!(
+            InstallAction::decide(Some(&same), &requested, || true),
+            InstallAction::Skip
+        );
+        assert_eq!(
+            InstallAction::decide(Some(&same), &requested, || false),
+            InstallAction::Reinstall
+        );
+        assert_eq!(
+            InstallAction::decide(None, &requested, || unreachable!()),
+            InstallAction::Install
+        );
+        assert_eq!(
+            InstallAction::decide(Some(&older), &requested, || unreachable!()),
+            InstallAction::Install
+        );
+
+        // any installed version satisfies a request without version
+        let any = Package::from("firefox");
+        assert_eq!(
+            InstallAction::decide(Some(&older), &any, || true),
+            InstallAction::Skip
+        );
+    }
+
+    #[test]
+    fn test_package_try_from_string() {
+        let pkg = Package::try_from("apt@hello@2.10".to_string()).unwrap();
+        assert_eq!(pkg, Package::new("hello", "apt".to_string(), Some("2.10")));
+        assert_eq!(
+            Package::try_from("hello".to_string()).unwrap(),
+            Package::new("hello", "".to_string(), None)
+        );
+
+        let url = Package::try_from("https://example.com/hello_2.10.deb".to_string()).unwrap();
+        assert_eq!(url.name(), "hello_2.10.deb");
+
+        assert!(Package::try_from("a@b@c@d".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_package_manager_prefix() {
+        assert_eq!(
+            "brew:wget".parse::<Package>().unwrap(),
+            Package::new("wget", "brew".to_string(), None)
+        );
+        assert_eq!(
+            "Flatpak:org.gimp.GIMP@2.10".parse::<Package>().unwrap(),
+            Package::new("org.gimp.GIMP", "flatpak".to_string(), Some("2.10"))
+        );
+        assert!("apt:a@b@c".parse::<Package>().is_err());
+
+        // URLs and other colons are not prefixes
+        let url = "https://example.com/hello_2.10.deb"
+            .parse::<Package>()
+            .unwrap();
+        assert_eq!(url.package_manager(), "");
+        assert!(url.url().is_some());
+        let pkg = "file:hello.deb".parse::<Package>().unwrap();
+        assert_eq!(pkg.package_manager(), "");
+
+        assert_eq!(
+            manager_prefix("brew:wget"),
+            Some((AvailablePackageManager::Brew, "wget"))
+        );
+        assert_eq!(manager_prefix("brew@wget"), None);
+        assert_eq!(manager_prefix("file:///tmp/hello.deb"), None);
+
+        // Nix flake references
+        assert_eq!(manager_prefix("github:owner/repo#tool"), None);
+        assert_eq!(
+            manager_prefix("nix:github:owner/repo#tool"),
+            Some((AvailablePackageManager::Nix, "github:owner/repo#tool"))
+        );
+        let pkg = "github:owner/repo#tool".parse::<Package>().unwrap();
+        assert_eq!(pkg.name(), "github:owner/repo#tool");
+        assert_eq!(pkg.package_manager(), "");
+        assert!(pkg.url().is_none());
+    }
+}
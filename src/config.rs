@@ -0,0 +1,297 @@
+//! mpm configuration file.
+//!
+//! The configuration is read from `mpm.toml` in the user's config directory
+//! (e.g. `~/.config/mpm/mpm.toml` on Linux) unless another file is given with
+//! `--config`. Command line arguments take precedence over the configuration.
+//!
+//! ```toml
+//! manager = "dnf"
+//! # install, remove and update apt packages with apt-get or aptitude, with
+//! # apt-get list, search and outdated use tools with a stable output as well
+//! apt_backend = "aptitude"
+//! # show the commands and ask before changing the installed packages
+//! confirm = true
+//!
+//! # order in which the package manager is picked when `manager` is not set,
+//! # overridden by the MPM_MANAGER_PRIORITY environment variable
+//! [priority]
+//! prefer = ["brew", "apt"]
+//! exclude = ["flatpak"]
+//!
+//! # install these packages with the given package manager
+//! [rules]
+//! docker = "apt"
+//! "python3-*" = "dnf"
+//!
+//! # names of a package for each package manager
+//! [aliases.build-essentials]
+//! apt = "build-essential"
+//! dnf = "@development-tools"
+//! apk = "build-base"
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::{
+    managers::{apt::AptBackend, ManagerPriority},
+    AvailablePackageManager, Package,
+};
+
+/// Contents of the configuration file. Missing keys take their default value.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Package manager to use when `--manager` is not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manager: Option<AvailablePackageManager>,
+
+    /// Frontend apt installs, removes and updates packages with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apt_backend: Option<AptBackend>,
+
+    /// Ask for confirmation before changing the installed packages, like
+    /// `--confirm`. `--yes` skips the question.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub confirm: bool,
+
+    /// Order in which the package manager is picked when neither
+    /// `--manager` nor `manager` is given. The `MPM_MANAGER_PRIORITY`
+    /// environment variable takes precedence.
+    #[serde(skip_serializing_if = "ManagerPriority::is_empty")]
+    pub priority: ManagerPriority,
+
+    /// Package name patterns mapped to the package manager to install them
+    /// with. A `*` in a pattern matches any sequence of characters.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<String, AvailablePackageManager>,
+
+    /// Package names mapped to the name of the package for each package
+    /// manager, e.g. `build-essentials` to `build-essential` for `apt`.
+    /// Package managers without an alias use the name as given.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Config {
+    /// Default location of the configuration file.
+    pub fn default_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("", "", "mpm").map(|d| d.config_dir().join("mpm.toml"))
+    }
+
+    /// Load the configuration from `path` if given, which must exist.
+    /// Otherwise load it from [``Config::default_path``] if it exists, or
+    /// fall back to the default configuration.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        Self::load_or(path, Self::default_path().as_deref())
+    }
+
+    fn load_or(path: Option<&Path>, default_path: Option<&Path>) -> anyhow::Result<Self> {
+        match (path, default_path) {
+            (Some(path), _) => {
+                anyhow::ensure!(path.is_file(), "Config file {path:?} does not exist");
+                Self::from_file(path)
+            }
+            (None, Some(default)) if default.is_file() => Self::from_file(default),
+            (None, _) => Ok(Self::default()),
+        }
+    }
+
+    /// Read the configuration from a TOML file.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {path:?}"))?;
+        toml::from_str(&content).with_context(|| format!("invalid config file {path:?}"))
+    }
+}
+
+/// Package manager that the config rules prefer for the package `name`.
+///
+/// An exact rule wins over patterns; among patterns, the longest one wins.
+pub fn resolve_manager_for(name: &str, config: &Config) -> Option<AvailablePackageManager> {
+    if let Some(manager) = config.rules.get(name) {
+        return Some(manager.clone());
+    }
+    config
+        .rules
+        .iter()
+        .filter(|(pattern, _)| glob_match(pattern, name))
+        .max_by_key(|(pattern, _)| pattern.len())
+        .map(|(_, manager)| manager.clone())
+}
+
+/// Name of the package `name` for the package manager `manager` (e.g.
+/// `apt`) according to the config aliases, `name` itself if there is none.
+pub fn resolve_alias<'a>(name: &'a str, manager: &str, config: &'a Config) -> &'a str {
+    config
+        .aliases
+        .get(name)
+        .and_then(|names| names.get(manager))
+        .map_or(name, String::as_str)
+}
+
+/// `pkg` renamed for the package manager `manager`, see [`resolve_alias`].
+pub fn alias_package(mut pkg: Package, manager: &str, config: &Config) -> Package {
+    let name = resolve_alias(pkg.name(), manager, config).to_string();
+    pkg.set_name(name);
+    pkg
+}
+
+/// Match `name` against `pattern` in which `*` matches any sequence of
+/// characters.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // no `*` in the pattern
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_path_overrides_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let default = dir.path().join("default.toml");
+        let custom = dir.path().join("custom.toml");
+        std::fs::write(&default, "manager = \"apt\"\n").unwrap();
+        std::fs::write(&custom, "manager = \"dnf\"\n").unwrap();
+
+        let config = Config::load_or(Some(&custom), Some(&default)).unwrap();
+        assert_eq!(config.manager, Some(AvailablePackageManager::Dnf));
+
+        let config = Config::load_or(None, Some(&default)).unwrap();
+        assert_eq!(config.manager, Some(AvailablePackageManager::Apt));
+
+        let missing = dir.path().join("missing.toml");
+        assert_eq!(
+            Config::load_or(None, Some(&missing)).unwrap(),
+            Config::default()
+        );
+        let err = Config::load_or(Some(&missing), Some(&default)).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+    }
+
+    #[test]
+    fn test_apt_backend() {
+        let config: Config = toml::from_str("apt_backend = \"apt-get\"\n").unwrap();
+        assert_eq!(config.apt_backend, Some(AptBackend::AptGet));
+        assert!(toml::from_str::<Config>("apt_backend = \"synaptic\"\n").is_err());
+    }
+
+    #[test]
+    fn test_confirm() {
+        let config: Config = toml::from_str("confirm = true\n").unwrap();
+        assert!(config.confirm);
+        assert_eq!(toml::to_string(&config).unwrap(), "confirm = true\n");
+        assert_eq!(toml::to_string(&Config::default()).unwrap(), "");
+    }
+
+    #[test]
+    fn test_priority() {
+        let config: Config = toml::from_str(
+            r#"
+            [priority]
+            prefer = ["brew", "apt"]
+            exclude = ["flatpak"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.priority.prefer,
+            [AvailablePackageManager::Brew, AvailablePackageManager::Apt]
+        );
+        assert_eq!(config.priority.exclude, [AvailablePackageManager::Flatpak]);
+        assert!(toml::from_str::<Config>("[priority]\nfirst = [\"apt\"]\n").is_err());
+    }
+
+    #[test]
+    fn test_resolve_manager_for() {
+        let config: Config = toml::from_str(
+            r#"
+            manager = "dnf"
+
+            [rules]
+            docker = "apt"
+            "python3-*" = "dnf"
+            "python3-*-doc" = "zypper"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.manager, Some(AvailablePackageManager::Dnf));
+        assert_eq!(
+            resolve_manager_for("docker", &config),
+            Some(AvailablePackageManager::Apt)
+        );
+        assert_eq!(
+            resolve_manager_for("python3-requests", &config),
+            Some(AvailablePackageManager::Dnf)
+        );
+        assert_eq!(
+            resolve_manager_for("python3-requests-doc", &config),
+            Some(AvailablePackageManager::Zypper)
+        );
+        assert_eq!(resolve_manager_for("docker-compose", &config), None);
+    }
+
+    #[test]
+    fn test_resolve_alias() {
+        let config: Config = toml::from_str(
+            r#"
+            [aliases.build-essentials]
+            apt = "build-essential"
+            apk = "build-base"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            resolve_alias("build-essentials", "apt", &config),
+            "build-essential"
+        );
+        assert_eq!(
+            resolve_alias("build-essentials", "apk", &config),
+            "build-base"
+        );
+        assert_eq!(
+            resolve_alias("build-essentials", "brew", &config),
+            "build-essentials"
+        );
+        assert_eq!(resolve_alias("curl", "apt", &config), "curl");
+
+        let pkg = Package::new("build-essentials", "apt".to_string(), Some("12.9"));
+        assert_eq!(
+            alias_package(pkg, "apt", &config),
+            Package::new("build-essential", "apt".to_string(), Some("12.9"))
+        );
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("docker", "docker"));
+        assert!(!glob_match("docker", "docker-ce"));
+        assert!(glob_match("docker*", "docker-ce"));
+        assert!(glob_match("*-doc", "python3-doc"));
+        assert!(glob_match("lib*-dev", "libssl-dev"));
+        assert!(!glob_match("lib*-dev", "libssl"));
+        assert!(!glob_match("a*a", "a"));
+        assert!(glob_match("*", "anything"));
+    }
+}
@@ -0,0 +1,357 @@
+//! Diagnostics of the environment the package managers run in, see
+//! `mpm doctor`.
+//!
+//! Every available package manager is checked for its version, whether
+//! another process holds its lock, whether its repositories are reachable
+//! and whether the directories it installs executables into are on `PATH`.
+//! Whether mpm can elevate with sudo is checked once. Each finding is a
+//! [`Check`] with a hint on what to do about it, so that monitoring agents
+//! can consume the report as JSON.
+
+use std::{path::Path, time::Duration};
+
+use strum::IntoEnumIterator;
+
+use crate::{AvailablePackageManager, MetaPackageManager, PackageManager, PackageManagerCommands};
+
+/// How long to wait for a repository to respond.
+const REPO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of a [`Check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    /// Works, but might not as expected.
+    Warning,
+    /// Operations will fail until it is fixed.
+    Error,
+}
+
+/// A single finding of `mpm doctor`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Check {
+    /// Name of the package manager the check is about, or `system`.
+    pub subject: String,
+    /// What was checked, e.g. `lock`.
+    pub check: String,
+    pub status: Status,
+    /// What was found.
+    pub message: String,
+    /// What to do about a warning or error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn new(subject: &str, check: &str, status: Status, message: impl Into<String>) -> Self {
+        Self {
+            subject: subject.to_string(),
+            check: check.to_string(),
+            status,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+/// Checks `manager`, or all available package managers if `None`, and
+/// whether mpm can elevate for those that need it.
+pub fn diagnose(manager: Option<AvailablePackageManager>) -> Vec<Check> {
+    let managers: Vec<MetaPackageManager> = match manager {
+        Some(manager) => match MetaPackageManager::new_if_available(manager) {
+            Ok(mpm) => vec![mpm],
+            Err(e) => {
+                return vec![Check::new(
+                    &manager.to_string().to_lowercase(),
+                    "available",
+                    Status::Error,
+                    e.to_string(),
+                )
+                .with_hint("install it, or check that it is on PATH")]
+            }
+        },
+        None => AvailablePackageManager::iter()
+            .map(MetaPackageManager::new)
+            .filter(|mpm| mpm.is_available())
+            .collect(),
+    };
+    if managers.is_empty() {
+        return vec![Check::new(
+            "system",
+            "managers",
+            Status::Error,
+            "no supported package manager was found",
+        )
+        .with_hint("install one, see `mpm managers`")];
+    }
+
+    // the repositories of each package manager are checked in parallel
+    let mut checks: Vec<Check> = std::thread::scope(|s| {
+        let handles: Vec<_> = managers
+            .iter()
+            .map(|mpm| (mpm, s.spawn(move || check_manager(mpm))))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|(mpm, h)| {
+                h.join().unwrap_or_else(|_| {
+                    vec![Check::new(
+                        &mpm.pkg_manager_name(),
+                        "checks",
+                        Status::Error,
+                        "checking it panicked",
+                    )
+                    .with_hint("run `RUST_LOG=debug mpm doctor` and report the output")]
+                })
+            })
+            .collect()
+    });
+    if managers.iter().any(|mpm| mpm.needs_sudo()) {
+        checks.extend(check_sudo());
+    }
+    checks
+}
+
+/// Checks the version, locks, repositories and executable directories of
+/// `mpm`.
+pub fn check_manager<P: PackageManager>(mpm: &P) -> Vec<Check> {
+    let name = mpm.pkg_manager_name();
+    let mut checks = vec![match mpm.version() {
+        Some(version) => Check::new(&name, "version", Status::Ok, version),
+        None => Check::new(
+            &name,
+            "version",
+            Status::Warning,
+            "failed to determine the version",
+        )
+        .with_hint(format!("check that `{name}` runs")),
+    }];
+
+    let locks = mpm.lock_files();
+    if !locks.is_empty() {
+        let held: Vec<_> = locks
+            .iter()
+            .filter_map(|path| lock_holder(path).map(|pid| (path, pid)))
+            .collect();
+        checks.push(match held.first() {
+            None => Check::new(&name, "lock", Status::Ok, "not locked"),
+            Some((path, pid)) => Check::new(
+                &name,
+                "lock",
+                Status::Error,
+                format!("{} is locked by process {pid}", path.display()),
+            )
+            .with_hint(format!(
+                "wait for process {pid} to finish, e.g. an automatic update"
+            )),
+        });
+    }
+
+    // none if listing the repositories is unsupported
+    if let Some(urls) = mpm.list_mirrors().ok().filter(|urls| !urls.is_empty()) {
+        let unreachable: Vec<String> = urls
+            .iter()
+            .filter(|url| !is_reachable(url))
+            .map(|url| url.to_string())
+            .collect();
+        checks.push(match unreachable.len() {
+            0 => Check::new(
+                &name,
+                "repositories",
+                Status::Ok,
+                format!("{} reachable", urls.len()),
+            ),
+            n => Check::new(
+                &name,
+                "repositories",
+                if n == urls.len() {
+                    Status::Error
+                } else {
+                    Status::Warning
+                },
+                format!("unreachable: {}", unreachable.join(", ")),
+            )
+            .with_hint("check the network connection and proxy, or `mpm mirrors set`"),
+        });
+    }
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    for dir in mpm.bin_dirs() {
+        if !on_path(&dir, &path) {
+            checks.push(
+                Check::new(
+                    &name,
+                    "path",
+                    Status::Warning,
+                    format!("{} is not on PATH", dir.display()),
+                )
+                .with_hint(format!(
+                    "add {} to PATH to run what {name} installs",
+                    dir.display()
+                )),
+            );
+        }
+    }
+    checks
+}
+
+/// Whether `dir` is one of the directories of the `PATH` variable `path`.
+fn on_path(dir: &Path, path: &std::ffi::OsStr) -> bool {
+    std::env::split_paths(path).any(|p| p == dir)
+}
+
+/// Whether a repository responds at all. Any HTTP status counts, as the
+/// root of many repositories is no page.
+fn is_reachable(url: &url::Url) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return true;
+    }
+    reqwest::blocking::Client::builder()
+        .timeout(REPO_TIMEOUT)
+        .build()
+        .and_then(|client| client.head(url.clone()).send())
+        .is_ok()
+}
+
+/// Whether mpm can elevate with sudo when a package manager needs it.
+#[cfg(target_os = "linux")]
+fn check_sudo() -> Option<Check> {
+    let check = |status, message: &str| Check::new("system", "sudo", status, message);
+    if !matches!(sudo::check(), sudo::RunningAs::User) {
+        return Some(check(Status::Ok, "running as root"));
+    }
+    let Ok(output) = std::process::Command::new("sudo")
+        .args(["--non-interactive", "true"])
+        .output()
+    else {
+        return Some(
+            check(Status::Error, "sudo is not installed")
+                .with_hint("install sudo, or run mpm as root"),
+        );
+    };
+    Some(if output.status.success() {
+        check(Status::Ok, "sudo works without a password")
+    } else {
+        check(Status::Warning, "sudo asks for a password")
+            .with_hint("run mpm in a terminal to enter it, or as root in scripts")
+    })
+}
+
+/// mpm only elevates on Linux.
+#[cfg(not(target_os = "linux"))]
+fn check_sudo() -> Option<Check> {
+    None
+}
+
+/// Process that holds a lock on the file at `path`.
+#[cfg(target_os = "linux")]
+fn lock_holder(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+
+    let meta = std::fs::metadata(path).ok()?;
+    let locks = std::fs::read_to_string("/proc/locks").ok()?;
+    lock_holder_in(&locks, device_numbers(meta.dev()), meta.ino())
+}
+
+/// Major and minor number of a Linux device number, like `major(3)` and
+/// `minor(3)`.
+#[cfg(target_os = "linux")]
+fn device_numbers(dev: u64) -> (u64, u64) {
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+    (major, minor)
+}
+
+/// Locks can only be looked up on Linux.
+#[cfg(not(target_os = "linux"))]
+fn lock_holder(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// Process holding a lock on the file with `inode` on the `device` with the
+/// given major and minor number, given the contents of `/proc/locks`, e.g.
+///
+/// ```text
+/// 1: POSIX  ADVISORY  WRITE 2143 08:02:1835023 0 EOF
+/// 1: -> POSIX  ADVISORY  WRITE 2200 08:02:1835023 0 EOF
+/// ```
+///
+/// The device numbers are hexadecimal. Waiting locks, marked with `->`, are
+/// not held.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn lock_holder_in(locks: &str, device: (u64, u64), inode: u64) -> Option<u32> {
+    locks.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields[..] {
+            [_id, kind, _, _, pid, file, ..] if kind != "->" => {
+                let mut numbers = file.split(':');
+                let major = u64::from_str_radix(numbers.next()?, 16).ok()?;
+                let minor = u64::from_str_radix(numbers.next()?, 16).ok()?;
+                let ino: u64 = numbers.next()?.parse().ok()?;
+                ((major, minor) == device && ino == inode).then_some(())?;
+                pid.parse().ok()
+            }
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_holder_in() {
+        let locks = "1: POSIX  ADVISORY  WRITE 2143 08:02:1835023 0 EOF\n\
+                     1: -> POSIX  ADVISORY  WRITE 2200 08:02:1835024 0 EOF\n\
+                     2: FLOCK  ADVISORY  WRITE 981 00:19:1034 0 EOF\n\
+                     3: FLOCK  ADVISORY  WRITE 1200 fd:01:1034 0 EOF\n";
+        assert_eq!(lock_holder_in(locks, (8, 2), 1835023), Some(2143));
+        assert_eq!(lock_holder_in(locks, (0, 0x19), 1034), Some(981));
+        // the same inode on another device
+        assert_eq!(lock_holder_in(locks, (0xfd, 1), 1034), Some(1200));
+        assert_eq!(lock_holder_in(locks, (8, 1), 1835023), None);
+        // only waiting for the lock
+        assert_eq!(lock_holder_in(locks, (8, 2), 1835024), None);
+        assert_eq!(lock_holder_in(locks, (8, 2), 42), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_device_numbers() {
+        assert_eq!(device_numbers(0x0802), (8, 2));
+        assert_eq!(device_numbers(0xfd01), (0xfd, 1));
+        assert_eq!(device_numbers(0x0010_0345), (3, 0x145));
+    }
+
+    #[test]
+    fn test_on_path() {
+        let path = std::env::join_paths(["/usr/bin", "/home/user/.cargo/bin"]).unwrap();
+        assert!(on_path(Path::new("/home/user/.cargo/bin"), &path));
+        assert!(!on_path(Path::new("/home/user/go/bin"), &path));
+    }
+
+    #[test]
+    fn test_check_serialize() {
+        let check = Check::new("apt", "lock", Status::Error, "locked")
+            .with_hint("wait for process 1 to finish");
+        assert_eq!(
+            serde_json::to_value(&check).unwrap(),
+            serde_json::json!({
+                "subject": "apt",
+                "check": "lock",
+                "status": "error",
+                "message": "locked",
+                "hint": "wait for process 1 to finish",
+            })
+        );
+        let check = Check::new("apt", "version", Status::Ok, "apt 2.7.14");
+        assert!(serde_json::to_value(&check).unwrap().get("hint").is_none());
+    }
+}
@@ -0,0 +1,207 @@
+//! Failures with a distinct exit code of the `mpm` binary.
+//!
+//! Scripts can branch on why mpm failed:
+//!
+//! | code | cause                                        |
+//! |------|----------------------------------------------|
+//! | 0    | success                                      |
+//! | 1    | any other failure                            |
+//! | 2    | a package was not found                      |
+//! | 3    | the package manager is not available         |
+//! | 4    | permission denied                            |
+//! | 5    | some of the operations failed, others worked |
+//!
+//! Functions return an [`Error`] wrapped in an [`anyhow::Error`], possibly
+//! with context added; [`exit_code`] finds it in the chain of causes. Package
+//! managers only report failures with their exit status, so their cause is
+//! guessed from what they printed to stderr, see [`permission_denied`] and
+//! [`not_found`].
+
+use std::fmt::Display;
+
+/// Exit code of a successful run.
+pub const EXIT_SUCCESS: i32 = 0;
+/// Exit code of failures without a more specific code.
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code when a package was not found, see [`Error::PackageNotFound`].
+pub const EXIT_PACKAGE_NOT_FOUND: i32 = 2;
+/// Exit code when the package manager is not available, see
+/// [`Error::ManagerUnavailable`].
+pub const EXIT_MANAGER_UNAVAILABLE: i32 = 3;
+/// Exit code when permission was denied, see [`Error::PermissionDenied`].
+pub const EXIT_PERMISSION_DENIED: i32 = 4;
+/// Exit code when only some operations failed, see
+/// [`Error::PartialFailure`].
+pub const EXIT_PARTIAL_FAILURE: i32 = 5;
+
+/// Cause of a failure that has its own exit code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The package with this name is neither installed nor available.
+    PackageNotFound(String),
+    /// The package manager is not installed or not supported on this system,
+    /// with a message saying which.
+    ManagerUnavailable(String),
+    /// The operation needs privileges mpm could not get, with the reason.
+    PermissionDenied(String),
+    /// Some operations failed while others succeeded, with the failed ones.
+    PartialFailure(String),
+}
+
+impl Error {
+    /// Exit code of the `mpm` binary for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::PackageNotFound(_) => EXIT_PACKAGE_NOT_FOUND,
+            Error::ManagerUnavailable(_) => EXIT_MANAGER_UNAVAILABLE,
+            Error::PermissionDenied(_) => EXIT_PERMISSION_DENIED,
+            Error::PartialFailure(_) => EXIT_PARTIAL_FAILURE,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::PackageNotFound(name) => write!(f, "package {name} not found"),
+            Error::PermissionDenied(reason) => write!(f, "permission denied: {reason}"),
+            Error::ManagerUnavailable(msg) | Error::PartialFailure(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Exit code for `err`: the code of the first [`Error`] among its causes,
+/// [`EXIT_PERMISSION_DENIED`] for I/O errors of that kind or if the last
+/// package manager command of this thread was denied permission, otherwise
+/// [`EXIT_FAILURE`].
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.chain()
+        .find_map(|cause| {
+            if let Some(err) = cause.downcast_ref::<Error>() {
+                return Some(err.exit_code());
+            }
+            cause
+                .downcast_ref::<std::io::Error>()
+                .filter(|e| e.kind() == std::io::ErrorKind::PermissionDenied)
+                .map(|_| EXIT_PERMISSION_DENIED)
+        })
+        .or_else(|| {
+            permission_denied(&crate::common::last_stderr()).map(|_| EXIT_PERMISSION_DENIED)
+        })
+        .unwrap_or(EXIT_FAILURE)
+}
+
+/// Messages of package managers, and of sudo, that lack privileges,
+/// lowercase.
+const PERMISSION_DENIED: &[&str] = &[
+    // apt, apk, pip
+    "permission denied",
+    // dnf
+    "superuser privileges",
+    // zypper
+    "root privileges are required",
+    // pacman
+    "unless you are root",
+    // choco
+    "not running from an elevated",
+    "operation not permitted",
+    "incorrect password attempt",
+    "a password is required",
+    "not in the sudoers file",
+];
+
+/// Messages of package managers that do not know a package, lowercase.
+const NOT_FOUND: &[&str] = &[
+    // apt
+    "unable to locate package",
+    // dnf
+    "no match for argument",
+    "unable to find a match",
+    // brew
+    "no available formula",
+    "no formulae or casks found",
+    // apk
+    "no such package",
+    // pip
+    "no matching distribution found",
+    // flatpak
+    "nothing matches",
+    // pacman (msys2, aur), zypper, choco
+    "not found",
+];
+
+/// The line of `stderr` saying that the package manager lacks privileges,
+/// if any.
+pub fn permission_denied(stderr: &[String]) -> Option<&str> {
+    find_message(stderr, PERMISSION_DENIED)
+}
+
+/// Whether `stderr` says that the package manager does not know a package.
+/// Messages like `not found` are vague, so the package should be looked up
+/// before reporting [`Error::PackageNotFound`].
+pub fn not_found(stderr: &[String]) -> bool {
+    find_message(stderr, NOT_FOUND).is_some()
+}
+
+fn find_message<'a>(stderr: &'a [String], messages: &[&str]) -> Option<&'a str> {
+    stderr
+        .iter()
+        .find(|line| {
+            let line = line.to_lowercase();
+            messages.iter().any(|m| line.contains(m))
+        })
+        .map(|line| line.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let err = anyhow::Error::from(Error::PackageNotFound("htop".to_string()));
+        assert_eq!(err.to_string(), "package htop not found");
+        assert_eq!(exit_code(&err), EXIT_PACKAGE_NOT_FOUND);
+
+        // the error is found below added context
+        let err = anyhow::Error::from(Error::ManagerUnavailable("dnf is not available".into()))
+            .context("failed to restore the snapshot");
+        assert_eq!(exit_code(&err), EXIT_MANAGER_UNAVAILABLE);
+
+        let err = anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::PermissionDenied))
+            .context("failed to write /etc/dnf/dnf.conf");
+        assert_eq!(exit_code(&err), EXIT_PERMISSION_DENIED);
+
+        assert_eq!(exit_code(&anyhow::anyhow!("failed")), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn test_permission_denied() {
+        let apt = [
+            "E: Could not open lock file /var/lib/dpkg/lock-frontend - open (13: Permission denied)"
+                .to_string(),
+            "E: Unable to acquire the dpkg frontend lock (/var/lib/dpkg/lock-frontend), are you root?"
+                .to_string(),
+        ];
+        assert_eq!(permission_denied(&apt), Some(apt[0].as_str()));
+        let dnf = ["Error: This command has to be run with superuser privileges (under the root user on most systems).".to_string()];
+        assert!(permission_denied(&dnf).is_some());
+        let sudo = ["sudo: 3 incorrect password attempts".to_string()];
+        assert!(permission_denied(&sudo).is_some());
+        let apt = ["E: Unable to locate package htop".to_string()];
+        assert!(permission_denied(&apt).is_none());
+    }
+
+    #[test]
+    fn test_not_found() {
+        assert!(not_found(&["E: Unable to locate package htop".to_string()]));
+        assert!(not_found(&["No match for argument: htop".to_string()]));
+        assert!(not_found(&["error: target not found: htop".to_string()]));
+        assert!(!not_found(&[
+            "E: Failed to fetch http://deb.debian.org/debian/pool/main/h/htop.deb".to_string()
+        ]));
+        assert!(!not_found(&[]));
+    }
+}
@@ -0,0 +1,326 @@
+//! History of the operations mpm ran.
+//!
+//! Every command that changes the installed packages appends an
+//! [`HistoryEntry`] to `history.jsonl` in the user's data directory, e.g.
+//! `~/.local/share/mpm/history.jsonl` on Linux, one JSON object per line.
+//! `mpm history --native` merges in the history of the package manager itself,
+//! see [``crate::PackageManager::history``], and `mpm rollback` reverts an
+//! entry, see [`rollback_target`].
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use chrono::{NaiveDateTime, SecondsFormat, TimeZone, Utc};
+
+/// An operation that changed the installed packages.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    /// Line of mpm's entries in the history file, starting at 1, so that the
+    /// ids stay the same when a line is corrupt. Assigned when the history is
+    /// read, native entries have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<usize>,
+    /// When the operation ran, in UTC as RFC 3339, e.g.
+    /// `2024-07-01T10:00:00Z`.
+    pub time: String,
+    /// Name of the package manager.
+    pub manager: String,
+    /// The operation, e.g. `install`.
+    pub operation: String,
+    /// Packages with the version the operation installed or removed, e.g.
+    /// `htop@3.3.0`, or as they were given if the version is unknown.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<String>,
+    /// Exit code of mpm, see [`crate::error`], unknown for native entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Id of the package manager's transaction that ran the operation, see
+    /// [`crate::PackageManager::last_transaction`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<String>,
+    /// Whether the entry was read from the package manager's own history.
+    #[serde(default)]
+    pub native: bool,
+}
+
+impl HistoryEntry {
+    /// Entry for an operation mpm ran just now.
+    pub fn new(manager: String, operation: &str, packages: Vec<String>, exit_code: i32) -> Self {
+        Self {
+            id: None,
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            manager,
+            operation: operation.to_string(),
+            packages,
+            exit_code: Some(exit_code),
+            transaction: None,
+            native: false,
+        }
+    }
+
+    /// Entry read from the package manager's own history, which logs the
+    /// local `time`, see [`local_to_utc`].
+    pub fn native(time: &str, manager: String, operation: &str, packages: Vec<String>) -> Self {
+        Self {
+            id: None,
+            time: local_to_utc(time),
+            manager,
+            operation: operation.to_string(),
+            packages,
+            exit_code: None,
+            transaction: None,
+            native: true,
+        }
+    }
+}
+
+/// Convert a local `YYYY-MM-DD HH:MM[:SS]` as package managers log it to
+/// UTC as RFC 3339. Other times are kept as they are.
+pub fn local_to_utc(time: &str) -> String {
+    to_utc(time, &chrono::Local)
+}
+
+/// Convert `time` in the time zone `tz` to UTC, see [`local_to_utc`].
+fn to_utc<Tz: TimeZone>(time: &str, tz: &Tz) -> String {
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(time.trim(), format).ok())
+        // the earlier of the ambiguous times when the clock is turned back
+        .and_then(|naive| tz.from_local_datetime(&naive).earliest())
+        .map_or_else(
+            || time.to_string(),
+            |t| {
+                t.with_timezone(&Utc)
+                    .to_rfc3339_opts(SecondsFormat::Secs, true)
+            },
+        )
+}
+
+/// Append-only log of the operations mpm ran.
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    /// Create a history stored in the file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Create a history in the default data directory of this user.
+    pub fn try_default() -> anyhow::Result<Self> {
+        let dirs = directories::ProjectDirs::from("", "", "mpm")
+            .context("failed to determine the data directory")?;
+        Ok(Self::new(dirs.data_dir().join("history.jsonl")))
+    }
+
+    /// File the entries are stored in.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry.
+    pub fn record(&self, entry: &HistoryEntry) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create history directory {dir:?}"))?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("failed to open history {:?}", self.path))?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+            .with_context(|| format!("failed to write history {:?}", self.path))
+    }
+
+    /// The recorded entries, oldest first, with their line as id. A missing
+    /// history is empty and corrupt lines are skipped.
+    pub fn entries(&self) -> anyhow::Result<Vec<HistoryEntry>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {:?}", self.path)),
+        };
+        Ok(content
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .filter_map(|(i, l)| match serde_json::from_str::<HistoryEntry>(l) {
+                Ok(entry) => Some(HistoryEntry {
+                    id: Some(i + 1),
+                    ..entry
+                }),
+                Err(e) => {
+                    tracing::warn!("Ignoring corrupt history entry {l:?}: {e}");
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// Operation recorded for `mpm rollback`, its package is the id of the
+/// reverted entry.
+pub const ROLLBACK: &str = "rollback";
+
+/// What `mpm rollback` reverted and what it could not.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct RollbackReport {
+    /// Id of the reverted entry.
+    pub id: usize,
+    /// Reverted changes, e.g. `uninstalled htop`.
+    pub reverted: Vec<String>,
+    /// Changes that could not be reverted, with the reason.
+    pub failed: Vec<String>,
+}
+
+/// Entry that `mpm rollback` reverts: the entry with the given id, or else
+/// the last successful operation of `manager` that was not rolled back yet.
+pub fn rollback_target<'a>(
+    entries: &'a [HistoryEntry],
+    manager: &str,
+    id: Option<usize>,
+) -> anyhow::Result<&'a HistoryEntry> {
+    let rolled_back: Vec<usize> = entries
+        .iter()
+        .filter(|e| e.operation == ROLLBACK && e.exit_code == Some(0))
+        .filter_map(|e| e.packages.first()?.parse().ok())
+        .collect();
+    let entry = match id {
+        Some(id) => entries
+            .iter()
+            .find(|e| e.id == Some(id))
+            .with_context(|| format!("no operation with id {id} in the history"))?,
+        None => entries
+            .iter()
+            .rev()
+            .filter(|e| e.manager == manager && e.operation != ROLLBACK)
+            .find(|e| e.exit_code == Some(0) && !e.id.is_some_and(|id| rolled_back.contains(&id)))
+            .with_context(|| format!("no {manager} operation left to roll back"))?,
+    };
+    anyhow::ensure!(
+        entry.manager == manager,
+        "operation {} was run with {}, roll it back with --manager {}",
+        entry.id.unwrap_or_default(),
+        entry.manager,
+        entry.manager
+    );
+    anyhow::ensure!(
+        entry.operation != ROLLBACK,
+        "a rollback cannot be rolled back"
+    );
+    anyhow::ensure!(
+        !entry.id.is_some_and(|id| rolled_back.contains(&id)),
+        "operation {} was already rolled back",
+        entry.id.unwrap_or_default()
+    );
+    anyhow::ensure!(
+        entry.exit_code == Some(0),
+        "operation {} failed, there is nothing to roll back",
+        entry.id.unwrap_or_default()
+    );
+    Ok(entry)
+}
+
+/// Merge mpm's entries with native ones, ordered by time. Entries of the same
+/// time keep mpm's entries first, those with an unknown time come first.
+pub fn merge(mut entries: Vec<HistoryEntry>, native: Vec<HistoryEntry>) -> Vec<HistoryEntry> {
+    entries.extend(native);
+    entries.sort_by_key(|e| chrono::DateTime::parse_from_rfc3339(&e.time).ok());
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_utc() {
+        let cest = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(to_utc("2024-07-01 12:00:00", &cest), "2024-07-01T10:00:00Z");
+        assert_eq!(to_utc("2024-07-01 01:30", &cest), "2024-06-30T23:30:00Z");
+        assert_eq!(to_utc("2024-07-01 10:00", &Utc), "2024-07-01T10:00:00Z");
+        assert_eq!(to_utc("yesterday", &Utc), "yesterday");
+
+        let time = HistoryEntry::new("apt".to_string(), "install", vec![], 0).time;
+        assert!(chrono::DateTime::parse_from_rfc3339(&time).is_ok());
+    }
+
+    #[test]
+    fn test_record_and_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::new(dir.path().join("nested/history.jsonl"));
+        assert!(history.entries().unwrap().is_empty());
+
+        let mut install = HistoryEntry::new("apt".to_string(), "install", vec!["htop".into()], 0);
+        install.time = "2024-07-01T10:00:00Z".to_string();
+        history.record(&install).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(history.path())
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+        let mut uninstall = HistoryEntry::new("apt".to_string(), "uninstall", vec![], 1);
+        uninstall.time = "2024-07-03T08:00:00Z".to_string();
+        history.record(&uninstall).unwrap();
+        // the corrupt line keeps its id, so the ids after it do not change
+        install.id = Some(1);
+        uninstall.id = Some(3);
+        assert_eq!(
+            history.entries().unwrap(),
+            [install.clone(), uninstall.clone()]
+        );
+
+        let mut native =
+            HistoryEntry::native("2024-07-02 09:00:00", "apt".to_string(), "remove", vec![]);
+        native.time = "2024-07-02T09:00:00Z".to_string();
+        assert_eq!(
+            merge(history.entries().unwrap(), vec![native.clone()]),
+            [install, native, uninstall]
+        );
+    }
+
+    #[test]
+    fn test_rollback_target() {
+        let entry = |operation: &str, manager: &str, packages: &[&str], exit_code| {
+            HistoryEntry::new(
+                manager.to_string(),
+                operation,
+                packages.iter().map(|p| p.to_string()).collect(),
+                exit_code,
+            )
+        };
+        let mut entries = vec![
+            entry("install", "apt", &["htop"], 0),
+            entry("install", "brew", &["wget"], 0),
+            entry("uninstall", "apt", &["tmux"], 0),
+            entry("install", "apt", &["vim"], 1),
+            entry(ROLLBACK, "apt", &["3"], 0),
+        ];
+        for (i, entry) in entries.iter_mut().enumerate() {
+            entry.id = Some(i + 1);
+        }
+
+        // the failed install and the rolled back uninstall are skipped
+        let target = rollback_target(&entries, "apt", None).unwrap();
+        assert_eq!(target.id, Some(1));
+        assert_eq!(rollback_target(&entries, "brew", None).unwrap().id, Some(2));
+        assert!(rollback_target(&entries, "dnf", None).is_err());
+
+        assert_eq!(
+            rollback_target(&entries, "apt", Some(1)).unwrap().id,
+            Some(1)
+        );
+        assert!(rollback_target(&entries, "apt", Some(2)).is_err());
+        assert!(rollback_target(&entries, "apt", Some(3)).is_err());
+        assert!(rollback_target(&entries, "apt", Some(4)).is_err());
+        assert!(rollback_target(&entries, "apt", Some(5)).is_err());
+        assert!(rollback_target(&entries, "apt", Some(9)).is_err());
+    }
+}
@@ -42,6 +42,26 @@ pub use print::*;
 
 pub mod cli;
 
+pub mod cache;
+
+pub mod config;
+
+pub mod logging;
+
+pub mod lockfile;
+
+pub mod history;
+
+pub mod snapshot;
+
+pub mod error;
+
+pub mod doctor;
+
+pub mod self_update;
+
+pub mod schedule;
+
 #[cfg(test)]
 mod tests {
 
@@ -91,6 +111,9 @@ mod tests {
         fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
             vec![]
         }
+        fn is_protected(&self, pkg: &Package) -> bool {
+            pkg.name() == "protected"
+        }
     }
 
     impl PackageManagerCommands for MockPackageManager {
@@ -114,6 +137,47 @@ mod tests {
         }
     }
 
+    /// Searches with `yes`, which prints the query forever.
+    #[derive(Debug, Default)]
+    struct EndlessSearch {
+        parsed: std::cell::Cell<usize>,
+    }
+
+    impl Display for EndlessSearch {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("endless")
+        }
+    }
+
+    impl PackageManager for EndlessSearch {
+        fn pkg_delimiter(&self) -> char {
+            '+'
+        }
+        fn pkg_manager_name(&self) -> String {
+            String::from("endless")
+        }
+        fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+            vec![]
+        }
+        fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+            self.parsed.set(self.parsed.get() + 1);
+            let (name, version) = line.split_once('+')?;
+            Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+        }
+    }
+
+    impl PackageManagerCommands for EndlessSearch {
+        fn cmd(&self) -> Command {
+            Command::new("yes")
+        }
+        fn get_cmds(&self, _: Cmd, _: Option<&Package>) -> Vec<String> {
+            vec![]
+        }
+        fn needs_sudo(&self) -> bool {
+            false
+        }
+    }
+
     #[test]
     fn default_cmd_consolidated_order() {
         let mock = MockCommands;
@@ -138,6 +202,52 @@ mod tests {
         assert_eq!(listiter.next(), None);
     }
 
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn search_limited_stops_parsing_at_limit() {
+        let pm = EndlessSearch::default();
+        let pkgs = pm.search_limited("foo+1.0", 5).unwrap();
+        assert_eq!(pkgs.len(), 5);
+        assert_eq!(
+            pkgs[0],
+            Package::new("foo", "endless".to_string(), Some("1.0"))
+        );
+        assert_eq!(pm.parsed.get(), 5);
+        assert!(pm.search_limited("foo+1.0", 0).unwrap().is_empty());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn search_streaming_fails_with_the_search() {
+        let pm = EndlessSearch::default();
+        let sh = |script: &str| {
+            let mut command = Command::new("sh");
+            command.args(["-c", script]);
+            command
+        };
+        let err = crate::traits::search_streaming(&pm, sh("echo 'index locked' >&2; exit 1"), 5)
+            .unwrap_err();
+        assert!(err.to_string().contains("index locked"), "{err}");
+        // finding nothing is not a failure
+        let pkgs = crate::traits::search_streaming(
+            &pm,
+            sh("echo 'No formulae or casks found' >&2; exit 1"),
+            5,
+        )
+        .unwrap();
+        assert!(pkgs.is_empty());
+        let pkgs = crate::traits::search_streaming(&pm, sh("echo foo+1.0"), 5).unwrap();
+        assert_eq!(pkgs.len(), 1);
+    }
+
+    #[test]
+    fn protected_package_uninstall_requires_force() {
+        let err = MockPackageManager
+            .try_uninstall("protected", false, false)
+            .unwrap_err();
+        assert!(err.to_string().contains("--force"));
+    }
+
     #[test]
     fn package_formatting() {
         assert_eq!(
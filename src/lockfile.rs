@@ -0,0 +1,439 @@
+//! Lockfile recording the exact versions of installed packages.
+//!
+//! `mpm lock` writes the installed packages of a package manager to
+//! `mpm.lock`, and `mpm install --locked` installs exactly those versions
+//! again, e.g. on another machine. `mpm export` writes the packages of all
+//! available package managers together with the platform they were installed
+//! on.
+//!
+//! Lockfiles are TOML, or JSON if their path ends in `.json`. Besides
+//! `packages`, which maps package names to versions by package manager, all
+//! fields are optional:
+//!
+//! ```toml
+//! schema_version = 1
+//!
+//! [platform]
+//! os = "linux"
+//! os_type = "Debian"
+//! os_version = "12"
+//! arch = "x86_64"
+//! mpm_version = "0.7.5"
+//!
+//! [packages.apt]
+//! curl = "7.88.1-10+deb12u8"
+//! hello = "2.10-3"
+//! tzdata = "*"
+//! ```
+//!
+//! Packages whose version is unknown are locked as `*` and installed in any
+//! version.
+
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use anyhow::Context;
+
+use crate::{AvailablePackageManager, MetaPackageManager, Package, PackageManager};
+
+/// Default file name of the lockfile.
+pub const DEFAULT_PATH: &str = "mpm.lock";
+
+/// Version of the lockfile schema written by `mpm export`. Incremented when
+/// a change would make existing lockfiles read differently.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Version recorded for packages whose version is unknown. Any installed
+/// version matches it.
+pub const ANY_VERSION: &str = "*";
+
+/// The version to install for a `locked` version, `None` for [`ANY_VERSION`].
+fn pinned(locked: &str) -> Option<&str> {
+    (locked != ANY_VERSION).then_some(locked)
+}
+
+/// Exact package versions, grouped by package manager.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lockfile {
+    /// Version of the schema, see [`SCHEMA_VERSION`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    /// Platform the packages were installed on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+    /// Package names mapped to their version, by package manager name.
+    pub packages: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Platform a lockfile was written on.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Platform {
+    /// Operating system family, e.g. `linux` or `windows`.
+    pub os: String,
+    /// Distribution or edition, e.g. `Debian` or `Windows`.
+    pub os_type: String,
+    /// Version of the operating system, e.g. `12`.
+    pub os_version: String,
+    /// CPU architecture, e.g. `x86_64` or `aarch64`.
+    pub arch: String,
+    /// Version of mpm that wrote the lockfile.
+    pub mpm_version: String,
+}
+
+impl Platform {
+    /// The platform mpm runs on.
+    pub fn current() -> Self {
+        let info = os_info::get();
+        Self {
+            os: std::env::consts::OS.to_string(),
+            os_type: info.os_type().to_string(),
+            os_version: info.version().to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            mpm_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Record the given packages. Packages without a version are recorded
+    /// as [`ANY_VERSION`].
+    pub fn from_packages<I: IntoIterator<Item = Package>>(pkgs: I) -> Self {
+        let mut lockfile = Self::default();
+        for pkg in pkgs {
+            let version = pkg.version().unwrap_or(ANY_VERSION);
+            lockfile
+                .packages
+                .entry(pkg.package_manager().to_string())
+                .or_default()
+                .insert(pkg.name().to_string(), version.to_string());
+        }
+        lockfile
+    }
+
+    /// Record the given packages like [`Lockfile::from_packages`], together
+    /// with the schema version and the current platform.
+    pub fn export<I: IntoIterator<Item = Package>>(pkgs: I) -> Self {
+        Self {
+            schema_version: Some(SCHEMA_VERSION),
+            platform: Some(Platform::current()),
+            ..Self::from_packages(pkgs)
+        }
+    }
+
+    /// The locked packages, with their version unless it is [`ANY_VERSION`].
+    pub fn packages(&self) -> impl Iterator<Item = Package> + '_ {
+        self.packages.iter().flat_map(|(manager, pkgs)| {
+            pkgs.iter()
+                .map(|(name, version)| Package::new(name, manager.clone(), pinned(version)))
+        })
+    }
+
+    /// Read a lockfile, as JSON if the path ends in `.json` and as TOML
+    /// otherwise.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read lockfile {path:?}"))?;
+        let lockfile: Self = if is_json(path) {
+            serde_json::from_str(&content).with_context(|| format!("invalid lockfile {path:?}"))?
+        } else {
+            toml::from_str(&content).with_context(|| format!("invalid lockfile {path:?}"))?
+        };
+        if let Some(version) = lockfile.schema_version.filter(|v| *v > SCHEMA_VERSION) {
+            anyhow::bail!(
+                "lockfile {path:?} has schema version {version}, this mpm supports up to {SCHEMA_VERSION}"
+            );
+        }
+        Ok(lockfile)
+    }
+
+    /// Write the lockfile, replacing an existing one. Written as JSON if the
+    /// path ends in `.json` and as TOML otherwise.
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let content = if is_json(path) {
+            serde_json::to_string_pretty(self)? + "\n"
+        } else {
+            toml::to_string(self)?
+        };
+        std::fs::write(path, content).with_context(|| format!("failed to write lockfile {path:?}"))
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+/// Lock the versions of the packages installed by `mpm`.
+pub fn generate_lockfile<P: PackageManager>(mpm: &P) -> Lockfile {
+    Lockfile::from_packages(mpm.list_installed())
+}
+
+/// Install exactly the versions recorded in the lockfile at `path` and return
+/// the names of the installed packages.
+///
+/// Fails if a package manager of the lockfile is not available or a locked
+/// version cannot be installed.
+pub fn install_locked(path: &Path, interactive: bool) -> anyhow::Result<Vec<String>> {
+    let lockfile = Lockfile::from_file(path)?;
+    if let Some(platform) = &lockfile.platform {
+        if platform.os != std::env::consts::OS || platform.arch != std::env::consts::ARCH {
+            tracing::warn!(
+                "{path:?} was written on {} {}, versions may not be available here",
+                platform.os,
+                platform.arch
+            );
+        }
+    }
+    let mut installed = vec![];
+    for (manager, pkgs) in &lockfile.packages {
+        let manager = AvailablePackageManager::from_str(manager)
+            .map_err(|_| anyhow::anyhow!("unknown package manager {manager:?} in {path:?}"))?;
+        let mpm = MetaPackageManager::new_if_available(manager)?;
+        for (name, version) in pkgs {
+            let pkg = Package::new(name, mpm.pkg_manager_name(), pinned(version));
+            let s = mpm.install(pkg, interactive);
+            match pinned(version) {
+                Some(version) => {
+                    anyhow::ensure!(s.success(), "Failed to install {name} version {version}")
+                }
+                None => anyhow::ensure!(s.success(), "Failed to install {name}"),
+            }
+            installed.push(name.clone());
+        }
+    }
+    Ok(installed)
+}
+
+/// How the installed packages differ from a lockfile, see [`check`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Drift {
+    /// Locked packages that are not installed, with the locked version.
+    pub missing: Vec<Package>,
+    /// Installed packages that are not in the lockfile.
+    pub extra: Vec<Package>,
+    /// Installed packages whose version differs from the locked one.
+    pub mismatched: Vec<VersionMismatch>,
+}
+
+/// A package installed in another version than the locked one.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct VersionMismatch {
+    pub name: String,
+    pub manager: String,
+    pub locked: String,
+    pub installed: String,
+}
+
+impl Drift {
+    /// Whether the installed packages match the lockfile.
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+/// Compare the `installed` packages of `manager` with its `locked` versions.
+/// Installed packages without a version, and packages locked as
+/// [`ANY_VERSION`], only need to be present.
+pub fn drift(manager: &str, locked: &BTreeMap<String, String>, installed: &[Package]) -> Drift {
+    let mut drift = Drift::default();
+    for (name, version) in locked {
+        let versions: Vec<Option<&str>> = installed
+            .iter()
+            .filter(|pkg| pkg.name() == name)
+            .map(|pkg| pkg.version())
+            .collect();
+        if versions.is_empty() {
+            drift
+                .missing
+                .push(Package::new(name, manager.to_string(), pinned(version)));
+        } else if pinned(version).is_some()
+            && !versions.iter().any(|v| v.map_or(true, |v| v == version))
+        {
+            drift.mismatched.push(VersionMismatch {
+                name: name.clone(),
+                manager: manager.to_string(),
+                locked: version.clone(),
+                installed: versions
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            });
+        }
+    }
+    for pkg in installed {
+        if !locked.contains_key(pkg.name()) {
+            drift
+                .extra
+                .push(Package::new(pkg.name(), manager.to_string(), pkg.version()));
+        }
+    }
+    drift.extra.sort_by(|a, b| a.name().cmp(b.name()));
+    drift.extra.dedup();
+    drift
+}
+
+/// Compare the installed packages with the lockfile at `path`. Only the
+/// package managers of the lockfile are checked; all packages of an
+/// unavailable one are missing.
+pub fn check(path: &Path) -> anyhow::Result<Drift> {
+    let lockfile = Lockfile::from_file(path)?;
+    let mut drift = Drift::default();
+    for (manager, locked) in &lockfile.packages {
+        let available = AvailablePackageManager::from_str(manager)
+            .map_err(|_| anyhow::anyhow!("unknown package manager {manager:?} in {path:?}"))?;
+        let installed = match MetaPackageManager::new_if_available(available) {
+            Ok(mpm) => mpm.list_installed(),
+            Err(e) => {
+                tracing::warn!("{e}");
+                vec![]
+            }
+        };
+        let Drift {
+            missing,
+            extra,
+            mismatched,
+        } = self::drift(manager, locked, &installed);
+        drift.missing.extend(missing);
+        drift.extra.extend(extra);
+        drift.mismatched.extend(mismatched);
+    }
+    Ok(drift)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::{Command, Output};
+
+    use super::*;
+    use crate::{Cmd, PackageManagerCommands, PkgFormat};
+
+    #[derive(Debug)]
+    struct MockManager;
+
+    impl std::fmt::Display for MockManager {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("mock")
+        }
+    }
+
+    impl PackageManagerCommands for MockManager {
+        fn cmd(&self) -> Command {
+            Command::new("")
+        }
+        fn get_cmds(&self, _: Cmd, _: Option<&Package>) -> Vec<String> {
+            vec![]
+        }
+        fn exec_cmds(&self, _: &[String]) -> Output {
+            Output {
+                status: crate::common::synthetic_exit_status(true),
+                stdout: b"hello+2.10-3\ncurl+7.88.1\nunversioned\n".to_vec(),
+                stderr: vec![],
+            }
+        }
+    }
+
+    impl PackageManager for MockManager {
+        fn pkg_delimiter(&self) -> char {
+            '+'
+        }
+        fn pkg_manager_name(&self) -> String {
+            "apt".to_string()
+        }
+        fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn test_generate_lockfile() {
+        let lockfile = generate_lockfile(&MockManager);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEFAULT_PATH);
+        lockfile.write(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "[packages.apt]\ncurl = \"7.88.1\"\nhello = \"2.10-3\"\nunversioned = \"*\"\n"
+        );
+
+        let parsed = Lockfile::from_file(&path).unwrap();
+        assert_eq!(parsed, lockfile);
+        assert_eq!(
+            parsed.packages().collect::<Vec<_>>(),
+            [
+                Package::new("curl", "apt".to_string(), Some("7.88.1")),
+                Package::new("hello", "apt".to_string(), Some("2.10-3")),
+                Package::new("unversioned", "apt".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drift() {
+        let locked = BTreeMap::from([
+            ("curl".to_string(), "7.88.1".to_string()),
+            ("hello".to_string(), "2.10-3".to_string()),
+            ("htop".to_string(), "3.2.2".to_string()),
+            ("jq".to_string(), ANY_VERSION.to_string()),
+            ("tzdata".to_string(), ANY_VERSION.to_string()),
+        ]);
+        let installed = [
+            Package::new("curl", "apt".to_string(), None),
+            Package::new("hello", "apt".to_string(), Some("2.10-4")),
+            Package::new("tmux", "apt".to_string(), Some("3.3a")),
+            Package::new("tzdata", "apt".to_string(), Some("2024a-0")),
+        ];
+        assert_eq!(
+            drift("apt", &locked, &installed),
+            Drift {
+                missing: vec![
+                    Package::new("htop", "apt".to_string(), Some("3.2.2")),
+                    Package::new("jq", "apt".to_string(), None),
+                ],
+                extra: vec![Package::new("tmux", "apt".to_string(), Some("3.3a"))],
+                mismatched: vec![VersionMismatch {
+                    name: "hello".to_string(),
+                    manager: "apt".to_string(),
+                    locked: "2.10-3".to_string(),
+                    installed: "2.10-4".to_string(),
+                }],
+            }
+        );
+        assert!(drift("apt", &locked, &installed[..2]).extra.is_empty());
+    }
+
+    #[test]
+    fn test_export() {
+        let mut lockfile = Lockfile::export(MockManager.list_installed());
+        lockfile.platform = Some(Platform {
+            os: "linux".to_string(),
+            os_type: "Debian".to_string(),
+            os_version: "12".to_string(),
+            arch: "x86_64".to_string(),
+            mpm_version: "0.7.5".to_string(),
+        });
+        let dir = tempfile::tempdir().unwrap();
+
+        let toml_path = dir.path().join(DEFAULT_PATH);
+        lockfile.write(&toml_path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&toml_path).unwrap(),
+            "schema_version = 1\n\n[platform]\nos = \"linux\"\nos_type = \"Debian\"\n\
+             os_version = \"12\"\narch = \"x86_64\"\nmpm_version = \"0.7.5\"\n\n\
+             [packages.apt]\ncurl = \"7.88.1\"\nhello = \"2.10-3\"\nunversioned = \"*\"\n"
+        );
+        assert_eq!(Lockfile::from_file(&toml_path).unwrap(), lockfile);
+
+        let json_path = dir.path().join("mpm.lock.json");
+        lockfile.write(&json_path).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(json["packages"]["apt"]["hello"], "2.10-3");
+        assert_eq!(json["platform"]["arch"], "x86_64");
+        assert_eq!(Lockfile::from_file(&json_path).unwrap(), lockfile);
+
+        lockfile.schema_version = Some(SCHEMA_VERSION + 1);
+        lockfile.write(&toml_path).unwrap();
+        assert!(Lockfile::from_file(&toml_path).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! Logging setup of the mpm binary.
+//!
+//! Logs are written to stdout, filtered by `RUST_LOG`, and optionally to a
+//! file given by `--log-file`. The file receives structured (JSON) records of
+//! level INFO and above so that a persistent log of operations can be kept.
+
+use std::path::Path;
+
+use anyhow::Context;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*, EnvFilter, Layer, Registry};
+
+/// How often the log file is rotated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogRotation {
+    /// Always append to the given file.
+    #[default]
+    Never,
+    /// Start a new file every day, suffixed with the date e.g.
+    /// `mpm.log.2024-10-18`.
+    Daily,
+}
+
+/// Open the log file at `path`, creating its parent directories.
+pub fn file_appender(path: &Path, rotation: LogRotation) -> anyhow::Result<RollingFileAppender> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .with_context(|| format!("log file {path:?} has no file name"))?;
+    std::fs::create_dir_all(dir).with_context(|| format!("failed to create {dir:?}"))?;
+
+    RollingFileAppender::builder()
+        .rotation(match rotation {
+            LogRotation::Never => Rotation::NEVER,
+            LogRotation::Daily => Rotation::DAILY,
+        })
+        .filename_prefix(name.to_string_lossy())
+        .build(dir)
+        .with_context(|| format!("failed to open log file {path:?}"))
+}
+
+/// Layer writing structured records to the given log file.
+pub fn file_layer(
+    path: &Path,
+    rotation: LogRotation,
+) -> anyhow::Result<impl Layer<Registry> + Send + Sync> {
+    let appender = file_appender(path, rotation)?;
+    Ok(fmt::layer()
+        .json()
+        .with_ansi(false)
+        .with_writer(appender)
+        .with_filter(LevelFilter::INFO))
+}
+
+/// Install the global tracing subscriber.
+pub fn init(log_file: Option<&Path>, rotation: LogRotation) -> anyhow::Result<()> {
+    let file = log_file.map(|p| file_layer(p, rotation)).transpose()?;
+    tracing_subscriber::registry()
+        .with(file)
+        .with(
+            fmt::layer()
+                .with_ansi(crate::print::colors_enabled())
+                .with_filter(EnvFilter::from_default_env()),
+        )
+        .try_init()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_file_contains_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs").join("mpm.log");
+
+        let subscriber =
+            tracing_subscriber::registry().with(file_layer(&path, LogRotation::Never).unwrap());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(command = "install", manager = "apt", "Executing command");
+            tracing::debug!("not recorded");
+        });
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert!(log.contains(r#""command":"install""#), "{log}");
+        assert!(log.contains("Executing command"));
+        assert!(!log.contains("not recorded"));
+    }
+}
@@ -1,19 +1,21 @@
 //! Meta Package Manager (MPM) binary
 
 use clap::Parser;
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
 fn main() {
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::from_default_env())
-        .init();
+    let args = mpm::cli::Cli::parse();
+    mpm::print::init_colors(args.no_color());
+    if let Err(err) = mpm::logging::init(args.log_file(), args.log_rotation()) {
+        mpm::print::log_error(err);
+        std::process::exit(1);
+    }
 
     let info = os_info::get();
     tracing::info!("Detected OS {:?}", info.os_type());
 
-    if let Err(err) = mpm::cli::execute(mpm::cli::Cli::parse()) {
+    if let Err(err) = mpm::cli::execute(args) {
+        let code = mpm::error::exit_code(&err);
         mpm::print::log_error(err);
-        std::process::exit(1);
+        std::process::exit(code);
     }
 }
@@ -0,0 +1,316 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{BufWriter, Write},
+    process::Command,
+};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PackageMetadata,
+    PkgFormat,
+};
+
+/// Wrapper for the Alpine Package Keeper (apk), the package manager of Alpine
+/// Linux.
+///
+/// [Alpine Package Keeper - Alpine Linux](https://wiki.alpinelinux.org/wiki/Alpine_Package_Keeper)
+///
+/// # Idiosyncracies
+/// apk has no command to add a repository, so [``AlpinePackageKeeper::add_repo``]
+/// appends the repositories to [``AlpinePackageKeeper::REPOSITORIES``].
+#[derive(Debug, Default)]
+pub struct AlpinePackageKeeper;
+
+impl AlpinePackageKeeper {
+    /// File listing the repositories, one URL per line.
+    pub const REPOSITORIES: &'static str = "/etc/apk/repositories";
+
+    /// Split a package like `py3-pip-23.1.2-r0` into its name and its
+    /// version including the release, `23.1.2-r0`.
+    pub(crate) fn split_name_version(s: &str) -> Option<(&str, &str)> {
+        let mut parts = s.rsplitn(3, '-');
+        let release = parts.next()?.strip_prefix('r')?;
+        let version = parts.next()?;
+        let name = parts.next()?;
+        let valid = !release.is_empty()
+            && release.chars().all(|c| c.is_ascii_digit())
+            && version.starts_with(|c: char| c.is_ascii_digit());
+        valid.then(|| (name, &s[name.len() + 1..]))
+    }
+
+    /// Parse the output of `apk info -a <pkg>`, which prints each field of
+    /// every version of the package in the installed and the available
+    /// repositories under a header, e.g.
+    ///
+    /// ```text
+    /// curl-8.5.0-r0 description:
+    /// URL retrival utility and library
+    ///
+    /// curl-8.5.0-r0 webpage:
+    /// https://curl.se/
+    ///
+    /// curl-8.5.0-r0 installed size:
+    /// 252 KiB
+    /// ```
+    ///
+    /// and the installed version of `apk list --installed <pkg>`.
+    pub(crate) fn parse_info(name: &str, info: &str, installed: &str) -> PackageMetadata {
+        // (version, field, first line of the value) in the order listed
+        let mut fields: Vec<(&str, &str, &str)> = vec![];
+        let mut header = None;
+        for line in info.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                header = None;
+                continue;
+            }
+            let parsed = line.strip_suffix(':').and_then(|h| {
+                let (pkg, field) = h.split_once(' ')?;
+                let (pkg, version) = Self::split_name_version(pkg)?;
+                (pkg == name).then_some((version, field))
+            });
+            match (parsed, header) {
+                (Some(parsed), _) => header = Some(parsed),
+                (None, Some((version, field))) => {
+                    fields.push((version, field, line));
+                    // only the first line of the value, e.g. of `depends on:`
+                    header = None;
+                }
+                (None, None) => (),
+            }
+        }
+
+        let installed = installed.lines().find_map(|l| {
+            let (pkg, version) = Self::split_name_version(l.split_whitespace().next()?)?;
+            (pkg == name).then(|| version.to_string())
+        });
+        let candidate = fields
+            .iter()
+            .map(|(version, _, _)| *version)
+            .find(|v| Some(*v) != installed.as_deref())
+            .map(str::to_string)
+            .or_else(|| installed.clone());
+        let field = |version: Option<&str>, key: &str| {
+            fields
+                .iter()
+                .find(|(v, k, _)| Some(*v) == version && *k == key)
+                .map(|(_, _, value)| value.to_string())
+        };
+        // some fields are only printed for the installed version
+        let latest = |key: &str| {
+            field(candidate.as_deref(), key).or_else(|| field(installed.as_deref(), key))
+        };
+        PackageMetadata {
+            name: name.to_string(),
+            description: latest("description"),
+            homepage: latest("webpage"),
+            license: latest("license"),
+            size: field(installed.as_deref(), "installed size")
+                .or_else(|| latest("installed size"))
+                .and_then(|s| crate::parse_size(&s)),
+            installed_version: installed,
+            candidate_version: candidate,
+        }
+    }
+}
+
+impl PackageManager for AlpinePackageKeeper {
+    fn pkg_delimiter(&self) -> char {
+        '='
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Apk.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![PkgFormat::Apk]
+    }
+
+    /// Parses the lines of `apk list` like `curl-8.5.0-r0 x86_64 {curl} (curl)
+    /// [installed]` and of `apk search` like `curl-8.5.0-r0`.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        let (name, version) = Self::split_name_version(line.split_whitespace().next()?)?;
+        Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+    }
+
+    fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
+        let repositories = fs::File::options().append(true).open(Self::REPOSITORIES)?;
+        let mut writer = BufWriter::new(repositories);
+        for line in repo {
+            writeln!(writer, "{line}")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Uses `apk info -a` and `apk list --installed`.
+    fn info(&self, pkg: &Package) -> anyhow::Result<PackageMetadata> {
+        let out = self.exec_cmds(&["info".to_string(), "-a".to_string(), pkg.name().to_string()]);
+        let info = String::from_utf8_lossy(&out.stdout);
+        // apk prints nothing for unknown packages
+        if !out.status.success() || info.trim().is_empty() {
+            return Err(crate::error::Error::PackageNotFound(pkg.name().to_string()).into());
+        }
+        let installed = self.exec_cmds(&[
+            "list".to_string(),
+            "--installed".to_string(),
+            pkg.name().to_string(),
+        ]);
+        Ok(Self::parse_info(
+            pkg.name(),
+            &info,
+            &String::from_utf8_lossy(&installed.stdout),
+        ))
+    }
+}
+
+impl PackageManagerCommands for AlpinePackageKeeper {
+    fn cmd(&self) -> Command {
+        Command::new("apk")
+    }
+
+    fn get_cmds(&self, cmd: Cmd, pkg: Option<&Package>) -> Vec<String> {
+        let mut cmds: Vec<String> = match cmd {
+            Cmd::Install => vec!["add"],
+            Cmd::Uninstall => vec!["del"],
+            Cmd::Update | Cmd::UpdateAll => vec!["upgrade"],
+            Cmd::List => vec!["list"],
+            Cmd::Sync => vec!["update"],
+            Cmd::AddRepo | Cmd::Clean | Cmd::Autoremove => vec![],
+            Cmd::Search => vec!["search"],
+            Cmd::Outdated => vec!["list"],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect();
+
+        // local packages are usually not signed by a trusted key
+        if cmd == Cmd::Install
+            && pkg
+                .and_then(Package::url)
+                .is_some_and(|u| u.scheme() == "file")
+        {
+            cmds.push("--allow-untrusted".to_string());
+        }
+        cmds
+    }
+
+    fn get_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::List => vec!["--installed"],
+            Cmd::Outdated => vec!["--upgradable"],
+            _ => vec![],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+}
+
+impl Display for AlpinePackageKeeper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Alpine Package Keeper (apk)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let apk = AlpinePackageKeeper;
+        let output = "WARNING: opening from cache https://dl-cdn.alpinelinux.org/alpine/v3.19/main: No such file or directory
+busybox-1.36.1-r15 x86_64 {busybox} (GPL-2.0-only) [installed]
+py3-pip-23.3.1-r0 noarch {py3-pip} (MIT) [upgradable from: py3-pip-23.1.2-r0]
+ca-certificates-bundle-20240226-r0
+";
+        let pkgs: Vec<_> = output.lines().filter_map(|l| apk.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("busybox", "apk".to_string(), Some("1.36.1-r15")),
+                Package::new("py3-pip", "apk".to_string(), Some("23.3.1-r0")),
+                Package::new(
+                    "ca-certificates-bundle",
+                    "apk".to_string(),
+                    Some("20240226-r0")
+                ),
+            ]
+        );
+        assert_eq!(AlpinePackageKeeper::split_name_version("musl-dev"), None);
+        assert_eq!(AlpinePackageKeeper::split_name_version("foo-bar-r1"), None);
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let info = "curl-8.5.0-r0 description:
+URL retrival utility and library
+
+curl-8.5.0-r0 webpage:
+https://curl.se/
+
+curl-8.5.0-r0 installed size:
+252 KiB
+
+curl-8.5.0-r0 depends on:
+ca-certificates
+so:libc.musl-x86_64.so.1
+
+curl-8.5.0-r0 license:
+curl
+
+curl-8.9.1-r0 description:
+URL retrival utility and library
+
+curl-8.9.1-r0 installed size:
+257 KiB
+
+";
+        let installed = "curl-8.5.0-r0 x86_64 {curl} (curl) [upgradable to: 8.9.1-r0]\n";
+        let info = AlpinePackageKeeper::parse_info("curl", info, installed);
+        assert_eq!(info.installed_version.as_deref(), Some("8.5.0-r0"));
+        assert_eq!(info.candidate_version.as_deref(), Some("8.9.1-r0"));
+        assert_eq!(
+            info.description.as_deref(),
+            Some("URL retrival utility and library")
+        );
+        assert_eq!(info.homepage.as_deref(), Some("https://curl.se/"));
+        assert_eq!(info.license.as_deref(), Some("curl"));
+        assert_eq!(info.size, Some(252 * 1024));
+
+        let info = AlpinePackageKeeper::parse_info(
+            "curl",
+            "curl-8.9.1-r0 webpage:\nhttps://curl.se/\n",
+            "",
+        );
+        assert_eq!(info.installed_version, None);
+        assert_eq!(info.candidate_version.as_deref(), Some("8.9.1-r0"));
+    }
+
+    #[test]
+    fn test_cmds() {
+        let apk = AlpinePackageKeeper;
+        let mut pkg = Package::new("curl", "apk".to_string(), Some("8.5.0-r0"));
+        let fmt = apk.reformat_for_command(&mut pkg);
+        assert_eq!(
+            apk.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["add", "curl=8.5.0-r0"]
+        );
+        assert_eq!(
+            apk.consolidated::<&str>(Cmd::List, None, &[]),
+            ["list", "--installed"]
+        );
+        assert_eq!(
+            apk.consolidated::<&str>(Cmd::Outdated, None, &[]),
+            ["list", "--upgradable"]
+        );
+
+        let local: Package = "file:///tmp/hello-2.12-r1.apk".parse().unwrap();
+        assert_eq!(
+            apk.consolidated(Cmd::Install, Some(&local), &["/tmp/hello-2.12-r1.apk"]),
+            ["add", "--allow-untrusted", "/tmp/hello-2.12-r1.apk"]
+        );
+    }
+}
@@ -1,13 +1,17 @@
 use std::{
     fmt::Display,
     fs,
-    io::{BufWriter, Write},
-    process::Command,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
 };
 
+use anyhow::Context;
+
 use crate::{
-    common::Package, AvailablePackageManager, Cmd, PackageManager, PackageManagerCommands,
-    PkgFormat,
+    common::Package, managers::DandifiedYUM, AvailablePackageManager, Cmd, DependencyGraph,
+    InstallPlan, NotOwned, PackageManager, PackageManagerCommands, PackageMetadata, PkgFormat,
+    Progress, Repository,
 };
 
 /// Wrapper for Advanced Pacakge Tool (APT), the default package management
@@ -21,11 +25,572 @@ use crate::{
 /// Another notable point is that the [``AdvancedPackageTool::add_repo``]
 /// implementation doesn't execute commands, but it writes to
 /// "/etc/apt/sources.list".
+///
+/// Packages can be installed, removed and updated with `apt-get` or
+/// `aptitude` instead of `apt`, see [``AptBackend``]. Queries whose output is
+/// parsed (list, search, outdated) use `apt`, except with the `apt-get`
+/// backend: `apt` warns that it "does not have a stable CLI interface", so
+/// for scripting `dpkg-query`, `apt-cache` and `apt-get --simulate` are used
+/// instead.
 #[derive(Debug, Default)]
-pub struct AdvancedPackageTool;
+pub struct AdvancedPackageTool {
+    force_ipv4: bool,
+    backend: AptBackend,
+}
+
+/// Frontend that [``AdvancedPackageTool``] uses to install, remove and update
+/// packages. With [``AptBackend::AptGet``] queries use tools with a stable
+/// output as well.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AptBackend {
+    #[default]
+    Apt,
+    AptGet,
+    Aptitude,
+}
+
+impl AptBackend {
+    /// Name of the executable.
+    pub fn binary(&self) -> &'static str {
+        match self {
+            Self::Apt => "apt",
+            Self::AptGet => "apt-get",
+            Self::Aptitude => "aptitude",
+        }
+    }
+}
 
 impl AdvancedPackageTool {
+    /// Use `backend` to install, remove and update packages.
+    pub fn set_backend(&mut self, backend: AptBackend) {
+        self.backend = backend;
+    }
+
+    /// Format of the installed packages printed by `dpkg-query --show`, see
+    /// [``AdvancedPackageTool::parse_dpkg_query``].
+    const DPKG_QUERY_FORMAT: &'static str = "${db:Status-Abbrev}\t${Package}\t${Version}\n";
+
+    /// Whether queries use `dpkg-query`, `apt-cache` and `apt-get
+    /// --simulate`, whose output is stable, instead of `apt`.
+    fn stable_queries(&self) -> bool {
+        self.backend == AptBackend::AptGet
+    }
+
+    /// Run `program` for a query whose output is parsed.
+    fn exec_query(program: &str, args: &[&str]) -> std::process::Output {
+        tracing::info!("Executing {program} with args {args:?}");
+        Command::new(program)
+            .args(args)
+            .output()
+            .expect("command executed without a prior check")
+    }
+
+    /// Parse a line of `dpkg-query --show` with
+    /// [``AdvancedPackageTool::DPKG_QUERY_FORMAT``], i.e. the status, the name
+    /// and the version separated by tabs. Packages whose status is not `ii`,
+    /// i.e. not installed, e.g. removed but not purged ones (`rc`), are
+    /// skipped.
+    pub(crate) fn parse_dpkg_query(&self, line: &str) -> Option<Package> {
+        let mut fields = line.split('\t');
+        let (status, name, version) = (fields.next()?, fields.next()?, fields.next()?);
+        (status.trim() == "ii" && !version.is_empty())
+            .then(|| Package::new(name, self.pkg_manager_name(), Some(version.trim())))
+    }
+
+    /// Parse a line of `apt-cache search` like `hello - example package based
+    /// on GNU hello`. The version is not printed.
+    pub(crate) fn parse_apt_cache_search(&self, line: &str) -> Option<Package> {
+        let (name, _description) = line.split_once(" - ")?;
+        (!name.is_empty() && !name.contains(char::is_whitespace))
+            .then(|| Package::new(name, self.pkg_manager_name(), None))
+    }
+
+    /// Packages of `output` parsed with `parse`, packages installed for
+    /// several architectures only once.
+    fn parse_lines(output: &[u8], parse: impl Fn(&str) -> Option<Package>) -> Vec<Package> {
+        let mut seen = std::collections::HashSet::new();
+        String::from_utf8_lossy(output)
+            .lines()
+            .filter_map(parse)
+            .filter(|p| seen.insert((p.name().to_string(), p.version().map(str::to_string))))
+            .collect()
+    }
+
+    /// Run `apt` regardless of the backend, for queries whose output is
+    /// parsed.
+    fn exec_apt(&self, args: &[String]) -> std::process::Output {
+        tracing::info!("Executing apt with args {args:?}");
+        self.elevated(Command::new("apt"))
+            .args(args)
+            .output()
+            .expect("command executed without a prior check")
+    }
+
     const SOURCES: &'static str = "/etc/apt/sources.list";
+    const SOURCES_DIR: &'static str = "/etc/apt/sources.list.d";
+    /// Configuration file that mpm writes apt options to.
+    const CONF: &'static str = "/etc/apt/apt.conf.d/99mpm";
+
+    /// Only connect to mirrors over IPv4 (`-o Acquire::ForceIPv4=true`).
+    /// Useful on networks with broken IPv6.
+    pub fn set_force_ipv4(&mut self, force_ipv4: bool) {
+        self.force_ipv4 = force_ipv4;
+    }
+
+    /// Commands run by [``AdvancedPackageTool::fix_broken``]: configure
+    /// half-configured packages, then resolve broken dependencies.
+    pub(crate) fn fix_broken_cmds(&self) -> Vec<Vec<String>> {
+        let mut apt_get: Vec<String> = ["apt-get", "install", "-f"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        if crate::common::assume_yes() {
+            apt_get.extend(self.yes_flags(Cmd::Install));
+        }
+        if self.force_ipv4 {
+            apt_get.extend(["-o".to_string(), "Acquire::ForceIPv4=true".to_string()]);
+        }
+        let dpkg = ["dpkg", "--configure", "-a"];
+        vec![dpkg.iter().map(|x| x.to_string()).collect(), apt_get]
+    }
+
+    /// `sources.list` followed by the `.list` and deb822 `.sources` files in
+    /// `sources.list.d`.
+    fn source_files() -> Vec<PathBuf> {
+        let mut files = vec![PathBuf::from(Self::SOURCES)];
+        files.extend(crate::files_with_extension(Self::SOURCES_DIR, "list"));
+        files.extend(crate::files_with_extension(Self::SOURCES_DIR, "sources"));
+        files
+    }
+
+    /// Repository URIs of the one-line (`deb [opts] uri suite ...`) and the
+    /// deb822 (`URIs: uri ...`) sources formats.
+    pub(crate) fn source_uris(sources: &str) -> Vec<&str> {
+        let mut uris = vec![];
+        for line in sources.lines().map(str::trim) {
+            if let Some(rest) = line.strip_prefix("URIs:") {
+                uris.extend(rest.split_whitespace());
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            if !matches!(fields.next(), Some("deb" | "deb-src")) {
+                continue;
+            }
+            let mut uri = fields.next();
+            if uri.is_some_and(|f| f.starts_with('[')) {
+                // skip the options, e.g. `[arch=amd64 signed-by=...]`
+                let mut field = uri;
+                while field.is_some_and(|f| !f.ends_with(']')) {
+                    field = fields.next();
+                }
+                uri = fields.next();
+            }
+            uris.extend(uri);
+        }
+        uris
+    }
+
+    /// Point the repository URIs on `host` to the scheme, host and port of
+    /// `mirror`.
+    pub(crate) fn rewrite_mirror(sources: &str, host: &str, mirror: &url::Url) -> String {
+        let mut out = String::with_capacity(sources.len());
+        for line in sources.split_inclusive('\n') {
+            let mut rewritten = line.to_string();
+            for uri in Self::source_uris(line) {
+                if let Ok(url) = url::Url::parse(uri) {
+                    if url.host_str() == Some(host) {
+                        let new_uri = crate::with_mirror_origin(&url, mirror);
+                        rewritten = rewritten.replacen(uri, &new_uri, 1);
+                    }
+                }
+            }
+            out.push_str(&rewritten);
+        }
+        out
+    }
+
+    /// Repositories of the one-line and the deb822 sources formats, named by
+    /// their URI. deb822 stanzas are disabled with `Enabled: no`, commented
+    /// out one-line entries are left out.
+    pub(crate) fn parse_sources(sources: &str) -> Vec<Repository> {
+        let mut repos = vec![];
+        let (mut uris, mut enabled) = (vec![], true);
+        for line in sources.lines().map(str::trim) {
+            if line.is_empty() {
+                repos.extend(uris.drain(..).map(|uri| Self::source_repo(uri, enabled)));
+                enabled = true;
+            } else if let Some(value) = line.strip_prefix("Enabled:") {
+                enabled = !value.trim().eq_ignore_ascii_case("no");
+            } else if line.starts_with("URIs:") {
+                uris.extend(Self::source_uris(line));
+            } else {
+                repos.extend(
+                    Self::source_uris(line)
+                        .into_iter()
+                        .map(|uri| Self::source_repo(uri, true)),
+                );
+            }
+        }
+        repos.extend(uris.into_iter().map(|uri| Self::source_repo(uri, enabled)));
+        repos
+    }
+
+    fn source_repo(uri: &str, enabled: bool) -> Repository {
+        Repository {
+            enabled,
+            ..Repository::new(uri, Some(uri))
+        }
+    }
+
+    /// Remove the entries of the repository at `uri` from `sources`: the
+    /// one-line entries, and the URI from deb822 stanzas, dropping stanzas
+    /// without another URI.
+    pub(crate) fn remove_source(sources: &str, uri: &str) -> String {
+        let mut out = String::with_capacity(sources.len());
+        let mut stanza = String::new();
+        for line in sources.split_inclusive('\n') {
+            stanza.push_str(line);
+            if line.trim().is_empty() {
+                out.push_str(&Self::remove_from_stanza(&stanza, uri));
+                stanza.clear();
+            }
+        }
+        out.push_str(&Self::remove_from_stanza(&stanza, uri));
+        out
+    }
+
+    /// [``AdvancedPackageTool::remove_source``] for the lines of a single
+    /// deb822 stanza, or of one-line entries.
+    fn remove_from_stanza(stanza: &str, uri: &str) -> String {
+        let same = |u: &&str| u.trim_end_matches('/') == uri.trim_end_matches('/');
+        let Some(uris) = stanza.lines().find_map(|l| l.trim().strip_prefix("URIs:")) else {
+            return stanza
+                .split_inclusive('\n')
+                .filter(|line| !Self::source_uris(line).iter().any(same))
+                .collect();
+        };
+        let kept: Vec<&str> = uris.split_whitespace().filter(|u| !same(u)).collect();
+        if kept.is_empty() {
+            return String::new();
+        }
+        stanza
+            .split_inclusive('\n')
+            .map(|line| {
+                if line.trim().starts_with("URIs:") {
+                    let eol = &line[line.trim_end().len()..];
+                    format!("URIs: {}{eol}", kept.join(" "))
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect()
+    }
+
+    /// Remove the repository at `uri` from the given sources files. Files
+    /// in `sources.list.d` without any other repository are deleted.
+    pub(crate) fn remove_source_in<P: AsRef<Path>>(files: &[P], uri: &str) -> anyhow::Result<()> {
+        let mut found = false;
+        for file in files.iter().map(AsRef::as_ref) {
+            let Ok(sources) = fs::read_to_string(file) else {
+                continue;
+            };
+            let rewritten = Self::remove_source(&sources, uri);
+            if rewritten == sources {
+                continue;
+            }
+            found = true;
+            if file != Path::new(Self::SOURCES) && Self::parse_sources(&rewritten).is_empty() {
+                fs::remove_file(file).with_context(|| format!("failed to remove {file:?}"))?;
+            } else {
+                fs::write(file, rewritten).with_context(|| format!("failed to write {file:?}"))?;
+            }
+        }
+        anyhow::ensure!(found, "no apt source has the URI {uri}");
+        Ok(())
+    }
+
+    /// Switch the primary mirror of the given sources files to `mirror`.
+    pub(crate) fn set_mirror_in<P: AsRef<Path>>(
+        files: &[P],
+        mirror: &url::Url,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            mirror.host_str().is_some(),
+            "mirror URL {mirror} has no host"
+        );
+        let contents: Vec<(&P, String)> = files
+            .iter()
+            .filter_map(|f| fs::read_to_string(f).ok().map(|c| (f, c)))
+            .collect();
+        let urls: Vec<url::Url> = contents
+            .iter()
+            .flat_map(|(_, c)| Self::source_uris(c))
+            .filter_map(|uri| url::Url::parse(uri).ok())
+            .collect();
+        let host = crate::primary_host(&urls).context("no apt sources found")?;
+        for (file, sources) in contents {
+            let file: &Path = file.as_ref();
+            let rewritten = Self::rewrite_mirror(&sources, &host, mirror);
+            if rewritten != sources {
+                fs::write(file, rewritten).with_context(|| format!("failed to write {file:?}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Value of `key` in the output of `apt-config dump`, whose lines look
+    /// like `Acquire::Retries "3";`. Keys are case-insensitive.
+    pub(crate) fn parse_config_dump(output: &str, key: &str) -> Option<String> {
+        output.lines().find_map(|line| {
+            let (k, value) = line.trim().split_once(' ')?;
+            if !k.eq_ignore_ascii_case(key) {
+                return None;
+            }
+            let value = value.trim().strip_suffix(';')?.trim_matches('"');
+            Some(value.to_string())
+        })
+    }
+
+    /// Set `key` to `value` in an apt configuration file, replacing the line
+    /// of `key` or appending it.
+    pub(crate) fn conf_set(conf: &str, key: &str, value: &str) -> String {
+        let option = format!("{key} \"{value}\";");
+        let mut lines: Vec<String> = conf.lines().map(String::from).collect();
+        let existing = lines.iter().position(|l| {
+            l.split_whitespace()
+                .next()
+                .is_some_and(|k| k.eq_ignore_ascii_case(key))
+        });
+        match existing {
+            Some(i) => lines[i] = option,
+            None => lines.push(option),
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// Parse the output of `apt-cache rdepends`, e.g.
+    ///
+    /// ```text
+    /// libfoo1
+    /// Reverse Depends:
+    ///   foo-utils
+    ///  |bar
+    /// ```
+    pub(crate) fn parse_rdepends(output: &str) -> Vec<String> {
+        let mut seen = std::collections::HashSet::new();
+        output
+            .lines()
+            .skip_while(|l| !l.starts_with("Reverse Depends:"))
+            .skip(1)
+            .map(|l| l.trim().trim_start_matches('|').to_string())
+            .filter(|l| !l.is_empty() && seen.insert(l.clone()))
+            .collect()
+    }
+
+    /// Parse the output of `dpkg -S <path>` into the name of the owning
+    /// package, e.g.
+    ///
+    /// ```text
+    /// diversion by dash from: /bin/sh
+    /// coreutils, libc-bin: /usr/share/doc
+    /// libc6:amd64: /lib/x86_64-linux-gnu/libc.so.6
+    /// ```
+    ///
+    /// Only the first of several packages sharing a directory is returned.
+    pub(crate) fn parse_dpkg_search(output: &str) -> Option<String> {
+        output
+            .lines()
+            .filter(|l| !l.starts_with("diversion by "))
+            .find_map(|l| l.rsplit_once(": "))
+            .and_then(|(pkgs, _)| pkgs.split(", ").next())
+            .map(|pkg| pkg.split_once(':').map_or(pkg, |(name, _)| name))
+            .map(str::to_string)
+    }
+
+    /// Parse the output of `apt-mark showhold`, one package per line with
+    /// an optional `:arch` suffix.
+    pub(crate) fn parse_showhold(output: &str) -> Vec<String> {
+        output
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|l| l.split_once(':').map_or(l, |(name, _)| name).to_string())
+            .collect()
+    }
+
+    /// Parse the summary apt prints before installing, e.g.
+    ///
+    /// ```text
+    /// Need to get 1,234 kB of archives.
+    /// After this operation, 5,678 kB of additional disk space will be used.
+    /// ```
+    ///
+    /// and return the larger of the two sizes in bytes.
+    pub(crate) fn parse_install_size(output: &str) -> Option<u64> {
+        let (download, installed) = Self::parse_sizes(output);
+        download.max(installed)
+    }
+
+    /// Download and additional disk space sizes of apt's summary, see
+    /// [``AdvancedPackageTool::parse_install_size``].
+    fn parse_sizes(output: &str) -> (Option<u64>, Option<u64>) {
+        let (mut download, mut installed) = (None, None);
+        for l in output.lines() {
+            if let Some(rest) = l.strip_prefix("Need to get ") {
+                // `0 B/1,234 kB` if some archives are already downloaded
+                let size = rest.split(" of archives").next().unwrap_or_default();
+                download = size.rsplit('/').next().and_then(crate::parse_size);
+            } else if let Some(rest) = l.strip_prefix("After this operation, ") {
+                installed = if rest.contains("will be freed") {
+                    Some(0)
+                } else {
+                    rest.split(" of additional")
+                        .next()
+                        .and_then(crate::parse_size)
+                };
+            }
+        }
+        (download, installed)
+    }
+
+    /// Parse the output of `apt-get install --simulate`, e.g.
+    ///
+    /// ```text
+    /// Remv foo [1.0-1]
+    /// Inst libc6 [2.36-9] (2.36-9+deb12u4 Debian:12.5/stable-security [amd64]) []
+    /// Inst hello (2.10-3 Debian:12.5/stable [amd64])
+    /// Conf hello (2.10-3 Debian:12.5/stable [amd64])
+    /// ```
+    ///
+    /// where packages with an installed version in brackets are upgraded.
+    pub(crate) fn parse_plan(&self, output: &str) -> InstallPlan {
+        let (download_size, installed_size) = Self::parse_sizes(output);
+        let mut plan = InstallPlan {
+            download_size,
+            installed_size,
+            ..Default::default()
+        };
+        for line in output.lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(action @ ("Inst" | "Remv")), Some(name)) = (fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let rest: Vec<&str> = fields.collect();
+            let upgrade = rest.first().is_some_and(|f| f.starts_with('['));
+            let version = match action {
+                "Remv" => rest
+                    .first()
+                    .map(|v| v.trim_matches(|c| c == '[' || c == ']')),
+                _ => rest
+                    .iter()
+                    .find(|f| f.starts_with('('))
+                    .map(|v| v.trim_start_matches('(')),
+            };
+            let pkg = Package::new(name, self.pkg_manager_name(), version);
+            match action {
+                "Remv" => plan.to_remove.push(pkg),
+                _ if upgrade => plan.to_upgrade.push(pkg),
+                _ => plan.to_install.push(pkg),
+            }
+        }
+        plan
+    }
+
+    /// Parse the output of `apt-cache depends --recurse --important <pkg>`,
+    /// where every package is followed by its indented dependencies, e.g.
+    ///
+    /// ```text
+    /// bash
+    ///   PreDepends: libc6
+    ///   Depends: base-files
+    ///  |Depends: debianutils
+    /// libc6
+    ///   Depends: libgcc-s1
+    /// ```
+    ///
+    /// Alternatives (`|`) are all recorded, virtual packages (`<name>`) are
+    /// recorded by their name.
+    pub(crate) fn parse_depends(root: &str, output: &str) -> DependencyGraph {
+        let mut graph = DependencyGraph::new(root);
+        fn name(s: &str) -> &str {
+            s.trim().trim_start_matches('<').trim_end_matches('>')
+        }
+        let mut current: Option<&str> = None;
+        for line in output.lines() {
+            if !line.starts_with(char::is_whitespace) {
+                current = Some(name(line)).filter(|n| !n.is_empty());
+                continue;
+            }
+            let line = line.trim().trim_start_matches('|');
+            let dep = line
+                .strip_prefix("Depends:")
+                .or_else(|| line.strip_prefix("PreDepends:"));
+            if let (Some(pkg), Some(dep)) = (current, dep) {
+                graph.add(pkg, name(dep));
+            }
+        }
+        graph
+    }
+
+    /// Parse the output of `apt-cache policy <pkg>` for the installed and
+    /// the candidate version and of `apt-cache show --no-all-versions <pkg>`
+    /// for the rest. apt does not record licenses.
+    pub(crate) fn parse_info(name: &str, policy: &str, show: &str) -> PackageMetadata {
+        let version = |key: &str| {
+            policy
+                .lines()
+                .find_map(|l| l.trim().strip_prefix(key))
+                .map(str::trim)
+                .filter(|v| *v != "(none)")
+                .map(str::to_string)
+        };
+        let field = |key: &str| {
+            show.lines()
+                .find_map(|l| l.strip_prefix(key)?.strip_prefix(':'))
+                .map(|v| v.trim().to_string())
+        };
+        PackageMetadata {
+            name: name.to_string(),
+            description: field("Description").or_else(|| field("Description-en")),
+            homepage: field("Homepage"),
+            license: None,
+            // in KiB
+            size: field("Installed-Size")
+                .and_then(|s| s.parse::<u64>().ok())
+                .map(|kib| kib * 1024),
+            installed_version: version("Installed:"),
+            candidate_version: version("Candidate:"),
+        }
+    }
+
+    /// Parse a line of `debsums -s` like `debsums: changed file /usr/bin/foo
+    /// (from foo package)` into the package and the file.
+    pub(crate) fn parse_debsums(line: &str) -> Option<(String, PathBuf)> {
+        let rest = line.strip_prefix("debsums: ")?;
+        let (_, rest) = rest.split_once(" file ")?;
+        let (path, from) = rest.rsplit_once(" (from ")?;
+        let name = from.strip_suffix(" package)")?;
+        Some((name.to_string(), PathBuf::from(path)))
+    }
+
+    /// Parse the output of `dpkg-sig --verify`. A package is valid if it has
+    /// at least one good signature and no bad or unknown ones.
+    pub(crate) fn parse_dpkg_sig(output: &str) -> bool {
+        let mut good = false;
+        for line in output.lines().map(str::trim) {
+            if line.starts_with("GOODSIG") {
+                good = true;
+            } else if ["BADSIG", "NOSIG", "UNKNOWNSIG"]
+                .iter()
+                .any(|s| line.starts_with(s))
+            {
+                return false;
+            }
+        }
+        good
+    }
 }
 
 impl PackageManager for AdvancedPackageTool {
@@ -41,14 +606,70 @@ impl PackageManager for AdvancedPackageTool {
         vec![PkgFormat::Deb]
     }
 
+    /// `apt search` interprets the query as a POSIX regular expression.
+    fn search_uses_regex(&self) -> bool {
+        true
+    }
+
+    /// Recognizes the `N upgraded, M newly installed, ...` summary and the
+    /// `Unpacking <pkg>` and `Setting up <pkg>` lines of dpkg.
+    fn parse_progress(&self, line: &str) -> Option<Progress> {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Unpacking ") {
+            return Some(Progress::Started(
+                rest.split_whitespace().next()?.to_string(),
+            ));
+        }
+        if let Some(rest) = line.strip_prefix("Setting up ") {
+            return Some(Progress::Finished(
+                rest.split_whitespace().next()?.to_string(),
+            ));
+        }
+        if line.contains(" upgraded, ") && line.contains(" newly installed") {
+            let mut counts = line.split(", ").map(|part| {
+                part.split_whitespace()
+                    .next()
+                    .and_then(|n| n.parse::<usize>().ok())
+            });
+            let upgraded = counts.next()??;
+            let installed = counts.next()??;
+            return Some(Progress::Total(upgraded + installed));
+        }
+        None
+    }
+
+    /// Parses lines of `apt list` and `apt search` of the form
+    /// `name/suites version arch [status]`, where the optional status is e.g.
+    /// `[installed]`, `[installed,local]`, `[installed,automatic]` or
+    /// `[upgradable from: 1.0-1]`.
     fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
         let (name, info) = line.split_once('/')?;
-        if matches!(info.split_whitespace().count(), 3 | 4 | 6) {
-            let ver = info.split_whitespace().nth(1)?;
-            Some(Package::new(name, self.pkg_manager_name(), Some(ver)))
-        } else {
-            None
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return None;
         }
+        let fields = match info.split_once(" [") {
+            Some((fields, status)) if status.trim_end().ends_with(']') => fields,
+            Some(_) => return None,
+            None => info,
+        };
+        let mut fields = fields.split_whitespace();
+        let (_suites, version, _arch) = (fields.next()?, fields.next()?, fields.next()?);
+        // debian versions start with a digit, also when they have an epoch
+        if fields.next().is_some() || !version.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+    }
+
+    /// Parses packages like [``PackageManager::parse_output_lenient``], but
+    /// lists packages installed for several architectures only once.
+    fn parse_output(&self, out: &[u8]) -> Vec<Package> {
+        let mut seen = std::collections::HashSet::new();
+        self.parse_output_lenient(out)
+            .0
+            .into_iter()
+            .filter(|p| seen.insert((p.name().to_string(), p.version().map(str::to_string))))
+            .collect()
     }
 
     // Apt doesn't support installing from URL.
@@ -66,6 +687,387 @@ impl PackageManager for AdvancedPackageTool {
         pkg.cli_display(self.pkg_delimiter()).to_string()
     }
 
+    /// Uses `apt-cache search` with the apt-get backend.
+    fn search_with_args(&self, query: &str, args: &[String]) -> Vec<Package> {
+        let mut args = args.to_vec();
+        args.push(self.search_query(query));
+        if self.stable_queries() {
+            let args: Vec<&str> = std::iter::once("search")
+                .chain(args.iter().map(String::as_str))
+                .collect();
+            let out = Self::exec_query("apt-cache", &args);
+            return Self::parse_lines(&out.stdout, |l| self.parse_apt_cache_search(l));
+        }
+        let cmds = self.consolidated(Cmd::Search, None, &args);
+        self.parse_output(&self.exec_apt(&cmds).stdout)
+    }
+
+    /// Checks the exit status of the search of
+    /// [``AdvancedPackageTool::search_with_args``].
+    fn try_search(&self, query: &str) -> anyhow::Result<Vec<Package>> {
+        let query = self.search_query(query);
+        if self.stable_queries() {
+            let out = Self::exec_query("apt-cache", &["search", &query]);
+            crate::traits::ensure_search_succeeded(std::ffi::OsStr::new("apt-cache"), &out)?;
+            return Ok(Self::parse_lines(&out.stdout, |l| {
+                self.parse_apt_cache_search(l)
+            }));
+        }
+        let cmds = self.consolidated(Cmd::Search, None, &[query]);
+        let out = self.exec_apt(&cmds);
+        crate::traits::ensure_search_succeeded(std::ffi::OsStr::new("apt"), &out)?;
+        Ok(self.parse_output(&out.stdout))
+    }
+
+    fn search_limited(&self, query: &str, limit: usize) -> anyhow::Result<Vec<Package>> {
+        if self.stable_queries() {
+            let mut pkgs = self.search(query);
+            pkgs.truncate(limit);
+            return Ok(pkgs);
+        }
+        let cmds = self.consolidated(Cmd::Search, None, &[self.search_query(query)]);
+        let mut command = self.elevated(Command::new("apt"));
+        command.args(&cmds);
+        crate::traits::search_streaming(self, command, limit)
+    }
+
+    /// Uses `dpkg-query --show` with the apt-get backend.
+    fn list_installed(&self) -> Vec<Package> {
+        if self.stable_queries() {
+            let format = format!("--showformat={}", Self::DPKG_QUERY_FORMAT);
+            let out = Self::exec_query("dpkg-query", &["--show", &format]);
+            return Self::parse_lines(&out.stdout, |l| self.parse_dpkg_query(l));
+        }
+        let cmds = self.consolidated::<&str>(Cmd::List, None, &[]);
+        self.parse_output(&self.exec_apt(&cmds).stdout)
+    }
+
+    /// Uses the upgrades of `apt-get --simulate upgrade` with the apt-get
+    /// backend, see [``AdvancedPackageTool::parse_plan``].
+    fn list_outdated(&self) -> Vec<Package> {
+        if self.stable_queries() {
+            let out = Self::exec_query("apt-get", &["--simulate", "upgrade"]);
+            return self
+                .parse_plan(&String::from_utf8_lossy(&out.stdout))
+                .to_upgrade;
+        }
+        let cmds = self.consolidated::<&str>(Cmd::Outdated, None, &[]);
+        self.parse_output(&self.exec_apt(&cmds).stdout)
+    }
+
+    /// Packages marked `Essential: yes` are protected.
+    fn is_protected(&self, pkg: &Package) -> bool {
+        Command::new("dpkg-query")
+            .args(["-W", "-f=${Essential}", pkg.name()])
+            .output()
+            .map(|out| out.status.success() && String::from_utf8_lossy(&out.stdout).trim() == "yes")
+            .unwrap_or(false)
+    }
+
+    /// Uses `dpkg-sig`, falling back to `debsig-verify`.
+    fn verify_package_signature(&self, path: &Path) -> anyhow::Result<bool> {
+        match Command::new("dpkg-sig").arg("--verify").arg(path).output() {
+            Ok(out) => Ok(Self::parse_dpkg_sig(&String::from_utf8_lossy(&out.stdout))),
+            Err(_) => {
+                let status = Command::new("debsig-verify")
+                    .arg(path)
+                    .status()
+                    .context("neither dpkg-sig nor debsig-verify is available")?;
+                Ok(status.success())
+            }
+        }
+    }
+
+    fn download_updates(&self) -> anyhow::Result<ExitStatus> {
+        let cmds = self.consolidated(Cmd::UpdateAll, None, &["--download-only"]);
+        Ok(self.exec_cmds_status(&cmds, Some(false)))
+    }
+
+    fn apply_downloaded_updates(&self) -> anyhow::Result<ExitStatus> {
+        anyhow::ensure!(
+            self.backend != AptBackend::Aptitude,
+            crate::Unsupported::new("aptitude", "applying downloaded updates")
+        );
+        let cmds = self.consolidated(Cmd::UpdateAll, None, &["--no-download"]);
+        Ok(self.exec_cmds_status(&cmds, Some(false)))
+    }
+
+    fn reinstall(&self, pkg: &Package, interactive: bool) -> anyhow::Result<ExitStatus> {
+        let mut pkg = pkg.clone();
+        let fmt = self.reformat_for_command(&mut pkg);
+        let cmds = if self.backend == AptBackend::Aptitude {
+            crate::traits::consolidated_as(self, &["reinstall"], Cmd::Install, Some(&pkg), &[fmt])
+        } else {
+            self.consolidated(Cmd::Install, Some(&pkg), &["--reinstall".to_string(), fmt])
+        };
+        Ok(self.exec_cmds_status(&cmds, Some(interactive)))
+    }
+
+    /// Uses `apt-cache pkgnames`.
+    fn list_available_with<F: FnMut(Package)>(&self, mut on_pkg: F) -> anyhow::Result<()> {
+        let status = crate::run_command_with(Command::new("apt-cache"), &["pkgnames"], |line| {
+            let name = line.trim();
+            if !name.is_empty() {
+                on_pkg(Package::new(name, self.pkg_manager_name(), None));
+            }
+        })?;
+        anyhow::ensure!(status.success(), "apt-cache pkgnames failed");
+        Ok(())
+    }
+
+    /// Uses `apt-cache rdepends`.
+    fn reverse_dependencies(&self, pkg: &Package) -> anyhow::Result<Vec<Package>> {
+        let out = Command::new("apt-cache")
+            .args(["rdepends", pkg.name()])
+            .output()
+            .context("failed to run apt-cache")?;
+        anyhow::ensure!(out.status.success(), "apt-cache rdepends failed");
+        Ok(Self::parse_rdepends(&String::from_utf8_lossy(&out.stdout))
+            .iter()
+            .map(|name| Package::new(name, self.pkg_manager_name(), None))
+            .collect())
+    }
+
+    /// Uses `apt clean`.
+    fn cache_dirs(&self) -> Vec<PathBuf> {
+        vec![PathBuf::from("/var/cache/apt")]
+    }
+
+    /// Locks of dpkg and of the package lists and archives of apt.
+    fn lock_files(&self) -> Vec<PathBuf> {
+        [
+            "/var/lib/dpkg/lock-frontend",
+            "/var/lib/dpkg/lock",
+            "/var/lib/apt/lists/lock",
+            "/var/cache/apt/archives/lock",
+        ]
+        .map(PathBuf::from)
+        .to_vec()
+    }
+
+    /// Uses `apt-mark hold`.
+    fn pin(&self, pkg: &Package) -> anyhow::Result<()> {
+        let apt_mark = self.elevated(Command::new("apt-mark"));
+        let s = crate::run_command(apt_mark, &["hold", pkg.name()], true, None)?.0;
+        anyhow::ensure!(s.success(), "failed to hold {}", pkg.name());
+        Ok(())
+    }
+
+    /// Uses `apt-mark unhold`.
+    fn unpin(&self, pkg: &Package) -> anyhow::Result<()> {
+        let apt_mark = self.elevated(Command::new("apt-mark"));
+        let s = crate::run_command(apt_mark, &["unhold", pkg.name()], true, None)?.0;
+        anyhow::ensure!(s.success(), "failed to unhold {}", pkg.name());
+        Ok(())
+    }
+
+    /// Uses `apt-mark showhold`.
+    fn list_pinned(&self) -> anyhow::Result<Vec<Package>> {
+        let out = Command::new("apt-mark")
+            .arg("showhold")
+            .output()
+            .context("failed to run apt-mark")?;
+        anyhow::ensure!(out.status.success(), "apt-mark showhold failed");
+        Ok(Self::parse_showhold(&String::from_utf8_lossy(&out.stdout))
+            .iter()
+            .map(|name| Package::new(name, self.pkg_manager_name(), None))
+            .collect())
+    }
+
+    /// Uses `dpkg -S`.
+    fn owner(&self, path: &Path) -> anyhow::Result<Package> {
+        let out = Command::new("dpkg")
+            .arg("-S")
+            .arg(path)
+            .output()
+            .context("failed to run dpkg")?;
+        // dpkg exits with an error if no package owns the file
+        out.status
+            .success()
+            .then(|| Self::parse_dpkg_search(&String::from_utf8_lossy(&out.stdout)))
+            .flatten()
+            .map(|name| Package::new(&name, self.pkg_manager_name(), None))
+            .ok_or_else(|| NotOwned(path.to_path_buf()).into())
+    }
+
+    /// Uses `dpkg -L`.
+    fn files(&self, pkg: &Package) -> anyhow::Result<Vec<PathBuf>> {
+        let out = Command::new("dpkg")
+            .args(["-L", pkg.name()])
+            .output()
+            .context("failed to run dpkg")?;
+        anyhow::ensure!(out.status.success(), "{} is not installed", pkg.name());
+        Ok(crate::common::parse_file_list(&String::from_utf8_lossy(
+            &out.stdout,
+        )))
+    }
+
+    /// Uses `apt-cache depends --recurse`.
+    fn dependencies(&self, pkg: &Package) -> anyhow::Result<DependencyGraph> {
+        let out = Command::new("apt-cache")
+            .args(["depends", "--recurse", "--important", pkg.name()])
+            .output()
+            .context("failed to run apt-cache")?;
+        anyhow::ensure!(out.status.success(), "apt-cache depends failed");
+        Ok(Self::parse_depends(
+            pkg.name(),
+            &String::from_utf8_lossy(&out.stdout),
+        ))
+    }
+
+    /// Uses `apt-cache policy` and `apt-cache show`.
+    fn info(&self, pkg: &Package) -> anyhow::Result<PackageMetadata> {
+        let run = |args: &[&str]| -> anyhow::Result<String> {
+            let out = Command::new("apt-cache")
+                .args(args)
+                .output()
+                .context("failed to run apt-cache")?;
+            if !out.status.success() {
+                return Err(crate::error::Error::PackageNotFound(pkg.name().to_string()).into());
+            }
+            Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+        };
+        let policy = run(&["policy", pkg.name()])?;
+        let show = run(&["show", "--no-all-versions", pkg.name()])?;
+        Ok(Self::parse_info(pkg.name(), &policy, &show))
+    }
+
+    /// Uses `apt-get install --simulate`.
+    fn install_plan(&self, pkg: &Package) -> anyhow::Result<InstallPlan> {
+        let mut pkg = pkg.clone();
+        let fmt = self.reformat_for_command(&mut pkg);
+        let out = self
+            .elevated(Command::new("apt-get"))
+            .args(["install", "--simulate", &fmt])
+            .output()
+            .context("failed to run apt-get")?;
+        anyhow::ensure!(
+            out.status.success(),
+            "apt-get --simulate failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+        Ok(self.parse_plan(&String::from_utf8_lossy(&out.stdout)))
+    }
+
+    /// Reads the sizes apt prints before asking to continue, which
+    /// `--assume-no` declines, see
+    /// [``AdvancedPackageTool::parse_install_size``].
+    fn estimate_operation_size(&self, pkg: &Package) -> Option<u64> {
+        let mut pkg = pkg.clone();
+        let fmt = self.reformat_for_command(&mut pkg);
+        // apt aborts (and exits with an error) after printing the summary
+        let out = self.exec_apt(&["install".to_string(), "--assume-no".to_string(), fmt]);
+        Self::parse_install_size(&String::from_utf8_lossy(&out.stdout))
+    }
+
+    /// Uses `dpkg --verify`, which prints nothing for intact packages.
+    fn verify_integrity(&self, pkg: &Package) -> anyhow::Result<bool> {
+        let out = Command::new("dpkg")
+            .args(["--verify", pkg.name()])
+            .output()?;
+        Ok(out.status.success() && out.stdout.iter().all(u8::is_ascii_whitespace))
+    }
+
+    /// Uses `dpkg --verify`, whose output is that of `rpm -V`.
+    fn verify_files(&self, pkg: &Package) -> anyhow::Result<Vec<PathBuf>> {
+        let out = Command::new("dpkg")
+            .args(["--verify", pkg.name()])
+            .output()
+            .context("failed to run dpkg")?;
+        DandifiedYUM::parse_verify_output(&out, pkg)
+    }
+
+    /// Uses `debsums -s`, which reports changed and missing files on stderr.
+    fn verify_all(&self) -> anyhow::Result<Vec<(Package, Vec<PathBuf>)>> {
+        let mut child = Command::new("debsums")
+            .arg("-s")
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to run debsums, is it installed?")?;
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let mut modified = vec![];
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            match Self::parse_debsums(&line) {
+                Some((name, path)) => {
+                    tracing::info!("{name}: {} was modified", path.display());
+                    modified.push((name, path));
+                }
+                None => tracing::warn!("{line}"),
+            }
+        }
+        // debsums exits with an error if it found modified files
+        child.wait()?;
+        Ok(crate::common::group_by_package(
+            self.pkg_manager_name(),
+            modified,
+        ))
+    }
+
+    /// Runs `dpkg --configure -a` followed by `apt-get install -f`.
+    fn fix_broken(&self) -> anyhow::Result<ExitStatus> {
+        for cmd in self.fix_broken_cmds() {
+            let (program, args) = cmd.split_first().context("empty command")?;
+            let command = self.elevated(Command::new(program));
+            let status = crate::run_command(command, args, true, None)?.0;
+            if !status.success() {
+                return Ok(status);
+            }
+        }
+        Ok(crate::common::synthetic_exit_status(true))
+    }
+
+    /// Uses `apt-config dump`, which includes the defaults.
+    fn get_option(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let out = Command::new("apt-config")
+            .arg("dump")
+            .output()
+            .context("failed to run apt-config")?;
+        anyhow::ensure!(out.status.success(), "apt-config dump failed");
+        Ok(Self::parse_config_dump(
+            &String::from_utf8_lossy(&out.stdout),
+            key,
+        ))
+    }
+
+    /// Writes `/etc/apt/apt.conf.d/99mpm`.
+    fn set_option(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !key.is_empty() && !key.contains(char::is_whitespace),
+            "invalid apt option {key:?}"
+        );
+        anyhow::ensure!(
+            !value.contains('"'),
+            "apt option values cannot contain quotes"
+        );
+        let conf = fs::read_to_string(Self::CONF).unwrap_or_default();
+        fs::write(Self::CONF, Self::conf_set(&conf, key, value))
+            .with_context(|| format!("failed to write {}", Self::CONF))
+    }
+
+    /// Repository URIs of `/etc/apt/sources.list` and `sources.list.d`.
+    fn list_mirrors(&self) -> anyhow::Result<Vec<url::Url>> {
+        let mut mirrors: Vec<url::Url> = vec![];
+        for file in Self::source_files() {
+            let Ok(sources) = fs::read_to_string(&file) else {
+                continue;
+            };
+            for uri in Self::source_uris(&sources) {
+                match url::Url::parse(uri) {
+                    Ok(url) if !mirrors.contains(&url) => mirrors.push(url),
+                    Ok(_) => (),
+                    Err(e) => tracing::debug!("Ignoring source {uri:?} in {file:?}: {e}"),
+                }
+            }
+        }
+        Ok(mirrors)
+    }
+
+    /// Rewrites the host of the repositories on the most used host.
+    fn set_mirror(&self, url: &url::Url) -> anyhow::Result<()> {
+        Self::set_mirror_in(&Self::source_files(), url)
+    }
+
     fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
         let sources = fs::File::options().append(true).open(Self::SOURCES)?;
         let mut writer = BufWriter::new(sources);
@@ -78,6 +1080,29 @@ impl PackageManager for AdvancedPackageTool {
 
         Ok(())
     }
+
+    /// Repositories of `/etc/apt/sources.list` and `sources.list.d`, named
+    /// by their URI.
+    fn list_repos(&self) -> anyhow::Result<Vec<Repository>> {
+        let mut repos: Vec<Repository> = vec![];
+        for sources in Self::source_files()
+            .iter()
+            .filter_map(|f| fs::read_to_string(f).ok())
+        {
+            for repo in Self::parse_sources(&sources) {
+                match repos.iter_mut().find(|r| r.name == repo.name) {
+                    Some(known) => known.enabled |= repo.enabled,
+                    None => repos.push(repo),
+                }
+            }
+        }
+        Ok(repos)
+    }
+
+    /// Removes the entries with the URI `name` from the apt sources.
+    fn remove_repo(&self, name: &str) -> anyhow::Result<()> {
+        Self::remove_source_in(&Self::source_files(), name)
+    }
 }
 
 impl Display for AdvancedPackageTool {
@@ -88,36 +1113,68 @@ impl Display for AdvancedPackageTool {
 
 impl PackageManagerCommands for AdvancedPackageTool {
     fn cmd(&self) -> Command {
-        Command::new("apt")
+        Command::new(self.backend.binary())
     }
 
+    /// List, search and outdated are `apt` commands for every backend, see
+    /// [``AdvancedPackageTool::exec_apt``], but the apt-get backend runs other
+    /// tools for them, see [``AdvancedPackageTool``].
     fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        let aptitude = self.backend == AptBackend::Aptitude;
         match cmd {
             Cmd::Install => vec!["install"],
             Cmd::Uninstall => vec!["remove"],
+            // aptitude only upgrades the given packages with `safe-upgrade`
+            Cmd::Update if aptitude => vec!["safe-upgrade"],
             Cmd::Update => vec!["install"],
+            Cmd::UpdateAll if aptitude => vec!["safe-upgrade"],
             Cmd::UpdateAll => vec!["upgrade"],
             Cmd::List => vec!["list"],
             Cmd::Sync => vec!["update"],
             Cmd::AddRepo => vec![],
             Cmd::Search => vec!["search"],
             Cmd::Outdated => vec!["list", "--upgradable"],
+            Cmd::Clean => vec!["clean"],
+            Cmd::Autoremove => vec!["autoremove"],
         }
         .iter()
         .map(|x| x.to_string())
         .collect()
     }
 
-    fn get_flags(&self, cmd: Cmd) -> Vec<String> {
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
         match cmd {
-            Cmd::Install | Cmd::Uninstall | Cmd::UpdateAll => vec!["--yes"],
-            Cmd::Update => vec!["--yes", "--only-upgrade"],
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll | Cmd::Autoremove => {
+                vec!["--yes".to_string()]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn get_flags(&self, cmd: Cmd) -> Vec<String> {
+        let mut flags: Vec<String> = match cmd {
+            Cmd::Update if self.backend == AptBackend::Aptitude => vec![],
+            Cmd::Update => vec!["--only-upgrade"],
             Cmd::List => vec!["--installed"],
             _ => vec![],
         }
         .iter()
         .map(|x| x.to_string())
-        .collect()
+        .collect();
+
+        if self.force_ipv4 {
+            flags.extend(["-o".to_string(), "Acquire::ForceIPv4=true".to_string()]);
+        }
+        flags
+    }
+
+    fn dry_run_flags(&self, cmd: Cmd) -> Option<Vec<String>> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll | Cmd::Autoremove => {
+                Some(vec!["--simulate".to_string()])
+            }
+            _ => None,
+        }
     }
 }
 
@@ -126,8 +1183,329 @@ mod tests {
     #![allow(unused_imports)]
     use std::str::FromStr;
 
-    use super::AdvancedPackageTool;
-    use crate::{Package, PackageManager, PackageManagerCommands};
+    use super::{AdvancedPackageTool, AptBackend};
+    use crate::{Cmd, Package, PackageManager, PackageManagerCommands};
+
+    #[test]
+    fn test_config_options() {
+        let dump = "APT \"\";\nAPT::Architecture \"amd64\";\nAcquire::Retries \"3\";\n";
+        assert_eq!(
+            AdvancedPackageTool::parse_config_dump(dump, "acquire::retries").as_deref(),
+            Some("3")
+        );
+        assert_eq!(
+            AdvancedPackageTool::parse_config_dump(dump, "Acquire"),
+            None
+        );
+
+        let conf = AdvancedPackageTool::conf_set("", "Acquire::Retries", "3");
+        assert_eq!(conf, "Acquire::Retries \"3\";\n");
+        let conf = AdvancedPackageTool::conf_set(&conf, "Acquire::Queue-Mode", "access");
+        let conf = AdvancedPackageTool::conf_set(&conf, "Acquire::Retries", "5");
+        assert_eq!(
+            conf,
+            "Acquire::Retries \"5\";\nAcquire::Queue-Mode \"access\";\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_depends() {
+        let out = "bash
+  PreDepends: libc6
+  Depends: base-files
+ |Depends: debianutils
+  Depends: <awk>
+libc6
+  Depends: libgcc-s1
+<awk>
+    mawk
+base-files
+";
+        let graph = AdvancedPackageTool::parse_depends("bash", out);
+        assert_eq!(graph.root, "bash");
+        assert_eq!(
+            graph.dependencies_of("bash"),
+            ["libc6", "base-files", "debianutils", "awk"]
+        );
+        assert_eq!(graph.dependencies_of("libc6"), ["libgcc-s1"]);
+        assert!(graph.dependencies_of("awk").is_empty());
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let policy =
+            "hello:\n  Installed: (none)\n  Candidate: 2.10-3\n  Version table:\n     2.10-3 500\n";
+        let show = "Package: hello
+Version: 2.10-3
+Installed-Size: 280
+Homepage: https://www.gnu.org/software/hello/
+Description: example package based on GNU hello
+ The GNU hello program produces a familiar, friendly greeting.
+Description-md5: 3a6a0a3b8a2e1f1a4c3d2b1a0f9e8d7c
+";
+        assert_eq!(
+            AdvancedPackageTool::parse_info("hello", policy, show),
+            crate::PackageMetadata {
+                name: "hello".to_string(),
+                description: Some("example package based on GNU hello".to_string()),
+                homepage: Some("https://www.gnu.org/software/hello/".to_string()),
+                license: None,
+                size: Some(280 * 1024),
+                installed_version: None,
+                candidate_version: Some("2.10-3".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_stable_queries() {
+        let apt = AdvancedPackageTool::default();
+        let dpkg =
+            "ii \tbash\t5.2.15-2+b2\nrc \tlibfoo1\t1.0-1\nii \tlibc6\t2.36-9\nii \tlibc6\t2.36-9\n";
+        assert_eq!(
+            AdvancedPackageTool::parse_lines(dpkg.as_bytes(), |l| apt.parse_dpkg_query(l)),
+            [
+                Package::new("bash", "apt".to_string(), Some("5.2.15-2+b2")),
+                Package::new("libc6", "apt".to_string(), Some("2.36-9")),
+            ]
+        );
+        assert_eq!(
+            apt.parse_apt_cache_search("hello - example package based on GNU hello"),
+            Some(Package::new("hello", "apt".to_string(), None))
+        );
+        assert_eq!(
+            apt.parse_apt_cache_search("WARNING: apt does not have a stable CLI interface."),
+            None
+        );
+    }
+
+    #[test]
+    fn test_backend_cmds() {
+        let pkg = Package::from_str("apt@hello").unwrap();
+        let mut apt = AdvancedPackageTool::default();
+        assert_eq!(apt.cmd().get_program(), "apt");
+
+        apt.set_backend(AptBackend::AptGet);
+        assert_eq!(apt.cmd().get_program(), "apt-get");
+        assert_eq!(
+            apt.consolidated(Cmd::Update, Some(&pkg), &["hello"]),
+            ["install", "--yes", "--only-upgrade", "hello"]
+        );
+        assert_eq!(
+            apt.consolidated::<&str>(Cmd::UpdateAll, None, &[]),
+            ["upgrade", "--yes"]
+        );
+
+        apt.set_backend(AptBackend::Aptitude);
+        assert_eq!(apt.cmd().get_program(), "aptitude");
+        assert_eq!(
+            apt.consolidated(Cmd::Install, Some(&pkg), &["hello"]),
+            ["install", "--yes", "hello"]
+        );
+        assert_eq!(
+            apt.consolidated(Cmd::Update, Some(&pkg), &["hello"]),
+            ["safe-upgrade", "--yes", "hello"]
+        );
+        assert_eq!(
+            apt.consolidated::<&str>(Cmd::UpdateAll, None, &[]),
+            ["safe-upgrade", "--yes"]
+        );
+        // queries keep apt's syntax, they always run apt
+        assert_eq!(
+            apt.consolidated::<&str>(Cmd::List, None, &[]),
+            ["list", "--installed"]
+        );
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_parse_output_lenient() {
+        let input = b"Sorting...\nhello/stable 2.10-3 amd64\n  example package\n\n";
+        let apt = AdvancedPackageTool::default();
+        let (pkgs, unparsed) = apt.parse_output_lenient(input);
+        assert_eq!(pkgs, [Package::from_str("apt@hello@2.10-3").unwrap()]);
+        assert_eq!(unparsed, 2);
+        assert!(logs_contain("dropped unparsed line \"Sorting...\""));
+        assert!(logs_contain("2 line(s) not parsed"));
+    }
+
+    #[test]
+    fn test_source_uris() {
+        let sources = r#"# comment
+deb http://deb.debian.org/debian bookworm main
+deb-src [arch=amd64 signed-by=/usr/share/keyrings/debian.gpg] http://deb.debian.org/debian bookworm main
+Types: deb
+URIs: http://security.debian.org/debian-security https://example.org/debian
+"#;
+        assert_eq!(
+            AdvancedPackageTool::source_uris(sources),
+            [
+                "http://deb.debian.org/debian",
+                "http://deb.debian.org/debian",
+                "http://security.debian.org/debian-security",
+                "https://example.org/debian"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_sources() {
+        let sources = r#"# deb http://deb.debian.org/debian bookworm-backports main
+deb http://deb.debian.org/debian bookworm main
+
+Types: deb
+URIs: https://packages.microsoft.com/repos/code
+Enabled: no
+"#;
+        let repos = AdvancedPackageTool::parse_sources(sources);
+        assert_eq!(repos.len(), 2);
+        assert_eq!(repos[0].name, "http://deb.debian.org/debian");
+        assert_eq!(
+            repos[0].url.as_deref(),
+            Some("http://deb.debian.org/debian")
+        );
+        assert!(repos[0].enabled);
+        assert_eq!(repos[1].name, "https://packages.microsoft.com/repos/code");
+        assert!(!repos[1].enabled);
+    }
+
+    #[test]
+    fn test_remove_source() {
+        let sources = "deb http://deb.debian.org/debian bookworm main\n\
+                       deb https://example.org/debian/ stable main\n\
+                       \n\
+                       Types: deb\n\
+                       URIs: https://example.org/debian http://deb.debian.org/debian\n\
+                       Suites: stable\n\
+                       \n\
+                       Types: deb\n\
+                       URIs: https://example.org/debian\n";
+        assert_eq!(
+            AdvancedPackageTool::remove_source(sources, "https://example.org/debian"),
+            "deb http://deb.debian.org/debian bookworm main\n\
+             \n\
+             Types: deb\n\
+             URIs: http://deb.debian.org/debian\n\
+             Suites: stable\n\
+             \n"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let list = dir.path().join("example.list");
+        std::fs::write(&list, "deb https://example.org/debian stable main\n").unwrap();
+        AdvancedPackageTool::remove_source_in(&[&list], "https://example.org/debian").unwrap();
+        assert!(!list.exists());
+        assert!(
+            AdvancedPackageTool::remove_source_in(&[&list], "https://example.org/debian").is_err()
+        );
+    }
+
+    #[test]
+    fn test_set_mirror() {
+        let dir = tempfile::tempdir().unwrap();
+        let list = dir.path().join("sources.list");
+        std::fs::write(
+            &list,
+            "deb http://deb.debian.org/debian bookworm main\n\
+             deb http://deb.debian.org/debian bookworm-updates main\n\
+             deb http://security.debian.org/debian-security bookworm-security main\n",
+        )
+        .unwrap();
+        let deb822 = dir.path().join("debian.sources");
+        std::fs::write(&deb822, "Types: deb\nURIs: http://deb.debian.org/debian\n").unwrap();
+
+        let mirror = "https://ftp.de.debian.org".parse().unwrap();
+        AdvancedPackageTool::set_mirror_in(&[&list, &deb822], &mirror).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&list).unwrap(),
+            "deb https://ftp.de.debian.org/debian bookworm main\n\
+             deb https://ftp.de.debian.org/debian bookworm-updates main\n\
+             deb http://security.debian.org/debian-security bookworm-security main\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&deb822).unwrap(),
+            "Types: deb\nURIs: https://ftp.de.debian.org/debian\n"
+        );
+
+        assert!(
+            AdvancedPackageTool::set_mirror_in(&[dir.path().join("missing")], &mirror).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_plan() {
+        let output = r#"NOTE: This is only a simulation!
+      apt-get needs root privileges for real execution.
+      Keep also in mind that locking is deactivated,
+      so don't depend on the relevance to the real current situation!
+Reading package lists...
+Building dependency tree...
+The following packages will be REMOVED:
+  foo
+The following NEW packages will be installed:
+  hello libhello1
+The following packages will be upgraded:
+  libc6
+1 upgraded, 2 newly installed, 1 to remove and 0 not upgraded.
+Remv foo [1.0-1]
+Inst libc6 [2.36-9] (2.36-9+deb12u4 Debian:12.5/stable-security [amd64]) []
+Inst libhello1 (1.0-2 Debian:12.5/stable [amd64])
+Inst hello (2.10-3 Debian:12.5/stable [amd64])
+Conf libc6 (2.36-9+deb12u4 Debian:12.5/stable-security [amd64])
+Conf libhello1 (1.0-2 Debian:12.5/stable [amd64])
+Conf hello (2.10-3 Debian:12.5/stable [amd64])"#;
+        let plan = AdvancedPackageTool::default().parse_plan(output);
+        assert_eq!(
+            plan.to_install,
+            [
+                Package::from_str("apt@libhello1@1.0-2").unwrap(),
+                Package::from_str("apt@hello@2.10-3").unwrap()
+            ]
+        );
+        assert_eq!(
+            plan.to_upgrade,
+            [Package::from_str("apt@libc6@2.36-9+deb12u4").unwrap()]
+        );
+        assert_eq!(
+            plan.to_remove,
+            [Package::from_str("apt@foo@1.0-1").unwrap()]
+        );
+        assert_eq!(plan.download_size, None);
+
+        let plan = AdvancedPackageTool::default()
+            .parse_plan("Need to get 53.3 kB of archives.\nAfter this operation, 280 kB of additional disk space will be used.");
+        assert_eq!(plan.download_size, Some(53_300));
+        assert_eq!(plan.installed_size, Some(280_000));
+        assert!(plan.to_install.is_empty());
+    }
+
+    #[test]
+    fn test_parse_install_size() {
+        let output = r#"Reading package lists...
+Building dependency tree...
+The following NEW packages will be installed:
+  hello
+0 upgraded, 1 newly installed, 0 to remove and 0 not upgraded.
+Need to get 53.3 kB of archives.
+After this operation, 280 kB of additional disk space will be used.
+Abort."#;
+        assert_eq!(
+            AdvancedPackageTool::parse_install_size(output),
+            Some(280_000)
+        );
+        let output = "Need to get 0 B/12.5 MB of archives.
+After this operation, 1,024 kB of additional disk space will be used.";
+        assert_eq!(
+            AdvancedPackageTool::parse_install_size(output),
+            Some(12_500_000)
+        );
+        let output = "After this operation, 3,072 B disk space will be freed.";
+        assert_eq!(AdvancedPackageTool::parse_install_size(output), Some(0));
+        assert_eq!(
+            AdvancedPackageTool::parse_install_size("hello is already the newest version"),
+            None
+        );
+    }
 
     #[test]
     fn test_parse_pkg() {
@@ -140,7 +1518,7 @@ iagno/stable 1:3.38.1-2 amd64
 
 mount/now 2.38.1-5+b1 amd64 [installed,local]
 mysql-common/now 5.8+1.1.0 all [installed,local]"#;
-        let apt = AdvancedPackageTool;
+        let apt = AdvancedPackageTool::default();
         let mut iter = input.lines().filter_map(|l| apt.parse_pkg(l));
         assert_eq!(iter.next(), Package::from_str("apt@hello@2.10-3").ok());
         assert_eq!(iter.next(), Package::from_str("apt@iagno@1:3.38.1-2").ok());
@@ -151,12 +1529,210 @@ mysql-common/now 5.8+1.1.0 all [installed,local]"#;
         );
     }
 
+    #[test]
+    fn test_parse_pkg_status() {
+        let apt = AdvancedPackageTool::default();
+        let parsed = |line: &str| apt.parse_pkg(line).map(|p| p.version().map(str::to_string));
+        for (line, version) in [
+            ("curl/stable,now 7.88.1-10 amd64 [installed]", "7.88.1-10"),
+            (
+                "mount/now 2.38.1-5+b1 amd64 [installed,local]",
+                "2.38.1-5+b1",
+            ),
+            (
+                "libc6/stable,now 2.36-9 amd64 [installed,automatic]",
+                "2.36-9",
+            ),
+            (
+                "tzdata/stable-updates 2024a-0 all [upgradable from: 2023c-5]",
+                "2024a-0",
+            ),
+            (
+                "vim/stable,now 2:9.0.1378-2 amd64 [installed,upgradable to: 2:9.0.1378-3]",
+                "2:9.0.1378-2",
+            ),
+            ("hello/stable 2.10-3 amd64", "2.10-3"),
+        ] {
+            assert_eq!(parsed(line), Some(Some(version.to_string())), "{line}");
+        }
+        assert_eq!(
+            apt.parse_pkg("tzdata/stable-updates 2024a-0 all [upgradable from: 2023c-5]")
+                .unwrap()
+                .name(),
+            "tzdata"
+        );
+
+        // descriptions, headers and truncated lines are not packages
+        for line in [
+            "Listing...",
+            "example package based on GNU hello",
+            "tools for TCP/IP networks and more",
+            "a/b c d",
+            "curl/stable 7.88.1-10 amd64 [installed",
+        ] {
+            assert_eq!(apt.parse_pkg(line), None, "{line}");
+        }
+    }
+
+    #[test]
+    fn test_list_installed_dedup() {
+        let apt = AdvancedPackageTool::default();
+        let out = b"Listing...\nlibc6/now 2.36-9 amd64 [installed,local]\nlibc6/stable,now 2.36-9 i386 [installed,automatic]\n";
+        assert_eq!(
+            apt.parse_output(out),
+            [Package::from_str("apt@libc6@2.36-9").unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_upgrade_progress() {
+        let output = r#"Reading package lists...
+Building dependency tree...
+Calculating upgrade...
+The following packages will be upgraded:
+  curl libcurl4 tzdata
+3 upgraded, 0 newly installed, 0 to remove and 0 not upgraded.
+Need to get 1,024 kB of archives.
+Get:1 http://deb.debian.org/debian bookworm/main amd64 curl amd64 7.88.1-10+deb12u8 [315 kB]
+Preparing to unpack .../curl_7.88.1-10+deb12u8_amd64.deb ...
+Unpacking curl (7.88.1-10+deb12u8) over (7.88.1-10+deb12u7) ...
+Preparing to unpack .../libcurl4_7.88.1-10+deb12u8_amd64.deb ...
+Unpacking libcurl4:amd64 (7.88.1-10+deb12u8) over (7.88.1-10+deb12u7) ...
+Preparing to unpack .../tzdata_2024b-0+deb12u1_all.deb ...
+Unpacking tzdata (2024b-0+deb12u1) over (2024a-0+deb12u1) ...
+Setting up tzdata (2024b-0+deb12u1) ...
+Setting up libcurl4:amd64 (7.88.1-10+deb12u8) ...
+Setting up curl (7.88.1-10+deb12u8) ...
+Processing triggers for man-db (2.11.2-2) ..."#;
+        let apt = AdvancedPackageTool::default();
+        let mut counter = crate::ProgressCounter::default();
+        let counts: Vec<_> = output
+            .lines()
+            .filter_map(|l| apt.parse_progress(l))
+            .filter_map(|p| counter.update(p))
+            .collect();
+        assert_eq!(counts, [(0, 3), (1, 3), (2, 3), (3, 3)]);
+    }
+
+    #[test]
+    fn test_parse_rdepends() {
+        let output = "libfoo1\nReverse Depends:\n  foo-utils\n |bar\n  foo-utils\n  baz\n";
+        assert_eq!(
+            AdvancedPackageTool::parse_rdepends(output),
+            ["foo-utils", "bar", "baz"]
+        );
+        assert!(AdvancedPackageTool::parse_rdepends("libfoo1\nReverse Depends:\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_dpkg_search() {
+        let output = "diversion by dash from: /bin/sh\ndiversion by dash to: /bin/sh.distrib\ndash: /bin/sh\n";
+        assert_eq!(
+            AdvancedPackageTool::parse_dpkg_search(output).as_deref(),
+            Some("dash")
+        );
+        assert_eq!(
+            AdvancedPackageTool::parse_dpkg_search(
+                "libc6:amd64: /lib/x86_64-linux-gnu/libc.so.6\n"
+            )
+            .as_deref(),
+            Some("libc6")
+        );
+        assert_eq!(
+            AdvancedPackageTool::parse_dpkg_search("coreutils, libc-bin: /usr/share/doc\n")
+                .as_deref(),
+            Some("coreutils")
+        );
+        assert_eq!(AdvancedPackageTool::parse_dpkg_search(""), None);
+    }
+
+    #[test]
+    fn test_parse_showhold() {
+        assert_eq!(
+            AdvancedPackageTool::parse_showhold("linux-image-generic\nlibc6:amd64\n\n"),
+            ["linux-image-generic", "libc6"]
+        );
+        assert!(AdvancedPackageTool::parse_showhold("").is_empty());
+    }
+
+    #[test]
+    fn test_force_ipv4() {
+        let mut apt = AdvancedPackageTool::default();
+        let pkg = Package::from("hello");
+        let args = apt.consolidated(Cmd::Install, Some(&pkg), &["hello"]);
+        assert!(!args.contains(&"Acquire::ForceIPv4=true".to_string()));
+
+        apt.set_force_ipv4(true);
+        let args = apt.consolidated(Cmd::Install, Some(&pkg), &["hello"]);
+        assert_eq!(
+            args,
+            ["install", "--yes", "-o", "Acquire::ForceIPv4=true", "hello"]
+        );
+    }
+
+    #[test]
+    fn test_fix_broken_cmds() {
+        let mut apt = AdvancedPackageTool::default();
+        assert_eq!(
+            apt.fix_broken_cmds(),
+            [
+                vec!["dpkg", "--configure", "-a"],
+                vec!["apt-get", "install", "-f", "--yes"]
+            ]
+        );
+        apt.set_force_ipv4(true);
+        assert_eq!(
+            apt.fix_broken_cmds()[1],
+            [
+                "apt-get",
+                "install",
+                "-f",
+                "--yes",
+                "-o",
+                "Acquire::ForceIPv4=true"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_debsums() {
+        assert_eq!(
+            AdvancedPackageTool::parse_debsums(
+                "debsums: changed file /usr/bin/curl (from curl package)"
+            ),
+            Some(("curl".to_string(), "/usr/bin/curl".into()))
+        );
+        assert_eq!(
+            AdvancedPackageTool::parse_debsums(
+                "debsums: missing file /usr/share/doc/my file (from foo package)"
+            ),
+            Some(("foo".to_string(), "/usr/share/doc/my file".into()))
+        );
+        assert_eq!(
+            AdvancedPackageTool::parse_debsums("debsums: no md5sums for bar"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_dpkg_sig() {
+        assert!(AdvancedPackageTool::parse_dpkg_sig(
+            "Processing hello.deb...\nGOODSIG _gpgbuilder 0123456789ABCDEF 1700000000\n"
+        ));
+        assert!(!AdvancedPackageTool::parse_dpkg_sig(
+            "Processing hello.deb...\nBADSIG _gpgbuilder\n"
+        ));
+        assert!(!AdvancedPackageTool::parse_dpkg_sig(
+            "Processing hello.deb...\nNOSIG\n"
+        ));
+    }
+
     // Requires elevated privilages to work
     #[cfg(target_os = "linux")]
     #[tracing_test::traced_test]
     #[test]
     fn test_apt() {
-        let apt = crate::managers::AdvancedPackageTool;
+        let apt = crate::managers::AdvancedPackageTool::default();
         if !apt.is_available() {
             println!("apt is not available");
             return;
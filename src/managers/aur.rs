@@ -0,0 +1,200 @@
+use std::{fmt::Display, process::Command};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+    Unsupported,
+};
+
+/// Wrapper for the packages of the Arch User Repository (AUR), using paru or
+/// yay, whichever is installed.
+///
+/// [Arch User Repository - ArchWiki](https://wiki.archlinux.org/title/Arch_User_Repository)
+///
+/// # Idiosyncracies
+/// Operations are restricted to the AUR with `--aur`, packages from the
+/// official repositories are not touched. AUR helpers refuse to run as root
+/// and ask for elevated privileges themselves when installing the built
+/// packages. The AUR is queried live, so there is nothing to sync.
+#[derive(Debug)]
+pub struct AurHelper {
+    binary: &'static str,
+}
+
+impl Default for AurHelper {
+    fn default() -> Self {
+        Self {
+            binary: Self::HELPERS[0],
+        }
+    }
+}
+
+impl AurHelper {
+    /// Supported AUR helpers, most preferred first.
+    pub const HELPERS: [&'static str; 2] = ["paru", "yay"];
+
+    /// Use the first of [``AurHelper::HELPERS``] that is installed.
+    pub fn preferred() -> Self {
+        Self::HELPERS
+            .iter()
+            .map(|&binary| Self { binary })
+            .find(|helper| helper.is_available())
+            .unwrap_or_default()
+    }
+}
+
+/// Parse a line of pacman-style output into the name and the version of a
+/// package: `paru 2.0.3-1` (`-Q`), `aur/paru 2.0.3-1 [+1234 ~12.34]`
+/// (`-Ss`) and `paru 2.0.2-1 -> 2.0.3-1` (`-Qu`), where the newer version is
+/// used. The indented descriptions of search results are skipped, as are
+/// messages: versions always end with a `-<pkgrel>`.
+pub(crate) fn parse_pacman_pkg(line: &str) -> Option<(&str, &str)> {
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let cols: Vec<&str> = line.split_whitespace().collect();
+    let (name, version) = match cols[..] {
+        [name, _, "->", newer, ..] => (name, newer),
+        [name, version, ..] => (name, version),
+        _ => return None,
+    };
+    let name = name.rsplit_once('/').map_or(name, |(_repo, name)| name);
+    (!name.contains(':') && version.contains('-')).then_some((name, version))
+}
+
+impl PackageManager for AurHelper {
+    fn pkg_delimiter(&self) -> char {
+        '='
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Aur.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    fn reformat_for_command(&self, pkg: &mut Package) -> String {
+        if pkg.version().is_some() {
+            tracing::warn!("AUR helpers cannot install {} at a version", pkg.name());
+        }
+        pkg.name().to_string()
+    }
+
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        let (name, version) = parse_pacman_pkg(line)?;
+        Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+    }
+
+    fn sync(&self) -> std::process::ExitStatus {
+        tracing::debug!("The AUR is queried live");
+        crate::common::synthetic_exit_status(true)
+    }
+
+    fn add_repo(&self, _repo: &Vec<String>) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "adding repositories").into())
+    }
+}
+
+impl PackageManagerCommands for AurHelper {
+    fn cmd(&self) -> Command {
+        Command::new(self.binary)
+    }
+
+    fn needs_sudo(&self) -> bool {
+        false
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Update => vec!["-S", "--aur"],
+            Cmd::Uninstall => vec!["-R"],
+            Cmd::UpdateAll => vec!["-Su", "--aur"],
+            // foreign packages, i.e. those not from the official repositories
+            Cmd::List => vec!["-Qm"],
+            Cmd::Sync | Cmd::AddRepo | Cmd::Clean | Cmd::Autoremove => vec![],
+            Cmd::Search => vec!["-Ss", "--aur"],
+            Cmd::Outdated => vec!["-Qu", "--aur"],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll => {
+                vec!["--noconfirm".to_string()]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl Display for AurHelper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AUR ({})", self.binary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let aur = AurHelper::default();
+        let search = "aur/paru 2.0.3-1 [+1680 ~24.08] [Installed]
+    Feature packed AUR helper
+aur/paru-bin 2.0.3-1 [+402 ~5.24]
+    Feature packed AUR helper
+";
+        let pkgs: Vec<_> = search.lines().filter_map(|l| aur.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("paru", "aur".to_string(), Some("2.0.3-1")),
+                Package::new("paru-bin", "aur".to_string(), Some("2.0.3-1")),
+            ]
+        );
+
+        assert_eq!(
+            aur.parse_pkg("visual-studio-code-bin 1.85.1-1"),
+            Some(Package::new(
+                "visual-studio-code-bin",
+                "aur".to_string(),
+                Some("1.85.1-1")
+            ))
+        );
+        assert_eq!(
+            aur.parse_pkg("paru 2.0.2-1 -> 2.0.3-1"),
+            Some(Package::new("paru", "aur".to_string(), Some("2.0.3-1")))
+        );
+        assert_eq!(
+            aur.parse_pkg("yay-git r2271.5a1f8a2-1"),
+            Some(Package::new(
+                "yay-git",
+                "aur".to_string(),
+                Some("r2271.5a1f8a2-1")
+            ))
+        );
+        assert_eq!(aur.parse_pkg(":: Searching AUR for updates..."), None);
+        assert_eq!(
+            aur.parse_pkg("warning: yay-git-debug is not installed"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cmds() {
+        let aur = AurHelper::default();
+        assert!(!aur.needs_sudo());
+        let mut pkg = Package::from("paru-bin");
+        let fmt = aur.reformat_for_command(&mut pkg);
+        assert_eq!(
+            aur.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["-S", "--aur", "--noconfirm", "paru-bin"]
+        );
+        assert_eq!(aur.consolidated::<&str>(Cmd::List, None, &[]), ["-Qm"]);
+    }
+}
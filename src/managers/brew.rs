@@ -1,15 +1,147 @@
-use std::{fmt::Display, process::Command};
+use std::{
+    ffi::OsStr,
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
 
 use crate::{
-    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+    AvailablePackageManager, Cmd, DependencyGraph, NotOwned, Package, PackageManager,
+    PackageManagerCommands, PackageMetadata, PkgFormat, Repository,
 };
 
 /// Wrapper for the Homebrew package manager.
 ///
 /// [Homebrew — The Missing Package Manager for macOS (or Linux)](https://brew.sh/)
+///
+/// # Idiosyncracies
+/// Homebrew on Linux (linuxbrew) is often not on `PATH`, so the standard
+/// linuxbrew install locations are probed as well, see
+/// [``Homebrew::LINUXBREW_PATH``].
 #[derive(Debug, Default)]
 pub struct Homebrew;
 
+impl Homebrew {
+    /// Default install location of linuxbrew.
+    pub const LINUXBREW_PATH: &'static str = "/home/linuxbrew/.linuxbrew/bin/brew";
+
+    /// Path of the `brew` executable: `brew` if it is on `PATH`, else the
+    /// first existing linuxbrew install location, else `brew`.
+    fn brew_path() -> PathBuf {
+        let mut fallbacks = vec![PathBuf::from(Self::LINUXBREW_PATH)];
+        if let Some(dirs) = directories::BaseDirs::new() {
+            fallbacks.push(dirs.home_dir().join(".linuxbrew/bin/brew"));
+        }
+        Self::find_brew(std::env::var_os("PATH").as_deref(), &fallbacks)
+            .unwrap_or_else(|| PathBuf::from("brew"))
+    }
+
+    /// Parse the output of `brew uses`, one or more names per line.
+    pub(crate) fn parse_uses(output: &str) -> Vec<String> {
+        output.split_whitespace().map(str::to_string).collect()
+    }
+
+    /// Parse the output of `brew tap-info --installed --json`, e.g.
+    ///
+    /// ```json
+    /// [{"name": "homebrew/cask", "remote": "https://github.com/Homebrew/homebrew-cask", "installed": true}]
+    /// ```
+    pub(crate) fn parse_taps(json: &str) -> anyhow::Result<Vec<Repository>> {
+        let taps: Vec<serde_json::Value> =
+            serde_json::from_str(json).context("brew printed invalid JSON")?;
+        Ok(taps
+            .iter()
+            .filter_map(|tap| {
+                let name = tap["name"].as_str()?;
+                Some(Repository::new(name, tap["remote"].as_str()))
+            })
+            .collect())
+    }
+
+    /// Formula or cask that installed `path`, taken from its location in
+    /// the `Cellar` or `Caskroom`, e.g.
+    /// `/opt/homebrew/Cellar/wget/1.24.5/bin/wget`. Links in the Homebrew
+    /// prefix must be resolved first.
+    pub(crate) fn parse_keg_path(path: &Path) -> Option<String> {
+        let mut components = path.components().map(|c| c.as_os_str());
+        components.find(|c| *c == "Cellar" || *c == "Caskroom")?;
+        let name = components.next()?.to_str()?;
+        // the keg itself is a version directory below the name
+        components.next()?;
+        Some(name.to_string())
+    }
+
+    /// Parse the output of `brew info --json=v2 <pkg>`, a formula or a cask.
+    /// Homebrew does not report sizes before installing.
+    pub(crate) fn parse_info(name: &str, json: &str) -> anyhow::Result<PackageMetadata> {
+        let info: serde_json::Value = serde_json::from_str(json)?;
+        let str_of = |v: &serde_json::Value| v.as_str().map(str::to_string);
+        let (pkg, candidate, installed) = if let Some(f) = info["formulae"].get(0) {
+            let installed = f["installed"].get(0).and_then(|i| str_of(&i["version"]));
+            (f, str_of(&f["versions"]["stable"]), installed)
+        } else {
+            let c = info["casks"].get(0).context("no formula or cask found")?;
+            (c, str_of(&c["version"]), str_of(&c["installed"]))
+        };
+        Ok(PackageMetadata {
+            name: name.to_string(),
+            description: str_of(&pkg["desc"]),
+            homepage: str_of(&pkg["homepage"]),
+            license: str_of(&pkg["license"]),
+            size: None,
+            installed_version: installed,
+            candidate_version: candidate,
+        })
+    }
+
+    /// Parse the output of `brew deps --tree <pkg>`, where the depth of a
+    /// dependency is given by its indentation, e.g.
+    ///
+    /// ```text
+    /// wget
+    /// ├── libidn2
+    /// │   └── libunistring
+    /// └── openssl@3
+    /// ```
+    pub(crate) fn parse_deps_tree(root: &str, output: &str) -> DependencyGraph {
+        let mut graph = DependencyGraph::new(root);
+        // ancestors of the current line, by depth
+        let mut stack: Vec<&str> = vec![];
+        for line in output.lines() {
+            let Some(start) = line.find(|c: char| c.is_alphanumeric()) else {
+                continue;
+            };
+            let depth = line[..start].chars().count() / 4;
+            let name = line[start..].trim();
+            stack.truncate(depth);
+            if let Some(parent) = stack.last() {
+                graph.add(parent, name);
+            }
+            stack.push(name);
+        }
+        graph
+    }
+
+    /// Find `brew` in the directories of `path_var`, falling back to the
+    /// first of `fallbacks` that exists.
+    fn find_brew(path_var: Option<&OsStr>, fallbacks: &[PathBuf]) -> Option<PathBuf> {
+        let on_path = path_var
+            .map(|p| {
+                std::env::split_paths(p)
+                    .map(|dir| dir.join("brew"))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        on_path
+            .iter()
+            .chain(fallbacks)
+            .find(|p| p.is_file())
+            .cloned()
+    }
+}
+
 impl PackageManager for Homebrew {
     fn pkg_delimiter(&self) -> char {
         '@'
@@ -22,11 +154,162 @@ impl PackageManager for Homebrew {
     fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
         vec![PkgFormat::Bottle]
     }
+
+    /// Uses `brew formulae`.
+    fn list_available_with<F: FnMut(Package)>(&self, mut on_pkg: F) -> anyhow::Result<()> {
+        let status = crate::run_command_with(self.cmd(), &["formulae"], |line| {
+            let name = line.trim();
+            if !name.is_empty() {
+                on_pkg(Package::new(name, self.pkg_manager_name(), None));
+            }
+        })?;
+        anyhow::ensure!(status.success(), "brew formulae failed");
+        Ok(())
+    }
+
+    /// Uses `brew deps --tree`.
+    fn dependencies(&self, pkg: &Package) -> anyhow::Result<DependencyGraph> {
+        let out = self.exec_cmds(&[
+            "deps".to_string(),
+            "--tree".to_string(),
+            pkg.name().to_string(),
+        ]);
+        anyhow::ensure!(out.status.success(), "brew deps failed");
+        Ok(Self::parse_deps_tree(
+            pkg.name(),
+            &String::from_utf8_lossy(&out.stdout),
+        ))
+    }
+
+    /// Uses `brew info --json=v2`.
+    fn info(&self, pkg: &Package) -> anyhow::Result<PackageMetadata> {
+        let out = self.exec_cmds(&[
+            "info".to_string(),
+            "--json=v2".to_string(),
+            pkg.name().to_string(),
+        ]);
+        if !out.status.success() {
+            return Err(crate::error::Error::PackageNotFound(pkg.name().to_string()).into());
+        }
+        Self::parse_info(pkg.name(), &String::from_utf8_lossy(&out.stdout))
+    }
+
+    /// Uses `brew cleanup`.
+    /// Uses `brew pin`.
+    fn pin(&self, pkg: &Package) -> anyhow::Result<()> {
+        let s = self.exec_cmds_status(&["pin", pkg.name()], None);
+        anyhow::ensure!(s.success(), "brew pin {} failed", pkg.name());
+        Ok(())
+    }
+
+    /// Uses `brew unpin`.
+    fn unpin(&self, pkg: &Package) -> anyhow::Result<()> {
+        let s = self.exec_cmds_status(&["unpin", pkg.name()], None);
+        anyhow::ensure!(s.success(), "brew unpin {} failed", pkg.name());
+        Ok(())
+    }
+
+    /// Uses `brew list --pinned`.
+    fn list_pinned(&self) -> anyhow::Result<Vec<Package>> {
+        let out = self.exec_cmds(&["list".to_string(), "--pinned".to_string()]);
+        anyhow::ensure!(out.status.success(), "brew list --pinned failed");
+        Ok(Self::parse_uses(&String::from_utf8_lossy(&out.stdout))
+            .iter()
+            .map(|name| Package::new(name, self.pkg_manager_name(), None))
+            .collect())
+    }
+
+    /// Uses `brew tap`, which lists the names of the taps.
+    fn list_repos(&self) -> anyhow::Result<Vec<Repository>> {
+        let out = self.exec_cmds(&["tap-info", "--installed", "--json"].map(String::from));
+        anyhow::ensure!(out.status.success(), "brew tap-info failed");
+        Self::parse_taps(&String::from_utf8_lossy(&out.stdout))
+    }
+
+    /// Uses `brew untap`.
+    fn remove_repo(&self, name: &str) -> anyhow::Result<()> {
+        let s = self.exec_cmds_status(&["untap", name], None);
+        anyhow::ensure!(s.success(), "brew untap {name} failed");
+        Ok(())
+    }
+
+    /// The directory printed by `brew --cache`.
+    fn cache_dirs(&self) -> Vec<PathBuf> {
+        let out = self.exec_cmds(&["--cache".to_string()]);
+        let dir = String::from_utf8_lossy(&out.stdout);
+        if out.status.success() && !dir.trim().is_empty() {
+            vec![PathBuf::from(dir.trim())]
+        } else {
+            vec![]
+        }
+    }
+
+    /// Resolves the links Homebrew puts in its prefix, like `brew
+    /// which-formula` does for commands, to find the keg the file is in.
+    fn owner(&self, path: &Path) -> anyhow::Result<Package> {
+        path.canonicalize()
+            .ok()
+            .and_then(|path| Self::parse_keg_path(&path))
+            .map(|name| Package::new(&name, self.pkg_manager_name(), None))
+            .ok_or_else(|| NotOwned(path.to_path_buf()).into())
+    }
+
+    /// Uses `brew list --verbose`, which lists every file of the keg.
+    fn files(&self, pkg: &Package) -> anyhow::Result<Vec<PathBuf>> {
+        let out = self.exec_cmds(&[
+            "list".to_string(),
+            "--verbose".to_string(),
+            pkg.name().to_string(),
+        ]);
+        anyhow::ensure!(out.status.success(), "{} is not installed", pkg.name());
+        Ok(crate::common::parse_file_list(&String::from_utf8_lossy(
+            &out.stdout,
+        )))
+    }
+
+    /// Homebrew keeps no checksums of the installed files, so only missing
+    /// files of `brew list --verbose` are reported.
+    fn verify_files(&self, pkg: &Package) -> anyhow::Result<Vec<PathBuf>> {
+        Ok(self
+            .files(pkg)?
+            .into_iter()
+            .filter(|f| !f.exists())
+            .collect())
+    }
+
+    /// Uses `brew uses --installed`.
+    fn reverse_dependencies(&self, pkg: &Package) -> anyhow::Result<Vec<Package>> {
+        let out = self.exec_cmds(&[
+            "uses".to_string(),
+            "--installed".to_string(),
+            pkg.name().to_string(),
+        ]);
+        anyhow::ensure!(out.status.success(), "brew uses failed");
+        Ok(Self::parse_uses(&String::from_utf8_lossy(&out.stdout))
+            .iter()
+            .map(|name| Package::new(name, self.pkg_manager_name(), None))
+            .collect())
+    }
+
+    fn reinstall(
+        &self,
+        pkg: &Package,
+        interactive: bool,
+    ) -> anyhow::Result<std::process::ExitStatus> {
+        let mut pkg = pkg.clone();
+        let fmt = self.reformat_for_command(&mut pkg);
+        Ok(self.exec_cmds_status(&["reinstall".to_string(), fmt], Some(interactive)))
+    }
 }
 
 impl PackageManagerCommands for Homebrew {
     fn cmd(&self) -> Command {
-        Command::new("brew")
+        Command::new(Self::brew_path())
+    }
+
+    /// Homebrew refuses to run as root.
+    fn needs_sudo(&self) -> bool {
+        false
     }
 
     fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
@@ -39,6 +322,8 @@ impl PackageManagerCommands for Homebrew {
             Cmd::AddRepo => vec!["tap"],
             Cmd::Search => vec!["search"],
             Cmd::Outdated => vec!["outdated"],
+            Cmd::Clean => vec!["cleanup"],
+            Cmd::Autoremove => vec!["autoremove"],
         }
         .iter()
         .map(|x| x.to_string())
@@ -54,12 +339,143 @@ impl Display for Homebrew {
 
 #[cfg(test)]
 mod tests {
-    #![cfg(target_os = "macos")]
     use super::*;
-    use crate::{Operation, PackageManager};
 
+    #[test]
+    fn test_parse_deps_tree() {
+        let out = "wget
+├── libidn2
+│   ├── gettext
+│   └── libunistring
+└── openssl@3
+    └── ca-certificates
+";
+        let graph = Homebrew::parse_deps_tree("wget", out);
+        assert_eq!(graph.dependencies_of("wget"), ["libidn2", "openssl@3"]);
+        assert_eq!(
+            graph.dependencies_of("libidn2"),
+            ["gettext", "libunistring"]
+        );
+        assert_eq!(graph.dependencies_of("openssl@3"), ["ca-certificates"]);
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let json = r#"{"formulae":[{"name":"wget","desc":"Internet file retriever","license":"GPL-3.0-or-later","homepage":"https://www.gnu.org/software/wget/","versions":{"stable":"1.24.5","head":"HEAD","bottle":true},"installed":[{"version":"1.21.4","installed_on_request":true}]}],"casks":[]}"#;
+        let info = Homebrew::parse_info("wget", json).unwrap();
+        assert_eq!(info.description.as_deref(), Some("Internet file retriever"));
+        assert_eq!(info.license.as_deref(), Some("GPL-3.0-or-later"));
+        assert_eq!(info.installed_version.as_deref(), Some("1.21.4"));
+        assert_eq!(info.candidate_version.as_deref(), Some("1.24.5"));
+
+        let json = r#"{"formulae":[],"casks":[{"token":"firefox","desc":"Web browser","homepage":"https://www.mozilla.org/firefox/","version":"125.0.3","installed":null}]}"#;
+        let info = Homebrew::parse_info("firefox", json).unwrap();
+        assert_eq!(info.installed_version, None);
+        assert_eq!(info.candidate_version.as_deref(), Some("125.0.3"));
+        assert_eq!(info.license, None);
+    }
+
+    #[test]
+    fn test_parse_keg_path() {
+        assert_eq!(
+            Homebrew::parse_keg_path(Path::new("/opt/homebrew/Cellar/wget/1.24.5/bin/wget"))
+                .as_deref(),
+            Some("wget")
+        );
+        assert_eq!(
+            Homebrew::parse_keg_path(Path::new("/usr/local/Caskroom/firefox/125.0.3/Firefox.app"))
+                .as_deref(),
+            Some("firefox")
+        );
+        assert_eq!(
+            Homebrew::parse_keg_path(Path::new("/opt/homebrew/Cellar/wget")),
+            None
+        );
+        assert_eq!(Homebrew::parse_keg_path(Path::new("/usr/bin/ls")), None);
+    }
+
+    #[test]
+    fn test_parse_uses() {
+        let output = "ffmpeg\ngdal\nimagemagick\nopencv\n";
+        assert_eq!(
+            Homebrew::parse_uses(output),
+            ["ffmpeg", "gdal", "imagemagick", "opencv"]
+        );
+        // brew lays out the names in columns on a terminal
+        assert_eq!(
+            Homebrew::parse_uses("ffmpeg    gdal\nopencv\n"),
+            ["ffmpeg", "gdal", "opencv"]
+        );
+        assert!(Homebrew::parse_uses("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_taps() {
+        let json = r#"[
+  {
+    "name": "homebrew/cask",
+    "user": "homebrew",
+    "repo": "cask",
+    "repository": "homebrew-cask",
+    "path": "/opt/homebrew/Library/Taps/homebrew/homebrew-cask",
+    "installed": true,
+    "official": true,
+    "remote": "https://github.com/Homebrew/homebrew-cask",
+    "formula_names": []
+  },
+  {
+    "name": "hashicorp/tap",
+    "installed": true,
+    "official": false,
+    "remote": null
+  }
+]"#;
+        assert_eq!(
+            Homebrew::parse_taps(json).unwrap(),
+            [
+                Repository::new(
+                    "homebrew/cask",
+                    Some("https://github.com/Homebrew/homebrew-cask")
+                ),
+                Repository::new("hashicorp/tap", None),
+            ]
+        );
+        assert!(Homebrew::parse_taps("[]").unwrap().is_empty());
+        assert!(Homebrew::parse_taps("homebrew/cask").is_err());
+    }
+
+    #[test]
+    fn test_find_brew() {
+        let path_dir = tempfile::tempdir().unwrap();
+        let linuxbrew = tempfile::tempdir().unwrap();
+        let fallback = linuxbrew.path().join("brew");
+        std::fs::write(&fallback, "").unwrap();
+        let fallbacks = [PathBuf::from("/nonexistent/brew"), fallback.clone()];
+
+        // not on PATH
+        let path_var = std::env::join_paths([path_dir.path()]).unwrap();
+        let path_var = path_var.as_os_str();
+        assert_eq!(
+            Homebrew::find_brew(Some(path_var), &fallbacks),
+            Some(fallback.clone())
+        );
+        assert_eq!(Homebrew::find_brew(None, &fallbacks), Some(fallback));
+        assert_eq!(Homebrew::find_brew(Some(path_var), &[]), None);
+
+        // on PATH takes precedence
+        let on_path = path_dir.path().join("brew");
+        std::fs::write(&on_path, "").unwrap();
+        assert_eq!(
+            Homebrew::find_brew(Some(path_var), &fallbacks),
+            Some(on_path)
+        );
+    }
+
+    #[cfg(target_os = "macos")]
     #[test]
     fn test_homebrew() {
+        use crate::Operation;
+
         let hb = Homebrew;
         // sync
         assert!(hb.sync().success());
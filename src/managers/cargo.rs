@@ -0,0 +1,194 @@
+use std::{fmt::Display, path::PathBuf, process::Command};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+    Unsupported,
+};
+
+/// Wrapper for the binaries installed from crates.io with `cargo install`.
+///
+/// [cargo install - The Cargo Book](https://doc.rust-lang.org/cargo/commands/cargo-install.html)
+///
+/// # Idiosyncracies
+/// Binaries are installed into `~/.cargo/bin` and never need elevated
+/// privileges. `cargo install` also upgrades installed crates, and cargo
+/// has no command to list outdated crates, so they are looked up on
+/// crates.io one by one.
+#[derive(Debug, Default)]
+pub struct Cargo;
+
+impl Cargo {
+    /// Maximum number of results `cargo search` prints.
+    pub const MAX_SEARCH_LIMIT: usize = 100;
+}
+
+impl PackageManager for Cargo {
+    fn pkg_delimiter(&self) -> char {
+        '@'
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Cargo.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    /// Parses the crates of `cargo install --list` like `ripgrep v14.1.0:`,
+    /// skipping the binaries listed below them, and the results of `cargo
+    /// search` like `ripgrep = "14.1.0" # ripgrep is a line-oriented search
+    /// tool`.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        if let Some((name, rest)) = line.split_once(" = \"") {
+            let (version, _) = rest.split_once('"')?;
+            return Some(Package::new(name, self.pkg_manager_name(), Some(version)));
+        }
+        if line.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let mut cols = line.strip_suffix(':')?.split_whitespace();
+        let name = cols.next()?;
+        let version = cols.next()?.strip_prefix('v')?;
+        Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+    }
+
+    /// `cargo search` prints at most [``Cargo::MAX_SEARCH_LIMIT``] results.
+    fn search_limit_args(&self, limit: usize) -> Vec<String> {
+        vec![
+            "--limit".to_string(),
+            limit.min(Self::MAX_SEARCH_LIMIT).to_string(),
+        ]
+    }
+
+    fn sync(&self) -> std::process::ExitStatus {
+        tracing::debug!("cargo fetches the crates.io index on demand");
+        crate::common::synthetic_exit_status(true)
+    }
+
+    /// Reinstalls all installed crates, which only upgrades the outdated ones.
+    fn update_all(&self, interactive: bool) -> std::process::ExitStatus {
+        let names: Vec<String> = self
+            .list_installed()
+            .iter()
+            .map(|pkg| pkg.name().to_string())
+            .collect();
+        // `cargo install` without crates installs the crate in the current
+        // directory
+        if names.is_empty() {
+            return crate::common::synthetic_exit_status(true);
+        }
+        self.exec_cmds_status(
+            &self.consolidated(Cmd::UpdateAll, None, &names),
+            Some(interactive),
+        )
+    }
+
+    /// Looks up the latest version of every installed crate on crates.io.
+    fn list_outdated(&self) -> Vec<Package> {
+        self.list_installed()
+            .into_iter()
+            .filter_map(|pkg| {
+                let latest = self
+                    .search_limited(pkg.name(), 1)
+                    .ok()?
+                    .into_iter()
+                    .find(|p| p.name() == pkg.name())?;
+                (latest.version() != pkg.version()).then_some(latest)
+            })
+            .collect()
+    }
+
+    /// `$CARGO_HOME/bin`, by default `~/.cargo/bin`.
+    fn bin_dirs(&self) -> Vec<PathBuf> {
+        let home = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .or_else(|| directories::BaseDirs::new().map(|d| d.home_dir().join(".cargo")));
+        home.map(|home| home.join("bin")).into_iter().collect()
+    }
+
+    fn add_repo(&self, _repo: &Vec<String>) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "adding registries").into())
+    }
+}
+
+impl PackageManagerCommands for Cargo {
+    fn cmd(&self) -> Command {
+        Command::new("cargo")
+    }
+
+    fn needs_sudo(&self) -> bool {
+        false
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Update | Cmd::UpdateAll => vec!["install"],
+            Cmd::Uninstall => vec!["uninstall"],
+            Cmd::List => vec!["install", "--list"],
+            Cmd::Sync | Cmd::AddRepo | Cmd::Outdated | Cmd::Clean | Cmd::Autoremove => vec![],
+            Cmd::Search => vec!["search"],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+}
+
+impl Display for Cargo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Cargo")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let cargo = Cargo;
+        let list = "bat v0.24.0:
+    bat
+ripgrep v14.1.0:
+    rg
+mpm v0.7.5 (/home/user/meta-package-manager):
+    mpm
+";
+        let pkgs: Vec<_> = list.lines().filter_map(|l| cargo.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("bat", "cargo".to_string(), Some("0.24.0")),
+                Package::new("ripgrep", "cargo".to_string(), Some("14.1.0")),
+                Package::new("mpm", "cargo".to_string(), Some("0.7.5")),
+            ]
+        );
+
+        let search = r#"ripgrep = "14.1.0"               # ripgrep is a line-oriented search tool
+ripgrep_all = "0.10.6"          # rga: ripgrep, but also search in PDFs, E-Books, Office documents, zip, tar.gz, etc.
+... and 123 crates more (use --limit N to see more)
+"#;
+        let pkgs: Vec<_> = search.lines().filter_map(|l| cargo.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("ripgrep", "cargo".to_string(), Some("14.1.0")),
+                Package::new("ripgrep_all", "cargo".to_string(), Some("0.10.6")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cmds() {
+        let cargo = Cargo;
+        assert!(!cargo.needs_sudo());
+        let mut pkg = Package::from("cargo@ripgrep@14.1.0");
+        let fmt = cargo.reformat_for_command(&mut pkg);
+        assert_eq!(
+            cargo.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["install", "ripgrep@14.1.0"]
+        );
+        assert_eq!(cargo.search_limit_args(500), ["--limit", "100"]);
+    }
+}
@@ -1,15 +1,103 @@
-use std::{fmt::Display, process::Command};
+use std::{fmt::Display, path::PathBuf, process::Command};
 
 use crate::{
     common::Package, AvailablePackageManager, Cmd, PackageManager, PackageManagerCommands,
-    PkgFormat,
+    PackageMetadata, PkgFormat, Repository,
 };
 
 /// Wrapper for the Chocolatey package manager for windows
 ///
 /// [Chocolatey Software | Chocolatey - The package manager for Windows](https://chocolatey.org/)
 #[derive(Debug, Default)]
-pub struct Chocolatey;
+pub struct Chocolatey {
+    params: Option<String>,
+    install_arguments: Option<String>,
+}
+
+impl Chocolatey {
+    /// Package parameters passed to installs and upgrades with `--params`,
+    /// e.g. `/InstallDir:C:\foo`.
+    pub fn set_params(&mut self, params: Option<String>) {
+        self.params = params;
+    }
+
+    /// Arguments passed to the native installer with `--install-arguments`.
+    pub fn set_install_arguments(&mut self, install_arguments: Option<String>) {
+        self.install_arguments = install_arguments;
+    }
+
+    /// Parse the output of `choco source list --limit-output`, lines of
+    /// `name|url|disabled|...`, e.g.
+    /// `chocolatey|https://community.chocolatey.org/api/v2/|False|...`.
+    pub(crate) fn parse_sources(output: &str) -> Vec<Repository> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut cols = line.split('|').map(str::trim);
+                let name = cols.next().filter(|n| !n.is_empty())?;
+                let url = cols.next().filter(|u| !u.is_empty());
+                let disabled = cols.next().is_some_and(|d| d.eq_ignore_ascii_case("true"));
+                Some(Repository {
+                    enabled: !disabled,
+                    ..Repository::new(name, url)
+                })
+            })
+            .collect()
+    }
+
+    /// Parse the output of `choco info <pkg>` like
+    ///
+    /// ```text
+    /// git 2.43.0 [Approved]
+    ///  Title: Git | Published: 11/21/2023
+    ///  Software Site: https://git-scm.com/
+    ///  Software License: http://www.gnu.org/licenses/old-licenses/gpl-2.0.html
+    ///  Summary: Git (for Windows) - Fast, scalable, distributed revision control system
+    /// ```
+    ///
+    /// and the installed version of `choco list --exact --limit-output
+    /// <pkg>`, printed as `git|2.43.0`.
+    pub(crate) fn parse_info(name: &str, info: &str, installed: &str) -> PackageMetadata {
+        let field = |key: &str| {
+            info.lines()
+                .find_map(|l| l.trim().strip_prefix(key)?.strip_prefix(':'))
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let candidate = info.lines().find_map(|l| {
+            let mut fields = l.split_whitespace();
+            let pkg = fields.next()?;
+            pkg.eq_ignore_ascii_case(name)
+                .then(|| fields.next())
+                .flatten()
+                .map(str::to_string)
+        });
+        let installed = installed.lines().find_map(|l| {
+            let (pkg, version) = l.trim().split_once('|')?;
+            pkg.eq_ignore_ascii_case(name).then(|| version.to_string())
+        });
+        PackageMetadata {
+            name: name.to_string(),
+            description: field("Summary"),
+            homepage: field("Software Site"),
+            license: field("Software License"),
+            size: None,
+            installed_version: installed,
+            candidate_version: candidate,
+        }
+    }
+
+    /// Quote a value the way chocolatey expects it, e.g.
+    /// `--params="'/InstallDir:C:\foo'"` in a shell. The arguments are not
+    /// passed through a shell, so only the inner single quotes are needed.
+    fn quote(value: &str) -> String {
+        if value.starts_with('\'') && value.ends_with('\'') && value.len() > 1 {
+            value.to_string()
+        } else {
+            format!("'{value}'")
+        }
+    }
+}
 
 impl PackageManager for Chocolatey {
     fn pkg_delimiter(&self) -> char {
@@ -32,6 +120,85 @@ impl PackageManager for Chocolatey {
     fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
         vec![PkgFormat::Msi, PkgFormat::Exe]
     }
+
+    fn search_limit_args(&self, limit: usize) -> Vec<String> {
+        vec!["--page=0".to_string(), format!("--page-size={limit}")]
+    }
+
+    fn search_exact_args(&self) -> Vec<String> {
+        vec!["--exact".to_string()]
+    }
+
+    /// Uses `choco pin add`.
+    fn pin(&self, pkg: &Package) -> anyhow::Result<()> {
+        let s = self.exec_cmds_status(&["pin", "add", &format!("--name={}", pkg.name())], None);
+        anyhow::ensure!(s.success(), "choco pin add {} failed", pkg.name());
+        Ok(())
+    }
+
+    /// Uses `choco pin remove`.
+    fn unpin(&self, pkg: &Package) -> anyhow::Result<()> {
+        let s = self.exec_cmds_status(&["pin", "remove", &format!("--name={}", pkg.name())], None);
+        anyhow::ensure!(s.success(), "choco pin remove {} failed", pkg.name());
+        Ok(())
+    }
+
+    /// Uses `choco pin list`, which prints `name|version` lines.
+    fn list_pinned(&self) -> anyhow::Result<Vec<Package>> {
+        let out = self.exec_cmds(&[
+            "pin".to_string(),
+            "list".to_string(),
+            "--limit-output".to_string(),
+        ]);
+        anyhow::ensure!(out.status.success(), "choco pin list failed");
+        Ok(self.parse_output(&out.stdout))
+    }
+
+    /// Uses `choco source list`.
+    fn list_repos(&self) -> anyhow::Result<Vec<Repository>> {
+        let out = self.exec_cmds(&["source", "list", "--limit-output"].map(String::from));
+        anyhow::ensure!(out.status.success(), "choco source list failed");
+        Ok(Self::parse_sources(&String::from_utf8_lossy(&out.stdout)))
+    }
+
+    /// Uses `choco source remove`.
+    fn remove_repo(&self, name: &str) -> anyhow::Result<()> {
+        let s = self.exec_cmds_status(&["source", "remove", &format!("--name={name}")], None);
+        anyhow::ensure!(s.success(), "choco source remove {name} failed");
+        Ok(())
+    }
+
+    /// The HTTP cache emptied by `choco cache remove`, which is kept in the
+    /// installation directory for administrators and in the temporary
+    /// directory otherwise.
+    fn cache_dirs(&self) -> Vec<PathBuf> {
+        let install = std::env::var_os("ChocolateyInstall")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData\chocolatey"));
+        [install, std::env::temp_dir().join("chocolatey")]
+            .into_iter()
+            .map(|dir| dir.join(".chocolatey").join("http-cache"))
+            .collect()
+    }
+
+    /// Uses `choco info` and `choco list --exact`.
+    fn info(&self, pkg: &Package) -> anyhow::Result<PackageMetadata> {
+        let info = self.exec_cmds(&["info".to_string(), pkg.name().to_string()]);
+        if !info.status.success() {
+            return Err(crate::error::Error::PackageNotFound(pkg.name().to_string()).into());
+        }
+        let installed = self.exec_cmds(&[
+            "list".to_string(),
+            "--exact".to_string(),
+            "--limit-output".to_string(),
+            pkg.name().to_string(),
+        ]);
+        Ok(Self::parse_info(
+            pkg.name(),
+            &String::from_utf8_lossy(&info.stdout),
+            &String::from_utf8_lossy(&installed.stdout),
+        ))
+    }
 }
 
 impl PackageManagerCommands for Chocolatey {
@@ -51,20 +218,37 @@ impl PackageManagerCommands for Chocolatey {
             Cmd::AddRepo => vec!["source", "add"],
             Cmd::Search => vec!["search"],
             Cmd::Outdated => vec!["outdated", "--limit-output"],
+            Cmd::Clean => vec!["cache", "remove"],
+            Cmd::Autoremove => vec![],
         }
         .iter()
         .map(|x| x.to_string())
         .collect()
     }
-    fn get_flags(&self, cmd: Cmd) -> Vec<String> {
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
         match cmd {
+            Cmd::Install | Cmd::Update | Cmd::UpdateAll => vec!["--yes".to_string()],
+            _ => vec![],
+        }
+    }
+    fn get_flags(&self, cmd: Cmd) -> Vec<String> {
+        let mut flags: Vec<String> = match cmd {
             Cmd::List | Cmd::Search => vec!["--limit-output"],
-            Cmd::Install | Cmd::Update | Cmd::UpdateAll => vec!["--yes"],
             _ => vec![],
         }
         .iter()
         .map(|x| x.to_string())
-        .collect()
+        .collect();
+
+        if matches!(cmd, Cmd::Install | Cmd::Update) {
+            if let Some(params) = &self.params {
+                flags.push(format!("--params={}", Self::quote(params)));
+            }
+            if let Some(args) = &self.install_arguments {
+                flags.push(format!("--install-arguments={}", Self::quote(args)));
+            }
+        }
+        flags
     }
 }
 
@@ -79,22 +263,83 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_parse_info() {
+        let info = "Chocolatey v2.2.2
+git 2.43.0 [Approved]
+ Title: Git | Published: 11/21/2023
+ Software Site: https://git-scm.com/
+ Software License: http://www.gnu.org/licenses/old-licenses/gpl-2.0.html
+ Summary: Git (for Windows) - Fast, scalable, distributed revision control system
+ Description: Git for Windows focuses on offering a lightweight, native set of tools.
+1 packages found.
+";
+        let info = Chocolatey::parse_info("git", info, "git|2.42.0\n");
+        assert_eq!(info.candidate_version.as_deref(), Some("2.43.0"));
+        assert_eq!(info.installed_version.as_deref(), Some("2.42.0"));
+        assert_eq!(info.homepage.as_deref(), Some("https://git-scm.com/"));
+        assert_eq!(
+            info.description.as_deref(),
+            Some("Git (for Windows) - Fast, scalable, distributed revision control system")
+        );
+    }
+
+    #[test]
+    fn test_parse_sources() {
+        let output =
+            "chocolatey|https://community.chocolatey.org/api/v2/|False|||0|False|False|False\n\
+                      internal|https://nuget.example.com/api/v2/|True|user||10|False|False|False\n";
+        let repos = Chocolatey::parse_sources(output);
+        assert_eq!(repos.len(), 2);
+        assert_eq!(repos[0].name, "chocolatey");
+        assert_eq!(
+            repos[0].url.as_deref(),
+            Some("https://community.chocolatey.org/api/v2/")
+        );
+        assert!(repos[0].enabled);
+        assert!(!repos[1].enabled);
+    }
+
     #[test]
     fn test_choco_pkg_fmt() {
+        let choco = Chocolatey::default();
         assert_eq!(
-            Chocolatey.reformat_for_command(&mut "choco@package".into()),
+            choco.reformat_for_command(&mut "choco@package".into()),
             "package".to_string()
         );
         assert_eq!(
-            &Chocolatey.reformat_for_command(&mut "choco@package@0.1.0".into()),
+            &choco.reformat_for_command(&mut "choco@package@0.1.0".into()),
             "package --version 0.1.0"
         );
     }
 
+    #[test]
+    fn test_choco_params() {
+        let mut choco = Chocolatey::default();
+        choco.set_params(Some(r"/InstallDir:C:\foo".to_string()));
+        choco.set_install_arguments(Some("'/VERYSILENT'".to_string()));
+        let pkg = Package::from("foo");
+        assert_eq!(
+            choco.consolidated(Cmd::Install, Some(&pkg), &["foo"]),
+            [
+                "install",
+                "--yes",
+                r"--params='/InstallDir:C:\foo'",
+                "--install-arguments='/VERYSILENT'",
+                "foo"
+            ]
+        );
+        // not passed to unrelated commands
+        assert_eq!(
+            choco.consolidated::<&str>(Cmd::List, None, &[]),
+            ["list", "--limit-output"]
+        );
+    }
+
     #[cfg(windows)]
     #[test]
     fn test_chocolatey() {
-        let choco = Chocolatey;
+        let choco = Chocolatey::default();
         let pkg = "tac";
         // sync
         assert!(choco.sync().success());
@@ -0,0 +1,262 @@
+use std::{collections::BTreeMap, fmt::Display, process::Command};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+};
+
+/// Wrapper for conda, using mamba if it is available.
+///
+/// [conda](https://docs.conda.io/projects/conda/en/stable/commands/index.html)
+///
+/// # Idiosyncracies
+/// Operations apply to the active environment unless an environment is
+/// given with [``Conda::set_env``]. Channels are added with
+/// `conda config --add channels`. The output of list, search and outdated
+/// is parsed from conda's `--json` output.
+#[derive(Debug)]
+pub struct Conda {
+    binary: &'static str,
+    env: Option<String>,
+}
+
+impl Default for Conda {
+    fn default() -> Self {
+        Self {
+            binary: "conda",
+            env: None,
+        }
+    }
+}
+
+/// A package in conda's JSON output.
+#[derive(Debug, serde::Deserialize)]
+struct CondaPkg {
+    name: String,
+    version: String,
+}
+
+/// Actions of a `--dry-run`.
+#[derive(Debug, serde::Deserialize)]
+struct CondaActions {
+    #[serde(rename = "LINK", default)]
+    link: Vec<CondaPkg>,
+}
+
+/// JSON printed by `conda list`, `conda update --dry-run` and
+/// `conda search`.
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum CondaJson {
+    List(Vec<CondaPkg>),
+    DryRun {
+        actions: CondaActions,
+    },
+    /// Every available build of every matching package, oldest first.
+    Search(BTreeMap<String, Vec<CondaPkg>>),
+}
+
+impl Conda {
+    /// Use mamba if it is available, else conda.
+    pub fn preferred() -> Self {
+        let mamba = Self {
+            binary: "mamba",
+            env: None,
+        };
+        if mamba.is_available() {
+            mamba
+        } else {
+            Self::default()
+        }
+    }
+
+    /// Operate on the environment `env` (`--name`) instead of the active
+    /// one.
+    pub fn set_env(&mut self, env: String) {
+        self.env = Some(env);
+    }
+}
+
+impl PackageManager for Conda {
+    fn pkg_delimiter(&self) -> char {
+        '='
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Conda.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    /// conda matches package names exactly unless they contain wildcards.
+    fn search_query(&self, query: &str) -> String {
+        format!("*{query}*")
+    }
+
+    /// Parses conda's `--json` output, see [``Conda``].
+    fn parse_output(&self, out: &[u8]) -> Vec<Package> {
+        let pkgs = match serde_json::from_slice::<CondaJson>(out) {
+            Ok(CondaJson::List(pkgs)) => pkgs,
+            Ok(CondaJson::DryRun { actions }) => actions.link,
+            Ok(CondaJson::Search(builds)) => builds
+                .into_values()
+                .filter_map(|builds| builds.into_iter().last())
+                .collect(),
+            Err(e) => {
+                tracing::debug!("Unexpected conda output: {e}");
+                vec![]
+            }
+        };
+        pkgs.iter()
+            .map(|p| Package::new(&p.name, self.pkg_manager_name(), Some(p.version.as_str())))
+            .collect()
+    }
+
+    fn sync(&self) -> std::process::ExitStatus {
+        tracing::debug!("conda refreshes its channels on demand");
+        crate::common::synthetic_exit_status(true)
+    }
+}
+
+impl PackageManagerCommands for Conda {
+    fn cmd(&self) -> Command {
+        Command::new(self.binary)
+    }
+
+    fn needs_sudo(&self) -> bool {
+        false
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            Cmd::Install => vec!["install"],
+            Cmd::Uninstall => vec!["remove"],
+            Cmd::Update => vec!["update"],
+            Cmd::UpdateAll => vec!["update", "--all"],
+            Cmd::List => vec!["list"],
+            Cmd::Sync | Cmd::Clean | Cmd::Autoremove => vec![],
+            Cmd::AddRepo => vec!["config", "--add", "channels"],
+            Cmd::Search => vec!["search"],
+            Cmd::Outdated => vec!["update", "--all", "--dry-run"],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll => {
+                vec!["--yes".to_string()]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn get_flags(&self, cmd: Cmd) -> Vec<String> {
+        let mut flags: Vec<String> = match cmd {
+            Cmd::List | Cmd::Search | Cmd::Outdated => vec!["--json"],
+            _ => vec![],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect();
+
+        // channels are configured for all environments and the search is not
+        // scoped to one
+        if let Some(env) = self
+            .env
+            .as_ref()
+            .filter(|_| !matches!(cmd, Cmd::AddRepo | Cmd::Search | Cmd::Sync))
+        {
+            flags.extend(["--name".to_string(), env.clone()]);
+        }
+        flags
+    }
+}
+
+impl Display for Conda {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.binary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        let conda = Conda::default();
+        let list = r#"[
+  {"base_url": "https://repo.anaconda.com/pkgs/main", "build_number": 0, "build_string": "py311h08b1b3b_0", "channel": "pkgs/main", "dist_name": "numpy-1.26.4-py311h08b1b3b_0", "name": "numpy", "platform": "linux-64", "version": "1.26.4"},
+  {"base_url": "https://repo.anaconda.com/pkgs/main", "build_number": 0, "build_string": "h955ad1f_0", "channel": "pkgs/main", "dist_name": "python-3.11.8-h955ad1f_0", "name": "python", "platform": "linux-64", "version": "3.11.8"}
+]"#;
+        assert_eq!(
+            conda.parse_output(list.as_bytes()),
+            [
+                Package::new("numpy", "conda".to_string(), Some("1.26.4")),
+                Package::new("python", "conda".to_string(), Some("3.11.8")),
+            ]
+        );
+
+        let search = r#"{
+  "numpy": [
+    {"build": "py311h08b1b3b_0", "name": "numpy", "version": "1.26.3"},
+    {"build": "py311h08b1b3b_0", "name": "numpy", "version": "1.26.4"}
+  ],
+  "numpy-base": [
+    {"build": "py311hf175353_0", "name": "numpy-base", "version": "1.26.4"}
+  ]
+}"#;
+        assert_eq!(
+            conda.parse_output(search.as_bytes()),
+            [
+                Package::new("numpy", "conda".to_string(), Some("1.26.4")),
+                Package::new("numpy-base", "conda".to_string(), Some("1.26.4")),
+            ]
+        );
+
+        let outdated = r#"{
+  "actions": {
+    "FETCH": [],
+    "LINK": [{"base_url": "https://repo.anaconda.com/pkgs/main", "build_string": "py311h08b1b3b_0", "channel": "pkgs/main", "name": "numpy", "version": "2.0.0"}],
+    "UNLINK": [{"base_url": "https://repo.anaconda.com/pkgs/main", "build_string": "py311h08b1b3b_0", "channel": "pkgs/main", "name": "numpy", "version": "1.26.4"}],
+    "PREFIX": "/opt/conda"
+  },
+  "dry_run": true,
+  "success": true
+}"#;
+        assert_eq!(
+            conda.parse_output(outdated.as_bytes()),
+            [Package::new("numpy", "conda".to_string(), Some("2.0.0"))]
+        );
+        assert!(conda
+            .parse_output(
+                br#"{"message": "All requested packages already installed.", "success": true}"#
+            )
+            .is_empty());
+    }
+
+    #[test]
+    fn test_cmds() {
+        let mut conda = Conda::default();
+        let mut pkg = Package::new("numpy", "conda".to_string(), Some("1.26.4"));
+        let fmt = conda.reformat_for_command(&mut pkg);
+        assert_eq!(
+            conda.consolidated(Cmd::Install, Some(&pkg), &[fmt.clone()]),
+            ["install", "--yes", "numpy=1.26.4"]
+        );
+
+        conda.set_env("analysis".to_string());
+        assert_eq!(
+            conda.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["install", "--yes", "--name", "analysis", "numpy=1.26.4"]
+        );
+        assert_eq!(
+            conda.consolidated(Cmd::AddRepo, None, &["conda-forge"]),
+            ["config", "--add", "channels", "conda-forge"]
+        );
+    }
+}
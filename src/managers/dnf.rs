@@ -1,7 +1,15 @@
-use std::{fmt::Display, process::Command};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
 
 use crate::{
-    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+    history::HistoryEntry, AvailablePackageManager, Cmd, DependencyGraph, InstallPlan, NotOwned,
+    Package, PackageManager, PackageManagerCommands, PackageMetadata, PkgFormat, Repository,
 };
 
 /// Wrapper for DandifiedYUM or DNF, the next upcoming major version of YUM
@@ -10,8 +18,915 @@ use crate::{
 /// # Idiosyncracies
 /// The [``DandifiedYUM::add_repo``] method also installs `config-manager`
 /// plugin for DNF before attempting to add a repo.
+///
+/// dnf5, the default since Fedora 41, renamed some subcommands and changed
+/// the output of others, see [``DandifiedYUM::preferred``].
 #[derive(Debug, Default)]
-pub struct DandifiedYUM;
+pub struct DandifiedYUM {
+    force_ipv4: bool,
+    dnf5: bool,
+}
+
+impl DandifiedYUM {
+    /// Directories listing the `protected_packages` of dnf and yum.
+    const PROTECTED_DIRS: [&'static str; 2] = ["/etc/dnf/protected.d", "/etc/yum/protected.d"];
+    /// Repository definitions of dnf and yum.
+    const REPOS_DIR: &'static str = "/etc/yum.repos.d";
+    /// Main configuration file of dnf.
+    const CONF: &'static str = "/etc/dnf/dnf.conf";
+    /// Locks of the rpm database, in its classic and in its newer location.
+    pub(crate) const RPM_LOCKS: [&'static str; 2] =
+        ["/var/lib/rpm/.rpm.lock", "/usr/lib/sysimage/rpm/.rpm.lock"];
+
+    /// Use dnf5 if it is installed, else the classic dnf.
+    pub fn preferred() -> Self {
+        let dnf5 = Self {
+            dnf5: true,
+            ..Default::default()
+        };
+        if dnf5.is_available() {
+            dnf5
+        } else {
+            Self::default()
+        }
+    }
+
+    /// Use dnf5 (`true`) or the classic dnf (`false`).
+    pub fn set_dnf5(&mut self, dnf5: bool) {
+        self.dnf5 = dnf5;
+    }
+
+    /// Only resolve mirrors to IPv4 addresses (`--setopt=ip_resolve=4`).
+    /// Useful on networks with broken IPv6.
+    pub fn set_force_ipv4(&mut self, force_ipv4: bool) {
+        self.force_ipv4 = force_ipv4;
+    }
+
+    /// The `.repo` files of dnf and yum.
+    pub(crate) fn repo_files() -> Vec<PathBuf> {
+        crate::files_with_extension(Self::REPOS_DIR, "repo")
+    }
+
+    /// Split a `.repo` line into key and value. Commented out options like
+    /// `#baseurl=...` are returned with `commented` set.
+    fn repo_option(line: &str) -> Option<(&str, &str, bool)> {
+        let line = line.trim();
+        let (line, commented) = match line.strip_prefix('#') {
+            Some(rest) => (rest.trim_start(), true),
+            None => (line, false),
+        };
+        let (key, value) = line.split_once('=')?;
+        Some((key.trim(), value.trim(), commented))
+    }
+
+    /// URLs of the active `baseurl`, `metalink` and `mirrorlist` options of a
+    /// `.repo` file.
+    pub(crate) fn repo_mirrors(repo: &str) -> Vec<&str> {
+        repo.lines()
+            .filter_map(Self::repo_option)
+            .filter(|(key, _, commented)| {
+                !commented && matches!(*key, "baseurl" | "metalink" | "mirrorlist")
+            })
+            .flat_map(|(_, value, _)| value.split(|c: char| c == ',' || c.is_whitespace()))
+            .filter(|url| !url.is_empty())
+            .collect()
+    }
+
+    /// URLs of the `baseurl` options, including commented out ones, which
+    /// point to the repositories' primary mirror.
+    fn repo_baseurls(repo: &str) -> Vec<url::Url> {
+        repo.lines()
+            .filter_map(Self::repo_option)
+            .filter(|(key, _, _)| *key == "baseurl")
+            .filter_map(|(_, value, _)| url::Url::parse(value).ok())
+            .collect()
+    }
+
+    /// Point the `baseurl` of the repositories on `host` to the scheme, host
+    /// and port of `mirror`. Their `metalink` and `mirrorlist` options are
+    /// commented out so that the mirror is used.
+    pub(crate) fn rewrite_mirror(repo: &str, host: &str, mirror: &url::Url) -> String {
+        let mut out = String::with_capacity(repo.len());
+        let mut section = vec![];
+        for line in repo.split_inclusive('\n') {
+            if line.trim_start().starts_with('[') {
+                Self::rewrite_section_mirror(&section, host, mirror, &mut out);
+                section.clear();
+            }
+            section.push(line);
+        }
+        Self::rewrite_section_mirror(&section, host, mirror, &mut out);
+        out
+    }
+
+    /// [``DandifiedYUM::rewrite_mirror``] for the lines of a single
+    /// repository.
+    fn rewrite_section_mirror(lines: &[&str], host: &str, mirror: &url::Url, out: &mut String) {
+        let on_host = Self::repo_baseurls(&lines.concat())
+            .iter()
+            .any(|u| u.host_str() == Some(host));
+        for line in lines {
+            let eol = if line.ends_with('\n') { "\n" } else { "" };
+            match Self::repo_option(line) {
+                Some(("baseurl", value, _)) if on_host => match url::Url::parse(value) {
+                    Ok(url) if url.host_str() == Some(host) => {
+                        let new = crate::with_mirror_origin(&url, mirror);
+                        out.push_str(&format!("baseurl={new}{eol}"));
+                    }
+                    _ => out.push_str(line),
+                },
+                Some(("metalink" | "mirrorlist", _, false)) if on_host => {
+                    out.push_str(&format!("#{line}"))
+                }
+                _ => out.push_str(line),
+            }
+        }
+    }
+
+    /// Switch the primary mirror of the given `.repo` files to `mirror`.
+    pub(crate) fn set_mirror_in<P: AsRef<Path>>(
+        files: &[P],
+        mirror: &url::Url,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            mirror.host_str().is_some(),
+            "mirror URL {mirror} has no host"
+        );
+        let contents: Vec<(&P, String)> = files
+            .iter()
+            .filter_map(|f| std::fs::read_to_string(f).ok().map(|c| (f, c)))
+            .collect();
+        let urls: Vec<url::Url> = contents
+            .iter()
+            .flat_map(|(_, c)| Self::repo_baseurls(c))
+            .collect();
+        let host = crate::primary_host(&urls).context("no repositories with a baseurl found")?;
+        for (file, repo) in contents {
+            let file: &Path = file.as_ref();
+            let rewritten = Self::rewrite_mirror(&repo, &host, mirror);
+            if rewritten != repo {
+                std::fs::write(file, rewritten)
+                    .with_context(|| format!("failed to write {file:?}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse the output of `dnf repolist --all` and `yum repolist all`, e.g.
+    ///
+    /// ```text
+    /// repo id                 repo name                         status
+    /// fedora                  Fedora 40 - x86_64                enabled
+    /// base/7/x86_64           CentOS-7 - Base                   enabled: 10,072
+    /// updates-testing         Fedora 40 - Test Updates          disabled
+    /// ```
+    ///
+    /// The status is read from its column, which starts where `status` does
+    /// in the header, so that names containing `enabled` are not mistaken
+    /// for it. The release and architecture yum appends to the ids are
+    /// dropped.
+    pub(crate) fn parse_repolist(output: &str) -> Vec<Repository> {
+        let mut lines = output.lines();
+        let Some(status_column) = lines
+            .by_ref()
+            .find(|line| line.starts_with("repo id"))
+            .and_then(|header| header.find("status"))
+        else {
+            return vec![];
+        };
+        lines
+            .filter_map(|line| {
+                let status: String = line.chars().skip(status_column).collect();
+                let enabled = match status.trim_start().split([':', ' ']).next()? {
+                    "enabled" => true,
+                    "disabled" => false,
+                    _ => return None,
+                };
+                let id = line.split_whitespace().next()?;
+                let id = id.trim_start_matches(['!', '*']);
+                let id = id.split_once('/').map_or(id, |(id, _)| id);
+                Some(Repository {
+                    enabled,
+                    ..Repository::new(id, None)
+                })
+            })
+            .collect()
+    }
+
+    /// `repo` without the section of the repository `id`, or `None` if it
+    /// does not define it.
+    pub(crate) fn remove_repo_section(repo: &str, id: &str) -> Option<String> {
+        let header = format!("[{id}]");
+        let (mut out, mut found, mut skip) = (String::new(), false, false);
+        for line in repo.split_inclusive('\n') {
+            if line.trim_start().starts_with('[') {
+                skip = line.trim() == header;
+                found |= skip;
+            }
+            if !skip {
+                out.push_str(line);
+            }
+        }
+        found.then_some(out)
+    }
+
+    /// Remove the repository `id` from the given `.repo` files. Files
+    /// without any other repository are deleted.
+    pub(crate) fn remove_repo_in<P: AsRef<Path>>(files: &[P], id: &str) -> anyhow::Result<()> {
+        let mut found = false;
+        for file in files.iter().map(AsRef::as_ref) {
+            let Some(rest) = std::fs::read_to_string(file)
+                .ok()
+                .and_then(|repo| Self::remove_repo_section(&repo, id))
+            else {
+                continue;
+            };
+            found = true;
+            if rest.lines().any(|l| l.trim_start().starts_with('[')) {
+                std::fs::write(file, rest).with_context(|| format!("failed to write {file:?}"))?;
+            } else {
+                std::fs::remove_file(file).with_context(|| format!("failed to remove {file:?}"))?;
+            }
+        }
+        anyhow::ensure!(found, "no repository {id} in {}", Self::REPOS_DIR);
+        Ok(())
+    }
+
+    /// Mirrors of the given `.repo` files.
+    pub(crate) fn list_mirrors_in<P: AsRef<Path>>(files: &[P]) -> Vec<url::Url> {
+        let mut mirrors: Vec<url::Url> = vec![];
+        for repo in files.iter().filter_map(|f| std::fs::read_to_string(f).ok()) {
+            for url in Self::repo_mirrors(&repo) {
+                match url::Url::parse(url) {
+                    Ok(url) if !mirrors.contains(&url) => mirrors.push(url),
+                    Ok(_) => (),
+                    Err(e) => tracing::debug!("Ignoring mirror {url:?}: {e}"),
+                }
+            }
+        }
+        mirrors
+    }
+
+    /// Parse the transaction summary of dnf and yum, e.g.
+    ///
+    /// ```text
+    /// Total download size: 20 M
+    /// Installed size: 60 M
+    /// ```
+    ///
+    /// and return the larger of the two sizes in bytes.
+    pub(crate) fn parse_install_size(output: &str) -> Option<u64> {
+        let (download, installed) = Self::parse_sizes(output);
+        download.max(installed)
+    }
+
+    /// Download and installed sizes of the transaction summary, see
+    /// [``DandifiedYUM::parse_install_size``]. dnf5 writes them as
+    /// `Total size of inbound packages is 86 KiB. Need to download 86 KiB.`
+    /// and `After this operation, 185 KiB extra will be used (install
+    /// 185 KiB, remove 0 B).`
+    fn parse_sizes(output: &str) -> (Option<u64>, Option<u64>) {
+        let (mut download, mut installed) = (None, None);
+        for l in output.lines().map(str::trim) {
+            if let Some(size) = l
+                .strip_prefix("Total download size:")
+                .or_else(|| l.strip_prefix("Total size:"))
+            {
+                download = crate::parse_size(size);
+            } else if let Some(size) = l.strip_prefix("Installed size:") {
+                installed = crate::parse_size(size);
+            } else if let Some((_, size)) = l.split_once("Need to download ") {
+                download = crate::parse_size(size.trim_end_matches('.'));
+            } else if let Some((_, rest)) = l.split_once("(install ") {
+                installed = rest.split(',').next().and_then(crate::parse_size);
+            }
+        }
+        (download, installed)
+    }
+
+    /// Arguments of `repoquery` listing the names of the packages that
+    /// provide the requirements of `pkg`.
+    pub(crate) fn requires_args(pkg: &Package) -> Vec<String> {
+        [
+            "repoquery",
+            "-q",
+            "--requires",
+            "--resolve",
+            pkg.name(),
+            "--qf",
+            "%{name}\n",
+        ]
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    /// Installed packages that require `pkg`, listed by `repoquery
+    /// --whatrequires` of `mpm`, i.e. dnf or yum.
+    pub(crate) fn whatrequires<P: PackageManager + ?Sized>(
+        mpm: &P,
+        pkg: &Package,
+    ) -> anyhow::Result<Vec<Package>> {
+        let out = mpm.exec_cmds(&Self::whatrequires_args(pkg));
+        anyhow::ensure!(
+            out.status.success(),
+            "{} repoquery failed",
+            mpm.pkg_manager_name()
+        );
+        Ok(Self::parse_names(
+            &String::from_utf8_lossy(&out.stdout),
+            mpm.pkg_manager_name(),
+        ))
+    }
+
+    /// Arguments of `repoquery` listing the names of the installed packages
+    /// that require `pkg`.
+    fn whatrequires_args(pkg: &Package) -> Vec<String> {
+        [
+            "repoquery",
+            "-q",
+            "--installed",
+            "--whatrequires",
+            pkg.name(),
+            "--qf",
+            "%{name}\n",
+        ]
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    /// Packages named by the lines of `output`, e.g. of
+    /// [``DandifiedYUM::whatrequires_args``].
+    fn parse_names(output: &str, pkg_manager: String) -> Vec<Package> {
+        output
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|name| Package::new(name, pkg_manager.clone(), None))
+            .collect()
+    }
+
+    /// Parse the package names printed by [``DandifiedYUM::requires_args``]
+    /// into the direct dependencies of `root`.
+    pub(crate) fn parse_requires(root: &str, output: &str) -> DependencyGraph {
+        let mut graph = DependencyGraph::new(root);
+        for dep in output.lines().map(str::trim) {
+            if !dep.is_empty() && dep != root {
+                graph.add(root, dep);
+            }
+        }
+        graph
+    }
+
+    /// Parse the output of `dnf info <pkg>` (or yum), which lists the
+    /// installed and the available versions in sections, e.g.
+    ///
+    /// ```text
+    /// Installed Packages
+    /// Name         : bash
+    /// Version      : 5.2.26
+    /// Release      : 3.fc40
+    /// Size         : 8.1 M
+    /// Summary      : The GNU Bourne Again shell
+    /// URL          : https://www.gnu.org/software/bash
+    /// License      : GPL-3.0-or-later
+    /// ```
+    ///
+    /// The size of available packages is their download size, except for
+    /// dnf5's `Installed size`.
+    pub(crate) fn parse_info(name: &str, output: &str) -> PackageMetadata {
+        // (installed, fields) per package, in the order listed
+        let mut records: Vec<(bool, Vec<(&str, &str)>)> = vec![];
+        let mut installed = false;
+        for line in output.lines() {
+            let header = line.trim().to_lowercase();
+            if header.starts_with("installed packages") {
+                installed = true;
+                continue;
+            }
+            if header.starts_with("available") {
+                installed = false;
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            match (key.trim(), records.last_mut()) {
+                ("Name", _) => records.push((installed, vec![("Name", value.trim())])),
+                // continuation lines of the description have no key
+                ("", _) | (_, None) => (),
+                (key, Some((_, fields))) => fields.push((key, value.trim())),
+            }
+        }
+
+        let field = |record: Option<&(bool, Vec<(&str, &str)>)>, key: &str| {
+            record?
+                .1
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v.to_string())
+        };
+        let version = |record| {
+            let version = field(record, "Version")?;
+            Some(match field(record, "Release") {
+                Some(release) => format!("{version}-{release}"),
+                None => version,
+            })
+        };
+        let installed = records.iter().find(|(installed, _)| *installed);
+        let available = records.iter().find(|(installed, _)| !*installed);
+        let any = available.or(installed);
+        PackageMetadata {
+            name: name.to_string(),
+            description: field(any, "Summary"),
+            homepage: field(any, "URL"),
+            license: field(any, "License"),
+            size: field(installed, "Installed size")
+                .or_else(|| field(installed, "Size"))
+                .or_else(|| field(available, "Installed size"))
+                .and_then(|s| crate::parse_size(&s)),
+            installed_version: version(installed),
+            candidate_version: version(any),
+        }
+    }
+
+    /// Parse the transaction of `dnf install --assumeno` (or yum), e.g.
+    ///
+    /// ```text
+    /// Installing:
+    ///  hello        x86_64     2.12.1-2.fc39     fedora      86 k
+    /// Upgrading:
+    ///  glibc        x86_64     2.38-16.fc39      updates    2.2 M
+    /// Removing dependent packages:
+    ///  foo          noarch     1.0-1.fc39        @System     10 k
+    /// ```
+    pub(crate) fn parse_plan(&self, output: &str) -> InstallPlan {
+        let (download_size, installed_size) = Self::parse_sizes(output);
+        let mut plan = InstallPlan {
+            download_size,
+            installed_size,
+            ..Default::default()
+        };
+        let mut section: Option<&mut Vec<Package>> = None;
+        for line in output.lines() {
+            if !line.starts_with(' ') {
+                section = match line.trim_end().trim_end_matches(':') {
+                    l if l.starts_with("Installing") => Some(&mut plan.to_install),
+                    l if l.starts_with("Upgrading") => Some(&mut plan.to_upgrade),
+                    l if l.starts_with("Removing") => Some(&mut plan.to_remove),
+                    _ => None,
+                };
+                continue;
+            }
+            let Some(pkgs) = section.as_mut() else {
+                continue;
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // `replacing  glibc.x86_64 2.38-14.fc39` follows upgraded packages
+            if fields.len() >= 4 && fields[0] != "replacing" {
+                pkgs.push(Package::new(
+                    fields[0],
+                    self.pkg_manager_name(),
+                    Some(fields[2]),
+                ));
+            }
+        }
+        plan
+    }
+
+    /// Plan of an `install --assumeno` that printed `out`, which fails
+    /// unless the transaction was resolved, see
+    /// [``DandifiedYUM::simulation_succeeded``].
+    pub(crate) fn plan_of_simulation(
+        &self,
+        out: &std::process::Output,
+    ) -> anyhow::Result<InstallPlan> {
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        let lines: Vec<String> = stdout.lines().map(String::from).collect();
+        anyhow::ensure!(
+            self.simulation_succeeded(out.status, &lines),
+            "failed to resolve the transaction: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+        Ok(self.parse_plan(&stdout))
+    }
+
+    /// Parse the output of `rpm --checksig`. The package must carry a
+    /// signature and every digest and signature must be OK; e.g.
+    /// `hello.rpm: digests signatures OK` is valid, while
+    /// `hello.rpm: digests SIGNATURES NOT OK` and the unsigned
+    /// `hello.rpm: digests OK` are not.
+    pub(crate) fn parse_rpm_checksig(output: &str) -> bool {
+        let mut lines = output.lines().map(str::trim).filter(|l| !l.is_empty());
+        lines.clone().next().is_some()
+            && lines.all(|line| {
+                let result = line
+                    .rsplit_once(':')
+                    .map_or(line, |(_, r)| r)
+                    .to_lowercase();
+                result.trim_end().ends_with("ok")
+                    && !result.contains("not ok")
+                    && ["signatures", "pgp", "gpg", "rsa", "dsa"]
+                        .iter()
+                        .any(|s| result.contains(s))
+            })
+    }
+
+    /// Parse a line of `dnf list available`, skipping the headers, e.g.
+    /// `0ad.x86_64    0.0.26-8.fc39    fedora`.
+    pub(crate) fn parse_available(&self, line: &str) -> Option<Package> {
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [name, version, _repo] if name.contains('.') => {
+                Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+            }
+            _ => None,
+        }
+    }
+
+    /// [``PackageManager::parse_pkg``] for dnf5, whose search results are
+    /// indented and separated by headings like `Matched fields: name`.
+    fn parse_pkg5(&self, line: &str) -> Option<Package> {
+        if line.starts_with(char::is_whitespace) {
+            let name = line.trim().split(|c: char| c == ':' || c == '\t').next()?;
+            let name = name.trim();
+            return (!name.is_empty() && !name.contains(char::is_whitespace))
+                .then(|| Package::new(name, self.pkg_manager_name(), None));
+        }
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            // headings like `Matched fields: name`
+            fields if fields.iter().any(|f| f.ends_with(':')) => None,
+            [name, version, _repo] => {
+                Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+            }
+            _ => {
+                let (name, version) = line.split_once('^')?;
+                Some(Package::new(
+                    name.trim(),
+                    self.pkg_manager_name(),
+                    Some(version.trim()),
+                ))
+            }
+        }
+    }
+
+    /// Verify the files of an installed package with `rpm -V`.
+    pub(crate) fn rpm_verify(pkg: &Package) -> anyhow::Result<bool> {
+        Ok(Command::new("rpm")
+            .args(["-V", pkg.name()])
+            .output()?
+            .status
+            .success())
+    }
+
+    /// Parse the output of `dnf versionlock list`, one
+    /// `name-epoch:version-release.*` per line, or `Package name: name` lines
+    /// with dnf5.
+    pub(crate) fn parse_versionlock(output: &str, pkg_manager: String) -> Vec<Package> {
+        output
+            .lines()
+            .map(str::trim)
+            .filter_map(|l| match l.strip_prefix("Package name:") {
+                Some(name) => Some(name.trim()),
+                // other lines are comments and metadata messages
+                None if l.contains(':') && !l.contains(char::is_whitespace) => {
+                    l.rsplitn(3, '-').nth(2)
+                }
+                None => None,
+            })
+            .map(|name| Package::new(name, pkg_manager.clone(), None))
+            .collect()
+    }
+
+    /// Parse the table of `dnf history list`, newest first, e.g.
+    ///
+    /// ```text
+    /// ID     | Command line             | Date and time    | Action(s)      | Altered
+    /// -------------------------------------------------------------------------------
+    ///     12 | install htop             | 2024-07-01 10:00 | Install        |    1
+    /// ```
+    ///
+    /// The operation and packages are taken from the command line, options
+    /// are dropped. Entries are returned oldest first.
+    pub(crate) fn parse_history(output: &str, pkg_manager: String) -> Vec<HistoryEntry> {
+        let mut entries: Vec<HistoryEntry> = output
+            .lines()
+            .filter_map(|line| {
+                let cols: Vec<&str> = line.split('|').map(str::trim).collect();
+                let [id, cmdline, time, action, _] = cols[..] else {
+                    return None;
+                };
+                id.parse::<u64>().ok()?;
+                Some(Self::history_entry(
+                    cmdline,
+                    time,
+                    action,
+                    pkg_manager.clone(),
+                ))
+            })
+            .collect();
+        entries.reverse();
+        entries
+    }
+
+    /// Parse the table of `dnf5 history list`, which is aligned with spaces
+    /// instead of separated by `|` and records the program, e.g.
+    ///
+    /// ```text
+    /// ID Command line            Date and time       Action(s) Altered
+    ///  3 dnf5 install -y htop    2024-11-04 12:53:41                 1
+    /// ```
+    ///
+    /// The columns are cut at the positions of the headings. Entries are
+    /// returned oldest first.
+    pub(crate) fn parse_history5(output: &str, pkg_manager: String) -> Vec<HistoryEntry> {
+        let mut lines = output.lines();
+        let Some(header) = lines.find(|l| l.trim_start().starts_with("ID ")) else {
+            return vec![];
+        };
+        let (Some(cmd), Some(time), Some(action)) = (
+            header.find("Command line"),
+            header.find("Date and time"),
+            header.find("Action(s)"),
+        ) else {
+            return vec![];
+        };
+        let altered = header.find("Altered").unwrap_or(header.len());
+        let mut entries: Vec<HistoryEntry> = lines
+            .filter_map(|line| {
+                let col = |from: usize, to: usize| {
+                    line.get(from.min(line.len())..to.min(line.len()))
+                        .unwrap_or_default()
+                        .trim()
+                };
+                col(0, cmd).parse::<u64>().ok()?;
+                let cmdline = col(cmd, time);
+                // the program, e.g. `dnf5` or `/usr/bin/dnf5`
+                let cmdline = match cmdline.split_once(char::is_whitespace) {
+                    Some((program, args)) if program.contains("dnf") => args,
+                    _ => cmdline,
+                };
+                Some(Self::history_entry(
+                    cmdline,
+                    col(time, action),
+                    col(action, altered),
+                    pkg_manager.clone(),
+                ))
+            })
+            .collect();
+        entries.reverse();
+        entries
+    }
+
+    /// Id of the newest transaction in the table of `dnf history list`, see
+    /// [`DandifiedYUM::parse_history`] and [`DandifiedYUM::parse_history5`].
+    pub(crate) fn parse_last_transaction(output: &str) -> Option<String> {
+        output.lines().find_map(|line| {
+            let id = line.split(['|', ' ']).find(|s| !s.is_empty())?;
+            id.chars()
+                .all(|c| c.is_ascii_digit())
+                .then(|| id.to_string())
+        })
+    }
+
+    /// History entry of a transaction run with the arguments `cmdline`: the
+    /// operation and packages are taken from it, options are dropped. The
+    /// `action` is the operation if `cmdline` is empty.
+    fn history_entry(cmdline: &str, time: &str, action: &str, manager: String) -> HistoryEntry {
+        let mut args = cmdline.split_whitespace().filter(|a| !a.starts_with('-'));
+        let operation = args
+            .next()
+            .map_or_else(|| action.to_lowercase(), str::to_string);
+        HistoryEntry::native(
+            time,
+            manager,
+            &operation,
+            args.map(str::to_string).collect(),
+        )
+    }
+
+    /// Run `versionlock <action> <pkg>`, installing the versionlock plugin
+    /// first for dnf4.
+    fn versionlock(&self, action: &str, pkg: &Package) -> anyhow::Result<()> {
+        if !self.dnf5 {
+            let plugin = Package::new("dnf-command(versionlock)", self.pkg_manager_name(), None);
+            anyhow::ensure!(
+                self.install(plugin, false).success(),
+                "failed to install versionlock plugin"
+            );
+        }
+        let s = self.exec_cmds_status(&["versionlock", action, pkg.name()], None);
+        anyhow::ensure!(
+            s.success(),
+            "dnf versionlock {action} {} failed",
+            pkg.name()
+        );
+        Ok(())
+    }
+
+    /// Files installed by `pkg`, using `rpm -ql`.
+    pub(crate) fn rpm_files(pkg: &Package) -> anyhow::Result<Vec<PathBuf>> {
+        let out = Command::new("rpm")
+            .args(["-ql", pkg.name()])
+            .output()
+            .context("failed to run rpm")?;
+        anyhow::ensure!(out.status.success(), "{} is not installed", pkg.name());
+        Ok(crate::common::parse_file_list(&String::from_utf8_lossy(
+            &out.stdout,
+        )))
+    }
+
+    /// Parse a line of `rpm -Va` like `S.5....T.  c /etc/foo.conf` or
+    /// `missing     /usr/bin/foo` into the reported file.
+    pub(crate) fn parse_rpm_verify(line: &str) -> Option<PathBuf> {
+        let (flags, rest) = line.split_once(char::is_whitespace)?;
+        let is_flags = flags.len() == 9 && flags.chars().all(|c| ".SM5DLUGTP?".contains(c));
+        if !(is_flags || flags == "missing") {
+            return None;
+        }
+        Some(PathBuf::from(&rest[rest.find('/')?..]))
+    }
+
+    /// Files reported by `rpm -V <pkg>`, or by `dpkg --verify <pkg>`, which
+    /// prints the same format. Both fail without printing files if the
+    /// package is not installed.
+    pub(crate) fn parse_verify_output(
+        out: &std::process::Output,
+        pkg: &Package,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let files: Vec<PathBuf> = String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .filter_map(Self::parse_rpm_verify)
+            .collect();
+        if files.is_empty() && !out.status.success() {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            let stdout = String::from_utf8_lossy(&out.stdout);
+            let reason = [stderr.trim(), stdout.trim()]
+                .into_iter()
+                .find(|s| !s.is_empty())
+                .unwrap_or("verification failed");
+            anyhow::bail!("failed to verify {}: {reason}", pkg.name());
+        }
+        Ok(files)
+    }
+
+    /// Modified and missing files of `pkg`, using `rpm -V`.
+    pub(crate) fn rpm_verify_files(pkg: &Package) -> anyhow::Result<Vec<PathBuf>> {
+        let out = Command::new("rpm")
+            .args(["-V", pkg.name()])
+            .output()
+            .context("failed to run rpm")?;
+        Self::parse_verify_output(&out, pkg)
+    }
+
+    /// Name of the package owning `path`, using `rpm -qf`.
+    pub(crate) fn rpm_owner(path: &Path) -> Option<String> {
+        Self::rpm_owners(&[path.to_path_buf()]).remove(path)
+    }
+
+    /// Names of the packages owning `paths`, with a single `rpm -qf`. Paths
+    /// that no package owns are missing.
+    pub(crate) fn rpm_owners(paths: &[PathBuf]) -> HashMap<PathBuf, String> {
+        if paths.is_empty() {
+            return HashMap::new();
+        }
+        // fails if any of the paths is not owned, the others are printed
+        let Ok(out) = Command::new("rpm")
+            .args(["-qf", "--qf", "[%{FILENAMES}\\t%{NAME}\\n]"])
+            .args(paths)
+            .output()
+        else {
+            return HashMap::new();
+        };
+        Self::parse_rpm_owners(&String::from_utf8_lossy(&out.stdout), paths)
+    }
+
+    /// Parse the files of the owning packages printed by
+    /// [``DandifiedYUM::rpm_owners``], one `<path>\t<name>` per line, into
+    /// the owner of each of `paths`, the first if there are several.
+    pub(crate) fn parse_rpm_owners(out: &str, paths: &[PathBuf]) -> HashMap<PathBuf, String> {
+        let mut owners = HashMap::new();
+        for (path, name) in out.lines().filter_map(|l| l.split_once('\t')) {
+            let path = Path::new(path);
+            if paths.iter().any(|p| p == path) && !owners.contains_key(path) {
+                owners.insert(path.to_path_buf(), name.to_string());
+            }
+        }
+        owners
+    }
+
+    /// Verify all installed packages with `rpm -Va` and group the reported
+    /// files by their package.
+    pub(crate) fn rpm_verify_all(manager: String) -> anyhow::Result<Vec<(Package, Vec<PathBuf>)>> {
+        let mut paths = vec![];
+        // rpm exits with an error if it found modified files
+        crate::common::run_command_with(Command::new("rpm"), &["-Va"], |line| {
+            paths.extend(Self::parse_rpm_verify(line));
+        })
+        .context("failed to run rpm")?;
+        let mut owners = Self::rpm_owners(&paths);
+        let mut modified = vec![];
+        for path in paths {
+            match owners.remove(&path) {
+                Some(name) => {
+                    tracing::info!("{name}: {} was modified", path.display());
+                    modified.push((name, path));
+                }
+                None => tracing::warn!("No package owns modified file {}", path.display()),
+            }
+        }
+        Ok(crate::common::group_by_package(manager, modified))
+    }
+
+    /// Verify a package file with `rpm --checksig`.
+    pub(crate) fn rpm_checksig(path: &Path) -> anyhow::Result<bool> {
+        let out = Command::new("rpm").arg("--checksig").arg(path).output()?;
+        Ok(out.status.success() && Self::parse_rpm_checksig(&String::from_utf8_lossy(&out.stdout)))
+    }
+
+    /// Value of `key` in the `[main]` section of a dnf or yum configuration.
+    pub(crate) fn conf_get(conf: &str, key: &str) -> Option<String> {
+        let mut in_main = false;
+        for line in conf.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_main = line == "[main]";
+            } else if in_main && !line.starts_with('#') {
+                match line.split_once('=') {
+                    Some((k, v)) if k.trim() == key => return Some(v.trim().to_string()),
+                    _ => (),
+                }
+            }
+        }
+        None
+    }
+
+    /// Set `key` to `value` in the `[main]` section of a dnf or yum
+    /// configuration, replacing its current value or adding it to the end of
+    /// the section.
+    pub(crate) fn conf_set(conf: &str, key: &str, value: &str) -> String {
+        let mut lines: Vec<String> = conf.lines().map(String::from).collect();
+        let (mut in_main, mut existing, mut main_end) = (false, None, None);
+        for (i, line) in lines.iter().enumerate() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                in_main = line == "[main]";
+                if in_main {
+                    main_end = Some(i + 1);
+                }
+            } else if in_main && !line.is_empty() {
+                if !line.starts_with('#')
+                    && line.split_once('=').is_some_and(|(k, _)| k.trim() == key)
+                {
+                    existing = Some(i);
+                    break;
+                }
+                main_end = Some(i + 1);
+            }
+        }
+        let option = format!("{key}={value}");
+        match (existing, main_end) {
+            (Some(i), _) => lines[i] = option,
+            (None, Some(i)) => lines.insert(i, option),
+            (None, None) => lines.extend(["[main]".to_string(), option]),
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// [``DandifiedYUM::conf_get``] of the configuration file at `path`.
+    pub(crate) fn get_option_in(path: &Path, key: &str) -> anyhow::Result<Option<String>> {
+        let conf =
+            std::fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+        Ok(Self::conf_get(&conf, key))
+    }
+
+    /// [``DandifiedYUM::conf_set``] of the configuration file at `path`,
+    /// which is created if it does not exist.
+    pub(crate) fn set_option_in(path: &Path, key: &str, value: &str) -> anyhow::Result<()> {
+        let conf = std::fs::read_to_string(path).unwrap_or_default();
+        std::fs::write(path, Self::conf_set(&conf, key, value))
+            .with_context(|| format!("failed to write {path:?}"))
+    }
+
+    /// Collect package names from the `*.conf` files in the given
+    /// `protected.d` directories. Missing directories are ignored.
+    pub(crate) fn protected_packages<P: AsRef<Path>>(dirs: &[P]) -> HashSet<String> {
+        dirs.iter()
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "conf"))
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .flat_map(|contents| {
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
 
 impl PackageManager for DandifiedYUM {
     fn pkg_delimiter(&self) -> char {
@@ -26,7 +941,14 @@ impl PackageManager for DandifiedYUM {
         vec![PkgFormat::Rpm]
     }
 
+    /// Parses the lines of `dnf list --installed` like `sudo.x86_64
+    /// 1.9.13-2.p2.fc38 @updates`, of `dnf repoquery --upgrades` like
+    /// `sudo^1.9.15` and of `dnf search` like `hello.x86_64 : Prints a
+    /// familiar, friendly greeting`.
     fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        if self.dnf5 {
+            return self.parse_pkg5(line);
+        }
         if line.contains('@') || line.split_whitespace().count() == 3 {
             let mut splt = line.split_whitespace();
             let name = splt.next()?;
@@ -56,35 +978,289 @@ impl PackageManager for DandifiedYUM {
         }
     }
 
+    /// Packages listed in dnf's `protected_packages` are protected. Names may
+    /// carry an architecture suffix, e.g. `systemd.x86_64`.
+    fn is_protected(&self, pkg: &Package) -> bool {
+        let protected = Self::protected_packages(&Self::PROTECTED_DIRS);
+        let name = pkg.name();
+        protected.contains(name)
+            || name
+                .rsplit_once('.')
+                .is_some_and(|(base, _arch)| protected.contains(base))
+    }
+
+    fn verify_package_signature(&self, path: &Path) -> anyhow::Result<bool> {
+        Self::rpm_checksig(path)
+    }
+
+    /// All packages are installed in a single dnf transaction.
+    fn installs_atomically(&self) -> bool {
+        true
+    }
+
+    /// dnf5 kept `--downloadonly` and `--cacheonly`.
+    fn download_updates(&self) -> anyhow::Result<std::process::ExitStatus> {
+        let cmds = self.consolidated(Cmd::Update, None, &["--downloadonly"]);
+        Ok(self.exec_cmds_status(&cmds, Some(false)))
+    }
+
+    fn apply_downloaded_updates(&self) -> anyhow::Result<std::process::ExitStatus> {
+        let cmds = self.consolidated(Cmd::Update, None, &["--cacheonly"]);
+        Ok(self.exec_cmds_status(&cmds, Some(false)))
+    }
+
+    fn list_available_with<F: FnMut(Package)>(&self, mut on_pkg: F) -> anyhow::Result<()> {
+        let args: &[&str] = if self.dnf5 {
+            &["list", "--available"]
+        } else {
+            &["list", "available"]
+        };
+        let status = crate::run_command_with(self.cmd(), args, |line| {
+            if let Some(pkg) = self.parse_available(line) {
+                on_pkg(pkg);
+            }
+        })?;
+        anyhow::ensure!(status.success(), "dnf list available failed");
+        Ok(())
+    }
+
+    /// Uses `dnf repoquery --requires --resolve`, which only reports the
+    /// direct dependencies.
+    fn dependencies(&self, pkg: &Package) -> anyhow::Result<DependencyGraph> {
+        let out = self.exec_cmds(&Self::requires_args(pkg));
+        anyhow::ensure!(out.status.success(), "dnf repoquery failed");
+        Ok(Self::parse_requires(
+            pkg.name(),
+            &String::from_utf8_lossy(&out.stdout),
+        ))
+    }
+
+    /// Uses `dnf info`.
+    fn info(&self, pkg: &Package) -> anyhow::Result<PackageMetadata> {
+        let out = self.exec_cmds(&["info".to_string(), pkg.name().to_string()]);
+        if !out.status.success() {
+            return Err(crate::error::Error::PackageNotFound(pkg.name().to_string()).into());
+        }
+        Ok(Self::parse_info(
+            pkg.name(),
+            &String::from_utf8_lossy(&out.stdout),
+        ))
+    }
+
+    /// Uses `dnf clean all`.
+    /// Uses `dnf history list`.
+    fn history(&self) -> anyhow::Result<Vec<HistoryEntry>> {
+        let out = self.exec_cmds(&["history".to_string(), "list".to_string()]);
+        anyhow::ensure!(out.status.success(), "dnf history list failed");
+        let output = String::from_utf8_lossy(&out.stdout);
+        Ok(if self.dnf5 {
+            Self::parse_history5(&output, self.pkg_manager_name())
+        } else {
+            Self::parse_history(&output, self.pkg_manager_name())
+        })
+    }
+
+    /// Uses `dnf history list`.
+    fn last_transaction(&self) -> Option<String> {
+        let out = self.exec_cmds(&["history".to_string(), "list".to_string()]);
+        out.status
+            .success()
+            .then(|| Self::parse_last_transaction(&String::from_utf8_lossy(&out.stdout)))
+            .flatten()
+    }
+
+    /// Uses `dnf history undo`.
+    fn undo_transaction(
+        &self,
+        id: &str,
+        interactive: bool,
+    ) -> anyhow::Result<std::process::ExitStatus> {
+        let mut args = vec!["history".to_string(), "undo".to_string(), id.to_string()];
+        if crate::common::assume_yes() {
+            args.extend(self.yes_flags(Cmd::UpdateAll));
+        }
+        Ok(self.exec_cmds_status(&args, Some(interactive)))
+    }
+
+    /// Uses `dnf versionlock add`.
+    fn pin(&self, pkg: &Package) -> anyhow::Result<()> {
+        self.versionlock("add", pkg)
+    }
+
+    /// Uses `dnf versionlock delete`.
+    fn unpin(&self, pkg: &Package) -> anyhow::Result<()> {
+        self.versionlock("delete", pkg)
+    }
+
+    /// Uses `dnf versionlock list`.
+    fn list_pinned(&self) -> anyhow::Result<Vec<Package>> {
+        let out = self.exec_cmds(&["versionlock".to_string(), "list".to_string()]);
+        anyhow::ensure!(out.status.success(), "dnf versionlock list failed");
+        Ok(Self::parse_versionlock(
+            &String::from_utf8_lossy(&out.stdout),
+            self.pkg_manager_name(),
+        ))
+    }
+
+    /// dnf5 keeps its cache in `/var/cache/libdnf5`.
+    fn cache_dirs(&self) -> Vec<PathBuf> {
+        vec![
+            PathBuf::from("/var/cache/dnf"),
+            PathBuf::from("/var/cache/libdnf5"),
+        ]
+    }
+
+    fn lock_files(&self) -> Vec<PathBuf> {
+        Self::RPM_LOCKS.map(PathBuf::from).to_vec()
+    }
+
+    /// Uses `rpm -qf`.
+    fn owner(&self, path: &Path) -> anyhow::Result<Package> {
+        Self::rpm_owner(path)
+            .map(|name| Package::new(&name, self.pkg_manager_name(), None))
+            .ok_or_else(|| NotOwned(path.to_path_buf()).into())
+    }
+
+    /// Uses `rpm -ql`.
+    fn files(&self, pkg: &Package) -> anyhow::Result<Vec<PathBuf>> {
+        Self::rpm_files(pkg)
+    }
+
+    /// Uses `dnf repoquery --whatrequires`.
+    fn reverse_dependencies(&self, pkg: &Package) -> anyhow::Result<Vec<Package>> {
+        Self::whatrequires(self, pkg)
+    }
+
+    fn reinstall(
+        &self,
+        pkg: &Package,
+        interactive: bool,
+    ) -> anyhow::Result<std::process::ExitStatus> {
+        let mut pkg = pkg.clone();
+        let fmt = self.reformat_for_command(&mut pkg);
+        let cmds =
+            crate::traits::consolidated_as(self, &["reinstall"], Cmd::Install, Some(&pkg), &[fmt]);
+        Ok(self.exec_cmds_status(&cmds, Some(interactive)))
+    }
+
+    fn verify_integrity(&self, pkg: &Package) -> anyhow::Result<bool> {
+        Self::rpm_verify(pkg)
+    }
+
+    fn verify_files(&self, pkg: &Package) -> anyhow::Result<Vec<PathBuf>> {
+        Self::rpm_verify_files(pkg)
+    }
+
+    fn verify_all(&self) -> anyhow::Result<Vec<(Package, Vec<PathBuf>)>> {
+        Self::rpm_verify_all(self.pkg_manager_name())
+    }
+
+    /// Reads the `[main]` section of `/etc/dnf/dnf.conf`.
+    fn get_option(&self, key: &str) -> anyhow::Result<Option<String>> {
+        Self::get_option_in(Path::new(Self::CONF), key)
+    }
+
+    /// Writes the `[main]` section of `/etc/dnf/dnf.conf`.
+    fn set_option(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        Self::set_option_in(Path::new(Self::CONF), key, value)
+    }
+
+    /// Active `baseurl`, `metalink` and `mirrorlist` URLs of
+    /// `/etc/yum.repos.d`.
+    fn list_mirrors(&self) -> anyhow::Result<Vec<url::Url>> {
+        Ok(Self::list_mirrors_in(&Self::repo_files()))
+    }
+
+    /// Rewrites the `baseurl` of the repositories on the most used host and
+    /// disables their `metalink` and `mirrorlist`.
+    fn set_mirror(&self, url: &url::Url) -> anyhow::Result<()> {
+        Self::set_mirror_in(&Self::repo_files(), url)
+    }
+
+    /// Uses the transaction of `dnf install --assumeno`.
+    fn install_plan(&self, pkg: &Package) -> anyhow::Result<InstallPlan> {
+        let mut pkg = pkg.clone();
+        let fmt = self.reformat_for_command(&mut pkg);
+        // dnf aborts (and exits with an error) after printing the transaction
+        let out = self.exec_cmds(&["install".to_string(), "--assumeno".to_string(), fmt]);
+        self.plan_of_simulation(&out)
+    }
+
+    /// Uses the transaction summary of `dnf install --assumeno`.
+    fn estimate_operation_size(&self, pkg: &Package) -> Option<u64> {
+        let mut pkg = pkg.clone();
+        let fmt = self.reformat_for_command(&mut pkg);
+        let out = self.exec_cmds(&["install".to_string(), "--assumeno".to_string(), fmt]);
+        Self::parse_install_size(&String::from_utf8_lossy(&out.stdout))
+    }
+
+    /// dnf has no direct equivalent of `dpkg --configure -a`; `dnf check`
+    /// reports problems in the package database.
+    fn fix_broken(&self) -> anyhow::Result<std::process::ExitStatus> {
+        Ok(self.exec_cmds_status(&["check"], None))
+    }
+
     fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
+        let plugin = if self.dnf5 {
+            "dnf5-command(config-manager)"
+        } else {
+            "dnf-command(config-manager)"
+        };
         anyhow::ensure!(
-            self.install(
-                Package::new("dnf-command(config-manager)", self.pkg_manager_name(), None),
-                false
-            )
-            .success(),
+            self.install(Package::new(plugin, self.pkg_manager_name(), None), false)
+                .success(),
             "failed to install config-manager plugin"
         );
 
-        let s = self.exec_cmds_status(&self.consolidated(Cmd::AddRepo, None, repo), None);
+        // `dnf5 config-manager addrepo --from-repofile=<url>`
+        let repo: Vec<String> = if self.dnf5 {
+            repo.iter()
+                .map(|r| format!("--from-repofile={r}"))
+                .collect()
+        } else {
+            repo.clone()
+        };
+        let s = self.exec_cmds_status(&self.consolidated(Cmd::AddRepo, None, &repo), None);
         anyhow::ensure!(s.success(), "failed to add repo");
         Ok(())
     }
+
+    /// Uses `dnf repolist --all`.
+    fn list_repos(&self) -> anyhow::Result<Vec<Repository>> {
+        let out = self.exec_cmds(&["repolist".to_string(), "--all".to_string()]);
+        anyhow::ensure!(out.status.success(), "dnf repolist failed");
+        Ok(Self::parse_repolist(&String::from_utf8_lossy(&out.stdout)))
+    }
+
+    /// Removes the section of the repository from its file in
+    /// `/etc/yum.repos.d`, as dnf has no command for it.
+    fn remove_repo(&self, name: &str) -> anyhow::Result<()> {
+        Self::remove_repo_in(&Self::repo_files(), name)
+    }
 }
 
 impl Display for DandifiedYUM {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("Dandified YUM (DNF)")
+        if self.dnf5 {
+            f.write_str("Dandified YUM (DNF5)")
+        } else {
+            f.write_str("Dandified YUM (DNF)")
+        }
     }
 }
 
 impl PackageManagerCommands for DandifiedYUM {
     fn cmd(&self) -> Command {
-        Command::new("dnf")
+        if self.dnf5 {
+            Command::new("dnf5")
+        } else {
+            Command::new("dnf")
+        }
     }
 
     fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
         match cmd {
+            Cmd::AddRepo if self.dnf5 => vec!["config-manager", "addrepo"],
             Cmd::Install => vec!["install"],
             Cmd::Uninstall => vec!["remove"],
             Cmd::Update => vec!["upgrade"],
@@ -95,35 +1271,230 @@ impl PackageManagerCommands for DandifiedYUM {
             Cmd::AddRepo => vec!["config-manager", "--add-repo"], // flag must come before repo
             Cmd::Search => vec!["search"],
             Cmd::Outdated => vec!["repoquery", "--upgrades", "--qf", "%{name}^%{version}\n"],
+            Cmd::Clean => vec!["clean", "all"],
+            Cmd::Autoremove => vec!["autoremove"],
         }
         .iter()
         .map(|x| x.to_string())
         .collect()
     }
 
-    fn get_flags(&self, cmd: Cmd) -> Vec<String> {
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
         match cmd {
-            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll => vec!["-y"],
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll | Cmd::Autoremove => {
+                vec!["-y".to_string()]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn get_flags(&self, cmd: Cmd) -> Vec<String> {
+        let mut flags: Vec<String> = match cmd {
             Cmd::List => vec!["--installed"],
             Cmd::Search => vec!["-q"],
             _ => vec![],
         }
         .iter()
         .map(|x| x.to_string())
-        .collect()
+        .collect();
+
+        // `config-manager --add-repo` must be directly followed by the repo
+        if self.force_ipv4 && cmd != Cmd::AddRepo {
+            flags.push("--setopt=ip_resolve=4".to_string());
+        }
+        flags
+    }
+
+    /// `--assumeno` takes precedence over `-y` and declines the transaction
+    /// after printing it.
+    fn dry_run_flags(&self, cmd: Cmd) -> Option<Vec<String>> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll | Cmd::Autoremove => {
+                Some(vec!["--assumeno".to_string()])
+            }
+            _ => None,
+        }
+    }
+
+    /// `--assumeno` fails even after declining a transaction that would have
+    /// run, which is recognized by its `Transaction Summary`.
+    fn simulation_succeeded(&self, status: std::process::ExitStatus, output: &[String]) -> bool {
+        status.success() || output.iter().any(|l| l.starts_with("Transaction Summary"))
+    }
+
+    /// dnf refuses to resolve a transaction without root.
+    fn simulation_needs_sudo(&self) -> bool {
+        true
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::{
+        path::{Path, PathBuf},
+        str::FromStr,
+    };
 
     use super::DandifiedYUM;
-    use crate::{Package, PackageManager};
+    use crate::{Cmd, Package, PackageManager, PackageManagerCommands};
+
+    #[test]
+    fn test_parse_requires() {
+        let pkg = Package::from("bash");
+        assert_eq!(
+            DandifiedYUM::requires_args(&pkg),
+            [
+                "repoquery",
+                "-q",
+                "--requires",
+                "--resolve",
+                "bash",
+                "--qf",
+                "%{name}\n"
+            ]
+        );
+        let graph =
+            DandifiedYUM::parse_requires("bash", "filesystem\nglibc\nbash\nglibc\nncurses-libs\n");
+        assert_eq!(
+            graph.dependencies_of("bash"),
+            ["filesystem", "glibc", "ncurses-libs"]
+        );
+    }
+
+    #[test]
+    fn test_parse_last_transaction() {
+        let output =
+            "ID     | Command line             | Date and time    | Action(s)      | Altered
+-------------------------------------------------------------------------------
+    12 | -y install htop tmux     | 2024-07-01 10:00 | Install        |    2
+    11 |                          | 2024-06-30 09:12 | Removed        |    1 EE
+";
+        assert_eq!(
+            DandifiedYUM::parse_last_transaction(output),
+            Some("12".to_string())
+        );
+        let output = "ID Command line                  Date and time       Action(s) Altered
+ 3 /usr/bin/dnf5 install -y htop 2024-11-04 12:53:41                 1
+";
+        assert_eq!(
+            DandifiedYUM::parse_last_transaction(output),
+            Some("3".to_string())
+        );
+        assert_eq!(
+            DandifiedYUM::parse_last_transaction("No transactions\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_history() {
+        let output =
+            "ID     | Command line             | Date and time    | Action(s)      | Altered
+-------------------------------------------------------------------------------
+    12 | -y install htop tmux     | 2024-07-01 10:00 | Install        |    2
+    11 |                          | 2024-06-30 09:12 | Removed        |    1 EE
+";
+        let entries = DandifiedYUM::parse_history(output, "dnf".to_string());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].time,
+            crate::history::local_to_utc("2024-06-30 09:12")
+        );
+        assert_eq!(entries[0].operation, "removed");
+        assert!(entries[0].packages.is_empty());
+        assert_eq!(entries[1].operation, "install");
+        assert_eq!(entries[1].packages, ["htop", "tmux"]);
+        assert!(entries[1].native);
+
+        let output = "ID Command line                  Date and time       Action(s) Altered
+ 3 /usr/bin/dnf5 install -y htop 2024-11-04 12:53:41                 1
+ 2 dnf5 remove tmux              2024-11-03 09:12:00                 1
+";
+        let entries = DandifiedYUM::parse_history5(output, "dnf".to_string());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].time,
+            crate::history::local_to_utc("2024-11-03 09:12:00")
+        );
+        assert_eq!(entries[0].operation, "remove");
+        assert_eq!(entries[0].packages, ["tmux"]);
+        assert_eq!(entries[1].operation, "install");
+        assert_eq!(entries[1].packages, ["htop"]);
+        assert!(DandifiedYUM::parse_history5("No transactions", "dnf".to_string()).is_empty());
+    }
+
+    #[test]
+    fn test_parse_versionlock() {
+        let output = "Last metadata expiration check: 0:12:04 ago on Mon 01 Jul 2024.\nkernel-0:6.8.5-301.fc40.*\nperl-Digest-SHA-1:6.04-1.fc40.*\n";
+        let pkgs = DandifiedYUM::parse_versionlock(output, "dnf".to_string());
+        assert_eq!(
+            pkgs.iter().map(Package::name).collect::<Vec<_>>(),
+            ["kernel", "perl-Digest-SHA"]
+        );
+
+        let output = "# Added by 'versionlock add' command on 2024-07-01 10:00:00\nPackage name: kernel\nevr = 6.8.5-301.fc40\n";
+        let pkgs = DandifiedYUM::parse_versionlock(output, "dnf".to_string());
+        assert_eq!(pkgs, [Package::new("kernel", "dnf".to_string(), None)]);
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let out = "Last metadata expiration check: 0:12:01 ago on Mon 01 Jul 2024.
+Installed Packages
+Name         : bash
+Version      : 5.2.26
+Release      : 3.fc40
+Architecture : x86_64
+Size         : 8.1 M
+Source       : bash-5.2.26-3.fc40.src.rpm
+Repository   : @System
+Summary      : The GNU Bourne Again shell
+URL          : https://www.gnu.org/software/bash
+License      : GPL-3.0-or-later
+Description  : The GNU Bourne Again shell (Bash) is a shell or command language
+             : interpreter that is compatible with the Bourne shell (sh).
+
+Available Upgrades
+Name         : bash
+Version      : 5.2.32
+Release      : 1.fc40
+Architecture : x86_64
+Size         : 1.8 M
+Summary      : The GNU Bourne Again shell
+URL          : https://www.gnu.org/software/bash
+License      : GPL-3.0-or-later
+";
+        let info = DandifiedYUM::parse_info("bash", out);
+        assert_eq!(info.installed_version.as_deref(), Some("5.2.26-3.fc40"));
+        assert_eq!(info.candidate_version.as_deref(), Some("5.2.32-1.fc40"));
+        assert_eq!(info.size, Some(8_493_466));
+        assert_eq!(info.license.as_deref(), Some("GPL-3.0-or-later"));
+        assert_eq!(
+            info.description.as_deref(),
+            Some("The GNU Bourne Again shell")
+        );
+        assert_eq!(
+            info.homepage.as_deref(),
+            Some("https://www.gnu.org/software/bash")
+        );
+
+        let dnf5 = "Available packages
+Name           : hello
+Epoch          : 0
+Version        : 2.12.1
+Release        : 4.fc40
+Installed size : 178.2 KiB
+Summary        : Prints a familiar, friendly greeting
+";
+        let info = DandifiedYUM::parse_info("hello", dnf5);
+        assert_eq!(info.installed_version, None);
+        assert_eq!(info.candidate_version.as_deref(), Some("2.12.1-4.fc40"));
+        assert_eq!(info.size, Some(182_477));
+    }
 
     #[test]
     fn test_parse_pkg() {
-        let dnf = DandifiedYUM;
+        let dnf = DandifiedYUM::default();
         let input = r#"
 sudo.x86_64                                                                                   1.9.13-2.p2.fc38                                                                @koji-override-0
 systemd-libs.x86_64                                                                           253.10-1.fc38                                                                   @koji-override-0
@@ -149,11 +1520,499 @@ rubygem-mixlib-shellout-doc.noarch : Documentation for rubygem-mixlib-shellout"#
         );
     }
 
+    #[test]
+    fn test_parse_pkg_dnf5() {
+        let mut dnf = DandifiedYUM::default();
+        dnf.set_dnf5(true);
+        let search = "Updating and loading repositories:
+Repositories loaded.
+Matched fields: name (exact)
+ hello.x86_64: Prints a familiar, friendly greeting
+Matched fields: name
+ rubygem-mixlib-shellout-doc.noarch: Documentation for rubygem-mixlib-shellout
+";
+        let pkgs: Vec<_> = search.lines().filter_map(|l| dnf.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::from_str("dnf@hello.x86_64").unwrap(),
+                Package::from_str("dnf@rubygem-mixlib-shellout-doc.noarch").unwrap(),
+            ]
+        );
+
+        let list = "Installed packages
+sudo.x86_64          1.9.15-2.p5.fc41          <unknown>
+";
+        let pkgs: Vec<_> = list.lines().filter_map(|l| dnf.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [Package::from_str("dnf@sudo.x86_64@1.9.15-2.p5.fc41").unwrap()]
+        );
+
+        assert_eq!(
+            dnf.parse_pkg("sudo^1.9.15"),
+            Package::from_str("dnf@sudo@1.9.15").ok()
+        );
+        assert_eq!(dnf.cmd().get_program(), "dnf5");
+        assert_eq!(
+            dnf.consolidated(
+                Cmd::AddRepo,
+                None,
+                &["--from-repofile=https://example.com/x.repo"]
+            ),
+            [
+                "config-manager",
+                "addrepo",
+                "--from-repofile=https://example.com/x.repo"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conf_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let conf = dir.path().join("dnf.conf");
+        std::fs::write(
+            &conf,
+            "[main]\ngpgcheck=True\ninstallonly_limit = 3\n# max_parallel_downloads=5\n\n[other]\nmax_parallel_downloads=1\n",
+        )
+        .unwrap();
+
+        let get = |key: &str| DandifiedYUM::get_option_in(&conf, key).unwrap();
+        assert_eq!(get("installonly_limit").as_deref(), Some("3"));
+        // commented out and other sections don't count
+        assert_eq!(get("max_parallel_downloads"), None);
+
+        DandifiedYUM::set_option_in(&conf, "max_parallel_downloads", "10").unwrap();
+        DandifiedYUM::set_option_in(&conf, "installonly_limit", "5").unwrap();
+        assert_eq!(get("max_parallel_downloads").as_deref(), Some("10"));
+        assert_eq!(
+            std::fs::read_to_string(&conf).unwrap(),
+            "[main]\ngpgcheck=True\ninstallonly_limit=5\n# max_parallel_downloads=5\nmax_parallel_downloads=10\n\n[other]\nmax_parallel_downloads=1\n"
+        );
+
+        // a missing file is created
+        let new = dir.path().join("new.conf");
+        DandifiedYUM::set_option_in(&new, "fastestmirror", "True").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&new).unwrap(),
+            "[main]\nfastestmirror=True\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_repolist() {
+        let output = "Loaded plugins: fastestmirror
+repo id                 repo name                         status
+!base/7/x86_64          CentOS-7 - Base                   enabled: 10,072
+updates-testing         Fedora 40 - Test Updates          disabled
+legacy                  Legacy, enabled on demand         disabled
+repolist: 10,072
+";
+        let repos = DandifiedYUM::parse_repolist(output);
+        assert_eq!(repos.len(), 3);
+        assert_eq!(repos[0].name, "base");
+        assert!(repos[0].enabled);
+        assert_eq!(repos[1].name, "updates-testing");
+        assert!(!repos[1].enabled);
+        assert_eq!(repos[2].name, "legacy");
+        assert!(!repos[2].enabled);
+        assert!(DandifiedYUM::parse_repolist("").is_empty());
+    }
+
+    #[test]
+    fn test_remove_repo_section() {
+        let repo = "[docker-ce-stable]\nname=Docker CE Stable\nbaseurl=https://download.docker.com/linux/fedora/$releasever/$basearch/stable\n\n\
+                    [docker-ce-test]\nname=Docker CE Test\nenabled=0\n";
+        assert_eq!(
+            DandifiedYUM::remove_repo_section(repo, "docker-ce-test").as_deref(),
+            Some("[docker-ce-stable]\nname=Docker CE Stable\nbaseurl=https://download.docker.com/linux/fedora/$releasever/$basearch/stable\n\n")
+        );
+        assert_eq!(DandifiedYUM::remove_repo_section(repo, "fedora"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("docker-ce.repo");
+        std::fs::write(&file, repo).unwrap();
+        DandifiedYUM::remove_repo_in(&[&file], "docker-ce-test").unwrap();
+        assert!(file.exists());
+        DandifiedYUM::remove_repo_in(&[&file], "docker-ce-stable").unwrap();
+        assert!(!file.exists());
+        assert!(DandifiedYUM::remove_repo_in(&[&file], "docker-ce-stable").is_err());
+    }
+
+    #[test]
+    fn test_set_mirror() {
+        let dir = tempfile::tempdir().unwrap();
+        let fedora = dir.path().join("fedora.repo");
+        std::fs::write(
+            &fedora,
+            r#"[fedora]
+name=Fedora $releasever - $basearch
+#baseurl=http://download.example/pub/fedora/linux/releases/$releasever/Everything/$basearch/os/
+metalink=https://mirrors.fedoraproject.org/metalink?repo=fedora-$releasever&arch=$basearch
+enabled=1
+
+[docker-ce-stable]
+baseurl=https://download.docker.com/linux/fedora/$releasever/$basearch/stable
+"#,
+        )
+        .unwrap();
+
+        let files = [&fedora];
+        assert_eq!(
+            DandifiedYUM::list_mirrors_in(&files)
+                .iter()
+                .map(|u| u.host_str().unwrap())
+                .collect::<Vec<_>>(),
+            ["mirrors.fedoraproject.org", "download.docker.com"]
+        );
+
+        let mirror = "https://mirror.example.org".parse().unwrap();
+        DandifiedYUM::set_mirror_in(&files, &mirror).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&fedora).unwrap(),
+            r#"[fedora]
+name=Fedora $releasever - $basearch
+baseurl=https://mirror.example.org/pub/fedora/linux/releases/$releasever/Everything/$basearch/os/
+#metalink=https://mirrors.fedoraproject.org/metalink?repo=fedora-$releasever&arch=$basearch
+enabled=1
+
+[docker-ce-stable]
+baseurl=https://download.docker.com/linux/fedora/$releasever/$basearch/stable
+"#
+        );
+    }
+
+    #[test]
+    fn test_parse_plan() {
+        let output = r#"Dependencies resolved.
+================================================================================
+ Package          Arch       Version                 Repository          Size
+================================================================================
+Installing:
+ hello            x86_64     2.12.1-2.fc39           fedora              86 k
+Upgrading:
+ glibc            x86_64     2.38-16.fc39            updates            2.2 M
+     replacing  glibc.x86_64 2.38-14.fc39
+Removing dependent packages:
+ foo              noarch     1.0-1.fc39              @System             10 k
+
+Transaction Summary
+================================================================================
+Install  1 Package
+Upgrade  1 Package
+Remove   1 Package
+
+Total download size: 2.3 M
+Operation aborted."#;
+        let plan = DandifiedYUM::default().parse_plan(output);
+        assert_eq!(
+            plan.to_install,
+            [Package::new(
+                "hello",
+                "dnf".to_string(),
+                Some("2.12.1-2.fc39")
+            )]
+        );
+        assert_eq!(
+            plan.to_upgrade,
+            [Package::new(
+                "glibc",
+                "dnf".to_string(),
+                Some("2.38-16.fc39")
+            )]
+        );
+        assert_eq!(
+            plan.to_remove,
+            [Package::new("foo", "dnf".to_string(), Some("1.0-1.fc39"))]
+        );
+        assert_eq!(plan.download_size, Some(2411725));
+        assert_eq!(plan.installed_size, None);
+    }
+
+    #[test]
+    fn test_simulation_succeeded() {
+        let dnf = DandifiedYUM::default();
+        let failed = crate::common::synthetic_exit_status(false);
+        let declined = [
+            "Transaction Summary".to_string(),
+            "Operation aborted.".to_string(),
+        ];
+        assert!(dnf.simulation_succeeded(failed, &declined));
+        assert!(!dnf.simulation_succeeded(failed, &["No match for argument: foo".to_string()]));
+        assert!(dnf.simulation_succeeded(crate::common::synthetic_exit_status(true), &[]));
+    }
+
+    #[test]
+    fn test_plan_of_simulation() {
+        let dnf = DandifiedYUM::default();
+        let declined = std::process::Output {
+            status: crate::common::synthetic_exit_status(false),
+            stdout: b"Installing:\n hello  x86_64  2.12.1-2.fc39  fedora  86 k\n\
+                      Transaction Summary\nOperation aborted.\n"
+                .to_vec(),
+            stderr: vec![],
+        };
+        let plan = dnf.plan_of_simulation(&declined).unwrap();
+        assert_eq!(
+            plan.to_install,
+            [Package::new(
+                "hello",
+                "dnf".to_string(),
+                Some("2.12.1-2.fc39")
+            )]
+        );
+
+        let missing = std::process::Output {
+            status: crate::common::synthetic_exit_status(false),
+            stdout: vec![],
+            stderr: b"Error: Unable to find a match: foo\n".to_vec(),
+        };
+        let err = dnf.plan_of_simulation(&missing).unwrap_err();
+        assert!(err.to_string().contains("Unable to find a match: foo"));
+    }
+
+    #[test]
+    fn test_parse_plan_dnf5() {
+        let output = r#"Updating and loading repositories:
+Repositories loaded.
+Package              Arch   Version          Repository      Size
+Installing:
+ hello               x86_64 2.12.1-5.fc41    fedora     185.2 KiB
+Installing dependencies:
+ info                x86_64 7.1.1-1.fc41     fedora     613.5 KiB
+
+Transaction Summary:
+ Installing:         2 packages
+
+Total size of inbound packages is 86 KiB. Need to download 86 KiB.
+After this operation, 799 KiB extra will be used (install 799 KiB, remove 0 B).
+Operation aborted by the user."#;
+        let mut dnf = DandifiedYUM::default();
+        dnf.set_dnf5(true);
+        let plan = dnf.parse_plan(output);
+        assert_eq!(
+            plan.to_install,
+            [
+                Package::new("hello", "dnf".to_string(), Some("2.12.1-5.fc41")),
+                Package::new("info", "dnf".to_string(), Some("7.1.1-1.fc41")),
+            ]
+        );
+        assert!(plan.to_upgrade.is_empty() && plan.to_remove.is_empty());
+        assert_eq!(plan.download_size, Some(86 * 1024));
+        assert_eq!(plan.installed_size, Some(799 * 1024));
+    }
+
+    #[test]
+    fn test_parse_install_size() {
+        let output = r#"Transaction Summary
+================================================================================
+Install  1 Package
+
+Total download size: 20 M
+Installed size: 60 M
+Operation aborted."#;
+        assert_eq!(
+            DandifiedYUM::parse_install_size(output),
+            Some(60 * 1024 * 1024)
+        );
+        assert_eq!(
+            DandifiedYUM::parse_install_size("Total size: 1.5 k\nInstalled size: 512"),
+            Some(1536)
+        );
+        assert_eq!(DandifiedYUM::parse_install_size("Nothing to do."), None);
+    }
+
+    #[test]
+    fn test_parse_available() {
+        let dnf = DandifiedYUM::default();
+        let input = r#"Last metadata expiration check: 0:12:31 ago on Fri 18 Oct 2024 10:00:00 AM UTC.
+Available Packages
+0ad.x86_64                            0.0.26-8.fc39                      fedora
+0ad-data.noarch                       0.0.26-2.fc39                      fedora
+hello.x86_64                          2.12.1-2.fc39                      updates"#;
+        let pkgs: Vec<_> = input
+            .lines()
+            .filter_map(|l| dnf.parse_available(l))
+            .collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::from_str("dnf@0ad.x86_64@0.0.26-8.fc39").unwrap(),
+                Package::from_str("dnf@0ad-data.noarch@0.0.26-2.fc39").unwrap(),
+                Package::from_str("dnf@hello.x86_64@2.12.1-2.fc39").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transaction_installs_in_single_invocation() {
+        let dnf = DandifiedYUM::default();
+        let pkgs = [Package::from("hello"), Package::from("dnf@tree@2.1")];
+        assert_eq!(
+            dnf.consolidated_install(&pkgs),
+            [["install", "-y", "hello", "tree-2.1"]]
+        );
+    }
+
+    #[test]
+    fn test_reinstall_args() {
+        let dnf = DandifiedYUM::default();
+        assert_eq!(
+            crate::traits::consolidated_as(&dnf, &["reinstall"], Cmd::Install, None, &["hello"]),
+            ["reinstall", "-y", "hello"]
+        );
+    }
+
+    #[test]
+    fn test_force_ipv4() {
+        let mut dnf = DandifiedYUM::default();
+        assert!(!dnf
+            .consolidated::<&str>(Cmd::Sync, None, &[])
+            .contains(&"--setopt=ip_resolve=4".to_string()));
+
+        dnf.set_force_ipv4(true);
+        let pkg = Package::from("hello");
+        assert_eq!(
+            dnf.consolidated(Cmd::Install, Some(&pkg), &["hello"]),
+            ["install", "-y", "--setopt=ip_resolve=4", "hello"]
+        );
+        assert_eq!(
+            dnf.consolidated::<&str>(Cmd::Sync, None, &[]),
+            ["makecache", "--setopt=ip_resolve=4"]
+        );
+    }
+
+    #[test]
+    fn test_parse_verify_output() {
+        let pkg = Package::from_str("openssh-server").unwrap();
+        let output = |success: bool, stdout: &str, stderr: &str| std::process::Output {
+            status: crate::common::synthetic_exit_status(success),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        };
+        let out = output(
+            false,
+            "S.5....T.  c /etc/ssh/sshd_config\nmissing     /usr/sbin/sshd\n",
+            "",
+        );
+        assert_eq!(
+            DandifiedYUM::parse_verify_output(&out, &pkg).unwrap(),
+            [
+                Path::new("/etc/ssh/sshd_config"),
+                Path::new("/usr/sbin/sshd")
+            ]
+        );
+        let out = output(true, "", "");
+        assert!(DandifiedYUM::parse_verify_output(&out, &pkg)
+            .unwrap()
+            .is_empty());
+        let out = output(false, "package openssh-server is not installed\n", "");
+        let err = DandifiedYUM::parse_verify_output(&out, &pkg).unwrap_err();
+        assert!(err.to_string().contains("is not installed"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_rpm_verify() {
+        let out = "S.5....T.  c /etc/ssh/sshd_config
+missing     /usr/share/doc/bash/README
+Unsatisfied dependencies for foo-1.0-1.x86_64:
+.M.......    /usr/bin/sudo
+..5....T.  c /etc/ssh/ssh_config
+";
+        let owner = |path: &Path| match path.to_str().unwrap() {
+            "/etc/ssh/sshd_config" => "openssh-server",
+            "/usr/share/doc/bash/README" => "bash",
+            "/usr/bin/sudo" => "sudo",
+            _ => "openssh-server",
+        };
+        let modified = out.lines().filter_map(|line| {
+            let path = DandifiedYUM::parse_rpm_verify(line)?;
+            Some((owner(&path).to_string(), path))
+        });
+        let grouped = crate::common::group_by_package("dnf".to_string(), modified);
+        let grouped: Vec<_> = grouped
+            .iter()
+            .map(|(pkg, paths)| {
+                (
+                    pkg.name(),
+                    paths
+                        .iter()
+                        .map(|p| p.to_str().unwrap())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            grouped,
+            [
+                (
+                    "openssh-server",
+                    vec!["/etc/ssh/sshd_config", "/etc/ssh/ssh_config"]
+                ),
+                ("bash", vec!["/usr/share/doc/bash/README"]),
+                ("sudo", vec!["/usr/bin/sudo"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rpm_owners() {
+        let out = "/etc/ssh/moduli\topenssh
+/etc/ssh/ssh_config\topenssh-clients
+/usr/bin/ssh\topenssh-clients
+/etc/ssh/sshd_config\topenssh-server
+file /tmp/foo is not owned by any package
+/etc/ssh/ssh_config\topenssh-clients
+";
+        let paths = ["/etc/ssh/ssh_config", "/etc/ssh/sshd_config", "/tmp/foo"].map(PathBuf::from);
+        let owners = DandifiedYUM::parse_rpm_owners(out, &paths);
+        assert_eq!(owners.len(), 2);
+        assert_eq!(owners[&paths[0]], "openssh-clients");
+        assert_eq!(owners[&paths[1]], "openssh-server");
+    }
+
+    #[test]
+    fn test_parse_rpm_checksig() {
+        assert!(DandifiedYUM::parse_rpm_checksig(
+            "hello-2.12.1-5.fc41.x86_64.rpm: digests signatures OK\n"
+        ));
+        assert!(DandifiedYUM::parse_rpm_checksig(
+            "hello.rpm: rsa sha1 (md5) pgp md5 OK\n"
+        ));
+        assert!(!DandifiedYUM::parse_rpm_checksig(
+            "hello-2.12.1-5.fc41.x86_64.rpm: digests SIGNATURES NOT OK\n"
+        ));
+        assert!(!DandifiedYUM::parse_rpm_checksig(
+            "hello.rpm: (SHA1) DSA sha1 md5 (GPG) NOT OK (MISSING KEYS: GPG#12345678)\n"
+        ));
+        // unsigned package
+        assert!(!DandifiedYUM::parse_rpm_checksig("hello.rpm: digests OK\n"));
+        assert!(!DandifiedYUM::parse_rpm_checksig(""));
+    }
+
+    #[test]
+    fn test_protected_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("dnf.conf"), "dnf\n").unwrap();
+        std::fs::write(
+            dir.path().join("systemd.conf"),
+            "# systemd is required\nsystemd\n\nsystemd-udev\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("README"), "ignored\n").unwrap();
+
+        let protected = DandifiedYUM::protected_packages(&[dir.path(), Path::new("/nonexistent")]);
+        let mut protected: Vec<_> = protected.into_iter().collect();
+        protected.sort();
+        assert_eq!(protected, ["dnf", "systemd", "systemd-udev"]);
+    }
+
     // Requires elevated privilages to work
     #[cfg(target_os = "linux")]
     #[test]
     fn test_dnf() {
-        dnf_yum_cases(crate::managers::DandifiedYUM)
+        dnf_yum_cases(crate::managers::DandifiedYUM::default())
     }
     #[cfg(target_os = "linux")]
     fn dnf_yum_cases(man: impl crate::PackageManager) {
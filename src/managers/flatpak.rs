@@ -1,13 +1,61 @@
 use std::{fmt::Display, process::Command};
 
 use crate::{
-    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+    AvailablePackageManager, Cmd, Package, PackageKind, PackageManager, PackageManagerCommands,
+    PkgFormat, Repository,
 };
 
 /// Wrapper for flatpak, which provides sandboxed, cross-distribution,
 /// and dependency-free application packaging for linux.
+///
+/// Applications and the runtimes they run on are both installed, their kind
+/// is set on the listed packages, see [``PackageKind``]. Packages are
+/// installed from the remote that provides them, or from the one given with
+/// [``Flatpak::set_remote``].
 #[derive(Debug, Default)]
-pub struct Flatpak;
+pub struct Flatpak {
+    remote: Option<String>,
+}
+
+impl Flatpak {
+    /// Remote that outdated packages are looked up in when none is set.
+    pub const DEFAULT_REMOTE: &'static str = "flathub";
+
+    /// Install from `remote`, e.g. `flathub`, and look up outdated packages
+    /// in it.
+    pub fn set_remote(&mut self, remote: String) {
+        self.remote = Some(remote);
+    }
+
+    /// Parse the output of `flatpak remotes --columns=name,url,options`,
+    /// whose options include `disabled` for disabled remotes.
+    pub(crate) fn parse_remotes(output: &str) -> Vec<Repository> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut cols = line.split('\t').map(str::trim);
+                let name = cols.next().filter(|n| !n.is_empty())?;
+                let url = cols.next().filter(|u| !u.is_empty());
+                let disabled = cols
+                    .next()
+                    .is_some_and(|o| o.split(',').any(|o| o == "disabled"));
+                Some(Repository {
+                    enabled: !disabled,
+                    ..Repository::new(name, url)
+                })
+            })
+            .collect()
+    }
+
+    /// Flag selecting apps or runtimes, if `pkg` is one of them.
+    fn kind_flag(pkg: Option<&Package>) -> Option<&'static str> {
+        match pkg.and_then(Package::kind)? {
+            PackageKind::App => Some("--app"),
+            PackageKind::Runtime => Some("--runtime"),
+            _ => None,
+        }
+    }
+}
 
 impl PackageManager for Flatpak {
     fn pkg_delimiter(&self) -> char {
@@ -45,6 +93,52 @@ impl PackageManager for Flatpak {
             _ => None,
         }
     }
+
+    fn supports_kind(&self, kind: PackageKind) -> bool {
+        matches!(
+            kind,
+            PackageKind::Package | PackageKind::App | PackageKind::Runtime
+        )
+    }
+
+    /// Lists apps and runtimes separately to set their kind.
+    fn list_installed(&self) -> Vec<Package> {
+        let mut pkgs = vec![];
+        for kind in [PackageKind::App, PackageKind::Runtime] {
+            let flag = format!("--{kind}");
+            let out = self.exec_cmds(&self.consolidated(Cmd::List, None, &[flag]));
+            pkgs.extend(self.parse_output(&out.stdout).into_iter().map(|mut pkg| {
+                pkg.set_kind(kind);
+                pkg
+            }));
+        }
+        pkgs
+    }
+
+    /// Adds a remote given as its name and its URL, e.g. `flathub
+    /// https://dl.flathub.org/repo/flathub.flatpakrepo`.
+    fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
+        anyhow::ensure!(repo.len() == 2, "flatpak remotes are added as <name> <url>");
+        let s = self.exec_cmds_status(&self.consolidated(Cmd::AddRepo, None, repo), None);
+        anyhow::ensure!(s.success(), "failed to add remote {}", repo[0]);
+        Ok(())
+    }
+
+    /// Uses `flatpak remotes`, including disabled remotes.
+    fn list_repos(&self) -> anyhow::Result<Vec<Repository>> {
+        let out = self.exec_cmds(
+            &["remotes", "--show-disabled", "--columns=name,url,options"].map(String::from),
+        );
+        anyhow::ensure!(out.status.success(), "flatpak remotes failed");
+        Ok(Self::parse_remotes(&String::from_utf8_lossy(&out.stdout)))
+    }
+
+    /// Uses `flatpak remote-delete`.
+    fn remove_repo(&self, name: &str) -> anyhow::Result<()> {
+        let s = self.exec_cmds_status(&["remote-delete", name], None);
+        anyhow::ensure!(s.success(), "failed to remove remote {name}");
+        Ok(())
+    }
 }
 
 impl Display for Flatpak {
@@ -58,26 +152,48 @@ impl PackageManagerCommands for Flatpak {
         Command::new("flatpak")
     }
 
-    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
-        match cmd {
+    fn get_cmds(&self, cmd: Cmd, pkg: Option<&Package>) -> Vec<String> {
+        let remote = self.remote.as_deref();
+        let mut cmds = match cmd {
             Cmd::Install => vec!["install"],
             Cmd::Uninstall => vec!["uninstall"],
             Cmd::Update => vec!["update"],
             Cmd::UpdateAll => vec!["update"],
             Cmd::List => vec!["list"],
-            Cmd::Sync => vec![],
+            Cmd::Sync | Cmd::Clean => vec![],
+            // removes unused runtimes
+            Cmd::Autoremove => vec!["uninstall", "--unused"],
             Cmd::AddRepo => vec!["remote-add"],
             Cmd::Search => vec!["search"],
-            Cmd::Outdated => vec!["remote-ls", "--updates", "flathub"],
+            Cmd::Outdated => vec![
+                "remote-ls",
+                "--updates",
+                remote.unwrap_or(Self::DEFAULT_REMOTE),
+            ],
+        };
+        if let (Cmd::Install | Cmd::Uninstall | Cmd::Update, Some(flag)) =
+            (cmd, Self::kind_flag(pkg))
+        {
+            cmds.push(flag);
+        }
+        // the remote comes before the ref, e.g. `install flathub org.gnome.Maps`
+        if let (Cmd::Install, Some(remote)) = (cmd, remote) {
+            cmds.push(remote);
+        }
+        cmds.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll | Cmd::Autoremove => {
+                vec!["-y".to_string()]
+            }
+            _ => vec![],
         }
-        .iter()
-        .map(|x| x.to_string())
-        .collect()
     }
 
     fn get_flags(&self, cmd: Cmd) -> Vec<String> {
         match cmd {
-            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll => vec!["-y"],
             Cmd::AddRepo => vec!["--if-not-exists"],
             _ => vec![],
         }
@@ -93,7 +209,7 @@ mod tests {
     use std::str::FromStr;
 
     use super::Flatpak;
-    use crate::{Package, PackageManager, PackageManagerCommands};
+    use crate::{Cmd, Package, PackageKind, PackageManager, PackageManagerCommands};
 
     #[test]
     fn test_parse_pkg() {
@@ -101,7 +217,7 @@ mod tests {
 Flatpak Developer Demo	Flatpak Developer Demo	org.flatpak.qtdemo	1.1.3	stable	flathub
 Blender	Free and open source 3D creation suite	org.blender.Blender	4.1	stable	fedora,flathub
 Inkscape	Vector Graphics Editor	org.inkscape.Inkscape	1.3.2	stable	fedora,flathub"#;
-        let flatpak = Flatpak;
+        let flatpak = Flatpak::default();
         let mut iter = input.lines().filter_map(|l| flatpak.parse_pkg(l));
         assert_eq!(
             iter.next(),
@@ -117,12 +233,75 @@ Inkscape	Vector Graphics Editor	org.inkscape.Inkscape	1.3.2	stable	fedora,flathu
         );
     }
 
+    #[test]
+    fn test_parse_remotes() {
+        let output = "flathub\thttps://dl.flathub.org/repo/\tsystem\n\
+                      fedora\toci+https://registry.fedoraproject.org\tsystem,oci,disabled\n";
+        let repos = Flatpak::parse_remotes(output);
+        assert_eq!(repos.len(), 2);
+        assert_eq!(repos[0].name, "flathub");
+        assert_eq!(
+            repos[0].url.as_deref(),
+            Some("https://dl.flathub.org/repo/")
+        );
+        assert!(repos[0].enabled);
+        assert_eq!(repos[1].name, "fedora");
+        assert!(!repos[1].enabled);
+    }
+
+    #[test]
+    fn test_cmds() {
+        let mut flatpak = Flatpak::default();
+        let mut pkg = Package::from("org.gnome.Platform");
+        pkg.set_kind(PackageKind::Runtime);
+        let fmt = flatpak.reformat_for_command(&mut pkg);
+        assert_eq!(
+            flatpak.consolidated(Cmd::Install, Some(&pkg), &[fmt.clone()]),
+            ["install", "--runtime", "-y", "org.gnome.Platform"]
+        );
+
+        let mut app = Package::from("org.gnome.Maps");
+        app.set_kind(PackageKind::App);
+        let mut sdk = Package::from("org.gnome.Sdk");
+        sdk.set_kind(PackageKind::Runtime);
+        assert_eq!(
+            flatpak.consolidated_install(&[pkg.clone(), app, sdk]),
+            [
+                vec![
+                    "install",
+                    "--runtime",
+                    "-y",
+                    "org.gnome.Platform",
+                    "org.gnome.Sdk"
+                ],
+                vec!["install", "--app", "-y", "org.gnome.Maps"],
+            ]
+        );
+
+        flatpak.set_remote("flathub-beta".to_string());
+        assert_eq!(
+            flatpak.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            [
+                "install",
+                "--runtime",
+                "flathub-beta",
+                "-y",
+                "org.gnome.Platform"
+            ]
+        );
+        assert_eq!(
+            flatpak.consolidated::<&str>(Cmd::Outdated, None, &[]),
+            ["remote-ls", "--updates", "flathub-beta"]
+        );
+        assert!(flatpak.add_repo(&vec!["flathub".to_string()]).is_err());
+    }
+
     // Requires elevated privilages to work
     #[cfg(target_os = "linux")]
     #[tracing_test::traced_test]
     #[test]
     fn test_flatpak() {
-        let flatpak = crate::managers::Flatpak;
+        let flatpak = crate::managers::Flatpak::default();
         if !flatpak.is_available() {
             println!("flatpak is not available");
             return;
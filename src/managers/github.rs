@@ -0,0 +1,675 @@
+//! GitHub releases pseudo package manager
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
+
+use anyhow::Context;
+
+use crate::{
+    AvailablePackageManager, Cmd, Operation, Package, PackageManager, PackageManagerCommands,
+    PkgFormat, Unsupported,
+};
+
+const API: &str = "https://api.github.com";
+
+/// Pseudo package manager for tools that are only shipped as GitHub release
+/// assets (similar to [eget](https://github.com/zyedidia/eget)).
+///
+/// Packages are named `owner/repo` and the version, if any, is the release tag.
+/// The asset matching the host OS and architecture is downloaded and the
+/// executables in it are installed to `~/.local/bin`.
+///
+/// # Idiosyncracies
+/// There is no native database of installed packages, so installed binaries
+/// are tracked in a manifest in mpm's data directory. This manager is never
+/// picked by [``crate::MetaPackageManager::new_default``]; it must be
+/// selected explicitly.
+#[derive(Debug, Default)]
+pub struct GithubReleases;
+
+/// A GitHub release as returned by the releases API.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// A downloadable asset of a [`Release`].
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+/// The installed binaries of a release, recorded in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct ManifestEntry {
+    tag: String,
+    binaries: Vec<PathBuf>,
+}
+
+/// Installed releases keyed by `owner/repo`.
+type Manifest = BTreeMap<String, ManifestEntry>;
+
+impl GithubReleases {
+    /// Directory where release binaries are installed.
+    pub fn bin_dir() -> anyhow::Result<PathBuf> {
+        let dirs = directories::BaseDirs::new().context("can not determine home directory")?;
+        Ok(dirs.home_dir().join(".local").join("bin"))
+    }
+
+    /// Location of the manifest of installed release binaries.
+    fn manifest_path() -> anyhow::Result<PathBuf> {
+        let dirs = directories::ProjectDirs::from("", "", "mpm")
+            .context("can not determine data directory")?;
+        Ok(dirs.data_dir().join("github.json"))
+    }
+
+    fn read_manifest() -> anyhow::Result<Manifest> {
+        let path = Self::manifest_path()?;
+        if !path.is_file() {
+            return Ok(Manifest::new());
+        }
+        let contents = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&contents).with_context(|| format!("invalid manifest {path:?}"))
+    }
+
+    fn write_manifest(manifest: &Manifest) -> anyhow::Result<()> {
+        let path = Self::manifest_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(manifest)?)?;
+        Ok(())
+    }
+
+    /// Query the releases API for the given tag, or the latest release.
+    pub fn fetch_release(repo: &str, tag: Option<&str>) -> anyhow::Result<Release> {
+        let url = release_url(repo, tag);
+        let text = api_get(url.as_str())?;
+        serde_json::from_str(&text).with_context(|| format!("invalid release data from {url}"))
+    }
+
+    /// Download the asset of `repo` matching this host and install the
+    /// executables in it.
+    fn install_release(&self, pkg: &Package) -> anyhow::Result<()> {
+        let repo = repo_slug(pkg)?;
+        let release = Self::fetch_release(&repo, pkg.version())?;
+        let asset = select_asset(&release, std::env::consts::OS, std::env::consts::ARCH)
+            .with_context(|| {
+                format!(
+                    "no asset of {repo}@{} matches {}/{}",
+                    release.tag_name,
+                    std::env::consts::OS,
+                    std::env::consts::ARCH
+                )
+            })?;
+        tracing::info!(
+            "Selected asset {} of {repo}@{}",
+            asset.name,
+            release.tag_name
+        );
+
+        let dir = tempfile::Builder::new().prefix("mpm-").tempdir()?;
+        let mut download: Package = asset.browser_download_url.parse()?;
+        let archive = download.make_available_on_disk(Some(&dir.path().join(&asset.name)), true)?;
+
+        // a bare binary asset is named after the repository
+        let name = repo.rsplit('/').next().context("missing repository name")?;
+        let binary_name = format!("{name}{}", std::env::consts::EXE_SUFFIX);
+        let bin_dir = Self::bin_dir()?;
+        std::fs::create_dir_all(&bin_dir)?;
+        let binaries = extract_executables(&archive, &binary_name, &bin_dir)?;
+
+        let mut manifest = Self::read_manifest()?;
+        let previous = manifest.insert(
+            repo,
+            ManifestEntry {
+                tag: release.tag_name,
+                binaries: binaries.clone(),
+            },
+        );
+        // binaries the new release no longer ships
+        for stale in previous.into_iter().flat_map(|entry| entry.binaries) {
+            if !binaries.contains(&stale) && stale.exists() {
+                std::fs::remove_file(&stale)?;
+            }
+        }
+        Self::write_manifest(&manifest)
+    }
+
+    /// Remove an installed release binary.
+    fn uninstall_release(&self, pkg: &Package) -> anyhow::Result<()> {
+        let repo = repo_slug(pkg)?;
+        let mut manifest = Self::read_manifest()?;
+        let entry = manifest
+            .remove(&repo)
+            .with_context(|| format!("{repo} is not installed"))?;
+        for binary in entry.binaries.iter().filter(|b| b.exists()) {
+            std::fs::remove_file(binary)?;
+        }
+        Self::write_manifest(&manifest)
+    }
+
+    /// Search repositories, returning at most `per_page` results if given.
+    fn search_repos(&self, query: &str, per_page: Option<usize>) -> anyhow::Result<Vec<Package>> {
+        #[derive(serde::Deserialize)]
+        struct SearchResult {
+            items: Vec<SearchItem>,
+        }
+        #[derive(serde::Deserialize)]
+        struct SearchItem {
+            full_name: String,
+        }
+
+        let mut url =
+            url::Url::parse(&format!("{API}/search/repositories")).expect("valid search url");
+        url.query_pairs_mut().append_pair("q", query);
+        if let Some(per_page) = per_page {
+            url.query_pairs_mut()
+                .append_pair("per_page", &per_page.to_string());
+        }
+        let text = api_get(url.as_str()).context("failed to search GitHub")?;
+        let result =
+            serde_json::from_str::<SearchResult>(&text).context("invalid search result")?;
+        Ok(result
+            .items
+            .into_iter()
+            .map(|item| Package::new(&item.full_name, self.pkg_manager_name(), None))
+            .collect())
+    }
+}
+
+impl PackageManager for GithubReleases {
+    fn pkg_delimiter(&self) -> char {
+        '@'
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Github.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    /// The API takes no flags, so `args` are ignored.
+    fn search_with_args(&self, query: &str, _args: &[String]) -> Vec<Package> {
+        self.search_repos(query, None).unwrap_or_else(|e| {
+            tracing::error!("{e:#}");
+            vec![]
+        })
+    }
+
+    fn try_search(&self, query: &str) -> anyhow::Result<Vec<Package>> {
+        self.search_repos(query, None)
+    }
+
+    /// Results are limited by the API, which returns at most 100 per page.
+    fn search_limited(&self, query: &str, limit: usize) -> anyhow::Result<Vec<Package>> {
+        let mut pkgs = self.search_repos(query, Some(limit.min(100)))?;
+        pkgs.truncate(limit);
+        Ok(pkgs)
+    }
+
+    fn sync(&self) -> ExitStatus {
+        tracing::debug!("GitHub releases have nothing to sync");
+        crate::common::synthetic_exit_status(true)
+    }
+
+    fn update_all(&self, _interactive: bool) -> ExitStatus {
+        let mut success = true;
+        for outdated in self.list_outdated() {
+            let mut pkg = Package::new(outdated.name(), self.pkg_manager_name(), None);
+            success &= self
+                .execute_pkg_command(&mut pkg, Operation::Update, false)
+                .success();
+        }
+        crate::common::synthetic_exit_status(success)
+    }
+
+    fn list_installed(&self) -> Vec<Package> {
+        match Self::read_manifest() {
+            Ok(manifest) => manifest
+                .iter()
+                .map(|(repo, entry)| Package::new(repo, self.pkg_manager_name(), Some(&entry.tag)))
+                .collect(),
+            Err(e) => {
+                tracing::error!("Failed to read manifest: {e:#}");
+                vec![]
+            }
+        }
+    }
+
+    fn bin_dirs(&self) -> Vec<PathBuf> {
+        Self::bin_dir().into_iter().collect()
+    }
+
+    fn list_outdated(&self) -> Vec<Package> {
+        self.list_installed()
+            .into_iter()
+            .filter_map(|pkg| {
+                let latest = Self::fetch_release(pkg.name(), None)
+                    .map_err(|e| tracing::warn!("Failed to query {}: {e:#}", pkg.name()))
+                    .ok()?;
+                (Some(latest.tag_name.as_str()) != pkg.version()).then(|| {
+                    Package::new(pkg.name(), self.pkg_manager_name(), Some(&latest.tag_name))
+                })
+            })
+            .collect()
+    }
+
+    fn execute_pkg_command(
+        &self,
+        pkg: &mut Package,
+        op: Operation,
+        _interactive: bool,
+    ) -> ExitStatus {
+        tracing::debug!("> Operation {op:?} on {pkg:?}...");
+        if crate::common::is_dry_run() {
+            let op = format!("{op:?}").to_lowercase();
+            println!("Would {op} the release of {}", pkg.name());
+            return crate::common::synthetic_exit_status(true);
+        }
+        let result = match op {
+            Operation::Install | Operation::Update => self.install_release(pkg),
+            Operation::Uninstall => self.uninstall_release(pkg),
+        };
+        if let Err(e) = &result {
+            tracing::error!("{op:?} of {} failed: {e:#}", pkg.name());
+        }
+        crate::common::synthetic_exit_status(result.is_ok())
+    }
+
+    /// Every release is downloaded and unpacked separately.
+    fn install_many(&self, pkgs: &[Package], interactive: bool) -> ExitStatus {
+        crate::traits::install_each(self, pkgs, interactive)
+    }
+
+    fn add_repo(&self, _repo: &Vec<String>) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "adding repositories").into())
+    }
+}
+
+impl PackageManagerCommands for GithubReleases {
+    /// Release archives are unpacked with `tar`, so it has to be available.
+    fn cmd(&self) -> Command {
+        Command::new("tar")
+    }
+
+    fn get_cmds(&self, _cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        vec![]
+    }
+
+    fn needs_sudo(&self) -> bool {
+        false
+    }
+}
+
+impl Display for GithubReleases {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("GitHub Releases")
+    }
+}
+
+/// GET a GitHub API url and return the response body.
+fn api_get(url: &str) -> anyhow::Result<String> {
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(concat!("mpm/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let mut req = client
+        .get(url)
+        .header("Accept", "application/vnd.github+json");
+    if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+        req = req.bearer_auth(token);
+    }
+    let resp = req.send()?;
+    resp.error_for_status_ref()?;
+    Ok(resp.text()?)
+}
+
+/// API url of the release of `repo` with the given tag, or of the latest one.
+fn release_url(repo: &str, tag: Option<&str>) -> url::Url {
+    let mut url = url::Url::parse(API).expect("valid API url");
+    {
+        let mut segments = url.path_segments_mut().expect("API url has a path");
+        segments
+            .push("repos")
+            .extend(repo.split('/'))
+            .push("releases");
+        match tag {
+            Some(tag) => segments.extend(["tags", tag]),
+            None => segments.push("latest"),
+        };
+    }
+    url
+}
+
+/// Extract `owner/repo` from a package.
+fn repo_slug(pkg: &Package) -> anyhow::Result<String> {
+    let name = pkg.name().trim_matches('/');
+    let mut parts = name.split('/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(owner), Some(repo), None) if !owner.is_empty() && !repo.is_empty() => {
+            Ok(name.to_string())
+        }
+        _ => anyhow::bail!("expected a package of the form `owner/repo`, got `{name}`"),
+    }
+}
+
+/// Substrings used in asset names for the given `std::env::consts::OS`.
+fn os_aliases(os: &str) -> Vec<&str> {
+    match os {
+        "linux" => vec!["linux"],
+        "macos" => vec!["darwin", "macos", "apple", "osx"],
+        // "win" alone would also match "darwin".
+        "windows" => vec!["windows", "win64", "win32"],
+        other => vec![other],
+    }
+}
+
+/// Substrings used in asset names for the given `std::env::consts::ARCH`.
+fn arch_aliases(os: &str, arch: &str) -> Vec<String> {
+    let mut aliases: Vec<String> = match arch {
+        "x86_64" => vec!["x86_64", "amd64", "x64"],
+        "aarch64" => vec!["aarch64", "arm64"],
+        "x86" => vec!["i686", "i386", "386"],
+        "arm" => vec!["armv7", "armhf"],
+        other => vec![other],
+    }
+    .into_iter()
+    .map(String::from)
+    .collect();
+    if os == "macos" {
+        aliases.push("universal".to_string());
+    }
+    aliases
+}
+
+/// Checksums, signatures and native installers are never installed directly.
+fn is_auxiliary(name: &str) -> bool {
+    const SKIP: &[&str] = &[
+        ".sha256",
+        ".sha512",
+        ".sha256sum",
+        ".md5",
+        ".sig",
+        ".asc",
+        ".pem",
+        ".sbom",
+        ".txt",
+        ".json",
+        ".deb",
+        ".rpm",
+        ".apk",
+        ".msi",
+        ".dmg",
+        ".pkg",
+    ];
+    SKIP.iter().any(|ext| name.ends_with(ext))
+}
+
+/// Lower is better: archives `tar` can unpack first, then bare binaries.
+fn asset_rank(name: &str) -> u8 {
+    if [".tar.gz", ".tgz", ".tar.xz", ".tar.bz2"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+    {
+        0
+    } else if name.ends_with(".zip") {
+        1
+    } else {
+        2
+    }
+}
+
+/// Pick the asset of a release that matches the given OS and architecture
+/// (as named by `std::env::consts`).
+pub fn select_asset<'a>(release: &'a Release, os: &str, arch: &str) -> Option<&'a ReleaseAsset> {
+    let oses = os_aliases(os);
+    let arches = arch_aliases(os, arch);
+    release
+        .assets
+        .iter()
+        .filter(|asset| {
+            let name = asset.name.to_lowercase();
+            !is_auxiliary(&name)
+                && oses.iter().any(|o| name.contains(o))
+                && arches.iter().any(|a| name.contains(a.as_str()))
+        })
+        .min_by_key(|asset| asset_rank(&asset.name.to_lowercase()))
+}
+
+/// Unpack `archive` into a private temporary directory next to it.
+fn unpack(archive: &Path) -> anyhow::Result<tempfile::TempDir> {
+    let file_name = archive
+        .file_name()
+        .and_then(|f| f.to_str())
+        .context("invalid archive path")?
+        .to_lowercase();
+    let parent = archive.parent().context("invalid archive path")?;
+    let dir = tempfile::Builder::new()
+        .prefix("mpm-extract-")
+        .tempdir_in(parent)?;
+    let mut cmd = if file_name.ends_with(".zip") && cfg!(target_os = "linux") {
+        let mut cmd = Command::new("unzip");
+        cmd.arg("-o").arg(archive).arg("-d").arg(dir.path());
+        cmd
+    } else {
+        let mut cmd = Command::new("tar");
+        cmd.arg("-xf").arg(archive).arg("-C").arg(dir.path());
+        cmd
+    };
+    let status = cmd.status()?;
+    anyhow::ensure!(status.success(), "failed to unpack {archive:?}");
+    Ok(dir)
+}
+
+/// Whether `archive` is a bare binary rather than an archive.
+fn is_bare_binary(archive: &Path) -> bool {
+    archive
+        .file_name()
+        .and_then(|f| f.to_str())
+        .is_some_and(|f| asset_rank(&f.to_lowercase()) == 2)
+}
+
+/// Copy `src` to `target` and make it executable.
+fn install_executable(src: &Path, target: &Path) -> anyhow::Result<()> {
+    std::fs::copy(src, target)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(target, std::fs::Permissions::from_mode(0o755))?;
+    }
+    tracing::info!("Installed {target:?}");
+    Ok(())
+}
+
+/// Unpack `archive` (or copy it, if it is the binary itself) and install the
+/// file called `binary_name` to `target`.
+pub(crate) fn extract_binary(
+    archive: &Path,
+    binary_name: &str,
+    target: &Path,
+) -> anyhow::Result<()> {
+    if is_bare_binary(archive) {
+        return install_executable(archive, target);
+    }
+    let dir = unpack(archive)?;
+    let found = find_file(dir.path(), binary_name)?
+        .with_context(|| format!("{binary_name} not found in {archive:?}"))?;
+    install_executable(&found, target)
+}
+
+/// Unpack `archive` and install every executable in it to `bin_dir`. A bare
+/// binary is installed as `binary_name`. Returns the installed paths.
+fn extract_executables(
+    archive: &Path,
+    binary_name: &str,
+    bin_dir: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    if is_bare_binary(archive) {
+        let target = bin_dir.join(binary_name);
+        install_executable(archive, &target)?;
+        return Ok(vec![target]);
+    }
+    let dir = unpack(archive)?;
+    let mut executables = vec![];
+    find_executables(dir.path(), &mut executables)?;
+    anyhow::ensure!(
+        !executables.is_empty(),
+        "no executable found in {archive:?}"
+    );
+    let mut installed = vec![];
+    for exe in executables {
+        let target = bin_dir.join(exe.file_name().context("invalid executable path")?);
+        install_executable(&exe, &target)?;
+        installed.push(target);
+    }
+    Ok(installed)
+}
+
+/// Recursively look for a file called `name` under `dir`.
+fn find_file(dir: &Path, name: &str) -> anyhow::Result<Option<PathBuf>> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, name)? {
+                return Ok(Some(found));
+            }
+        } else if path.file_name().and_then(|f| f.to_str()) == Some(name) {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+/// Recursively collect the executable files under `dir`, sorted by path.
+fn find_executables(dir: &Path, found: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            find_executables(&path, found)?;
+        } else if is_executable(&path) {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELEASE: &str = r#"{
+  "tag_name": "14.1.0",
+  "assets": [
+    {"name": "ripgrep-14.1.0-aarch64-apple-darwin.tar.gz", "browser_download_url": "https://github.com/BurntSushi/ripgrep/releases/download/14.1.0/ripgrep-14.1.0-aarch64-apple-darwin.tar.gz"},
+    {"name": "ripgrep-14.1.0-aarch64-apple-darwin.tar.gz.sha256", "browser_download_url": "https://github.com/BurntSushi/ripgrep/releases/download/14.1.0/ripgrep-14.1.0-aarch64-apple-darwin.tar.gz.sha256"},
+    {"name": "ripgrep-14.1.0-aarch64-unknown-linux-gnu.tar.gz", "browser_download_url": "https://github.com/BurntSushi/ripgrep/releases/download/14.1.0/ripgrep-14.1.0-aarch64-unknown-linux-gnu.tar.gz"},
+    {"name": "ripgrep-14.1.0-x86_64-apple-darwin.tar.gz", "browser_download_url": "https://github.com/BurntSushi/ripgrep/releases/download/14.1.0/ripgrep-14.1.0-x86_64-apple-darwin.tar.gz"},
+    {"name": "ripgrep-14.1.0-x86_64-pc-windows-msvc.zip", "browser_download_url": "https://github.com/BurntSushi/ripgrep/releases/download/14.1.0/ripgrep-14.1.0-x86_64-pc-windows-msvc.zip"},
+    {"name": "ripgrep-14.1.0-x86_64-unknown-linux-musl.tar.gz", "browser_download_url": "https://github.com/BurntSushi/ripgrep/releases/download/14.1.0/ripgrep-14.1.0-x86_64-unknown-linux-musl.tar.gz"},
+    {"name": "ripgrep-14.1.0-x86_64-unknown-linux-musl.tar.gz.sha256", "browser_download_url": "https://github.com/BurntSushi/ripgrep/releases/download/14.1.0/ripgrep-14.1.0-x86_64-unknown-linux-musl.tar.gz.sha256"},
+    {"name": "ripgrep_14.1.0-1_amd64.deb", "browser_download_url": "https://github.com/BurntSushi/ripgrep/releases/download/14.1.0/ripgrep_14.1.0-1_amd64.deb"}
+  ]
+}"#;
+
+    #[test]
+    fn test_select_asset() {
+        let release: Release = serde_json::from_str(RELEASE).unwrap();
+        assert_eq!(release.tag_name, "14.1.0");
+
+        let pick = |os, arch| select_asset(&release, os, arch).map(|a| a.name.as_str());
+        assert_eq!(
+            pick("linux", "x86_64"),
+            Some("ripgrep-14.1.0-x86_64-unknown-linux-musl.tar.gz")
+        );
+        assert_eq!(
+            pick("linux", "aarch64"),
+            Some("ripgrep-14.1.0-aarch64-unknown-linux-gnu.tar.gz")
+        );
+        assert_eq!(
+            pick("macos", "aarch64"),
+            Some("ripgrep-14.1.0-aarch64-apple-darwin.tar.gz")
+        );
+        assert_eq!(
+            pick("macos", "x86_64"),
+            Some("ripgrep-14.1.0-x86_64-apple-darwin.tar.gz")
+        );
+        assert_eq!(
+            pick("windows", "x86_64"),
+            Some("ripgrep-14.1.0-x86_64-pc-windows-msvc.zip")
+        );
+        assert_eq!(pick("linux", "riscv64"), None);
+    }
+
+    #[test]
+    fn test_release_url() {
+        assert_eq!(
+            release_url("BurntSushi/ripgrep", None).as_str(),
+            "https://api.github.com/repos/BurntSushi/ripgrep/releases/latest"
+        );
+        assert_eq!(
+            release_url("BurntSushi/ripgrep", Some("14.1.0")).as_str(),
+            "https://api.github.com/repos/BurntSushi/ripgrep/releases/tags/14.1.0"
+        );
+        assert_eq!(
+            release_url("owner/repo", Some("v1/../../x?y#z")).as_str(),
+            "https://api.github.com/repos/owner/repo/releases/tags/v1%2F..%2F..%2Fx%3Fy%23z"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_executables() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // ripgrep ships `rg`, not `ripgrep`
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("ripgrep-14.1.0-x86_64-unknown-linux-musl");
+        std::fs::create_dir_all(root.join("doc")).unwrap();
+        for (name, mode) in [("rg", 0o755), ("README.md", 0o644), ("doc/rg.1", 0o644)] {
+            let path = root.join(name);
+            std::fs::write(&path, "").unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let mut found = vec![];
+        find_executables(dir.path(), &mut found).unwrap();
+        assert_eq!(found, vec![root.join("rg")]);
+    }
+
+    #[test]
+    fn test_repo_slug() {
+        assert_eq!(
+            repo_slug(&"BurntSushi/ripgrep".into()).unwrap(),
+            "BurntSushi/ripgrep"
+        );
+        assert_eq!(
+            repo_slug(&"github@BurntSushi/ripgrep@14.1.0".into()).unwrap(),
+            "BurntSushi/ripgrep"
+        );
+        assert!(repo_slug(&"ripgrep".into()).is_err());
+    }
+}
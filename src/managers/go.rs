@@ -0,0 +1,360 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    AvailablePackageManager, Cmd, Operation, Package, PackageManager, PackageManagerCommands,
+    PkgFormat, Unsupported,
+};
+
+/// Wrapper for the binaries installed with `go install`.
+///
+/// [go install - The Go Programming Language](https://go.dev/ref/mod#go-install)
+///
+/// # Idiosyncracies
+/// Binaries are named by the package path they are built from, e.g.
+/// `golang.org/x/tools/gopls`, and installed into `$GOBIN`, or
+/// `$GOPATH/bin` if it is not set. Go keeps no record of them, so they are
+/// listed with the module versions that `go version -m` reads from the
+/// binaries, and uninstalling removes the binary. There is no search: a
+/// module is looked up by its exact path with `go list -m`.
+#[derive(Debug, Default)]
+pub struct GoBin;
+
+/// A binary in the `bin` directory of Go, see [``GoBin::parse_version_m``].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GoBinary {
+    pub file: PathBuf,
+    /// Package path the binary was built from.
+    pub path: String,
+    /// Main module of the binary.
+    pub module: String,
+    pub version: String,
+}
+
+impl GoBin {
+    /// Directory `go install` installs into, given the output of `go env
+    /// GOBIN GOPATH`.
+    pub(crate) fn parse_bin_dir(env: &str) -> Option<PathBuf> {
+        let mut lines = env.lines().map(str::trim);
+        let gobin = lines.next().unwrap_or_default();
+        if !gobin.is_empty() {
+            return Some(PathBuf::from(gobin));
+        }
+        let gopath = lines.next().filter(|p| !p.is_empty())?;
+        let first = std::env::split_paths(gopath).next()?;
+        Some(first.join("bin"))
+    }
+
+    /// Parse the output of `go version -m <dir>`, e.g.
+    ///
+    /// ```text
+    /// /home/user/go/bin/gopls: go1.22.0
+    ///         path    golang.org/x/tools/gopls
+    ///         mod     golang.org/x/tools/gopls        v0.15.1 h1:...
+    ///         dep     github.com/BurntSushi/toml      v1.3.2  h1:...
+    /// ```
+    pub(crate) fn parse_version_m(output: &str) -> Vec<GoBinary> {
+        let mut binaries = vec![];
+        let mut file = None;
+        let mut path = None;
+        for line in output.lines() {
+            if !line.starts_with(char::is_whitespace) {
+                file = line.rsplit_once(": ").map(|(f, _go)| PathBuf::from(f));
+                path = None;
+                continue;
+            }
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["path", p] => path = Some(p.to_string()),
+                ["mod", module, version, ..] => {
+                    if let (Some(file), Some(path)) = (file.take(), path.take()) {
+                        binaries.push(GoBinary {
+                            file,
+                            path,
+                            module: module.to_string(),
+                            version: version.to_string(),
+                        });
+                    }
+                }
+                _ => (),
+            }
+        }
+        binaries
+    }
+
+    /// Directory `go install` installs into.
+    fn bin_dir(&self) -> Option<PathBuf> {
+        let env = self.exec_cmds(&["env".to_string(), "GOBIN".to_string(), "GOPATH".to_string()]);
+        Self::parse_bin_dir(&String::from_utf8_lossy(&env.stdout))
+    }
+
+    /// The binaries that `go install` installed.
+    pub(crate) fn binaries(&self) -> Vec<GoBinary> {
+        let Some(dir) = self.bin_dir() else {
+            tracing::warn!("Failed to determine the bin directory of go");
+            return vec![];
+        };
+        if !dir.is_dir() {
+            return vec![];
+        }
+        let out = self.exec_cmds(&self.consolidated(Cmd::List, None, &[dir.display().to_string()]));
+        Self::parse_version_m(&String::from_utf8_lossy(&out.stdout))
+    }
+
+    /// Remove the binary built from the package path or named `name`.
+    fn remove(&self, name: &str) -> bool {
+        let binaries: Vec<GoBinary> = self
+            .binaries()
+            .into_iter()
+            .filter(|bin| {
+                bin.path == name || bin.file.file_stem() == Some(std::ffi::OsStr::new(name))
+            })
+            .collect();
+        if binaries.is_empty() {
+            tracing::error!("{name} is not installed with go install");
+            return false;
+        }
+        binaries.iter().all(|bin| Self::remove_file(&bin.file))
+    }
+
+    fn remove_file(file: &Path) -> bool {
+        if crate::common::is_dry_run() {
+            println!("Would remove {}", file.display());
+            return true;
+        }
+        tracing::info!("Removing {}", file.display());
+        match std::fs::remove_file(file) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::error!("Failed to remove {}: {e}", file.display());
+                false
+            }
+        }
+    }
+}
+
+impl PackageManager for GoBin {
+    fn pkg_delimiter(&self) -> char {
+        '@'
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Go.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    /// `go install` requires a version, `latest` unless one is given.
+    fn reformat_for_command(&self, pkg: &mut Package) -> String {
+        format!("{}@{}", pkg.name(), pkg.version().unwrap_or("latest"))
+    }
+
+    /// Modules are looked up by their exact path.
+    fn search_query(&self, query: &str) -> String {
+        format!("{query}@latest")
+    }
+
+    /// Parses the modules of `go list -m` like `golang.org/x/tools/gopls
+    /// v0.15.1`.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [name, version] if version.starts_with('v') => {
+                Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Uninstalling removes the binary, see [``GoBin``], unless this is a
+    /// dry run.
+    fn execute_pkg_command(
+        &self,
+        pkg: &mut Package,
+        op: Operation,
+        interactive: bool,
+    ) -> std::process::ExitStatus {
+        let (cmd, arg) = match op {
+            Operation::Install => (Cmd::Install, self.reformat_for_command(pkg)),
+            Operation::Update => (Cmd::Update, format!("{}@latest", pkg.name())),
+            Operation::Uninstall => {
+                return crate::common::synthetic_exit_status(self.remove(pkg.name()));
+            }
+        };
+        let cmds = self.consolidated(cmd, Some(pkg), &[arg]);
+        self.exec_cmds_status(&cmds, Some(interactive))
+    }
+
+    /// `go install` only installs several packages at once if they are in
+    /// the same module.
+    fn install_many(&self, pkgs: &[Package], interactive: bool) -> std::process::ExitStatus {
+        crate::traits::install_each(self, pkgs, interactive)
+    }
+
+    fn list_installed(&self) -> Vec<Package> {
+        self.binaries()
+            .iter()
+            .map(|bin| {
+                Package::new(
+                    &bin.path,
+                    self.pkg_manager_name(),
+                    Some(bin.version.as_str()),
+                )
+            })
+            .collect()
+    }
+
+    fn bin_dirs(&self) -> Vec<PathBuf> {
+        self.bin_dir().into_iter().collect()
+    }
+
+    /// Looks up the latest version of the module of every binary.
+    fn list_outdated(&self) -> Vec<Package> {
+        self.binaries()
+            .into_iter()
+            .filter_map(|bin| {
+                let latest = self.search(&bin.module).into_iter().next()?;
+                (latest.version() != Some(bin.version.as_str()))
+                    .then(|| Package::new(&bin.path, self.pkg_manager_name(), latest.version()))
+            })
+            .collect()
+    }
+
+    fn sync(&self) -> std::process::ExitStatus {
+        tracing::debug!("go fetches modules on demand");
+        crate::common::synthetic_exit_status(true)
+    }
+
+    /// Reinstalls the latest version of every binary.
+    fn update_all(&self, interactive: bool) -> std::process::ExitStatus {
+        let mut status = crate::common::synthetic_exit_status(true);
+        for bin in self.binaries() {
+            let cmds = self.consolidated(Cmd::UpdateAll, None, &[format!("{}@latest", bin.path)]);
+            let s = self.exec_cmds_status(&cmds, Some(interactive));
+            if !s.success() {
+                status = s;
+            }
+        }
+        status
+    }
+
+    fn add_repo(&self, _repo: &Vec<String>) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "adding module proxies").into())
+    }
+}
+
+impl PackageManagerCommands for GoBin {
+    fn cmd(&self) -> Command {
+        Command::new("go")
+    }
+
+    /// `go version` prints the version, `go --version` fails.
+    fn is_available(&self) -> bool {
+        self.cmd()
+            .arg("version")
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    fn version(&self) -> Option<String> {
+        let output = self.cmd().arg("version").output().ok()?;
+        output.status.success().then_some(())?;
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn needs_sudo(&self) -> bool {
+        false
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Update | Cmd::UpdateAll => vec!["install"],
+            // the binary is removed, see `GoBin::execute_pkg_command`
+            Cmd::Uninstall => vec![],
+            Cmd::List => vec!["version", "-m"],
+            Cmd::Sync | Cmd::AddRepo | Cmd::Clean | Cmd::Autoremove => vec![],
+            Cmd::Search | Cmd::Outdated => vec!["list", "-m"],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+}
+
+impl Display for GoBin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("go install")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version_m() {
+        let out = "/home/user/go/bin/gopls: go1.22.0
+\tpath\tgolang.org/x/tools/gopls
+\tmod\tgolang.org/x/tools/gopls\tv0.15.1\th1:abc=
+\tdep\tgithub.com/BurntSushi/toml\tv1.3.2\th1:def=
+\tbuild\t-compiler=gc
+/home/user/go/bin/stringer: go1.22.0
+\tpath\tgolang.org/x/tools/cmd/stringer
+\tmod\tgolang.org/x/tools\tv0.18.0\th1:ghi=
+/home/user/go/bin/README: could not read Go build info from /home/user/go/bin/README
+";
+        assert_eq!(
+            GoBin::parse_version_m(out),
+            [
+                GoBinary {
+                    file: PathBuf::from("/home/user/go/bin/gopls"),
+                    path: "golang.org/x/tools/gopls".to_string(),
+                    module: "golang.org/x/tools/gopls".to_string(),
+                    version: "v0.15.1".to_string(),
+                },
+                GoBinary {
+                    file: PathBuf::from("/home/user/go/bin/stringer"),
+                    path: "golang.org/x/tools/cmd/stringer".to_string(),
+                    module: "golang.org/x/tools".to_string(),
+                    version: "v0.18.0".to_string(),
+                },
+            ]
+        );
+
+        assert_eq!(
+            GoBin::parse_bin_dir("/opt/gobin\n/home/user/go\n"),
+            Some(PathBuf::from("/opt/gobin"))
+        );
+        assert_eq!(
+            GoBin::parse_bin_dir("\n/home/user/go\n"),
+            Some(PathBuf::from("/home/user/go/bin"))
+        );
+        assert_eq!(GoBin::parse_bin_dir("\n\n"), None);
+    }
+
+    #[test]
+    fn test_cmds() {
+        let go = GoBin;
+        let mut pkg = Package::from("golang.org/x/tools/gopls");
+        let fmt = go.reformat_for_command(&mut pkg);
+        assert_eq!(
+            go.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["install", "golang.org/x/tools/gopls@latest"]
+        );
+        assert_eq!(
+            go.consolidated(Cmd::Search, None, &[go.search_query("golang.org/x/tools")]),
+            ["list", "-m", "golang.org/x/tools@latest"]
+        );
+        assert_eq!(
+            go.parse_pkg("golang.org/x/tools v0.18.0"),
+            Some(Package::new(
+                "golang.org/x/tools",
+                "go".to_string(),
+                Some("v0.18.0")
+            ))
+        );
+    }
+}
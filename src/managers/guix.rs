@@ -0,0 +1,191 @@
+use std::{fmt::Display, process::Command};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+    Unsupported,
+};
+
+/// Wrapper for the packages of a user's GNU Guix profile.
+///
+/// [GNU Guix Reference Manual](https://guix.gnu.org/manual/en/html_node/Invoking-guix-package.html)
+///
+/// # Idiosyncracies
+/// Guix installs into the user's profile and never needs elevated
+/// privileges. `guix pull` updates Guix and its package definitions, and
+/// `guix search` prints its results as recutils records. Channels are
+/// configured in Scheme, so they cannot be added by mpm.
+#[derive(Debug, Default)]
+pub struct Guix;
+
+impl PackageManager for Guix {
+    fn pkg_delimiter(&self) -> char {
+        '@'
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Guix.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    /// `guix search` and `guix upgrade` take regular expressions.
+    fn search_uses_regex(&self) -> bool {
+        true
+    }
+
+    /// Parses the lines of `guix package --list-installed` like `hello
+    /// 2.12.1 out /gnu/store/<hash>-hello-2.12.1` and of `guix upgrade
+    /// --dry-run` like `hello 2.10 → 2.12.1`, where the newer version is used.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        let (name, version) = match cols[..] {
+            [name, _, "→", newer, ..] => (name, newer),
+            [name, version, _output, store_path, ..] if store_path.starts_with("/gnu/store/") => {
+                (name, version)
+            }
+            _ => return None,
+        };
+        Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+    }
+
+    /// Also parses the `name` and `version` fields of the records printed by
+    /// `guix search`.
+    fn parse_output(&self, out: &[u8]) -> Vec<Package> {
+        let out = String::from_utf8_lossy(out);
+        let mut pkgs = vec![];
+        let mut name = None;
+        for line in out.lines() {
+            if let Some(n) = line.strip_prefix("name: ") {
+                name = Some(n.trim().to_string());
+            } else if let Some(version) = line.strip_prefix("version: ") {
+                if let Some(name) = name.take() {
+                    pkgs.push(Package::new(
+                        &name,
+                        self.pkg_manager_name(),
+                        Some(version.trim()),
+                    ));
+                }
+            } else if let Some(pkg) = self.parse_pkg(line) {
+                pkgs.push(pkg);
+            }
+        }
+        pkgs
+    }
+
+    /// `guix upgrade --dry-run` prints the upgrades to stderr.
+    fn list_outdated(&self) -> Vec<Package> {
+        let out = self.exec_cmds(&self.consolidated::<&str>(Cmd::Outdated, None, &[]));
+        self.parse_output(&[out.stdout, out.stderr].concat())
+    }
+
+    fn add_repo(&self, _repo: &Vec<String>) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "adding channels").into())
+    }
+}
+
+impl PackageManagerCommands for Guix {
+    fn cmd(&self) -> Command {
+        Command::new("guix")
+    }
+
+    fn needs_sudo(&self) -> bool {
+        false
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            Cmd::Install => vec!["install"],
+            Cmd::Uninstall => vec!["remove"],
+            Cmd::Update | Cmd::UpdateAll => vec!["upgrade"],
+            Cmd::List => vec!["package", "--list-installed"],
+            Cmd::Sync => vec!["pull"],
+            Cmd::AddRepo | Cmd::Clean | Cmd::Autoremove => vec![],
+            Cmd::Search => vec!["search"],
+            Cmd::Outdated => vec!["upgrade", "--dry-run"],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+}
+
+impl Display for Guix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("GNU Guix")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        let guix = Guix;
+        let search = "name: hello
+version: 2.12.1
+outputs:
++ out: everything
+systems: x86_64-linux i686-linux
+dependencies:
+location: gnu/packages/base.scm:92:2
+homepage: https://www.gnu.org/software/hello/
+license: GPL 3+
+synopsis: Hello, GNU world: An example GNU package
+description: GNU Hello prints the message \"Hello, world!\" and then exits.
++ It serves as an example of standard GNU coding practices.
+relevance: 15
+
+name: hello-rs
+version: 0.1.0
+outputs:
++ out: everything
+relevance: 6
+";
+        assert_eq!(
+            guix.parse_output(search.as_bytes()),
+            [
+                Package::new("hello", "guix".to_string(), Some("2.12.1")),
+                Package::new("hello-rs", "guix".to_string(), Some("0.1.0")),
+            ]
+        );
+
+        let installed =
+            "hello\t2.12.1\tout\t/gnu/store/0vmxx2h2x5dpsx8pdncxgglspbw1a1zc-hello-2.12.1
+glibc-locales\t2.35\tout\t/gnu/store/8a2v6ahbbgkk6sv5m1kqyrs2xfsf9bk4-glibc-locales-2.35
+";
+        assert_eq!(
+            guix.parse_output(installed.as_bytes()),
+            [
+                Package::new("hello", "guix".to_string(), Some("2.12.1")),
+                Package::new("glibc-locales", "guix".to_string(), Some("2.35")),
+            ]
+        );
+
+        let outdated = "The following package would be upgraded:
+   hello 2.10 → 2.12.1\t/gnu/store/0vmxx2h2x5dpsx8pdncxgglspbw1a1zc-hello-2.12.1
+";
+        assert_eq!(
+            guix.parse_output(outdated.as_bytes()),
+            [Package::new("hello", "guix".to_string(), Some("2.12.1"))]
+        );
+    }
+
+    #[test]
+    fn test_cmds() {
+        let guix = Guix;
+        assert!(!guix.needs_sudo());
+        let mut pkg = Package::new("hello", "guix".to_string(), Some("2.12.1"));
+        let fmt = guix.reformat_for_command(&mut pkg);
+        assert_eq!(
+            guix.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["install", "hello@2.12.1"]
+        );
+        assert_eq!(
+            guix.consolidated::<&str>(Cmd::List, None, &[]),
+            ["package", "--list-installed"]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{BufWriter, Write},
+    process::Command,
+};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+};
+
+/// Wrapper for MacPorts, a package manager for macOS that builds ports from
+/// source or installs them from prebuilt archives.
+///
+/// [The MacPorts Project](https://www.macports.org/)
+///
+/// # Idiosyncracies
+/// MacPorts only installs the latest version of a port, so versions are
+/// ignored when installing. Installed versions include the revision and the
+/// variants, e.g. `@8.5.0_0+ssl`; the variants are dropped from
+/// [``Package::version``]. Port sources are added to
+/// [``MacPorts::SOURCES``].
+#[derive(Debug, Default)]
+pub struct MacPorts;
+
+impl MacPorts {
+    /// Configuration file listing the port sources, one URL per line.
+    pub const SOURCES: &'static str = "/opt/local/etc/macports/sources.conf";
+}
+
+impl PackageManager for MacPorts {
+    fn pkg_delimiter(&self) -> char {
+        '@'
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::MacPorts.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    fn reformat_for_command(&self, pkg: &mut Package) -> String {
+        if pkg.version().is_some() {
+            tracing::warn!("MacPorts cannot install {} at a version", pkg.name());
+        }
+        pkg.name().to_string()
+    }
+
+    /// Parses the lines of `port installed` like `curl @8.5.0_0+ssl
+    /// (active)`, of `port outdated` like `curl 8.4.0_0 < 8.5.0_0`, where the
+    /// newer version is used, and of `port search --line` like `curl 8.5.0
+    /// net www Tool for transferring files with URL syntax`.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        let version = match cols[..] {
+            [_, _, "<", newer, ..] => newer,
+            [_, version, ..] => version.strip_prefix('@').unwrap_or(version),
+            _ => return None,
+        };
+        if !version.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        // drop the variants
+        let version = version.split('+').next()?;
+        Some(Package::new(
+            cols[0],
+            self.pkg_manager_name(),
+            Some(version),
+        ))
+    }
+
+    fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
+        self.ensure_sudo();
+        let sources = fs::File::options().append(true).open(Self::SOURCES)?;
+        let mut writer = BufWriter::new(sources);
+        for url in repo {
+            writeln!(writer, "{url}")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl PackageManagerCommands for MacPorts {
+    fn cmd(&self) -> Command {
+        Command::new("port")
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        // `-N` (non-interactive) and `-q` (no headings) must precede the action
+        match cmd {
+            Cmd::Install => vec!["-N", "install"],
+            Cmd::Uninstall => vec!["-N", "uninstall"],
+            Cmd::Update => vec!["-N", "upgrade"],
+            Cmd::UpdateAll => vec!["-N", "upgrade", "outdated"],
+            Cmd::List => vec!["-q", "installed"],
+            Cmd::Sync => vec!["selfupdate"],
+            // written to `MacPorts::SOURCES`, see `MacPorts::add_repo`
+            Cmd::AddRepo => vec![],
+            Cmd::Search => vec!["-q", "search", "--line"],
+            Cmd::Outdated => vec!["-q", "outdated"],
+            Cmd::Clean | Cmd::Autoremove => vec![],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+}
+
+impl Display for MacPorts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MacPorts")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let port = MacPorts;
+        let installed = "The following ports are currently installed:
+  curl @8.5.0_0+ssl (active)
+  git @2.43.0_0+credential_osxkeychain+diff_highlight+doc+pcre+perl5_34 (active)
+";
+        let pkgs: Vec<_> = installed
+            .lines()
+            .filter_map(|l| port.parse_pkg(l))
+            .collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("curl", "macports".to_string(), Some("8.5.0_0")),
+                Package::new("git", "macports".to_string(), Some("2.43.0_0")),
+            ]
+        );
+
+        let outdated = "The following installed ports are outdated:
+curl                           8.4.0_0 < 8.5.0_0
+";
+        let pkgs: Vec<_> = outdated.lines().filter_map(|l| port.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [Package::new(
+                "curl",
+                "macports".to_string(),
+                Some("8.5.0_0")
+            )]
+        );
+
+        let search = "curl\t8.5.0\tnet www\tTool for transferring files with URL syntax\n";
+        assert_eq!(
+            port.parse_pkg(search),
+            Some(Package::new("curl", "macports".to_string(), Some("8.5.0")))
+        );
+        assert_eq!(port.parse_pkg("No match for foo found"), None);
+    }
+
+    #[test]
+    fn test_cmds() {
+        let port = MacPorts;
+        let mut pkg = Package::new("curl", "macports".to_string(), Some("8.5.0_0"));
+        let fmt = port.reformat_for_command(&mut pkg);
+        assert_eq!(
+            port.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["-N", "install", "curl"]
+        );
+        assert_eq!(
+            port.consolidated::<&str>(Cmd::UpdateAll, None, &[]),
+            ["-N", "upgrade", "outdated"]
+        );
+    }
+}
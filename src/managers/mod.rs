@@ -4,23 +4,61 @@
 //! If the module is empty, it means that no package manager feature flag is
 //! enabled.
 
+use std::collections::BTreeMap;
+
 use ambassador::Delegate;
 use anyhow::Context;
 use strum::IntoEnumIterator;
 
+pub mod apk;
 pub mod apt;
+pub mod aur;
 pub mod brew;
+pub mod cargo;
 pub mod choco;
+pub mod conda;
 pub mod dnf;
 pub mod flatpak;
+pub mod github;
+pub mod go;
+pub mod guix;
+pub mod macports;
+pub mod msys2;
+pub mod nala;
+pub mod nix;
+pub mod oneget;
+pub mod opkg;
+pub mod pip;
+pub mod portage;
+pub mod scoop;
+pub mod swupd;
+pub mod xbps;
 pub mod yum;
 pub mod zypper;
 
+use apk::AlpinePackageKeeper;
 use apt::AdvancedPackageTool;
+use aur::AurHelper;
 use brew::Homebrew;
+use cargo::Cargo;
 use choco::Chocolatey;
+use conda::Conda;
 use dnf::DandifiedYUM;
 use flatpak::Flatpak;
+use github::GithubReleases;
+use go::GoBin;
+use guix::Guix;
+use macports::MacPorts;
+use msys2::Msys2;
+use nala::Nala;
+use nix::Nix;
+use oneget::OneGet;
+use opkg::Opkg;
+use pip::Pip;
+use portage::Portage;
+use scoop::Scoop;
+use swupd::Swupd;
+use xbps::Xbps;
 use yum::YellowdogUpdaterModified;
 use zypper::Zypper;
 
@@ -31,13 +69,31 @@ use crate::{common::*, traits::*};
 #[delegate(crate::PackageManagerCommands)]
 #[delegate(crate::PackageManager)]
 pub enum MetaPackageManager {
+    Apk(AlpinePackageKeeper),
+    Nala(Nala),
     Apt(AdvancedPackageTool),
+    Aur(AurHelper),
     Brew(Homebrew),
+    Cargo(Cargo),
     Choco(Chocolatey),
+    Conda(Conda),
     Dnf(DandifiedYUM),
     Flatpak(Flatpak),
+    Github(GithubReleases),
+    Go(GoBin),
+    Guix(Guix),
+    MacPorts(MacPorts),
+    Msys2(Msys2),
+    Nix(Nix),
+    Opkg(Opkg),
+    Pip(Pip),
+    Portage(Portage),
+    Scoop(Scoop),
+    Swupd(Swupd),
+    Xbps(Xbps),
     Yum(YellowdogUpdaterModified),
     Zypper(Zypper),
+    OneGet(OneGet),
 }
 
 impl MetaPackageManager {
@@ -45,13 +101,31 @@ impl MetaPackageManager {
     pub fn new(manager: AvailablePackageManager) -> Self {
         tracing::debug!("Creating meta-package-manager interface for {manager:?}");
         match manager {
-            AvailablePackageManager::Apt => Self::Apt(AdvancedPackageTool),
+            AvailablePackageManager::Apk => Self::Apk(AlpinePackageKeeper),
+            AvailablePackageManager::Apt => Self::Apt(AdvancedPackageTool::default()),
+            AvailablePackageManager::Aur => Self::Aur(AurHelper::preferred()),
             AvailablePackageManager::Brew => Self::Brew(Homebrew),
-            AvailablePackageManager::Choco => Self::Choco(Chocolatey),
-            AvailablePackageManager::Dnf => Self::Dnf(DandifiedYUM),
-            AvailablePackageManager::Flatpak => Self::Flatpak(Flatpak),
+            AvailablePackageManager::Cargo => Self::Cargo(Cargo),
+            AvailablePackageManager::Choco => Self::Choco(Chocolatey::default()),
+            AvailablePackageManager::Conda => Self::Conda(Conda::preferred()),
+            AvailablePackageManager::Dnf => Self::Dnf(DandifiedYUM::preferred()),
+            AvailablePackageManager::Flatpak => Self::Flatpak(Flatpak::default()),
+            AvailablePackageManager::Github => Self::Github(GithubReleases),
+            AvailablePackageManager::Go => Self::Go(GoBin),
+            AvailablePackageManager::Guix => Self::Guix(Guix),
+            AvailablePackageManager::MacPorts => Self::MacPorts(MacPorts),
+            AvailablePackageManager::Msys2 => Self::Msys2(Msys2::from_env()),
+            AvailablePackageManager::Nala => Self::Nala(Nala::default()),
+            AvailablePackageManager::Nix => Self::Nix(Nix),
+            AvailablePackageManager::OneGet => Self::OneGet(OneGet),
+            AvailablePackageManager::Opkg => Self::Opkg(Opkg),
+            AvailablePackageManager::Pip => Self::Pip(Pip::preferred()),
+            AvailablePackageManager::Portage => Self::Portage(Portage),
+            AvailablePackageManager::Scoop => Self::Scoop(Scoop),
+            AvailablePackageManager::Swupd => Self::Swupd(Swupd),
+            AvailablePackageManager::Xbps => Self::Xbps(Xbps),
             AvailablePackageManager::Yum => Self::Yum(YellowdogUpdaterModified::default()),
-            AvailablePackageManager::Zypper => Self::Zypper(Zypper),
+            AvailablePackageManager::Zypper => Self::Zypper(Zypper::default()),
         }
     }
 
@@ -60,7 +134,10 @@ impl MetaPackageManager {
     pub fn new_if_available(manager: AvailablePackageManager) -> anyhow::Result<Self> {
         let mpm = Self::new(manager);
         if !mpm.is_available() {
-            anyhow::bail!("failed to run {mpm} command")
+            return Err(crate::error::Error::ManagerUnavailable(format!(
+                "failed to run {mpm} command"
+            ))
+            .into());
         }
         Ok(mpm)
     }
@@ -68,37 +145,353 @@ impl MetaPackageManager {
     /// Try to find the system package manager.
     ///
     /// First enum variant is given the highest priority, second, the second
-    /// highest, and so on.
+    /// highest, and so on. Pseudo package managers like
+    /// [``AvailablePackageManager::Github``], language package managers like
+    /// [``AvailablePackageManager::Cargo``] and
+    /// [``AvailablePackageManager::Aur``], which only covers the AUR, are
+    /// never picked.
+    ///
+    /// The order can be changed with the [`PRIORITY_ENV`] environment
+    /// variable, see [`ManagerPriority`].
     pub fn new_default() -> anyhow::Result<Self> {
-        AvailablePackageManager::iter()
+        Self::new_default_with(&ManagerPriority::from_env()?.unwrap_or_default())
+    }
+
+    /// Like [``MetaPackageManager::new_default``], but try the package
+    /// managers in the order of `priority`.
+    pub fn new_default_with(priority: &ManagerPriority) -> anyhow::Result<Self> {
+        priority
+            .candidates()
+            .into_iter()
             .find_map(|m| Self::new_if_available(m).ok())
-            .context("no supported package manager found")
+            .ok_or_else(|| {
+                crate::error::Error::ManagerUnavailable(
+                    "no supported package manager found".to_string(),
+                )
+                .into()
+            })
     }
+
+    /// Force the package manager to connect to mirrors over IPv4 only. Returns
+    /// [``Unsupported``] for package managers without such an option.
+    pub fn set_force_ipv4(&mut self, force_ipv4: bool) -> anyhow::Result<()> {
+        match self {
+            Self::Apt(m) => m.set_force_ipv4(force_ipv4),
+            Self::Dnf(m) => m.set_force_ipv4(force_ipv4),
+            Self::Yum(m) => m.set_force_ipv4(force_ipv4),
+            Self::Zypper(m) => m.set_force_ipv4(force_ipv4),
+            m => return Err(Unsupported::new(m.pkg_manager_name(), "forcing IPv4").into()),
+        }
+        Ok(())
+    }
+
+    /// Scope the operations to the named environment of package managers that
+    /// manage environments, e.g. conda's `--name <name>`. Returns
+    /// [``Unsupported``] for package managers without environments.
+    pub fn set_env(&mut self, env: String) -> anyhow::Result<()> {
+        match self {
+            Self::Conda(m) => {
+                m.set_env(env);
+                Ok(())
+            }
+            m => Err(Unsupported::new(m.pkg_manager_name(), "scoping to an environment").into()),
+        }
+    }
+
+    /// Install from the named remote, e.g. flatpak's `flathub`. Returns
+    /// [``Unsupported``] for other package managers.
+    pub fn set_remote(&mut self, remote: String) -> anyhow::Result<()> {
+        match self {
+            Self::Flatpak(m) => {
+                m.set_remote(remote);
+                Ok(())
+            }
+            m => Err(Unsupported::new(m.pkg_manager_name(), "installing from a remote").into()),
+        }
+    }
+
+    /// Set chocolatey's package parameters (`--params`) and native installer
+    /// arguments (`--install-arguments`). Returns [``Unsupported``] for other
+    /// package managers.
+    pub fn set_choco_params(
+        &mut self,
+        params: Option<String>,
+        install_arguments: Option<String>,
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::Choco(m) => {
+                m.set_params(params);
+                m.set_install_arguments(install_arguments);
+                Ok(())
+            }
+            m => Err(Unsupported::new(m.pkg_manager_name(), "package parameters").into()),
+        }
+    }
+}
+
+/// Normalize a package name for comparison across package managers, e.g.
+/// `Firefox.x86_64` and `firefox` are the same package.
+pub(crate) fn normalize_pkg_name(name: &str) -> String {
+    const ARCH_SUFFIXES: [&str; 8] = [
+        ".x86_64", ".aarch64", ".i686", ".noarch", ":amd64", ":arm64", ":i386", ":all",
+    ];
+    let name = name.to_lowercase();
+    ARCH_SUFFIXES
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+/// Returns the normalized names of packages that are installed by more than
+/// one of the given package managers, along with those package managers.
+pub fn cross_manager_duplicates(
+    listings: impl IntoIterator<Item = (AvailablePackageManager, Vec<Package>)>,
+) -> Vec<(String, Vec<AvailablePackageManager>)> {
+    let mut owners: BTreeMap<String, Vec<AvailablePackageManager>> = BTreeMap::new();
+    for (manager, pkgs) in listings {
+        for pkg in pkgs {
+            let entry = owners.entry(normalize_pkg_name(pkg.name())).or_default();
+            if !entry.contains(&manager) {
+                entry.push(manager.clone());
+            }
+        }
+    }
+    owners.into_iter().filter(|(_, m)| m.len() > 1).collect()
+}
+
+/// Cross-reference the installed packages of all available package managers
+/// and report packages owned by more than one of them. Updating such a
+/// package through one manager leaves the other copy stale.
+pub fn find_cross_manager_duplicates() -> Vec<(String, Vec<AvailablePackageManager>)> {
+    // Nala lists the packages of apt
+    let managers = AvailablePackageManager::iter().filter(|m| *m != AvailablePackageManager::Nala);
+    cross_manager_duplicates(managers.filter_map(|manager| {
+        let mpm = MetaPackageManager::new(manager.clone());
+        mpm.is_available().then(|| (manager, mpm.list_installed()))
+    }))
 }
 
 impl std::fmt::Display for MetaPackageManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            MetaPackageManager::Brew(_) => Homebrew.fmt(f),
-            MetaPackageManager::Choco(_) => Chocolatey.fmt(f),
-            MetaPackageManager::Apt(_) => AdvancedPackageTool.fmt(f),
-            MetaPackageManager::Dnf(_) => DandifiedYUM.fmt(f),
-            MetaPackageManager::Flatpak(_) => Flatpak.fmt(f),
-            MetaPackageManager::Zypper(_) => Zypper.fmt(f),
-            MetaPackageManager::Yum(_) => YellowdogUpdaterModified::default().fmt(f),
+            MetaPackageManager::Brew(m) => m.fmt(f),
+            MetaPackageManager::Cargo(m) => m.fmt(f),
+            MetaPackageManager::Choco(m) => m.fmt(f),
+            MetaPackageManager::Conda(m) => m.fmt(f),
+            MetaPackageManager::Apk(m) => m.fmt(f),
+            MetaPackageManager::Apt(m) => m.fmt(f),
+            MetaPackageManager::Aur(m) => m.fmt(f),
+            MetaPackageManager::Dnf(m) => m.fmt(f),
+            MetaPackageManager::Flatpak(m) => m.fmt(f),
+            MetaPackageManager::Github(m) => m.fmt(f),
+            MetaPackageManager::Go(m) => m.fmt(f),
+            MetaPackageManager::Guix(m) => m.fmt(f),
+            MetaPackageManager::MacPorts(m) => m.fmt(f),
+            MetaPackageManager::Msys2(m) => m.fmt(f),
+            MetaPackageManager::Nala(m) => m.fmt(f),
+            MetaPackageManager::Nix(m) => m.fmt(f),
+            MetaPackageManager::OneGet(m) => m.fmt(f),
+            MetaPackageManager::Opkg(m) => m.fmt(f),
+            MetaPackageManager::Pip(m) => m.fmt(f),
+            MetaPackageManager::Portage(m) => m.fmt(f),
+            MetaPackageManager::Scoop(m) => m.fmt(f),
+            MetaPackageManager::Swupd(m) => m.fmt(f),
+            MetaPackageManager::Xbps(m) => m.fmt(f),
+            MetaPackageManager::Zypper(m) => m.fmt(f),
+            MetaPackageManager::Yum(m) => m.fmt(f),
         }
     }
 }
 
+/// Environment variable overriding the order in which
+/// [``MetaPackageManager::new_default``] tries the package managers, e.g.
+/// `MPM_MANAGER_PRIORITY=brew,apt,-flatpak`. See [`ManagerPriority`].
+pub const PRIORITY_ENV: &str = "MPM_MANAGER_PRIORITY";
+
+/// Order in which the default package manager is picked.
+///
+/// The `prefer`red package managers are tried first, in the given order, then
+/// the usual candidates of [``MetaPackageManager::new_default``]. `exclude`d
+/// package managers are never picked. As a string, package managers are
+/// separated by commas and excluded ones start with `-`, e.g. `brew,apt,-flatpak`.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ManagerPriority {
+    /// Package managers to try first, in this order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prefer: Vec<AvailablePackageManager>,
+    /// Package managers that are never picked.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<AvailablePackageManager>,
+}
+
+impl ManagerPriority {
+    /// Read the priority from [`PRIORITY_ENV`], `None` if it is not set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        match std::env::var(PRIORITY_ENV) {
+            Ok(value) if !value.trim().is_empty() => value
+                .parse()
+                .map(Some)
+                .with_context(|| format!("invalid {PRIORITY_ENV}")),
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether the priority changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.prefer.is_empty() && self.exclude.is_empty()
+    }
+
+    /// The package managers to try, in order.
+    pub fn candidates(&self) -> Vec<AvailablePackageManager> {
+        let usual = AvailablePackageManager::iter().filter(|m| {
+            !matches!(
+                m,
+                AvailablePackageManager::Github
+                    | AvailablePackageManager::Aur
+                    | AvailablePackageManager::Cargo
+                    | AvailablePackageManager::Go
+                    | AvailablePackageManager::Pip
+                    | AvailablePackageManager::Conda
+            )
+        });
+        let mut candidates: Vec<AvailablePackageManager> = vec![];
+        for m in self.prefer.iter().cloned().chain(usual) {
+            if !self.exclude.contains(&m) && !candidates.contains(&m) {
+                candidates.push(m);
+            }
+        }
+        candidates
+    }
+}
+
+impl std::str::FromStr for ManagerPriority {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut priority = Self::default();
+        for name in s.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let (list, name) = match name.strip_prefix('-') {
+                Some(name) => (&mut priority.exclude, name),
+                None => (&mut priority.prefer, name),
+            };
+            let manager = name
+                .parse::<AvailablePackageManager>()
+                .map_err(|_| anyhow::anyhow!("unknown package manager {name:?}"))?;
+            list.push(manager);
+        }
+        Ok(priority)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_manager_priority() {
+        use AvailablePackageManager::{Apt, Brew, Flatpak, Pip};
+
+        let priority: ManagerPriority = "brew, apt,-Flatpak".parse().unwrap();
+        assert_eq!(priority.prefer, [Brew, Apt]);
+        assert_eq!(priority.exclude, [Flatpak]);
+        let candidates = priority.candidates();
+        assert_eq!(candidates[..2], [Brew, Apt]);
+        assert!(!candidates.contains(&Flatpak));
+        assert_eq!(candidates.iter().filter(|m| **m == Apt).count(), 1);
+
+        // preferred package managers are tried even if never picked otherwise
+        let priority: ManagerPriority = "pip".parse().unwrap();
+        assert_eq!(priority.candidates()[0], Pip);
+        assert!(!ManagerPriority::default().candidates().contains(&Pip));
+        assert!("brew,snap".parse::<ManagerPriority>().is_err());
+    }
+
     #[test]
     fn test_supported_fmts() {
         let mpm = MetaPackageManager::new_default().unwrap();
         let exts = mpm.supported_pkg_formats();
         assert!(!exts.is_empty());
+        let host_exts = mpm.supported_pkg_formats_for(std::env::consts::ARCH);
+        assert!(!host_exts.is_empty());
+        assert!(host_exts.iter().all(|fmt| exts.contains(fmt)));
+    }
+
+    #[test]
+    fn test_default_priority() {
+        // MacPorts is only picked on systems without Homebrew
+        let managers: Vec<_> = AvailablePackageManager::iter().collect();
+        let position = |m| managers.iter().position(|x| *x == m).unwrap();
+        assert!(
+            position(AvailablePackageManager::Brew) < position(AvailablePackageManager::MacPorts)
+        );
+        // OneGet comes with Windows, so it is the last resort
+        assert_eq!(managers.last(), Some(&AvailablePackageManager::OneGet));
+        // Nala is preferred over the apt it wraps
+        assert!(position(AvailablePackageManager::Nala) < position(AvailablePackageManager::Apt));
+
+        // system package managers are preferred over a Nix or Guix profile,
+        // e.g. on the foreign distributions Guix is installed on
+        let candidates = ManagerPriority::default().candidates();
+        let rank = |m| candidates.iter().position(|x| *x == m).unwrap();
+        for system in [
+            AvailablePackageManager::Apt,
+            AvailablePackageManager::Dnf,
+            AvailablePackageManager::Yum,
+            AvailablePackageManager::Zypper,
+        ] {
+            assert!(rank(system.clone()) < rank(AvailablePackageManager::Nix));
+            assert!(rank(system) < rank(AvailablePackageManager::Guix));
+        }
+    }
+
+    #[test]
+    fn test_cross_manager_duplicates() {
+        let apt = vec![
+            Package::new("firefox", "apt".to_string(), Some("128.0")),
+            Package::new("hello", "apt".to_string(), Some("2.10")),
+        ];
+        let dnf = vec![
+            Package::new("Firefox.x86_64", "dnf".to_string(), Some("129.0")),
+            Package::new("tree.x86_64", "dnf".to_string(), Some("2.1")),
+        ];
+        let duplicates = cross_manager_duplicates([
+            (AvailablePackageManager::Apt, apt),
+            (AvailablePackageManager::Dnf, dnf),
+        ]);
+        assert_eq!(
+            duplicates,
+            vec![(
+                "firefox".to_string(),
+                vec![AvailablePackageManager::Apt, AvailablePackageManager::Dnf]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_env() {
+        let mut mpm = MetaPackageManager::new(AvailablePackageManager::Apt);
+        let err = mpm.set_env("myenv".to_string()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "scoping to an environment is not supported by apt"
+        );
+
+        let mut mpm = MetaPackageManager::Conda(Conda::default());
+        mpm.set_env("myenv".to_string()).unwrap();
+        assert_eq!(
+            mpm.consolidated::<&str>(Cmd::List, None, &[]),
+            ["list", "--json", "--name", "myenv"]
+        );
+    }
+
+    #[test]
+    fn test_search_query_escaping() {
+        let apt = AdvancedPackageTool::default();
+        assert_eq!(apt.search_query("c++"), r"c\+\+");
+        assert_eq!(apt.search_query("lib.*"), r"lib\.\*");
+        assert_eq!(Chocolatey::default().search_query("c++"), "c++");
     }
 
     #[test]
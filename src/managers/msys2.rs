@@ -0,0 +1,241 @@
+use std::{fmt::Display, path::PathBuf, process::Command};
+
+use super::aur::parse_pacman_pkg;
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+    Unsupported,
+};
+
+/// Wrapper for the pacman of MSYS2 on Windows.
+///
+/// [Package Management - MSYS2](https://www.msys2.org/docs/package-management/)
+///
+/// # Idiosyncracies
+/// The packages of the MinGW environments are named with a prefix like
+/// `mingw-w64-ucrt-x86_64-`, which is derived from `MSYSTEM`. Names without
+/// a prefix are installed for the active environment, MSYS packages are
+/// named like `msys/git`. Outside of an MSYS2 shell, pacman is run from
+/// [``Msys2::INSTALL_DIR``].
+#[derive(Debug)]
+pub struct Msys2 {
+    pacman: PathBuf,
+    prefix: Option<&'static str>,
+}
+
+impl Default for Msys2 {
+    fn default() -> Self {
+        Self {
+            pacman: PathBuf::from("pacman"),
+            prefix: None,
+        }
+    }
+}
+
+impl Msys2 {
+    /// Default installation directory of MSYS2.
+    pub const INSTALL_DIR: &'static str = r"C:\msys64";
+
+    /// Prefix of MSYS packages, which every environment shares.
+    pub const MSYS_REPO: &'static str = "msys/";
+
+    /// Detect the environment from `MSYSTEM` and pacman from the `PATH` of
+    /// an MSYS2 shell or from [``Msys2::INSTALL_DIR``].
+    pub fn from_env() -> Self {
+        let msystem = std::env::var("MSYSTEM").ok();
+        let pacman = match msystem {
+            Some(_) => PathBuf::from("pacman"),
+            None => [Self::INSTALL_DIR, "usr", "bin", "pacman.exe"]
+                .iter()
+                .collect(),
+        };
+        Self {
+            pacman,
+            prefix: msystem.as_deref().and_then(Self::package_prefix),
+        }
+    }
+
+    /// Prefix of the packages of the environment `msystem`, `None` for the
+    /// MSYS environment.
+    pub fn package_prefix(msystem: &str) -> Option<&'static str> {
+        match msystem.to_uppercase().as_str() {
+            "MINGW64" => Some("mingw-w64-x86_64-"),
+            "MINGW32" => Some("mingw-w64-i686-"),
+            "UCRT64" => Some("mingw-w64-ucrt-x86_64-"),
+            "CLANG64" => Some("mingw-w64-clang-x86_64-"),
+            "CLANGARM64" => Some("mingw-w64-clang-aarch64-"),
+            _ => None,
+        }
+    }
+
+    /// Operate on the environment `msystem` instead of the detected one.
+    pub fn set_msystem(&mut self, msystem: &str) {
+        self.prefix = Self::package_prefix(msystem);
+    }
+}
+
+impl PackageManager for Msys2 {
+    fn pkg_delimiter(&self) -> char {
+        '='
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Msys2.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    /// Prefixes bare names with the prefix of the environment, see
+    /// [``Msys2``].
+    fn reformat_for_command(&self, pkg: &mut Package) -> String {
+        if pkg.version().is_some() {
+            tracing::warn!("pacman cannot install {} at a version", pkg.name());
+        }
+        let name = pkg.name();
+        if let Some(name) = name.strip_prefix(Self::MSYS_REPO) {
+            return name.to_string();
+        }
+        match self.prefix {
+            Some(prefix) if !name.starts_with("mingw-w64-") => format!("{prefix}{name}"),
+            _ => name.to_string(),
+        }
+    }
+
+    /// Parses pacman's output and names the packages like
+    /// [``Msys2::reformat_for_command``] expects them.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        let (name, version) = parse_pacman_pkg(line)?;
+        let name = match self.prefix {
+            Some(prefix) => match name.strip_prefix(prefix) {
+                Some(name) => name.to_string(),
+                None if name.starts_with("mingw-w64-") => name.to_string(),
+                None => format!("{}{name}", Self::MSYS_REPO),
+            },
+            None => name.to_string(),
+        };
+        Some(Package::new(&name, self.pkg_manager_name(), Some(version)))
+    }
+
+    fn add_repo(&self, _repo: &Vec<String>) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "adding repositories").into())
+    }
+}
+
+impl PackageManagerCommands for Msys2 {
+    fn cmd(&self) -> Command {
+        Command::new(&self.pacman)
+    }
+
+    /// pacman of MSYS2 is only available on Windows, a pacman elsewhere is
+    /// the one of Arch Linux.
+    fn is_available(&self) -> bool {
+        cfg!(windows)
+            && self
+                .cmd()
+                .arg("--version")
+                .output()
+                .is_ok_and(|output| output.status.success())
+    }
+
+    fn needs_sudo(&self) -> bool {
+        false
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Update => vec!["-S"],
+            Cmd::Uninstall => vec!["-R"],
+            Cmd::UpdateAll => vec!["-Syu"],
+            Cmd::List => vec!["-Q"],
+            Cmd::Sync => vec!["-Sy"],
+            // repositories are configured in `/etc/pacman.conf`
+            Cmd::AddRepo => vec![],
+            Cmd::Search => vec!["-Ss"],
+            Cmd::Outdated => vec!["-Qu"],
+            Cmd::Clean | Cmd::Autoremove => vec![],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll => {
+                vec!["--noconfirm".to_string()]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl Display for Msys2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MSYS2")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let mut msys2 = Msys2::default();
+        msys2.set_msystem("UCRT64");
+        let search = "ucrt64/mingw-w64-ucrt-x86_64-gcc 13.2.0-3 (mingw-w64-ucrt-x86_64-toolchain)
+    GNU Compiler Collection (C,C++,OpenMP) for MinGW-w64
+mingw64/mingw-w64-x86_64-gcc 13.2.0-3 (mingw-w64-x86_64-toolchain)
+    GNU Compiler Collection (C,C++,OpenMP) for MinGW-w64
+msys/gcc 13.2.0-2 (msys2-devel)
+    The GNU Compiler Collection
+";
+        let pkgs: Vec<_> = search.lines().filter_map(|l| msys2.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("gcc", "msys2".to_string(), Some("13.2.0-3")),
+                Package::new(
+                    "mingw-w64-x86_64-gcc",
+                    "msys2".to_string(),
+                    Some("13.2.0-3")
+                ),
+                Package::new("msys/gcc", "msys2".to_string(), Some("13.2.0-2")),
+            ]
+        );
+
+        let msys = Msys2::default();
+        assert_eq!(
+            msys.parse_pkg("git 2.43.0-1"),
+            Some(Package::new("git", "msys2".to_string(), Some("2.43.0-1")))
+        );
+    }
+
+    #[test]
+    fn test_cmds() {
+        assert_eq!(
+            Msys2::package_prefix("clang64"),
+            Some("mingw-w64-clang-x86_64-")
+        );
+        assert_eq!(Msys2::package_prefix("MSYS"), None);
+
+        let mut msys2 = Msys2::default();
+        msys2.set_msystem("UCRT64");
+        let names: Vec<_> = ["gcc", "msys/git", "mingw-w64-x86_64-gcc"]
+            .into_iter()
+            .map(|name| msys2.reformat_for_command(&mut Package::from(name)))
+            .collect();
+        assert_eq!(
+            names,
+            ["mingw-w64-ucrt-x86_64-gcc", "git", "mingw-w64-x86_64-gcc"]
+        );
+
+        let mut pkg = Package::from("gcc");
+        let fmt = msys2.reformat_for_command(&mut pkg);
+        assert_eq!(
+            msys2.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["-S", "--noconfirm", "mingw-w64-ucrt-x86_64-gcc"]
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    managers::AdvancedPackageTool, AvailablePackageManager, Cmd, DependencyGraph, Package,
+    PackageManager, PackageManagerCommands, PackageMetadata, PkgFormat, Progress, Repository,
+};
+
+/// Wrapper for Nala, a frontend for apt with parallel downloads and a
+/// transaction history.
+///
+/// [Nala](https://gitlab.com/volian/nala)
+///
+/// Note: Nala installs from apt's repositories, so everything that is not a
+/// Nala command, e.g. repositories and options, is delegated to
+/// [``AdvancedPackageTool``]. [``MetaPackageManager::new_default``] prefers
+/// Nala over apt when it is installed.
+///
+/// [``MetaPackageManager::new_default``]: crate::MetaPackageManager::new_default
+#[derive(Debug, Default)]
+pub struct Nala(AdvancedPackageTool);
+
+impl Display for Nala {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Nala")
+    }
+}
+
+impl PackageManager for Nala {
+    fn pkg_delimiter(&self) -> char {
+        self.0.pkg_delimiter()
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Nala.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![PkgFormat::Deb]
+    }
+
+    /// `nala search` interprets the query as a regular expression.
+    fn search_uses_regex(&self) -> bool {
+        true
+    }
+
+    fn reformat_for_command(&self, pkg: &mut Package) -> String {
+        self.0.reformat_for_command(pkg)
+    }
+
+    /// Parses the package lines of `nala list` and `nala search` like `bash
+    /// 5.2.15-2+b2 [Debian/bookworm main]`, skipping the tree of details
+    /// below them. Upgrades like `curl 7.88.1-10 -> 7.88.1-10+deb12u5
+    /// [...]` are parsed with the newer version.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        if line.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        let (name, version) = match cols[..] {
+            [name, _, "->", newer, ..] => (name, newer),
+            [name, version, ..] => (name, version),
+            _ => return None,
+        };
+        // debian versions start with a digit, also when they have an epoch
+        if name.contains(':') || !version.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+    }
+
+    fn parse_progress(&self, line: &str) -> Option<Progress> {
+        self.0.parse_progress(line)
+    }
+
+    fn is_protected(&self, pkg: &Package) -> bool {
+        self.0.is_protected(pkg)
+    }
+
+    fn verify_package_signature(&self, path: &Path) -> anyhow::Result<bool> {
+        self.0.verify_package_signature(path)
+    }
+
+    fn verify_integrity(&self, pkg: &Package) -> anyhow::Result<bool> {
+        self.0.verify_integrity(pkg)
+    }
+
+    fn verify_files(&self, pkg: &Package) -> anyhow::Result<Vec<PathBuf>> {
+        self.0.verify_files(pkg)
+    }
+
+    fn info(&self, pkg: &Package) -> anyhow::Result<PackageMetadata> {
+        self.0.info(pkg)
+    }
+
+    fn cache_dirs(&self) -> Vec<PathBuf> {
+        self.0.cache_dirs()
+    }
+
+    fn lock_files(&self) -> Vec<PathBuf> {
+        self.0.lock_files()
+    }
+
+    fn pin(&self, pkg: &Package) -> anyhow::Result<()> {
+        self.0.pin(pkg)
+    }
+
+    fn unpin(&self, pkg: &Package) -> anyhow::Result<()> {
+        self.0.unpin(pkg)
+    }
+
+    fn list_pinned(&self) -> anyhow::Result<Vec<Package>> {
+        Ok(self
+            .0
+            .list_pinned()?
+            .iter()
+            .map(|p| Package::new(p.name(), self.pkg_manager_name(), None))
+            .collect())
+    }
+
+    fn owner(&self, path: &Path) -> anyhow::Result<Package> {
+        let pkg = self.0.owner(path)?;
+        Ok(Package::new(pkg.name(), self.pkg_manager_name(), None))
+    }
+
+    fn files(&self, pkg: &Package) -> anyhow::Result<Vec<PathBuf>> {
+        self.0.files(pkg)
+    }
+
+    fn dependencies(&self, pkg: &Package) -> anyhow::Result<DependencyGraph> {
+        self.0.dependencies(pkg)
+    }
+
+    fn reverse_dependencies(&self, pkg: &Package) -> anyhow::Result<Vec<Package>> {
+        Ok(self
+            .0
+            .reverse_dependencies(pkg)?
+            .iter()
+            .map(|p| Package::new(p.name(), self.pkg_manager_name(), None))
+            .collect())
+    }
+
+    fn get_option(&self, key: &str) -> anyhow::Result<Option<String>> {
+        self.0.get_option(key)
+    }
+
+    fn set_option(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        self.0.set_option(key, value)
+    }
+
+    fn list_mirrors(&self) -> anyhow::Result<Vec<url::Url>> {
+        self.0.list_mirrors()
+    }
+
+    fn set_mirror(&self, url: &url::Url) -> anyhow::Result<()> {
+        self.0.set_mirror(url)
+    }
+
+    fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
+        self.0.add_repo(repo)
+    }
+
+    fn list_repos(&self) -> anyhow::Result<Vec<Repository>> {
+        self.0.list_repos()
+    }
+
+    fn remove_repo(&self, name: &str) -> anyhow::Result<()> {
+        self.0.remove_repo(name)
+    }
+}
+
+impl PackageManagerCommands for Nala {
+    fn cmd(&self) -> Command {
+        Command::new("nala")
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            // installing an installed package upgrades it
+            Cmd::Install | Cmd::Update => vec!["install"],
+            Cmd::Uninstall => vec!["remove"],
+            Cmd::UpdateAll => vec!["upgrade"],
+            Cmd::List => vec!["list", "--installed"],
+            Cmd::Sync => vec!["update"],
+            // written to apt's sources, see `AdvancedPackageTool::add_repo`
+            Cmd::AddRepo => vec![],
+            Cmd::Search => vec!["search"],
+            Cmd::Outdated => vec!["list", "--upgradable"],
+            Cmd::Clean => vec!["clean"],
+            Cmd::Autoremove => vec!["autoremove"],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll | Cmd::Autoremove => {
+                vec!["--assume-yes".to_string()]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let nala = Nala::default();
+        let list = "bash 5.2.15-2+b2 [Debian/bookworm main]
+├── is installed
+└── GNU Bourne Again SHell
+libc6 2.36-9+deb12u4 [Debian/bookworm main]
+└── is installed and automatic
+";
+        let pkgs: Vec<_> = list.lines().filter_map(|l| nala.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("bash", "nala".to_string(), Some("5.2.15-2+b2")),
+                Package::new("libc6", "nala".to_string(), Some("2.36-9+deb12u4")),
+            ]
+        );
+
+        assert_eq!(
+            nala.parse_pkg("curl 7.88.1-10 -> 7.88.1-10+deb12u5 [Debian/bookworm main]"),
+            Some(Package::new(
+                "curl",
+                "nala".to_string(),
+                Some("7.88.1-10+deb12u5")
+            ))
+        );
+        assert_eq!(nala.parse_pkg("Nala: nothing to upgrade"), None);
+    }
+
+    #[test]
+    fn test_cmds() {
+        let nala = Nala::default();
+        let mut pkg = Package::new("hello", "nala".to_string(), Some("2.10-3"));
+        let fmt = nala.reformat_for_command(&mut pkg);
+        assert_eq!(
+            nala.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["install", "--assume-yes", "hello=2.10-3"]
+        );
+        assert_eq!(
+            nala.consolidated::<&str>(Cmd::Outdated, None, &[]),
+            ["list", "--upgradable"]
+        );
+    }
+}
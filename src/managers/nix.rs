@@ -0,0 +1,276 @@
+use std::{fmt::Display, process::Command};
+
+use crate::{
+    AvailablePackageManager, Cmd, Operation, Package, PackageManager, PackageManagerCommands,
+    PkgFormat,
+};
+
+/// Wrapper for the packages of a user's Nix profile, using `nix profile` and
+/// `nix search`.
+///
+/// [nix profile - Nix Reference Manual](https://nix.dev/manual/nix/latest/command-ref/new-cli/nix3-profile)
+///
+/// # Idiosyncracies
+/// Packages are installed from the flake [``Nix::FLAKE``], i.e. `hello` is
+/// installed as `nixpkgs#hello`, and cannot be pinned to a version. The
+/// experimental `nix-command` and `flakes` features are enabled for every
+/// command.
+#[derive(Debug, Default)]
+pub struct Nix;
+
+impl Nix {
+    /// Flake that packages are installed from and searched in.
+    pub const FLAKE: &'static str = "nixpkgs";
+
+    /// Flake reference of a package, e.g. `nixpkgs#hello` for `hello`.
+    /// References like `github:owner/repo#pkg` are kept as they are.
+    pub(crate) fn flake_ref(name: &str) -> String {
+        if name.contains('#') {
+            name.to_string()
+        } else {
+            format!("{}#{name}", Self::FLAKE)
+        }
+    }
+
+    /// Attribute path without the `legacyPackages.<system>.` prefix, e.g.
+    /// `python311Packages.requests`.
+    fn attr_name(attr: &str) -> &str {
+        attr.strip_prefix("legacyPackages.")
+            .and_then(|rest| rest.split_once('.'))
+            .map_or(attr, |(_system, name)| name)
+    }
+
+    /// Version of the package in a store path like
+    /// `/nix/store/<hash>-hello-2.12.1`, i.e. everything after the first dash
+    /// that is followed by a digit.
+    pub(crate) fn store_path_version(path: &str) -> Option<&str> {
+        let (_hash, name) = path.rsplit('/').next()?.split_once('-')?;
+        let start = name
+            .char_indices()
+            .find(|&(i, c)| c == '-' && name[i + 1..].starts_with(|c: char| c.is_ascii_digit()))?
+            .0;
+        Some(&name[start + 1..])
+    }
+}
+
+impl PackageManager for Nix {
+    fn pkg_delimiter(&self) -> char {
+        '#'
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Nix.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    fn reformat_for_command(&self, pkg: &mut Package) -> String {
+        if pkg.version().is_some() {
+            tracing::warn!("Nix cannot pin {} to a version", pkg.name());
+        }
+        Self::flake_ref(pkg.name())
+    }
+
+    /// Parses `nix search` results like `* legacyPackages.x86_64-linux.hello
+    /// (2.12.1)` and the rows of the `nix profile list` format of Nix before
+    /// 2.20 like `0 flake:nixpkgs#legacyPackages.x86_64-linux.hello <locked
+    /// url> /nix/store/<hash>-hello-2.12.1`.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        if let Some(result) = line.strip_prefix("* ") {
+            let (attr, version) = result.split_once(' ')?;
+            let version = version.trim().strip_prefix('(')?.strip_suffix(')')?;
+            let version = (!version.is_empty()).then_some(version);
+            return Some(Package::new(
+                Self::attr_name(attr),
+                self.pkg_manager_name(),
+                version,
+            ));
+        }
+
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        let [index, flake, _locked, store_path, ..] = cols[..] else {
+            return None;
+        };
+        index.parse::<usize>().ok()?;
+        let (_url, attr) = flake.split_once('#')?;
+        Some(Package::new(
+            Self::attr_name(attr),
+            self.pkg_manager_name(),
+            Self::store_path_version(store_path),
+        ))
+    }
+
+    /// Also parses the `Name:` and `Store paths:` fields that `nix profile
+    /// list` prints for every package since Nix 2.20.
+    fn parse_output(&self, out: &[u8]) -> Vec<Package> {
+        let out = String::from_utf8_lossy(out);
+        let mut pkgs = vec![];
+        let mut name = None;
+        for line in out.lines() {
+            if let Some(n) = line.strip_prefix("Name:") {
+                name = Some(n.trim().to_string());
+            } else if let Some(paths) = line.strip_prefix("Store paths:") {
+                if let Some(name) = name.take() {
+                    let version = paths
+                        .split_whitespace()
+                        .next()
+                        .and_then(Self::store_path_version);
+                    pkgs.push(Package::new(&name, self.pkg_manager_name(), version));
+                }
+            } else if let Some(pkg) = self.parse_pkg(line) {
+                pkgs.push(pkg);
+            }
+        }
+        pkgs
+    }
+
+    /// Profile entries are removed and upgraded by name rather than by flake
+    /// reference.
+    fn execute_pkg_command(
+        &self,
+        pkg: &mut Package,
+        op: Operation,
+        interactive: bool,
+    ) -> std::process::ExitStatus {
+        let (cmd, arg) = match op {
+            Operation::Install => (Cmd::Install, self.reformat_for_command(pkg)),
+            Operation::Uninstall => (Cmd::Uninstall, pkg.name().to_string()),
+            Operation::Update => (Cmd::Update, pkg.name().to_string()),
+        };
+        let cmds = self.consolidated(cmd, Some(pkg), &[arg]);
+        self.exec_cmds_status(&cmds, Some(interactive))
+    }
+
+    fn sync(&self) -> std::process::ExitStatus {
+        tracing::debug!("Nix profiles have nothing to sync");
+        crate::common::synthetic_exit_status(true)
+    }
+
+    fn list_outdated(&self) -> Vec<Package> {
+        tracing::warn!("Nix cannot list outdated packages");
+        vec![]
+    }
+}
+
+impl PackageManagerCommands for Nix {
+    fn cmd(&self) -> Command {
+        Command::new("nix")
+    }
+
+    fn needs_sudo(&self) -> bool {
+        false
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        let mut cmds = vec!["--extra-experimental-features", "nix-command flakes"];
+        cmds.extend(match cmd {
+            Cmd::Install => vec!["profile", "install"],
+            Cmd::Uninstall => vec!["profile", "remove"],
+            Cmd::Update | Cmd::UpdateAll => vec!["profile", "upgrade"],
+            Cmd::List => vec!["profile", "list"],
+            Cmd::Sync | Cmd::Outdated | Cmd::Clean | Cmd::Autoremove => vec![],
+            Cmd::AddRepo => vec!["registry", "add"],
+            Cmd::Search => vec!["search", Self::FLAKE],
+        });
+        cmds.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn get_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::UpdateAll => vec!["--all".to_string()],
+            _ => vec![],
+        }
+    }
+}
+
+impl Display for Nix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Nix")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profile_list() {
+        let nix = Nix;
+        let output = "Name:               hello
+Flake attribute:    legacyPackages.x86_64-linux.hello
+Original flake URL: flake:nixpkgs
+Locked flake URL:   github:NixOS/nixpkgs/b06025f1533a1e07b6db3e75151caa155d1c7eb3
+Store paths:        /nix/store/63l345l7dgcfz789w1y93j1540czafqh-hello-2.12.1
+
+Name:               python311Packages.requests
+Flake attribute:    legacyPackages.x86_64-linux.python311Packages.requests
+Original flake URL: flake:nixpkgs
+Locked flake URL:   github:NixOS/nixpkgs/b06025f1533a1e07b6db3e75151caa155d1c7eb3
+Store paths:        /nix/store/0mbq7l1gvhgmzsq44bnj9f3ssfn4qysk-python3.11-requests-2.31.0
+";
+        assert_eq!(
+            nix.parse_output(output.as_bytes()),
+            [
+                Package::new("hello", "nix".to_string(), Some("2.12.1")),
+                Package::new(
+                    "python311Packages.requests",
+                    "nix".to_string(),
+                    Some("2.31.0")
+                ),
+            ]
+        );
+
+        // before Nix 2.20
+        let output = "0 flake:nixpkgs#legacyPackages.x86_64-linux.hello github:NixOS/nixpkgs/b06025f1533a1e07b6db3e75151caa155d1c7eb3#legacyPackages.x86_64-linux.hello /nix/store/63l345l7dgcfz789w1y93j1540czafqh-hello-2.12.1\n";
+        assert_eq!(
+            nix.parse_output(output.as_bytes()),
+            [Package::new("hello", "nix".to_string(), Some("2.12.1"))]
+        );
+    }
+
+    #[test]
+    fn test_parse_search() {
+        let nix = Nix;
+        let output = "* legacyPackages.x86_64-linux.hello (2.12.1)
+  Program that produces a familiar, friendly greeting
+
+* legacyPackages.x86_64-linux.python311Packages.hello ()
+  Hello world
+";
+        assert_eq!(
+            nix.parse_output(output.as_bytes()),
+            [
+                Package::new("hello", "nix".to_string(), Some("2.12.1")),
+                Package::new("python311Packages.hello", "nix".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cmds() {
+        let nix = Nix;
+        let mut pkg = Package::from("hello");
+        let fmt = nix.reformat_for_command(&mut pkg);
+        assert_eq!(
+            nix.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            [
+                "--extra-experimental-features",
+                "nix-command flakes",
+                "profile",
+                "install",
+                "nixpkgs#hello"
+            ]
+        );
+        assert_eq!(
+            Nix::flake_ref("github:owner/repo#tool"),
+            "github:owner/repo#tool"
+        );
+        assert_eq!(
+            nix.consolidated::<&str>(Cmd::UpdateAll, None, &[])[2..],
+            ["profile", "upgrade", "--all"]
+        );
+        assert_eq!(Nix::store_path_version("/nix/store/abc-hello"), None);
+    }
+}
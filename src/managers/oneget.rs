@@ -0,0 +1,248 @@
+use std::{fmt::Display, process::Command};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+};
+
+/// Wrapper for PackageManagement (OneGet), the package management cmdlets of
+/// Windows PowerShell.
+///
+/// [PackageManagement - PowerShell](https://learn.microsoft.com/en-us/powershell/module/packagemanagement/)
+///
+/// # Idiosyncracies
+/// Every command is a PowerShell script: the arguments after `-Command` are
+/// joined with spaces, so names and versions are quoted by
+/// [``OneGet::reformat_for_command``]. The packages of list and search are
+/// printed as `name|version` by [``OneGet::FORMAT``]. Updating all packages
+/// is limited to modules of the PowerShellGet provider (`Update-Module`),
+/// and outdated packages cannot be listed.
+#[derive(Debug, Default)]
+pub struct OneGet;
+
+impl OneGet {
+    /// Pipeline appended to queries to print the packages in a parseable
+    /// format.
+    pub const FORMAT: &'static str = "| ForEach-Object { '{0}|{1}' -f $_.Name, $_.Version }";
+
+    /// Quote `s` as a PowerShell string literal.
+    fn quote(s: &str) -> String {
+        format!("'{}'", s.replace('\'', "''"))
+    }
+
+    /// Run the query `cmd`, which prints the packages formatted.
+    fn query_output(&self, cmd: Cmd, args: &[String]) -> std::process::Output {
+        let mut cmds = self.consolidated(cmd, None, args);
+        cmds.push(Self::FORMAT.to_string());
+        self.exec_cmds(&cmds)
+    }
+
+    /// Run the query `cmd` and parse the formatted packages.
+    fn query(&self, cmd: Cmd, args: &[String]) -> Vec<Package> {
+        self.parse_output(&self.query_output(cmd, args).stdout)
+    }
+}
+
+impl PackageManager for OneGet {
+    fn pkg_delimiter(&self) -> char {
+        '|'
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::OneGet.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![PkgFormat::Msi]
+    }
+
+    /// Quotes the name, or the path of a package file, and pins the version
+    /// with `-RequiredVersion`.
+    fn reformat_for_command(&self, pkg: &mut Package) -> String {
+        if pkg.url().is_some() {
+            let path = pkg
+                .make_available_on_disk(None, false)
+                .expect("failed to ensure that package exists locally");
+            return Self::quote(&path.display().to_string());
+        }
+        match pkg.version() {
+            Some(version) => format!(
+                "{} -RequiredVersion {}",
+                Self::quote(pkg.name()),
+                Self::quote(version)
+            ),
+            None => Self::quote(pkg.name()),
+        }
+    }
+
+    /// `Find-Package` matches wildcards.
+    fn search_query(&self, query: &str) -> String {
+        Self::quote(&format!("*{query}*"))
+    }
+
+    /// Parses the `name|version` lines of [``OneGet::FORMAT``].
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        let (name, version) = line.trim().split_once('|')?;
+        let version = (!version.is_empty()).then_some(version);
+        Some(Package::new(name, self.pkg_manager_name(), version))
+    }
+
+    /// `Install-Package` takes several names only as a comma separated
+    /// `-Name` array, which `-RequiredVersion` does not apply to.
+    fn install_many(&self, pkgs: &[Package], interactive: bool) -> std::process::ExitStatus {
+        crate::traits::install_each(self, pkgs, interactive)
+    }
+
+    fn search_with_args(&self, query: &str, args: &[String]) -> Vec<Package> {
+        let mut args = args.to_vec();
+        args.push(self.search_query(query));
+        self.query(Cmd::Search, &args)
+    }
+
+    fn try_search(&self, query: &str) -> anyhow::Result<Vec<Package>> {
+        let out = self.query_output(Cmd::Search, &[self.search_query(query)]);
+        crate::traits::ensure_search_succeeded(self.cmd().get_program(), &out)?;
+        Ok(self.parse_output(&out.stdout))
+    }
+
+    fn search_limited(&self, query: &str, limit: usize) -> anyhow::Result<Vec<Package>> {
+        Ok(self.search(query).into_iter().take(limit).collect())
+    }
+
+    fn list_installed(&self) -> Vec<Package> {
+        self.query(Cmd::List, &[])
+    }
+
+    fn list_outdated(&self) -> Vec<Package> {
+        tracing::warn!("PackageManagement cannot list outdated packages");
+        vec![]
+    }
+
+    fn sync(&self) -> std::process::ExitStatus {
+        tracing::debug!("PackageManagement queries its sources on demand");
+        crate::common::synthetic_exit_status(true)
+    }
+
+    /// Registers a NuGet source given as its name and its location.
+    fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
+        let [name, location] = &repo[..] else {
+            anyhow::bail!("package sources are added as <name> <location>");
+        };
+        let args = [
+            "-Name".to_string(),
+            Self::quote(name),
+            "-Location".to_string(),
+            Self::quote(location),
+        ];
+        let s = self.exec_cmds_status(&self.consolidated(Cmd::AddRepo, None, &args), None);
+        anyhow::ensure!(s.success(), "failed to register package source {name}");
+        Ok(())
+    }
+}
+
+impl PackageManagerCommands for OneGet {
+    fn cmd(&self) -> Command {
+        Command::new("powershell")
+    }
+
+    /// PackageManagement is only available on Windows.
+    fn is_available(&self) -> bool {
+        cfg!(windows)
+            && self
+                .cmd()
+                .args(["-NoProfile", "-Command", "Get-Command", "Install-Package"])
+                .output()
+                .is_ok_and(|output| output.status.success())
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        let cmdlet = match cmd {
+            Cmd::Install | Cmd::Update => "Install-Package",
+            Cmd::Uninstall => "Uninstall-Package",
+            Cmd::UpdateAll => "Update-Module",
+            Cmd::List => "Get-Package",
+            // sources are queried on demand
+            Cmd::Sync => "",
+            Cmd::AddRepo => "Register-PackageSource",
+            Cmd::Search => "Find-Package",
+            Cmd::Outdated => "",
+            Cmd::Clean | Cmd::Autoremove => return vec![],
+        };
+        ["-NoProfile", "-NonInteractive", "-Command", cmdlet]
+            .iter()
+            .map(|x| x.to_string())
+            .collect()
+    }
+
+    /// `-Force` also skips the confirmation of untrusted sources.
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll => {
+                vec!["-Force".to_string()]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn get_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::AddRepo => vec!["-ProviderName", "NuGet", "-Trusted"],
+            _ => vec![],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+}
+
+impl Display for OneGet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PackageManagement (OneGet)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let oneget = OneGet;
+        let out = "PSReadLine|2.3.4
+Microsoft Edge|120.0.2210.91
+7-Zip 23.01 (x64)|
+";
+        let pkgs: Vec<_> = out.lines().filter_map(|l| oneget.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("PSReadLine", "oneget".to_string(), Some("2.3.4")),
+                Package::new(
+                    "Microsoft Edge",
+                    "oneget".to_string(),
+                    Some("120.0.2210.91")
+                ),
+                Package::new("7-Zip 23.01 (x64)", "oneget".to_string(), None),
+            ]
+        );
+        assert_eq!(oneget.parse_pkg("WARNING: No match was found"), None);
+    }
+
+    #[test]
+    fn test_cmds() {
+        let oneget = OneGet;
+        let mut pkg = Package::new("Pester", "oneget".to_string(), Some("5.5.0"));
+        let fmt = oneget.reformat_for_command(&mut pkg);
+        assert_eq!(
+            oneget.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            [
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                "Install-Package",
+                "-Force",
+                "'Pester' -RequiredVersion '5.5.0'"
+            ]
+        );
+        assert_eq!(oneget.search_query("it's"), "'*it''s*'");
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{BufWriter, Write},
+    process::Command,
+};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+};
+
+/// Wrapper for opkg, the package manager of OpenWrt and other embedded
+/// distributions.
+///
+/// [Opkg package manager - OpenWrt Wiki](https://openwrt.org/docs/guide-user/additional-software/opkg)
+///
+/// # Idiosyncracies
+/// opkg cannot install a specific version of a package or upgrade all
+/// packages at once, so the upgradable packages are upgraded by name. Feeds
+/// are added to [``Opkg::CUSTOM_FEEDS``].
+#[derive(Debug, Default)]
+pub struct Opkg;
+
+impl Opkg {
+    /// Configuration file for feeds that are not part of the distribution.
+    pub const CUSTOM_FEEDS: &'static str = "/etc/opkg/customfeeds.conf";
+}
+
+impl PackageManager for Opkg {
+    fn pkg_delimiter(&self) -> char {
+        '='
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Opkg.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![PkgFormat::Ipk]
+    }
+
+    fn reformat_for_command(&self, pkg: &mut Package) -> String {
+        if pkg.url().is_some() {
+            return pkg.cli_display(self.pkg_delimiter());
+        }
+        if pkg.version().is_some() {
+            tracing::warn!("opkg cannot install {} at a version", pkg.name());
+        }
+        pkg.name().to_string()
+    }
+
+    /// `opkg find` matches shell globs.
+    fn search_query(&self, query: &str) -> String {
+        format!("*{query}*")
+    }
+
+    /// Parses the lines of `opkg list-installed` like `busybox -
+    /// 1.36.1-1`, of `opkg find` like `curl - 8.5.0-1 - A client-side URL
+    /// transfer utility` and of `opkg list-upgradable` like `curl - 8.4.0-1
+    /// - 8.5.0-1`, where the newer version is used.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        let mut cols = line.split(" - ");
+        let name = cols.next()?.trim();
+        let version = cols.next()?.trim();
+        let version = match cols.next().map(str::trim) {
+            Some(newer)
+                if newer.starts_with(|c: char| c.is_ascii_digit())
+                    && !newer.contains(char::is_whitespace) =>
+            {
+                newer
+            }
+            _ => version,
+        };
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+    }
+
+    /// Upgrades the upgradable packages by name.
+    fn update_all(&self, interactive: bool) -> std::process::ExitStatus {
+        let names: Vec<String> = self
+            .list_outdated()
+            .iter()
+            .map(|pkg| pkg.name().to_string())
+            .collect();
+        if names.is_empty() {
+            return crate::common::synthetic_exit_status(true);
+        }
+        self.exec_cmds_status(
+            &self.consolidated(Cmd::UpdateAll, None, &names),
+            Some(interactive),
+        )
+    }
+
+    /// Adds a feed given as its name and its URL.
+    fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
+        let [name, url] = &repo[..] else {
+            anyhow::bail!("opkg feeds are added as <name> <url>");
+        };
+        self.ensure_sudo();
+        let feeds = fs::File::options().append(true).open(Self::CUSTOM_FEEDS)?;
+        let mut writer = BufWriter::new(feeds);
+        writeln!(writer, "src/gz {name} {url}")?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl PackageManagerCommands for Opkg {
+    fn cmd(&self) -> Command {
+        Command::new("opkg")
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            Cmd::Install => vec!["install"],
+            Cmd::Uninstall => vec!["remove"],
+            Cmd::Update | Cmd::UpdateAll => vec!["upgrade"],
+            Cmd::List => vec!["list-installed"],
+            Cmd::Sync => vec!["update"],
+            // written to `Opkg::CUSTOM_FEEDS`, see `Opkg::add_repo`
+            Cmd::AddRepo => vec![],
+            Cmd::Search => vec!["find"],
+            Cmd::Outdated => vec!["list-upgradable"],
+            Cmd::Clean | Cmd::Autoremove => vec![],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+}
+
+impl Display for Opkg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("opkg")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let opkg = Opkg;
+        let installed = "busybox - 1.36.1-1
+libc - 1.2.4-4
+";
+        let pkgs: Vec<_> = installed
+            .lines()
+            .filter_map(|l| opkg.parse_pkg(l))
+            .collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("busybox", "opkg".to_string(), Some("1.36.1-1")),
+                Package::new("libc", "opkg".to_string(), Some("1.2.4-4")),
+            ]
+        );
+
+        let find = "curl - 8.5.0-1 - A client-side URL transfer utility
+luci-app-3ginfo - 1.0 - 3G modem information
+";
+        let pkgs: Vec<_> = find.lines().filter_map(|l| opkg.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("curl", "opkg".to_string(), Some("8.5.0-1")),
+                Package::new("luci-app-3ginfo", "opkg".to_string(), Some("1.0")),
+            ]
+        );
+
+        assert_eq!(
+            opkg.parse_pkg("curl - 8.4.0-1 - 8.5.0-1"),
+            Some(Package::new("curl", "opkg".to_string(), Some("8.5.0-1")))
+        );
+        assert_eq!(
+            opkg.parse_pkg("Downloading https://downloads.openwrt.org/"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cmds() {
+        let opkg = Opkg;
+        let mut pkg = Package::from("curl");
+        let fmt = opkg.reformat_for_command(&mut pkg);
+        assert_eq!(
+            opkg.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["install", "curl"]
+        );
+        assert_eq!(opkg.search_query("curl"), "*curl*");
+        assert!(opkg
+            .add_repo(&vec!["https://example.com".to_string()])
+            .is_err());
+    }
+}
@@ -0,0 +1,314 @@
+use std::{collections::BTreeMap, fmt::Display, process::Command};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+    Unsupported,
+};
+
+/// Wrapper for Python packages, installed with pipx if it is available and
+/// with pip otherwise.
+///
+/// [pipx](https://pipx.pypa.io/) / [pip](https://pip.pypa.io/)
+///
+/// # Idiosyncracies
+/// pipx installs every application into its own virtual environment, pip
+/// installs into the active Python environment. Neither needs elevated
+/// privileges. Neither can search PyPI, so [``PackageManager::search``]
+/// looks the package up by its exact name with `pip index versions`. pipx
+/// cannot list outdated applications.
+#[derive(Debug, Default)]
+pub struct Pip {
+    backend: PipBackend,
+}
+
+/// Frontend that [``Pip``] uses.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PipBackend {
+    #[default]
+    Pip,
+    Pipx,
+}
+
+impl PipBackend {
+    /// Name of the executable.
+    pub fn binary(&self) -> &'static str {
+        match self {
+            Self::Pip => "pip",
+            Self::Pipx => "pipx",
+        }
+    }
+}
+
+/// A package of `pip list --format=json`.
+#[derive(Debug, serde::Deserialize)]
+struct PipListEntry {
+    name: String,
+    version: String,
+    /// Only listed with `--outdated`.
+    latest_version: Option<String>,
+}
+
+/// Output of `pipx list --json`.
+#[derive(Debug, serde::Deserialize)]
+struct PipxList {
+    venvs: BTreeMap<String, PipxVenv>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct PipxVenv {
+    metadata: PipxMetadata,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct PipxMetadata {
+    main_package: PipxPackage,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct PipxPackage {
+    package: String,
+    package_version: String,
+}
+
+impl Pip {
+    /// Use pipx if it is available, else pip.
+    pub fn preferred() -> Self {
+        let pipx = Self {
+            backend: PipBackend::Pipx,
+        };
+        if pipx.is_available() {
+            pipx
+        } else {
+            Self::default()
+        }
+    }
+
+    /// Use `backend` to manage packages.
+    pub fn set_backend(&mut self, backend: PipBackend) {
+        self.backend = backend;
+    }
+
+    /// Parse the JSON printed by `pip list --format=json` and `pipx list
+    /// --json`.
+    fn parse_json(&self, out: &str) -> Option<Vec<Package>> {
+        let pkgs = match self.backend {
+            PipBackend::Pip => serde_json::from_str::<Vec<PipListEntry>>(out)
+                .ok()?
+                .into_iter()
+                .map(|p| {
+                    let version = p.latest_version.unwrap_or(p.version);
+                    Package::new(&p.name, self.pkg_manager_name(), Some(version.as_str()))
+                })
+                .collect(),
+            PipBackend::Pipx => serde_json::from_str::<PipxList>(out)
+                .ok()?
+                .venvs
+                .into_values()
+                .map(|venv| {
+                    let pkg = venv.metadata.main_package;
+                    Package::new(
+                        &pkg.package,
+                        self.pkg_manager_name(),
+                        Some(pkg.package_version.as_str()),
+                    )
+                })
+                .collect(),
+        };
+        Some(pkgs)
+    }
+}
+
+impl PackageManager for Pip {
+    fn pkg_delimiter(&self) -> char {
+        '='
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Pip.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    /// Versions are pinned with requirements like `black==24.1.0`.
+    fn reformat_for_command(&self, pkg: &mut Package) -> String {
+        match pkg.version() {
+            Some(version) => format!("{}=={version}", pkg.name()),
+            None => pkg.name().to_string(),
+        }
+    }
+
+    /// Parses the package line of `pip index versions` like `black
+    /// (24.1.0)`.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        let (name, version) = line.trim().split_once(" (")?;
+        let version = version.strip_suffix(')')?;
+        if name.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+    }
+
+    /// Also parses the JSON listings of pip and pipx.
+    fn parse_output(&self, out: &[u8]) -> Vec<Package> {
+        let out = String::from_utf8_lossy(out);
+        if let Some(pkgs) = self.parse_json(&out) {
+            return pkgs;
+        }
+        out.lines()
+            .filter_map(|line| self.parse_pkg(line))
+            .collect()
+    }
+
+    fn sync(&self) -> std::process::ExitStatus {
+        tracing::debug!("{} has no package index to sync", self.backend.binary());
+        crate::common::synthetic_exit_status(true)
+    }
+
+    /// pip has no command to upgrade everything, so the outdated packages are
+    /// upgraded by name.
+    fn update_all(&self, interactive: bool) -> std::process::ExitStatus {
+        let names: Vec<String> = match self.backend {
+            PipBackend::Pipx => vec![],
+            PipBackend::Pip => {
+                let names: Vec<String> = self
+                    .list_outdated()
+                    .iter()
+                    .map(|pkg| pkg.name().to_string())
+                    .collect();
+                if names.is_empty() {
+                    return crate::common::synthetic_exit_status(true);
+                }
+                names
+            }
+        };
+        self.exec_cmds_status(
+            &self.consolidated(Cmd::UpdateAll, None, &names),
+            Some(interactive),
+        )
+    }
+
+    fn list_outdated(&self) -> Vec<Package> {
+        if self.backend == PipBackend::Pipx {
+            tracing::warn!("pipx cannot list outdated packages");
+            return vec![];
+        }
+        let out = self.exec_cmds(&self.consolidated::<&str>(Cmd::Outdated, None, &[]));
+        self.parse_output(&out.stdout)
+    }
+
+    fn add_repo(&self, _repo: &Vec<String>) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "adding package indexes").into())
+    }
+}
+
+impl PackageManagerCommands for Pip {
+    fn cmd(&self) -> Command {
+        Command::new(self.backend.binary())
+    }
+
+    /// pipx has no search, see [``Pip``].
+    fn cmd_for(&self, cmd: Cmd) -> Option<Command> {
+        (self.backend == PipBackend::Pipx && cmd == Cmd::Search).then(|| Command::new("pip"))
+    }
+
+    fn needs_sudo(&self) -> bool {
+        false
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        let pipx = self.backend == PipBackend::Pipx;
+        match cmd {
+            Cmd::Install => vec!["install"],
+            Cmd::Uninstall => vec!["uninstall"],
+            Cmd::Update if pipx => vec!["upgrade"],
+            Cmd::UpdateAll if pipx => vec!["upgrade-all"],
+            Cmd::Update | Cmd::UpdateAll => vec!["install", "--upgrade"],
+            Cmd::List if pipx => vec!["list", "--json"],
+            Cmd::List => vec!["list", "--format=json"],
+            Cmd::Sync | Cmd::AddRepo | Cmd::Clean | Cmd::Autoremove => vec![],
+            Cmd::Search => vec!["index", "versions"],
+            Cmd::Outdated => vec!["list", "--outdated", "--format=json"],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::Uninstall if self.backend == PipBackend::Pip => vec!["--yes".to_string()],
+            _ => vec![],
+        }
+    }
+}
+
+impl Display for Pip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.backend {
+            PipBackend::Pip => f.write_str("pip"),
+            PipBackend::Pipx => f.write_str("pipx"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        let pip = Pip::default();
+        let list = r#"[{"name": "black", "version": "24.1.0"}, {"name": "requests", "version": "2.31.0"}]"#;
+        assert_eq!(
+            pip.parse_output(list.as_bytes()),
+            [
+                Package::new("black", "pip".to_string(), Some("24.1.0")),
+                Package::new("requests", "pip".to_string(), Some("2.31.0")),
+            ]
+        );
+
+        let outdated = r#"[{"name": "black", "version": "23.12.1", "latest_version": "24.1.0", "latest_filetype": "wheel"}]"#;
+        assert_eq!(
+            pip.parse_output(outdated.as_bytes()),
+            [Package::new("black", "pip".to_string(), Some("24.1.0"))]
+        );
+
+        let index = "black (24.1.0)
+Available versions: 24.1.0, 23.12.1, 23.12.0
+";
+        assert_eq!(
+            pip.parse_output(index.as_bytes()),
+            [Package::new("black", "pip".to_string(), Some("24.1.0"))]
+        );
+
+        let mut pipx = Pip::default();
+        pipx.set_backend(PipBackend::Pipx);
+        let list = r#"{"pipx_spec_version": "0.1", "venvs": {"black": {"metadata": {"main_package": {"package": "black", "package_version": "24.1.0", "apps": ["black", "blackd"]}, "python_version": "Python 3.11.6"}}}}"#;
+        assert_eq!(
+            pipx.parse_output(list.as_bytes()),
+            [Package::new("black", "pip".to_string(), Some("24.1.0"))]
+        );
+    }
+
+    #[test]
+    fn test_cmds() {
+        let mut pip = Pip::default();
+        let mut pkg = Package::new("black", "pip".to_string(), Some("24.1.0"));
+        let fmt = pip.reformat_for_command(&mut pkg);
+        assert_eq!(
+            pip.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["install", "black==24.1.0"]
+        );
+        assert!(pip.cmd_for(Cmd::Search).is_none());
+
+        pip.set_backend(PipBackend::Pipx);
+        assert_eq!(pip.cmd().get_program(), "pipx");
+        assert_eq!(pip.cmd_for(Cmd::Search).unwrap().get_program(), "pip");
+        assert_eq!(
+            pip.consolidated::<&str>(Cmd::UpdateAll, None, &[]),
+            ["upgrade-all"]
+        );
+    }
+}
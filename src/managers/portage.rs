@@ -0,0 +1,327 @@
+use std::{fmt::Display, fs, path::Path, process::Command};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+};
+
+/// Wrapper for Portage, the package manager of Gentoo Linux, using `emerge`.
+///
+/// [Portage - Gentoo Wiki](https://wiki.gentoo.org/wiki/Portage)
+///
+/// # Idiosyncracies
+/// Packages are named by their category, e.g. `app-editors/vim`. Packages
+/// installed in a slot other than the default slot `0` are named with their
+/// slot, e.g. `dev-lang/python:3.11`, so that the slots can be told apart.
+///
+/// `emerge` cannot list installed packages, so they are read from the
+/// package database in [``Portage::VDB``] instead. Repositories are enabled
+/// with `eselect repository`.
+#[derive(Debug, Default)]
+pub struct Portage;
+
+impl Portage {
+    /// Database of the installed packages, with a `<category>/<name>-<version>`
+    /// directory per package.
+    pub const VDB: &'static str = "/var/db/pkg";
+
+    /// Parse an atom like `dev-lang/python-3.11.8-r1:3.11/3.11::gentoo` into
+    /// a package named `dev-lang/python:3.11` with version `3.11.8-r1`.
+    fn parse_atom(&self, atom: &str) -> Option<Package> {
+        let atom = atom.split_once("::").map_or(atom, |(atom, _repo)| atom);
+        let (cpv, slot) = match atom.split_once(':') {
+            // ignore the sub-slot
+            Some((cpv, slot)) => (cpv, slot.split('/').next()),
+            None => (atom, None),
+        };
+        let (category, pv) = cpv.split_once('/')?;
+        let (name, version) = Self::split_version(pv);
+        if category.is_empty() || name.is_empty() {
+            return None;
+        }
+        let name = match slot.filter(|s| !s.is_empty() && *s != "0") {
+            Some(slot) => format!("{category}/{name}:{slot}"),
+            None => format!("{category}/{name}"),
+        };
+        Some(Package::new(&name, self.pkg_manager_name(), version))
+    }
+
+    /// Split a package like `glibc-2.38-r10` into its name and its version
+    /// including the revision, `2.38-r10`.
+    pub(crate) fn split_version(pv: &str) -> (&str, Option<&str>) {
+        let without_revision = match pv.rsplit_once("-r") {
+            Some((rest, rev)) if !rev.is_empty() && rev.chars().all(|c| c.is_ascii_digit()) => rest,
+            _ => pv,
+        };
+        let start = without_revision
+            .char_indices()
+            .filter(|&(i, c)| {
+                c == '-' && without_revision[i + 1..].starts_with(|c: char| c.is_ascii_digit())
+            })
+            .last();
+        match start {
+            Some((i, _)) => (&pv[..i], Some(&pv[i + 1..])),
+            None => (pv, None),
+        }
+    }
+
+    /// Read the installed packages from the package database at `vdb`.
+    pub(crate) fn read_vdb(&self, vdb: &Path) -> std::io::Result<Vec<Package>> {
+        let mut pkgs = vec![];
+        for category in fs::read_dir(vdb)? {
+            let category = category?;
+            if !category.file_type()?.is_dir() {
+                continue;
+            }
+            for pkg in fs::read_dir(category.path())? {
+                let pkg = pkg?;
+                let pf = pkg.file_name();
+                let pf = pf.to_string_lossy();
+                // packages that are being merged right now
+                if pf.starts_with("-MERGING-") || !pkg.file_type()?.is_dir() {
+                    continue;
+                }
+                let slot = fs::read_to_string(pkg.path().join("SLOT")).unwrap_or_default();
+                let atom = format!(
+                    "{}/{pf}:{}",
+                    category.file_name().to_string_lossy(),
+                    slot.trim()
+                );
+                pkgs.extend(self.parse_atom(&atom));
+            }
+        }
+        pkgs.sort_by(|a, b| a.name().cmp(b.name()));
+        Ok(pkgs)
+    }
+}
+
+impl PackageManager for Portage {
+    fn pkg_delimiter(&self) -> char {
+        '-'
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Portage.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    /// `emerge --search` detects regular expressions in the query.
+    fn search_uses_regex(&self) -> bool {
+        true
+    }
+
+    /// Versions are pinned with atoms like `=dev-lang/python-3.11.8:3.11`.
+    fn reformat_for_command(&self, pkg: &mut Package) -> String {
+        let Some(version) = pkg.version() else {
+            return pkg.name().to_string();
+        };
+        match pkg.name().split_once(':') {
+            Some((name, slot)) => format!("={name}-{version}:{slot}"),
+            None => format!("={}-{version}", pkg.name()),
+        }
+    }
+
+    /// Parses the packages that `emerge --pretend` would merge, like
+    /// `[ebuild     U  ] dev-lang/python-3.11.9:3.11::gentoo
+    /// [3.11.8:3.11::gentoo]`.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        if !(line.starts_with("[ebuild") || line.starts_with("[binary")) {
+            return None;
+        }
+        let (_, rest) = line.split_once(']')?;
+        self.parse_atom(rest.split_whitespace().next()?)
+    }
+
+    /// Also parses the results of `emerge --search`, which print the name
+    /// and the latest version of a package on separate lines.
+    fn parse_output(&self, out: &[u8]) -> Vec<Package> {
+        let out = String::from_utf8_lossy(out);
+        let mut pkgs = vec![];
+        let mut name = None;
+        for line in out.lines() {
+            if let Some(result) = line.strip_prefix("*  ") {
+                name = result.split_whitespace().next().map(str::to_string);
+            } else if let Some(version) = line.trim().strip_prefix("Latest version available:") {
+                if let Some(name) = name.take() {
+                    let version = version.trim();
+                    // `[ Masked ]`
+                    let version = (!version.starts_with('[')).then_some(version);
+                    pkgs.push(Package::new(&name, self.pkg_manager_name(), version));
+                }
+            } else if let Some(pkg) = self.parse_pkg(line) {
+                pkgs.push(pkg);
+            }
+        }
+        pkgs
+    }
+
+    fn list_installed(&self) -> Vec<Package> {
+        self.read_vdb(Path::new(Self::VDB)).unwrap_or_else(|e| {
+            tracing::warn!("Failed to read {}: {e}", Self::VDB);
+            vec![]
+        })
+    }
+
+    fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
+        self.ensure_sudo();
+        let s = Command::new("eselect")
+            .args(["repository", "enable"])
+            .args(repo)
+            .status()?;
+        anyhow::ensure!(s.success(), "Error adding repo");
+        Ok(())
+    }
+}
+
+impl PackageManagerCommands for Portage {
+    fn cmd(&self) -> Command {
+        Command::new("emerge")
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            Cmd::Install => vec![],
+            Cmd::Uninstall => vec!["--unmerge"],
+            // don't add the package to the world set
+            Cmd::Update => vec!["--update", "--oneshot"],
+            Cmd::UpdateAll => vec!["--update", "--deep", "--newuse", "@world"],
+            // read from the package database, see `Portage::list_installed`
+            Cmd::List => vec![],
+            Cmd::Sync => vec!["--sync"],
+            // enabled with `eselect repository`, see `Portage::add_repo`
+            Cmd::AddRepo => vec![],
+            Cmd::Search => vec!["--search"],
+            Cmd::Outdated => vec!["--pretend", "--update", "--deep", "--newuse", "@world"],
+            Cmd::Clean | Cmd::Autoremove => vec![],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    fn get_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::Outdated => vec!["--quiet".to_string()],
+            _ => vec![],
+        }
+    }
+}
+
+impl Display for Portage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Portage")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let portage = Portage;
+        let output = "
+These are the packages that would be merged, in order:
+
+Calculating dependencies... done!
+[ebuild     U  ] sys-libs/glibc-2.38-r10::gentoo [2.38-r9::gentoo]
+[ebuild     U  ] dev-lang/python-3.11.9:3.11/3.11::gentoo [3.11.8:3.11/3.11::gentoo]
+[binary   R    ] x11-libs/gtk+-3.24.41-r1:3
+";
+        assert_eq!(
+            portage.parse_output(output.as_bytes()),
+            [
+                Package::new("sys-libs/glibc", "portage".to_string(), Some("2.38-r10")),
+                Package::new(
+                    "dev-lang/python:3.11",
+                    "portage".to_string(),
+                    Some("3.11.9")
+                ),
+                Package::new("x11-libs/gtk+:3", "portage".to_string(), Some("3.24.41-r1")),
+            ]
+        );
+        assert_eq!(Portage::split_version("libsdl2"), ("libsdl2", None));
+        assert_eq!(
+            Portage::split_version("libsdl2-2.28.5"),
+            ("libsdl2", Some("2.28.5"))
+        );
+    }
+
+    #[test]
+    fn test_parse_search() {
+        let portage = Portage;
+        let output = "Searching...
+
+[ Results for search key : vim ]
+Number of packages found: 2
+
+*  app-editors/vim
+      Latest version available: 9.0.2167
+      Latest version installed: [ Not Installed ]
+      Size of files: 16,752 KiB
+      Homepage:      https://vim.org/
+      Description:   Vim, an improved vi-style text editor
+      License:       vim
+
+*  app-vim/vim-latex [ Masked ]
+      Latest version available: [ Masked ]
+      Latest version installed: [ Not Installed ]
+";
+        assert_eq!(
+            portage.parse_output(output.as_bytes()),
+            [
+                Package::new("app-editors/vim", "portage".to_string(), Some("9.0.2167")),
+                Package::new("app-vim/vim-latex", "portage".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_vdb() {
+        let portage = Portage;
+        let vdb = tempfile::tempdir().unwrap();
+        for (pkg, slot) in [
+            ("dev-lang/python-3.11.8-r1", "3.11/3.11\n"),
+            ("app-editors/vim-9.0.2167", "0\n"),
+            ("app-editors/-MERGING-nano-7.2-r1", "0\n"),
+        ] {
+            let dir = vdb.path().join(pkg);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("SLOT"), slot).unwrap();
+        }
+        assert_eq!(
+            portage.read_vdb(vdb.path()).unwrap(),
+            [
+                Package::new("app-editors/vim", "portage".to_string(), Some("9.0.2167")),
+                Package::new(
+                    "dev-lang/python:3.11",
+                    "portage".to_string(),
+                    Some("3.11.8-r1")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cmds() {
+        let portage = Portage;
+        let mut pkg = Package::new(
+            "dev-lang/python:3.11",
+            "portage".to_string(),
+            Some("3.11.8"),
+        );
+        let fmt = portage.reformat_for_command(&mut pkg);
+        assert_eq!(
+            portage.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["=dev-lang/python-3.11.8:3.11"]
+        );
+        let mut pkg = Package::from("app-editors/vim");
+        let fmt = portage.reformat_for_command(&mut pkg);
+        assert_eq!(
+            portage.consolidated(Cmd::Uninstall, Some(&pkg), &[fmt]),
+            ["--unmerge", "app-editors/vim"]
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use std::{fmt::Display, process::Command};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+};
+
+/// Wrapper for Scoop, a command-line installer for Windows that installs
+/// programs into the user's home directory.
+///
+/// [Scoop](https://scoop.sh/)
+///
+/// # Idiosyncracies
+/// Scoop is a PowerShell script that is run through its `scoop.cmd` shim on
+/// Windows, and never needs elevated privileges.
+#[derive(Debug, Default)]
+pub struct Scoop;
+
+impl PackageManager for Scoop {
+    fn pkg_delimiter(&self) -> char {
+        '@'
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Scoop.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    /// Parses the rows of the tables printed by `scoop list`, `scoop search`
+    /// and `scoop status`, whose first two columns are the name and the
+    /// (installed) version.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        let mut cols = line.split_whitespace();
+        let name = cols.next()?;
+        let version = cols.next()?;
+        // headings like `Installed apps:` and `Results from local buckets...`
+        let line = line.trim_end();
+        if name == "Name" || name.starts_with('-') || line.ends_with(':') || line.ends_with("...") {
+            return None;
+        }
+        Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+    }
+}
+
+impl PackageManagerCommands for Scoop {
+    fn cmd(&self) -> Command {
+        if cfg!(windows) {
+            Command::new("scoop.cmd")
+        } else {
+            Command::new("scoop")
+        }
+    }
+
+    fn needs_sudo(&self) -> bool {
+        false
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            Cmd::Install => vec!["install"],
+            Cmd::Uninstall => vec!["uninstall"],
+            Cmd::Update => vec!["update"],
+            Cmd::UpdateAll => vec!["update", "*"],
+            Cmd::List => vec!["list"],
+            // updates scoop and its buckets
+            Cmd::Sync => vec!["update"],
+            Cmd::AddRepo => vec!["bucket", "add"],
+            Cmd::Search => vec!["search"],
+            Cmd::Outdated => vec!["status"],
+            Cmd::Clean | Cmd::Autoremove => vec![],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+}
+
+impl Display for Scoop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Scoop")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let scoop = Scoop;
+        let list = "Installed apps:
+
+Name   Version Source Updated             Info
+----   ------- ------ -------             ----
+7zip   23.01   main   2024-01-02 10:11:12
+git    2.43.0  main   2024-01-02 10:12:13
+";
+        let pkgs: Vec<_> = list.lines().filter_map(|l| scoop.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("7zip", "scoop".to_string(), Some("23.01")),
+                Package::new("git", "scoop".to_string(), Some("2.43.0")),
+            ]
+        );
+
+        let search = "Results from local buckets...
+
+Name Version Source Binaries
+---- ------- ------ --------
+git  2.43.0  main
+";
+        let pkgs: Vec<_> = search.lines().filter_map(|l| scoop.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [Package::new("git", "scoop".to_string(), Some("2.43.0"))]
+        );
+
+        let status = "Name Installed Version Latest Version Missing Dependencies Info
+---- ----------------- -------------- -------------------- ----
+git  2.42.0            2.43.0
+";
+        let pkgs: Vec<_> = status.lines().filter_map(|l| scoop.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [Package::new("git", "scoop".to_string(), Some("2.42.0"))]
+        );
+    }
+
+    #[test]
+    fn test_cmds() {
+        let scoop = Scoop;
+        assert!(!scoop.needs_sudo());
+        let mut pkg = Package::new("git", "scoop".to_string(), Some("2.43.0"));
+        let fmt = scoop.reformat_for_command(&mut pkg);
+        assert_eq!(
+            scoop.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["install", "git@2.43.0"]
+        );
+        assert_eq!(
+            scoop.consolidated::<&str>(Cmd::UpdateAll, None, &[]),
+            ["update", "*"]
+        );
+        assert_eq!(
+            scoop.consolidated(Cmd::AddRepo, None, &["extras"]),
+            ["bucket", "add", "extras"]
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::{fmt::Display, process::Command};
+
+use crate::{
+    AvailablePackageManager, Cmd, Operation, Package, PackageManager, PackageManagerCommands,
+    PkgFormat,
+};
+
+/// Wrapper for swupd, the software updater of Clear Linux OS.
+///
+/// [swupd - Clear Linux OS](https://www.clearlinux.org/clear-linux-documentation/guides/clear/swupd.html)
+///
+/// # Idiosyncracies
+/// Software is installed as bundles, which have no versions of their own:
+/// all bundles are updated together with the OS by `swupd update`. An
+/// available OS update is therefore listed as an update of the
+/// [``Swupd::OS_BUNDLE``] bundle.
+#[derive(Debug, Default)]
+pub struct Swupd;
+
+impl Swupd {
+    /// Bundle that every installation contains.
+    pub const OS_BUNDLE: &'static str = "os-core";
+
+    /// Parse the OS version that `swupd check-update` reports as available.
+    pub(crate) fn parse_check_update(output: &str) -> Option<&str> {
+        output.lines().find_map(|line| {
+            line.strip_prefix("There is a new OS version available:")
+                .map(str::trim)
+        })
+    }
+}
+
+impl PackageManager for Swupd {
+    fn pkg_delimiter(&self) -> char {
+        '@'
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Swupd.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    fn reformat_for_command(&self, pkg: &mut Package) -> String {
+        if pkg.version().is_some() {
+            tracing::warn!("swupd cannot install bundle {} at a version", pkg.name());
+        }
+        pkg.name().to_string()
+    }
+
+    /// Parses the bundles of `swupd bundle-list` like ` - editors` and of
+    /// `swupd search` like `vim - Contains vim text editor`.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        let line = line.trim();
+        let line = line
+            .strip_prefix("- ")
+            .or_else(|| line.strip_prefix("* "))
+            .unwrap_or(line);
+        let name = line.split(" - ").next()?.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) || name.contains(':') {
+            return None;
+        }
+        Some(Package::new(name, self.pkg_manager_name(), None))
+    }
+
+    /// Bundles are only updated together with the OS, so updating a bundle
+    /// updates the OS.
+    fn execute_pkg_command(
+        &self,
+        pkg: &mut Package,
+        op: Operation,
+        interactive: bool,
+    ) -> std::process::ExitStatus {
+        let (cmd, args) = match op {
+            Operation::Install => (Cmd::Install, vec![self.reformat_for_command(pkg)]),
+            Operation::Uninstall => (Cmd::Uninstall, vec![self.reformat_for_command(pkg)]),
+            Operation::Update => {
+                tracing::info!("Updating the OS to update bundle {}", pkg.name());
+                (Cmd::Update, vec![])
+            }
+        };
+        let cmds = self.consolidated(cmd, Some(pkg), &args);
+        self.exec_cmds_status(&cmds, Some(interactive))
+    }
+
+    fn sync(&self) -> std::process::ExitStatus {
+        tracing::debug!("swupd fetches the update content on demand");
+        crate::common::synthetic_exit_status(true)
+    }
+
+    fn list_outdated(&self) -> Vec<Package> {
+        let out = self.exec_cmds(&self.consolidated::<&str>(Cmd::Outdated, None, &[]));
+        Self::parse_check_update(&String::from_utf8_lossy(&out.stdout))
+            .map(|version| Package::new(Self::OS_BUNDLE, self.pkg_manager_name(), Some(version)))
+            .into_iter()
+            .collect()
+    }
+}
+
+impl PackageManagerCommands for Swupd {
+    fn cmd(&self) -> Command {
+        Command::new("swupd")
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            Cmd::Install => vec!["bundle-add"],
+            Cmd::Uninstall => vec!["bundle-remove"],
+            Cmd::Update | Cmd::UpdateAll => vec!["update"],
+            Cmd::List => vec!["bundle-list"],
+            Cmd::Sync | Cmd::Clean | Cmd::Autoremove => vec![],
+            // `swupd 3rd-party add <name> <url>`
+            Cmd::AddRepo => vec!["3rd-party", "add"],
+            Cmd::Search => vec!["search"],
+            Cmd::Outdated => vec!["check-update"],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+}
+
+impl Display for Swupd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("swupd")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let swupd = Swupd;
+        let list = "Installed bundles:
+ - bootloader
+ - editors
+ - os-core
+
+Total: 3
+";
+        let pkgs: Vec<_> = list.lines().filter_map(|l| swupd.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("bootloader", "swupd".to_string(), None),
+                Package::new("editors", "swupd".to_string(), None),
+                Package::new("os-core", "swupd".to_string(), None),
+            ]
+        );
+
+        let search = "Bundles with the term 'vim' in their name or description:
+
+vim - Contains vim text editor
+editors - Popular text editors (terminal-based)
+";
+        let pkgs: Vec<_> = search.lines().filter_map(|l| swupd.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("vim", "swupd".to_string(), None),
+                Package::new("editors", "swupd".to_string(), None),
+            ]
+        );
+
+        let check = "Current OS version: 39000
+Latest server version: 39100
+There is a new OS version available: 39100
+";
+        assert_eq!(Swupd::parse_check_update(check), Some("39100"));
+        assert_eq!(
+            Swupd::parse_check_update("Current OS version: 39100\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cmds() {
+        let swupd = Swupd;
+        let mut pkg = Package::from("editors");
+        let fmt = swupd.reformat_for_command(&mut pkg);
+        assert_eq!(
+            swupd.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            ["bundle-add", "editors"]
+        );
+        assert_eq!(
+            swupd.consolidated(Cmd::AddRepo, None, &["myrepo", "https://example.com/repo"]),
+            ["3rd-party", "add", "myrepo", "https://example.com/repo"]
+        );
+    }
+}
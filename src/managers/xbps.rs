@@ -0,0 +1,187 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{BufWriter, Write},
+    process::Command,
+};
+
+use crate::{
+    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+};
+
+/// Wrapper for the X Binary Package System (xbps), the package manager of
+/// Void Linux.
+///
+/// [XBPS Package Manager - Void Linux Handbook](https://docs.voidlinux.org/xbps/index.html)
+///
+/// # Idiosyncracies
+/// xbps is a set of executables: packages are installed and updated with
+/// `xbps-install`, removed with `xbps-remove` and queried with `xbps-query`
+/// (see [``PackageManagerCommands::cmd_for``]). Repositories are added to
+/// [``Xbps::REPOSITORIES``].
+#[derive(Debug, Default)]
+pub struct Xbps;
+
+impl Xbps {
+    /// Configuration file that repositories are added to.
+    pub const REPOSITORIES: &'static str = "/etc/xbps.d/10-mpm-repositories.conf";
+
+    /// Split a package like `xbps-triggers-0.127_1` into its name and its
+    /// version including the revision, `0.127_1`.
+    pub(crate) fn split_name_version(pkgver: &str) -> Option<(&str, &str)> {
+        let (name, version) = pkgver.rsplit_once('-')?;
+        let (_, revision) = version.rsplit_once('_')?;
+        let valid = !name.is_empty()
+            && version.starts_with(|c: char| c.is_ascii_digit())
+            && !revision.is_empty()
+            && revision.chars().all(|c| c.is_ascii_digit());
+        valid.then_some((name, version))
+    }
+}
+
+impl PackageManager for Xbps {
+    fn pkg_delimiter(&self) -> char {
+        '-'
+    }
+
+    fn pkg_manager_name(&self) -> String {
+        AvailablePackageManager::Xbps.to_string().to_lowercase()
+    }
+
+    fn supported_pkg_formats(&self) -> Vec<PkgFormat> {
+        vec![]
+    }
+
+    /// Parses the lines of `xbps-query -l` like `ii bash-5.2.21_1 GNU Bourne
+    /// Again SHell`, of `xbps-query -Rs` like `[*] bash-5.2.21_1 GNU Bourne
+    /// Again SHell` and of `xbps-install -un` like `bash-5.2.26_1 update
+    /// x86_64 https://repo-default.voidlinux.org/current`.
+    fn parse_pkg<'a>(&self, line: &str) -> Option<Package> {
+        let mut cols = line.split_whitespace();
+        let first = cols.next()?;
+        let (name, version) =
+            Self::split_name_version(first).or_else(|| Self::split_name_version(cols.next()?))?;
+        Some(Package::new(name, self.pkg_manager_name(), Some(version)))
+    }
+
+    fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
+        self.ensure_sudo();
+        let repositories = fs::File::options()
+            .create(true)
+            .append(true)
+            .open(Self::REPOSITORIES)?;
+        let mut writer = BufWriter::new(repositories);
+        for url in repo {
+            writeln!(writer, "repository={url}")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl PackageManagerCommands for Xbps {
+    fn cmd(&self) -> Command {
+        Command::new("xbps-install")
+    }
+
+    fn cmd_for(&self, cmd: Cmd) -> Option<Command> {
+        match cmd {
+            Cmd::Uninstall => Some(Command::new("xbps-remove")),
+            Cmd::List | Cmd::Search => Some(Command::new("xbps-query")),
+            _ => None,
+        }
+    }
+
+    fn get_cmds(&self, cmd: Cmd, _pkg: Option<&Package>) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall => vec![],
+            Cmd::Update | Cmd::UpdateAll => vec!["-u"],
+            Cmd::List => vec!["-l"],
+            Cmd::Sync => vec!["-S"],
+            // written to `Xbps::REPOSITORIES`, see `Xbps::add_repo`
+            Cmd::AddRepo => vec![],
+            Cmd::Search => vec!["-Rs"],
+            // only prints the updates
+            Cmd::Outdated => vec!["-un"],
+            Cmd::Clean | Cmd::Autoremove => vec![],
+        }
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+    }
+
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll => {
+                vec!["-y".to_string()]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl Display for Xbps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("X Binary Package System (xbps)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkg() {
+        let xbps = Xbps;
+        let list = "ii  base-files-0.143_2       Void Linux base system files
+ii  xbps-triggers-0.127_1    XBPS triggers for Void Linux
+";
+        let pkgs: Vec<_> = list.lines().filter_map(|l| xbps.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("base-files", "xbps".to_string(), Some("0.143_2")),
+                Package::new("xbps-triggers", "xbps".to_string(), Some("0.127_1")),
+            ]
+        );
+
+        let search = "[*] bash-5.2.21_1        GNU Bourne Again Shell
+[-] bash-completion-2.11_2 Programmable completion for bash
+";
+        let pkgs: Vec<_> = search.lines().filter_map(|l| xbps.parse_pkg(l)).collect();
+        assert_eq!(
+            pkgs,
+            [
+                Package::new("bash", "xbps".to_string(), Some("5.2.21_1")),
+                Package::new("bash-completion", "xbps".to_string(), Some("2.11_2")),
+            ]
+        );
+
+        let outdated = "bash-5.2.26_1 update x86_64 https://repo-default.voidlinux.org/current 1916928 1048576\n";
+        assert_eq!(
+            xbps.parse_pkg(outdated),
+            Some(Package::new("bash", "xbps".to_string(), Some("5.2.26_1")))
+        );
+        assert_eq!(Xbps::split_name_version("void-repo-nonfree"), None);
+    }
+
+    #[test]
+    fn test_cmds() {
+        let xbps = Xbps;
+        let program = |cmd| xbps.cmd_for(cmd).unwrap_or_else(|| xbps.cmd());
+        assert_eq!(program(Cmd::Install).get_program(), "xbps-install");
+        assert_eq!(program(Cmd::Uninstall).get_program(), "xbps-remove");
+        assert_eq!(program(Cmd::Search).get_program(), "xbps-query");
+
+        let mut pkg = Package::from("bash");
+        let fmt = xbps.reformat_for_command(&mut pkg);
+        assert_eq!(
+            xbps.consolidated(Cmd::Uninstall, Some(&pkg), &[fmt]),
+            ["-y", "bash"]
+        );
+        assert_eq!(
+            xbps.consolidated(Cmd::Search, None, &["bash"]),
+            ["-Rs", "bash"]
+        );
+    }
+}
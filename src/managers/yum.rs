@@ -1,8 +1,8 @@
-use std::{fmt::Display, process::Command};
+use std::{fmt::Display, path::Path, process::Command};
 
 use crate::{
     managers::DandifiedYUM, AvailablePackageManager, Cmd, Package, PackageManager,
-    PackageManagerCommands, PkgFormat,
+    PackageManagerCommands, PkgFormat, Repository,
 };
 
 /// Wrapper for Yellowdog Updater Modified (YUM) package manager.
@@ -17,7 +17,18 @@ pub struct YellowdogUpdaterModified(DandifiedYUM);
 
 impl Default for YellowdogUpdaterModified {
     fn default() -> Self {
-        Self(DandifiedYUM)
+        Self(DandifiedYUM::default())
+    }
+}
+
+impl YellowdogUpdaterModified {
+    /// Main configuration file of yum.
+    const CONF: &'static str = "/etc/yum.conf";
+
+    /// Only resolve mirrors to IPv4 addresses. See
+    /// [``DandifiedYUM::set_force_ipv4``].
+    pub fn set_force_ipv4(&mut self, force_ipv4: bool) {
+        self.0.set_force_ipv4(force_ipv4);
     }
 }
 
@@ -43,19 +54,237 @@ impl PackageManager for YellowdogUpdaterModified {
     fn parse_pkg<'a>(&self, line: &str) -> Option<crate::Package> {
         self.0.parse_pkg(line)
     }
+
+    fn is_protected(&self, pkg: &Package) -> bool {
+        self.0.is_protected(pkg)
+    }
+
+    fn verify_package_signature(&self, path: &Path) -> anyhow::Result<bool> {
+        self.0.verify_package_signature(path)
+    }
+
+    fn installs_atomically(&self) -> bool {
+        self.0.installs_atomically()
+    }
+
+    fn download_updates(&self) -> anyhow::Result<std::process::ExitStatus> {
+        let cmds = self.consolidated(Cmd::Update, None, &["--downloadonly"]);
+        Ok(self.exec_cmds_status(&cmds, Some(false)))
+    }
+
+    fn apply_downloaded_updates(&self) -> anyhow::Result<std::process::ExitStatus> {
+        let cmds = self.consolidated(Cmd::Update, None, &["--cacheonly"]);
+        Ok(self.exec_cmds_status(&cmds, Some(false)))
+    }
+
+    fn reinstall(
+        &self,
+        pkg: &Package,
+        interactive: bool,
+    ) -> anyhow::Result<std::process::ExitStatus> {
+        let mut pkg = pkg.clone();
+        let fmt = self.reformat_for_command(&mut pkg);
+        let cmds =
+            crate::traits::consolidated_as(self, &["reinstall"], Cmd::Install, Some(&pkg), &[fmt]);
+        Ok(self.exec_cmds_status(&cmds, Some(interactive)))
+    }
+
+    fn verify_integrity(&self, pkg: &Package) -> anyhow::Result<bool> {
+        DandifiedYUM::rpm_verify(pkg)
+    }
+
+    fn verify_files(&self, pkg: &Package) -> anyhow::Result<Vec<std::path::PathBuf>> {
+        DandifiedYUM::rpm_verify_files(pkg)
+    }
+
+    fn verify_all(&self) -> anyhow::Result<Vec<(Package, Vec<std::path::PathBuf>)>> {
+        DandifiedYUM::rpm_verify_all(self.pkg_manager_name())
+    }
+
+    /// Reads the `[main]` section of `/etc/yum.conf`.
+    fn get_option(&self, key: &str) -> anyhow::Result<Option<String>> {
+        DandifiedYUM::get_option_in(Path::new(Self::CONF), key)
+    }
+
+    /// Writes the `[main]` section of `/etc/yum.conf`.
+    fn set_option(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        DandifiedYUM::set_option_in(Path::new(Self::CONF), key, value)
+    }
+
+    fn list_mirrors(&self) -> anyhow::Result<Vec<url::Url>> {
+        Ok(DandifiedYUM::list_mirrors_in(&DandifiedYUM::repo_files()))
+    }
+
+    fn set_mirror(&self, url: &url::Url) -> anyhow::Result<()> {
+        DandifiedYUM::set_mirror_in(&DandifiedYUM::repo_files(), url)
+    }
+
+    /// Uses `yum history list`.
+    fn last_transaction(&self) -> Option<String> {
+        let out = self.exec_cmds(&["history".to_string(), "list".to_string()]);
+        out.status
+            .success()
+            .then(|| DandifiedYUM::parse_last_transaction(&String::from_utf8_lossy(&out.stdout)))
+            .flatten()
+    }
+
+    /// Uses `yum history undo`.
+    fn undo_transaction(
+        &self,
+        id: &str,
+        interactive: bool,
+    ) -> anyhow::Result<std::process::ExitStatus> {
+        let mut args = vec!["history".to_string(), "undo".to_string(), id.to_string()];
+        if crate::common::assume_yes() {
+            args.extend(self.yes_flags(Cmd::UpdateAll));
+        }
+        Ok(self.exec_cmds_status(&args, Some(interactive)))
+    }
+
+    /// Uses `yum versionlock`, which needs the versionlock plugin.
+    fn pin(&self, pkg: &Package) -> anyhow::Result<()> {
+        let s = self.exec_cmds_status(&["versionlock", "add", pkg.name()], None);
+        anyhow::ensure!(s.success(), "yum versionlock add {} failed", pkg.name());
+        Ok(())
+    }
+
+    fn unpin(&self, pkg: &Package) -> anyhow::Result<()> {
+        let s = self.exec_cmds_status(&["versionlock", "delete", pkg.name()], None);
+        anyhow::ensure!(s.success(), "yum versionlock delete {} failed", pkg.name());
+        Ok(())
+    }
+
+    fn list_pinned(&self) -> anyhow::Result<Vec<Package>> {
+        let out = self.exec_cmds(&["versionlock".to_string(), "list".to_string()]);
+        anyhow::ensure!(out.status.success(), "yum versionlock list failed");
+        Ok(DandifiedYUM::parse_versionlock(
+            &String::from_utf8_lossy(&out.stdout),
+            self.pkg_manager_name(),
+        ))
+    }
+
+    fn cache_dirs(&self) -> Vec<std::path::PathBuf> {
+        vec![std::path::PathBuf::from("/var/cache/yum")]
+    }
+
+    fn lock_files(&self) -> Vec<std::path::PathBuf> {
+        DandifiedYUM::RPM_LOCKS
+            .map(std::path::PathBuf::from)
+            .to_vec()
+    }
+
+    fn owner(&self, path: &Path) -> anyhow::Result<Package> {
+        DandifiedYUM::rpm_owner(path)
+            .map(|name| Package::new(&name, self.pkg_manager_name(), None))
+            .ok_or_else(|| crate::NotOwned(path.to_path_buf()).into())
+    }
+
+    fn files(&self, pkg: &Package) -> anyhow::Result<Vec<std::path::PathBuf>> {
+        DandifiedYUM::rpm_files(pkg)
+    }
+
+    /// Uses `yum repoquery --whatrequires`, like dnf.
+    fn reverse_dependencies(&self, pkg: &Package) -> anyhow::Result<Vec<Package>> {
+        DandifiedYUM::whatrequires(self, pkg)
+    }
+
+    fn dependencies(&self, pkg: &Package) -> anyhow::Result<crate::DependencyGraph> {
+        let out = self.exec_cmds(&DandifiedYUM::requires_args(pkg));
+        anyhow::ensure!(out.status.success(), "yum repoquery failed");
+        Ok(DandifiedYUM::parse_requires(
+            pkg.name(),
+            &String::from_utf8_lossy(&out.stdout),
+        ))
+    }
+
+    fn info(&self, pkg: &Package) -> anyhow::Result<crate::PackageMetadata> {
+        let out = self.exec_cmds(&["info".to_string(), pkg.name().to_string()]);
+        if !out.status.success() {
+            return Err(crate::error::Error::PackageNotFound(pkg.name().to_string()).into());
+        }
+        Ok(DandifiedYUM::parse_info(
+            pkg.name(),
+            &String::from_utf8_lossy(&out.stdout),
+        ))
+    }
+
+    fn install_plan(&self, pkg: &Package) -> anyhow::Result<crate::InstallPlan> {
+        let mut pkg = pkg.clone();
+        let fmt = self.reformat_for_command(&mut pkg);
+        let out = self.exec_cmds(&["install".to_string(), "--assumeno".to_string(), fmt]);
+        self.0.plan_of_simulation(&out)
+    }
+
+    /// Uses the transaction summary of `yum install --assumeno`, like dnf.
+    fn estimate_operation_size(&self, pkg: &Package) -> Option<u64> {
+        let mut pkg = pkg.clone();
+        let fmt = self.reformat_for_command(&mut pkg);
+        let out = self.exec_cmds(&["install".to_string(), "--assumeno".to_string(), fmt]);
+        DandifiedYUM::parse_install_size(&String::from_utf8_lossy(&out.stdout))
+    }
+
+    fn list_available_with<F: FnMut(Package)>(&self, mut on_pkg: F) -> anyhow::Result<()> {
+        let status = crate::run_command_with(self.cmd(), &["list", "available"], |line| {
+            if let Some(pkg) = self.0.parse_available(line) {
+                on_pkg(Package::new(
+                    pkg.name(),
+                    self.pkg_manager_name(),
+                    pkg.version(),
+                ));
+            }
+        })?;
+        anyhow::ensure!(status.success(), "yum list available failed");
+        Ok(())
+    }
+
+    fn fix_broken(&self) -> anyhow::Result<std::process::ExitStatus> {
+        Ok(self.exec_cmds_status(&["check"], None))
+    }
+
     fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
         self.0.add_repo(repo)
     }
+
+    /// Uses `yum repolist all`.
+    fn list_repos(&self) -> anyhow::Result<Vec<Repository>> {
+        let out = self.exec_cmds(&["repolist".to_string(), "all".to_string()]);
+        anyhow::ensure!(out.status.success(), "yum repolist failed");
+        Ok(DandifiedYUM::parse_repolist(&String::from_utf8_lossy(
+            &out.stdout,
+        )))
+    }
+
+    fn remove_repo(&self, name: &str) -> anyhow::Result<()> {
+        DandifiedYUM::remove_repo_in(&DandifiedYUM::repo_files(), name)
+    }
 }
 
 impl PackageManagerCommands for YellowdogUpdaterModified {
     fn cmd(&self) -> Command {
         Command::new("yum")
     }
+
     fn get_cmds(&self, cmd: crate::Cmd, pkg: Option<&Package>) -> Vec<String> {
         self.0.get_cmds(cmd, pkg)
     }
+
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
+        self.0.yes_flags(cmd)
+    }
+
     fn get_flags(&self, cmd: Cmd) -> Vec<String> {
         self.0.get_flags(cmd)
     }
+
+    fn dry_run_flags(&self, cmd: Cmd) -> Option<Vec<String>> {
+        self.0.dry_run_flags(cmd)
+    }
+
+    fn simulation_succeeded(&self, status: std::process::ExitStatus, output: &[String]) -> bool {
+        self.0.simulation_succeeded(status, output)
+    }
+
+    fn simulation_needs_sudo(&self) -> bool {
+        self.0.simulation_needs_sudo()
+    }
 }
@@ -1,14 +1,145 @@
 //! Zypper package manager
 
-use std::{fmt::Display, process::Command};
+use std::{fmt::Display, path::Path, process::Command};
+
+use anyhow::Context;
 
 use crate::{
-    AvailablePackageManager, Cmd, Package, PackageManager, PackageManagerCommands, PkgFormat,
+    history::HistoryEntry, managers::DandifiedYUM, AvailablePackageManager, Cmd, Package,
+    PackageKind, PackageManager, PackageManagerCommands, PkgFormat, Repository,
 };
 
 /// Wrapper for Zypper package manager. Some openSUSE might support dnf as well.
+///
+/// Besides packages, zypper installs patterns, patches and products, see
+/// [``PackageKind``]. Packages of another kind are installed, removed and
+/// updated with `-t <kind>`, and are parsed with their kind from the XML
+/// output of zypper.
 #[derive(Debug, Default)]
-pub struct Zypper;
+pub struct Zypper {
+    force_ipv4: bool,
+}
+
+impl Zypper {
+    /// Log of installed and removed packages.
+    pub const HISTORY: &'static str = "/var/log/zypp/history";
+
+    /// Parse the zypper history log, e.g.
+    ///
+    /// ```text
+    /// # 2024-07-01 10:00:00 htop-3.3.0-1.1.x86_64.rpm installed ok
+    /// 2024-07-01 10:00:00|install|htop|3.3.0-1.1|x86_64|root@host|repo-oss|...|
+    /// 2024-07-01 10:05:00|remove |htop|3.3.0-1.1|x86_64|root@host|
+    /// ```
+    ///
+    /// Consecutive lines with the same time and action form one entry, the
+    /// packages are given as `name@version`. Repository changes are skipped.
+    pub(crate) fn parse_history(log: &str, pkg_manager: String) -> Vec<HistoryEntry> {
+        let mut entries: Vec<HistoryEntry> = vec![];
+        for line in log.lines().filter(|l| !l.starts_with('#')) {
+            let mut cols = line.split('|').map(str::trim);
+            let (Some(time), Some(action @ ("install" | "remove")), Some(name), Some(version)) =
+                (cols.next(), cols.next(), cols.next(), cols.next())
+            else {
+                continue;
+            };
+            let pkg = format!("{name}@{version}");
+            let time = crate::history::local_to_utc(time);
+            match entries.last_mut() {
+                Some(last) if last.time == time && last.operation == action => {
+                    last.packages.push(pkg)
+                }
+                _ => entries.push(HistoryEntry::native(
+                    &time,
+                    pkg_manager.clone(),
+                    action,
+                    vec![pkg],
+                )),
+            }
+        }
+        entries
+    }
+
+    /// Parse the XML output of `zypper lr`, e.g.
+    ///
+    /// ```xml
+    /// <repo-list>
+    /// <repo alias="repo-oss" name="Main Repository (OSS)" enabled="1" ...>
+    /// <url>http://download.opensuse.org/tumbleweed/repo/oss/</url>
+    /// </repo>
+    /// </repo-list>
+    /// ```
+    pub(crate) fn parse_repos(out: &[u8]) -> anyhow::Result<Vec<Repository>> {
+        let root = xmltree::Element::parse(out).context("zypper printed invalid XML")?;
+        let Some(list) = root.get_child("repo-list") else {
+            return Ok(vec![]);
+        };
+        Ok(list
+            .children
+            .iter()
+            .filter_map(|r| r.as_element())
+            .filter_map(|r| {
+                let url = r
+                    .get_child("url")
+                    .and_then(|u| u.get_text())
+                    .map(|u| u.trim().to_string());
+                Some(Repository {
+                    name: r.attributes.get("alias")?.clone(),
+                    url,
+                    enabled: r.attributes.get("enabled").map(String::as_str) == Some("1"),
+                })
+            })
+            .collect())
+    }
+
+    /// Names of the packages in the XML output of `zypper packages
+    /// --unneeded`, e.g.
+    ///
+    /// ```xml
+    /// <search-result version="0.0">
+    /// <solvable-list>
+    /// <solvable status="installed" name="libfoo1" kind="package" edition="1.0-1.1" arch="x86_64" repository="(System Packages)"/>
+    /// </solvable-list>
+    /// </search-result>
+    /// ```
+    pub(crate) fn parse_unneeded(out: &[u8]) -> anyhow::Result<Vec<String>> {
+        let root = xmltree::Element::parse(out).context("zypper printed invalid XML")?;
+        let Some(list) = root
+            .get_child("search-result")
+            .and_then(|r| r.get_child("solvable-list"))
+        else {
+            return Ok(vec![]);
+        };
+        let mut names: Vec<String> = list
+            .children
+            .iter()
+            .filter_map(|s| s.as_element())
+            .filter(|s| s.attributes.get("kind").map_or(true, |k| k == "package"))
+            .filter_map(|s| s.attributes.get("name").cloned())
+            .collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Only connect to repositories over IPv4 (`--ipv4`). Useful on networks
+    /// with broken IPv6.
+    pub fn set_force_ipv4(&mut self, force_ipv4: bool) {
+        self.force_ipv4 = force_ipv4;
+    }
+
+    /// Options that must come before the command.
+    fn global_options(&self, with_pkg: bool) -> Vec<String> {
+        let mut opts = vec![];
+        if self.force_ipv4 {
+            opts.push("--ipv4".to_string());
+        }
+        if with_pkg {
+            opts.push("--no-gpg-checks".to_string());
+        }
+        opts
+    }
+}
 
 impl PackageManager for Zypper {
     fn pkg_delimiter(&self) -> char {
@@ -23,30 +154,56 @@ impl PackageManager for Zypper {
         vec![PkgFormat::Rpm]
     }
 
-    /// Parses output, generally from stdout, to a Vec of Packages.
-    ///
-    /// The default implementation uses [``PackageManager::parse_pkg``] for
-    /// parsing each line into a [`Package`].
+    fn search_exact_args(&self) -> Vec<String> {
+        vec!["--match-exact".to_string()]
+    }
+
+    /// The XML output of zypper can only be parsed as a whole.
+    fn search_limited(&self, query: &str, limit: usize) -> anyhow::Result<Vec<Package>> {
+        let mut pkgs = self.search(query);
+        pkgs.truncate(limit);
+        Ok(pkgs)
+    }
+
+    /// Parses the XML output of `search` (`search-result/solvable-list`) and
+    /// of `list-updates` and `list-patches` (`update-status/update-list`).
+    /// The version is only known for updates and patches.
     fn parse_output(&self, out: &[u8]) -> Vec<Package> {
         use xmltree::Element;
 
-        let xml = String::from_utf8_lossy(out);
-        let root = Element::parse(xml.as_bytes()).expect("invalid xml");
+        let root = match Element::parse(out) {
+            Ok(root) => root,
+            Err(e) => {
+                tracing::warn!("zypper printed invalid XML: {e}");
+                return vec![];
+            }
+        };
         let list = root
             .get_child("search-result")
-            .expect("no search result found")
-            .get_child("solvable-list")
-            .expect("no solvable-list found");
+            .and_then(|r| r.get_child("solvable-list"))
+            .or_else(|| {
+                root.get_child("update-status")
+                    .and_then(|s| s.get_child("update-list"))
+            });
+        let Some(list) = list else {
+            tracing::debug!("zypper found no packages");
+            return vec![];
+        };
 
         let mut packages = vec![];
-        for p in &list.children {
-            if let Some(p) = p.as_element() {
-                packages.push(Package::new(
-                    p.attributes.get("name").expect("must have name"),
-                    self.pkg_manager_name(),
-                    None,
-                ));
+        for p in list.children.iter().filter_map(|p| p.as_element()) {
+            let Some(name) = p.attributes.get("name") else {
+                continue;
+            };
+            let version = p.attributes.get("edition").map(String::as_str);
+            let mut pkg = Package::new(name, self.pkg_manager_name(), version);
+            if let Some(kind) = p.attributes.get("kind") {
+                match kind.parse::<PackageKind>() {
+                    Ok(kind) => pkg.set_kind(kind),
+                    Err(_) => tracing::debug!("unknown kind {kind} of {name}"),
+                }
             }
+            packages.push(pkg);
         }
         packages
     }
@@ -73,6 +230,98 @@ impl PackageManager for Zypper {
         }
     }
 
+    /// All packages are installed in a single zypper transaction.
+    fn installs_atomically(&self) -> bool {
+        true
+    }
+
+    /// `zypper install --force` reinstalls an installed package.
+    fn reinstall(
+        &self,
+        pkg: &Package,
+        interactive: bool,
+    ) -> anyhow::Result<std::process::ExitStatus> {
+        let mut pkg = pkg.clone();
+        let fmt = self.reformat_for_command(&mut pkg);
+        let cmds = self.consolidated(Cmd::Install, Some(&pkg), &["--force".to_string(), fmt]);
+        Ok(self.exec_cmds_status(&cmds, Some(interactive)))
+    }
+
+    fn supports_kind(&self, kind: PackageKind) -> bool {
+        matches!(
+            kind,
+            PackageKind::Package | PackageKind::Pattern | PackageKind::Patch | PackageKind::Product
+        )
+    }
+
+    /// `zypper list-patches`, with `--all` to include the patches that are
+    /// not needed.
+    fn list_patches(&self, all: bool) -> anyhow::Result<Vec<Package>> {
+        let mut cmds = self.global_options(false);
+        cmds.extend(["--xmlout".to_string(), "list-patches".to_string()]);
+        if all {
+            cmds.push("--all".to_string());
+        }
+        let out = self.exec_cmds(&cmds);
+        anyhow::ensure!(out.status.success(), "zypper list-patches failed");
+        Ok(self.parse_output(&out.stdout))
+    }
+
+    fn verify_integrity(&self, pkg: &Package) -> anyhow::Result<bool> {
+        DandifiedYUM::rpm_verify(pkg)
+    }
+
+    fn verify_files(&self, pkg: &Package) -> anyhow::Result<Vec<std::path::PathBuf>> {
+        DandifiedYUM::rpm_verify_files(pkg)
+    }
+
+    fn verify_all(&self) -> anyhow::Result<Vec<(Package, Vec<std::path::PathBuf>)>> {
+        DandifiedYUM::rpm_verify_all(self.pkg_manager_name())
+    }
+
+    fn verify_package_signature(&self, path: &Path) -> anyhow::Result<bool> {
+        DandifiedYUM::rpm_checksig(path)
+    }
+
+    /// Reads [``Zypper::HISTORY``].
+    fn history(&self) -> anyhow::Result<Vec<HistoryEntry>> {
+        let log = std::fs::read_to_string(Self::HISTORY)
+            .with_context(|| format!("failed to read {}", Self::HISTORY))?;
+        Ok(Self::parse_history(&log, self.pkg_manager_name()))
+    }
+
+    /// Uses `zypper clean --all`.
+    fn cache_dirs(&self) -> Vec<std::path::PathBuf> {
+        vec![std::path::PathBuf::from("/var/cache/zypp")]
+    }
+
+    /// zypper has no autoremove, the packages listed by `zypper packages
+    /// --unneeded` are removed instead.
+    fn autoremove(&self, interactive: bool) -> anyhow::Result<std::process::ExitStatus> {
+        let out = self.exec_cmds(&self.consolidated::<&str>(Cmd::Autoremove, None, &[]));
+        anyhow::ensure!(out.status.success(), "zypper packages --unneeded failed");
+        let names = Self::parse_unneeded(&out.stdout)?;
+        if names.is_empty() {
+            return Ok(crate::common::synthetic_exit_status(true));
+        }
+        Ok(self.exec_cmds_status(
+            &self.consolidated(Cmd::Uninstall, None, &names),
+            Some(interactive),
+        ))
+    }
+
+    /// Uses `rpm -qf`.
+    fn owner(&self, path: &Path) -> anyhow::Result<Package> {
+        DandifiedYUM::rpm_owner(path)
+            .map(|name| Package::new(&name, self.pkg_manager_name(), None))
+            .ok_or_else(|| crate::NotOwned(path.to_path_buf()).into())
+    }
+
+    /// Uses `rpm -ql`.
+    fn files(&self, pkg: &Package) -> anyhow::Result<Vec<std::path::PathBuf>> {
+        DandifiedYUM::rpm_files(pkg)
+    }
+
     fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
         anyhow::ensure!(
             self.install(
@@ -90,6 +339,24 @@ impl PackageManager for Zypper {
         );
         Ok(())
     }
+
+    /// Uses `zypper lr`, the repositories are named by their alias.
+    fn list_repos(&self) -> anyhow::Result<Vec<Repository>> {
+        let mut cmds = self.global_options(false);
+        cmds.extend(["--xmlout".to_string(), "lr".to_string()]);
+        let out = self.exec_cmds(&cmds);
+        anyhow::ensure!(out.status.success(), "zypper lr failed");
+        Self::parse_repos(&out.stdout)
+    }
+
+    /// Uses `zypper removerepo`.
+    fn remove_repo(&self, name: &str) -> anyhow::Result<()> {
+        let mut cmds = self.global_options(false);
+        cmds.extend(["removerepo".to_string(), name.to_string()]);
+        let s = self.exec_cmds_status(&cmds, None);
+        anyhow::ensure!(s.success(), "zypper removerepo {name} failed");
+        Ok(())
+    }
 }
 
 impl Display for Zypper {
@@ -105,27 +372,43 @@ impl PackageManagerCommands for Zypper {
     }
 
     fn get_cmds(&self, cmd: Cmd, pkg: Option<&Package>) -> Vec<String> {
-        let mut cmd: Vec<_> = match cmd {
-            Cmd::Install => vec!["install"],
-            Cmd::Uninstall => vec!["remove"],
-            Cmd::Update => vec!["update"],
-            Cmd::UpdateAll => vec!["dist-upgrade"],
-            Cmd::List => vec!["--xmlout", "search"],
-            Cmd::Sync => vec!["refresh"],
-            Cmd::AddRepo => vec!["addrepo"],
-            Cmd::Search => vec!["--xmlout", "search"],
-            Cmd::Outdated => vec!["--xmlout", "list-updates"],
+        let mut cmds = self.global_options(pkg.is_some());
+        cmds.extend(
+            match cmd {
+                Cmd::Install => vec!["install"],
+                Cmd::Uninstall => vec!["remove"],
+                Cmd::Update => vec!["update"],
+                Cmd::UpdateAll => vec!["dist-upgrade"],
+                Cmd::List => vec!["--xmlout", "search"],
+                Cmd::Sync => vec!["refresh"],
+                Cmd::AddRepo => vec!["addrepo"],
+                Cmd::Search => vec!["--xmlout", "search"],
+                Cmd::Outdated => vec!["--xmlout", "list-updates"],
+                Cmd::Clean => vec!["clean", "--all"],
+                // lists the unneeded packages, see `Zypper::autoremove`
+                Cmd::Autoremove => vec!["--xmlout", "packages", "--unneeded"],
+            }
+            .iter()
+            .map(|x| x.to_string()),
+        );
+
+        // e.g. `install -t pattern devel_basis`
+        if let (Cmd::Install | Cmd::Uninstall | Cmd::Update, Some(kind)) =
+            (cmd, pkg.and_then(Package::kind))
+        {
+            cmds.push("-t".to_string());
+            cmds.push(kind.to_string());
         }
-        .iter()
-        .map(|x| x.to_string())
-        .collect();
+        cmds
+    }
 
-        // run zypper in non-interactive mode.
-        cmd.insert(0, "-n".to_string());
-        if pkg.is_some() {
-            cmd.insert(1, "--no-gpg-checks".to_string());
+    fn yes_flags(&self, cmd: Cmd) -> Vec<String> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll => {
+                vec!["--no-confirm".to_string()]
+            }
+            _ => vec![],
         }
-        cmd
     }
 
     fn get_flags(&self, cmd: Cmd) -> Vec<String> {
@@ -140,6 +423,20 @@ impl PackageManagerCommands for Zypper {
         .map(|x| x.to_string())
         .collect()
     }
+
+    fn dry_run_flags(&self, cmd: Cmd) -> Option<Vec<String>> {
+        match cmd {
+            Cmd::Install | Cmd::Uninstall | Cmd::Update | Cmd::UpdateAll => {
+                Some(vec!["--dry-run".to_string()])
+            }
+            _ => None,
+        }
+    }
+
+    /// `zypper --dry-run` locks the package database like a real run.
+    fn simulation_needs_sudo(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -151,6 +448,155 @@ mod tests {
     #[test]
     #[traced_test]
     fn test_generate_cmd_zypper() {
-        let _zypper = Zypper;
+        let _zypper = Zypper::default();
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let zypper = Zypper::default();
+        let search = br#"<?xml version='1.0'?>
+<stream>
+<search-result version="0.0">
+<solvable-list>
+<solvable status="not-installed" name="devel_basis" summary="Base Development" kind="pattern"/>
+<solvable status="installed" name="vim" summary="Vi IMproved" kind="package"/>
+</solvable-list>
+</search-result>
+</stream>"#;
+        let mut pattern = Package::new("devel_basis", "zypper".to_string(), None);
+        pattern.set_kind(PackageKind::Pattern);
+        assert_eq!(
+            zypper.parse_output(search),
+            [pattern, Package::new("vim", "zypper".to_string(), None)]
+        );
+
+        let patches = br#"<?xml version='1.0'?>
+<stream>
+<update-status version="0.6">
+<update-list>
+<update name="openSUSE-SLE-15.5-2024-123" edition="1" arch="noarch" kind="patch" status="needed" category="security" severity="important"/>
+</update-list>
+</update-status>
+</stream>"#;
+        let pkgs = zypper.parse_output(patches);
+        assert_eq!(pkgs.len(), 1);
+        assert_eq!(pkgs[0].version(), Some("1"));
+        assert_eq!(pkgs[0].kind(), Some(PackageKind::Patch));
+
+        assert!(zypper
+            .parse_output(b"Repository 'oss' is out-of-date.")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_parse_unneeded() {
+        let out = br#"<?xml version='1.0'?>
+<stream>
+<message type="info">Loading repository data...</message>
+<message type="info">Reading installed packages...</message>
+<search-result version="0.0">
+<solvable-list>
+<solvable status="installed" name="libfoo1" kind="package" edition="1.0-1.1" arch="x86_64" repository="(System Packages)"/>
+<solvable status="installed" name="libfoo1" kind="package" edition="1.0-1.1" arch="i586" repository="(System Packages)"/>
+<solvable status="installed" name="libbar2" kind="package" edition="2.3-4.2" arch="x86_64" repository="repo-oss"/>
+</solvable-list>
+</search-result>
+</stream>"#;
+        assert_eq!(Zypper::parse_unneeded(out).unwrap(), ["libbar2", "libfoo1"]);
+
+        let none = br#"<?xml version='1.0'?>
+<stream>
+<message type="info">No packages found.</message>
+</stream>"#;
+        assert!(Zypper::parse_unneeded(none).unwrap().is_empty());
+        assert!(Zypper::parse_unneeded(b"libfoo1 | 1.0-1.1").is_err());
+    }
+
+    #[test]
+    fn test_parse_repos() {
+        let out = br#"<?xml version='1.0'?>
+<stream>
+<repo-list>
+<repo alias="repo-oss" name="Main Repository (OSS)" type="rpm-md" priority="99" enabled="1" autorefresh="1" gpgcheck="1">
+<url>http://download.opensuse.org/tumbleweed/repo/oss/</url>
+</repo>
+<repo alias="repo-debug" name="Debug Repository" type="rpm-md" priority="99" enabled="0" autorefresh="1" gpgcheck="1">
+<url>http://download.opensuse.org/debug/tumbleweed/repo/oss/</url>
+</repo>
+</repo-list>
+</stream>"#;
+        let repos = Zypper::parse_repos(out).unwrap();
+        assert_eq!(repos.len(), 2);
+        assert_eq!(repos[0].name, "repo-oss");
+        assert_eq!(
+            repos[0].url.as_deref(),
+            Some("http://download.opensuse.org/tumbleweed/repo/oss/")
+        );
+        assert!(repos[0].enabled);
+        assert!(!repos[1].enabled);
+        assert!(Zypper::parse_repos(b"not xml").is_err());
+    }
+
+    #[test]
+    fn test_parse_history() {
+        let log = "# 2024-07-01 10:00:00 htop-3.3.0-1.1.x86_64.rpm installed ok
+2024-07-01 10:00:00|install|htop|3.3.0-1.1|x86_64|root@host|repo-oss|abc|
+2024-07-01 10:00:00|install|tmux|3.4-1.1|x86_64|root@host|repo-oss|def|
+2024-07-01 10:02:00|radd   |repo-oss|https://download.opensuse.org/tumbleweed/repo/oss/|
+2024-07-01 10:05:00|remove |htop|3.3.0-1.1|x86_64|root@host|
+";
+        let entries = Zypper::parse_history(log, "zypper".to_string());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].operation, "install");
+        assert_eq!(entries[0].packages, ["htop@3.3.0-1.1", "tmux@3.4-1.1"]);
+        assert_eq!(
+            entries[1].time,
+            crate::history::local_to_utc("2024-07-01 10:05:00")
+        );
+        assert_eq!(entries[1].operation, "remove");
+    }
+
+    #[test]
+    fn test_cmds() {
+        let zypper = Zypper::default();
+        let mut pkg = Package::from("devel_basis");
+        pkg.set_kind(PackageKind::Pattern);
+        let fmt = zypper.reformat_for_command(&mut pkg);
+        assert_eq!(
+            zypper.consolidated(Cmd::Install, Some(&pkg), &[fmt]),
+            [
+                "--no-gpg-checks",
+                "install",
+                "-t",
+                "pattern",
+                "--no-confirm",
+                "devel_basis"
+            ]
+        );
+        assert_eq!(
+            zypper.consolidated::<&str>(Cmd::Outdated, None, &[]),
+            ["--xmlout", "list-updates"]
+        );
+        assert_eq!(
+            zypper.consolidated::<&str>(Cmd::Clean, None, &[]),
+            ["clean", "--all"]
+        );
+        let mut args = zypper.search_exact_args();
+        args.push("vim".to_string());
+        assert_eq!(
+            zypper.consolidated(Cmd::Search, None, &args),
+            [
+                "--xmlout",
+                "search",
+                "--no-refresh",
+                "-q",
+                "--match-exact",
+                "vim"
+            ]
+        );
+        assert_eq!(
+            zypper.consolidated::<&str>(Cmd::Autoremove, None, &[]),
+            ["--xmlout", "packages", "--unneeded"]
+        );
     }
 }
@@ -1,12 +1,19 @@
+use std::{
+    cmp::Ordering,
+    io::IsTerminal,
+    sync::atomic::{AtomicBool, Ordering as AtomicOrdering},
+};
+
 use colored::{ColoredString, Colorize};
 use strum::{EnumCount, IntoEnumIterator};
 use tabled::{
+    builder::Builder,
     settings::{object::Rows, themes::Colorization, Color, Style},
     Table, Tabled,
 };
 
 use crate::{
-    common::AvailablePackageManager, managers::MetaPackageManager, PackageManager,
+    common::AvailablePackageManager, managers::MetaPackageManager, Package, PackageManager,
     PackageManagerCommands,
 };
 
@@ -36,7 +43,7 @@ impl Listing {
         Listing {
             supported: format!("{pm}").green(),
             file_extensions: mpm
-                .supported_pkg_formats()
+                .supported_pkg_formats_for(std::env::consts::ARCH)
                 .iter()
                 .map(|pkg| pkg.file_extention())
                 .collect::<Vec<_>>()
@@ -51,6 +58,33 @@ impl Listing {
     }
 }
 
+/// A supported package manager, its package file formats and whether it is
+/// available, for structured output.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ManagerStatus {
+    pub name: String,
+    pub file_extensions: Vec<String>,
+    pub available: bool,
+}
+
+/// Supported package managers with availability information.
+pub fn managers() -> Vec<ManagerStatus> {
+    AvailablePackageManager::iter()
+        .map(|pm| {
+            let mpm = MetaPackageManager::new(pm.clone());
+            ManagerStatus {
+                name: pm.to_string().to_lowercase(),
+                file_extensions: mpm
+                    .supported_pkg_formats_for(std::env::consts::ARCH)
+                    .iter()
+                    .map(|pkg| pkg.file_extention())
+                    .collect(),
+                available: mpm.is_available(),
+            }
+        })
+        .collect()
+}
+
 /// Creates a table and prints supported package managers with availability
 /// information
 pub fn print_managers() {
@@ -62,16 +96,208 @@ pub fn print_managers() {
     print_table(table);
 }
 
+/// A field of [`Package`] that can be shown as a column of a package table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Column {
+    Name,
+    Manager,
+    Version,
+    Url,
+    Installed,
+}
+
+impl Column {
+    /// Header of the column in a table.
+    pub fn header(&self) -> &'static str {
+        match self {
+            Column::Name => "name",
+            Column::Manager => "package manager",
+            Column::Version => "version",
+            Column::Url => "url",
+            Column::Installed => "installed",
+        }
+    }
+
+    /// Key of the field in JSON output, same as the serialized [`Package`].
+    pub fn key(&self) -> &'static str {
+        match self {
+            Column::Name => "name",
+            Column::Manager => "package_manager",
+            Column::Version => "version",
+            Column::Url => "url",
+            Column::Installed => "installed",
+        }
+    }
+
+    /// Value of this column for the given package in a table.
+    pub fn cell(&self, pkg: &Package) -> String {
+        match self {
+            Column::Name => pkg.name().to_string(),
+            Column::Manager => pkg.package_manager().to_string(),
+            Column::Version => pkg.version().unwrap_or("~").to_string(),
+            Column::Url => pkg.url().map(|u| u.to_string()).unwrap_or_default(),
+            Column::Installed => match pkg.installed() {
+                Some(true) => "✓".to_string(),
+                _ => String::new(),
+            },
+        }
+    }
+
+    /// Value of this column for the given package in JSON output.
+    pub fn json(&self, pkg: &Package) -> serde_json::Value {
+        match self {
+            Column::Name => pkg.name().into(),
+            Column::Manager => pkg.package_manager().into(),
+            Column::Version => pkg.version().into(),
+            Column::Url => pkg.url().map(|u| u.as_str()).into(),
+            Column::Installed => pkg.installed().into(),
+        }
+    }
+}
+
+/// A field of [`Package`] to sort package tables by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortKey {
+    Name,
+    Version,
+    Manager,
+}
+
+impl SortKey {
+    /// Sorts `pkgs` by this field, then by name. Versions are compared by
+    /// their numbers, so 1.10 comes after 1.9 and pre-releases like 1.9~rc1
+    /// before 1.9; packages without one come last.
+    pub fn sort(&self, pkgs: &mut [Package]) {
+        pkgs.sort_by(|a, b| {
+            let ordering = match self {
+                SortKey::Name => Ordering::Equal,
+                SortKey::Version => match (a.version(), b.version()) {
+                    (Some(a), Some(b)) => cmp_versions(a, b),
+                    (a, b) => b.is_some().cmp(&a.is_some()),
+                },
+                SortKey::Manager => a.package_manager().cmp(b.package_manager()),
+            };
+            ordering.then_with(|| a.name().cmp(b.name()))
+        });
+    }
+}
+
+/// Compares versions by their numbers, so 1.10 comes after 1.9. A `~` sorts
+/// before anything, even the end of the version, so 1.9~rc1 comes before 1.9
+/// like in Debian versions.
+pub(crate) fn cmp_versions(a: &str, b: &str) -> Ordering {
+    let (a, b) = (version_parts(a), version_parts(b));
+    for i in 0..a.len().max(b.len()) {
+        let ordering = match (a.get(i), b.get(i)) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(VersionPart::Tilde), None) => Ordering::Less,
+            (None, Some(VersionPart::Tilde)) => Ordering::Greater,
+            (a, b) => a.is_some().cmp(&b.is_some()),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// A `~`, or a run of digits or of other characters in a version.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum VersionPart<'a> {
+    Tilde,
+    Text(&'a str),
+    Number(u64),
+}
+
+/// Splits `version` into `~` and runs of digits and of other characters.
+fn version_parts(version: &str) -> Vec<VersionPart<'_>> {
+    let mut parts = vec![];
+    let mut rest = version;
+    while let Some(first) = rest.chars().next() {
+        if first == '~' {
+            parts.push(VersionPart::Tilde);
+            rest = &rest[1..];
+            continue;
+        }
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c == '~' || c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (part, tail) = rest.split_at(end);
+        parts.push(match part.parse() {
+            Ok(n) if digits => VersionPart::Number(n),
+            _ => VersionPart::Text(part),
+        });
+        rest = tail;
+    }
+    parts
+}
+
+/// Table of packages showing only the given columns.
+pub fn pkgs_table(pkgs: &[Package], columns: &[Column]) -> Table {
+    let mut builder = Builder::default();
+    builder.push_record(columns.iter().map(Column::header));
+    for pkg in pkgs {
+        builder.push_record(columns.iter().map(|c| c.cell(pkg)));
+    }
+    builder.build()
+}
+
+/// JSON array of packages with only the given fields.
+pub fn pkgs_json(pkgs: &[Package], columns: &[Column]) -> serde_json::Value {
+    pkgs.iter()
+        .map(|pkg| {
+            columns
+                .iter()
+                .map(|c| (c.key().to_string(), c.json(pkg)))
+                .collect::<serde_json::Map<_, _>>()
+        })
+        .collect()
+}
+
 /// Takes a `Table` type and sets appropriate styling options, then prints in
 pub fn print_table(mut table: Table) {
-    table
-        .with(Style::rounded().remove_horizontals())
-        .with(Colorization::exact([Color::FG_CYAN], Rows::first()));
+    table.with(Style::rounded().remove_horizontals());
+    if colors_enabled() {
+        table.with(Colorization::exact([Color::FG_CYAN], Rows::first()));
+    }
     println!("{table}");
 }
 
-/// Log error
+/// Whether `--no-color` was given, see [`init_colors`].
+static NO_COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Decide once whether the output is colored: never with `no_color` (the
+/// `--no-color` flag), always if `CLICOLOR_FORCE` is set, never if
+/// `NO_COLOR` is set, otherwise only when stdout is a terminal.
+pub fn init_colors(no_color: bool) {
+    NO_COLOR.store(no_color, AtomicOrdering::Relaxed);
+    colored::control::set_override(colors_for(std::io::stdout().is_terminal()));
+}
+
+/// Whether a stream is colored, given whether it is a terminal.
+fn colors_for(terminal: bool) -> bool {
+    should_color(
+        NO_COLOR.load(AtomicOrdering::Relaxed),
+        std::env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0"),
+        std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+        terminal,
+    )
+}
+
+/// Whether output is colored, see [`init_colors`].
+pub fn colors_enabled() -> bool {
+    colored::control::SHOULD_COLORIZE.should_colorize()
+}
+
+fn should_color(no_color: bool, force: bool, no_color_env: bool, terminal: bool) -> bool {
+    !no_color && (force || (!no_color_env && terminal))
+}
+
+/// Log error, colored when stderr is, whatever stdout is.
 pub fn log_error(err: anyhow::Error) {
+    let stdout_colors = colors_enabled();
+    colored::control::set_override(colors_for(std::io::stderr().is_terminal()));
     eprintln!("{} {err}", "Error:".red().bold());
     for (i, cause) in err.chain().skip(1).enumerate() {
         if i == 0 {
@@ -79,4 +305,60 @@ pub fn log_error(err: anyhow::Error) {
         }
         eprintln!("({}) {cause}", i + 1);
     }
+    colored::control::set_override(stdout_colors);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pkgs_table_columns() {
+        let pkgs = [
+            Package::new("foo", "apt".to_string(), Some("1.0")),
+            Package::new("bar", "apt".to_string(), Some("2.0")),
+        ];
+        let table = pkgs_table(&pkgs, &[Column::Name]).to_string();
+        assert!(table.contains("name"));
+        assert!(table.contains("foo") && table.contains("bar"));
+        assert!(!table.contains("version"));
+        assert!(!table.contains("apt"));
+        assert!(!table.contains("1.0"));
+
+        assert_eq!(
+            pkgs_json(&pkgs, &[Column::Name, Column::Version]),
+            serde_json::json!([
+                {"name": "foo", "version": "1.0"},
+                {"name": "bar", "version": "2.0"},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_should_color() {
+        assert!(should_color(false, false, false, true));
+        // piped output, e.g. in CI or journald
+        assert!(!should_color(false, false, false, false));
+        assert!(!should_color(false, false, true, true));
+        assert!(should_color(false, true, true, false));
+        assert!(!should_color(true, true, false, true));
+    }
+
+    #[test]
+    fn test_sort_key() {
+        let mut pkgs = [
+            Package::new("foo", "brew".to_string(), Some("1.10")),
+            Package::new("bar", "apt".to_string(), None),
+            Package::new("baz", "apt".to_string(), Some("1.9")),
+            Package::new("qux", "brew".to_string(), Some("1.9~rc1")),
+        ];
+        let names = |pkgs: &[Package]| pkgs.iter().map(|p| p.name()).collect::<Vec<_>>();
+
+        SortKey::Name.sort(&mut pkgs);
+        assert_eq!(names(&pkgs), ["bar", "baz", "foo", "qux"]);
+        SortKey::Version.sort(&mut pkgs);
+        assert_eq!(names(&pkgs), ["qux", "baz", "foo", "bar"]);
+        SortKey::Manager.sort(&mut pkgs);
+        assert_eq!(names(&pkgs), ["bar", "baz", "foo", "qux"]);
+    }
 }
@@ -0,0 +1,399 @@
+//! Jobs that run mpm on a schedule, see `mpm schedule`.
+//!
+//! A [`Job`] wraps an mpm invocation, e.g. `mpm --yes update --all`, in the
+//! native scheduler of the system: a systemd service and timer on Linux, a
+//! launchd daemon on macOS and a Task Scheduler task on Windows. The jobs
+//! run as root, or as SYSTEM on Windows, since updating packages needs it.
+
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+
+/// Directory of the systemd units of the administrator.
+const SYSTEMD_DIR: &str = "/etc/systemd/system";
+/// Directory of the system-wide launchd daemons.
+const LAUNCHD_DIR: &str = "/Library/LaunchDaemons";
+/// Prefix of the launchd labels of the jobs.
+const LAUNCHD_PREFIX: &str = "com.subconsciouscompute";
+/// Folder of the scheduled tasks of mpm in the Task Scheduler.
+const TASK_FOLDER: &str = "mpm";
+
+/// How often a [`Job`] runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Interval {
+    /// At the start of every hour.
+    Hourly,
+    /// Every day at 03:00.
+    #[default]
+    Daily,
+    /// Every Monday at 03:00.
+    Weekly,
+}
+
+impl Interval {
+    /// Value of `OnCalendar` in a systemd timer.
+    fn on_calendar(&self) -> &'static str {
+        match self {
+            Interval::Hourly => "hourly",
+            Interval::Daily => "*-*-* 03:00:00",
+            Interval::Weekly => "Mon *-*-* 03:00:00",
+        }
+    }
+
+    /// Entries of `StartCalendarInterval` in a launchd plist.
+    fn calendar_interval(&self) -> &'static [(&'static str, u8)] {
+        match self {
+            Interval::Hourly => &[("Minute", 0)],
+            Interval::Daily => &[("Hour", 3), ("Minute", 0)],
+            Interval::Weekly => &[("Weekday", 1), ("Hour", 3), ("Minute", 0)],
+        }
+    }
+
+    /// Arguments of `schtasks /Create` for the schedule.
+    fn schtasks_schedule(&self) -> &'static [&'static str] {
+        match self {
+            Interval::Hourly => &["/SC", "HOURLY"],
+            Interval::Daily => &["/SC", "DAILY", "/ST", "03:00"],
+            Interval::Weekly => &["/SC", "WEEKLY", "/D", "MON", "/ST", "03:00"],
+        }
+    }
+}
+
+/// An mpm invocation that runs on a schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    /// Name of the job, e.g. `mpm-update`.
+    pub name: String,
+    pub interval: Interval,
+    /// Command line of the job, the mpm executable first.
+    pub command: Vec<String>,
+}
+
+impl Job {
+    /// Job `name` running the mpm executable `exe` with `args`. Characters
+    /// that are not allowed in the names of units and tasks are replaced.
+    pub fn new(name: &str, exe: &Path, args: &[String], interval: Interval) -> Self {
+        let name = name
+            .chars()
+            .map(|c| match c {
+                c if c.is_ascii_alphanumeric() || c == '-' || c == '_' => c,
+                _ => '-',
+            })
+            .collect();
+        Self {
+            name,
+            interval,
+            command: std::iter::once(exe.display().to_string())
+                .chain(args.iter().cloned())
+                .collect(),
+        }
+    }
+
+    /// The job's command line without the path of the executable.
+    fn description(&self) -> String {
+        let args = self.command.iter().skip(1).map(|arg| systemd_quote(arg));
+        std::iter::once(Cow::Borrowed("mpm"))
+            .chain(args)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Paths and contents of the systemd service and timer.
+    pub fn systemd_units(&self) -> Vec<(PathBuf, String)> {
+        let exec: Vec<Cow<'_, str>> = self.command.iter().map(|a| systemd_quote(a)).collect();
+        let service = format!(
+            "# Generated by mpm\n\
+             [Unit]\n\
+             Description={description}\n\
+             Wants=network-online.target\n\
+             After=network-online.target\n\
+             \n\
+             [Service]\n\
+             Type=oneshot\n\
+             ExecStart={exec}\n",
+            description = self.description(),
+            exec = exec.join(" "),
+        );
+        let timer = format!(
+            "# Generated by mpm\n\
+             [Unit]\n\
+             Description=Run {description} on a schedule\n\
+             \n\
+             [Timer]\n\
+             OnCalendar={calendar}\n\
+             Persistent=true\n\
+             RandomizedDelaySec=10min\n\
+             \n\
+             [Install]\n\
+             WantedBy=timers.target\n",
+            description = self.description(),
+            calendar = self.interval.on_calendar(),
+        );
+        let dir = Path::new(SYSTEMD_DIR);
+        vec![
+            (dir.join(format!("{}.service", self.name)), service),
+            (dir.join(format!("{}.timer", self.name)), timer),
+        ]
+    }
+
+    /// Label of the launchd daemon.
+    fn launchd_label(&self) -> String {
+        format!("{LAUNCHD_PREFIX}.{}", self.name)
+    }
+
+    /// Path and contents of the launchd property list.
+    pub fn launchd_plist(&self) -> (PathBuf, String) {
+        let args: String = self
+            .command
+            .iter()
+            .map(|arg| format!("        <string>{}</string>\n", xml_escape(arg)))
+            .collect();
+        let calendar: String = self
+            .interval
+            .calendar_interval()
+            .iter()
+            .map(|(key, value)| {
+                format!("        <key>{key}</key>\n        <integer>{value}</integer>\n")
+            })
+            .collect();
+        let label = self.launchd_label();
+        let plist = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \
+             \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+             <!-- Generated by mpm -->\n\
+             <plist version=\"1.0\">\n\
+             <dict>\n    \
+                 <key>Label</key>\n    \
+                 <string>{label}</string>\n    \
+                 <key>ProgramArguments</key>\n    \
+                 <array>\n\
+             {args}    \
+                 </array>\n    \
+                 <key>StartCalendarInterval</key>\n    \
+                 <dict>\n\
+             {calendar}    \
+                 </dict>\n\
+             </dict>\n\
+             </plist>\n"
+        );
+        (Path::new(LAUNCHD_DIR).join(format!("{label}.plist")), plist)
+    }
+
+    /// Name of the task in the Task Scheduler.
+    fn task_name(&self) -> String {
+        format!("{TASK_FOLDER}\\{}", self.name)
+    }
+
+    /// Arguments of `schtasks` creating the task, replacing an existing one.
+    pub fn schtasks_args(&self) -> Vec<String> {
+        let run: Vec<Cow<'_, str>> = self.command.iter().map(|a| windows_quote(a)).collect();
+        ["/Create", "/TN", &self.task_name(), "/TR", &run.join(" ")]
+            .into_iter()
+            .chain(self.interval.schtasks_schedule().iter().copied())
+            .chain(["/RU", "SYSTEM", "/F"])
+            .map(String::from)
+            .collect()
+    }
+
+    /// What [`Job::install`] would set up on `os` (as named by
+    /// `std::env::consts::OS`): the files with their path in a comment, or
+    /// the `schtasks` command on Windows.
+    pub fn emit(&self, os: &str) -> anyhow::Result<String> {
+        let files = match os {
+            "linux" => self.systemd_units(),
+            "macos" => vec![self.launchd_plist()],
+            "windows" => {
+                let args = self.schtasks_args();
+                let args: Vec<Cow<'_, str>> = args.iter().map(|a| windows_quote(a)).collect();
+                return Ok(format!("schtasks {}\n", args.join(" ")));
+            }
+            os => anyhow::bail!("scheduling jobs is not supported on {os}"),
+        };
+        Ok(files
+            .iter()
+            .map(|(path, content)| {
+                let comment = if path.extension().is_some_and(|e| e == "plist") {
+                    format!("<!-- {} -->", path.display())
+                } else {
+                    format!("# {}", path.display())
+                };
+                format!("{comment}\n{content}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    /// Installs and enables the job in the scheduler of this system. Returns
+    /// the files that were written, or the name of the task on Windows.
+    pub fn install(&self) -> anyhow::Result<Vec<PathBuf>> {
+        match std::env::consts::OS {
+            "linux" => {
+                let units = self.systemd_units();
+                for (path, content) in &units {
+                    std::fs::write(path, content)
+                        .with_context(|| format!("failed to write {path:?}"))?;
+                }
+                run(Command::new("systemctl"), &["daemon-reload"])?;
+                let timer = format!("{}.timer", self.name);
+                run(Command::new("systemctl"), &["enable", "--now", &timer])?;
+                Ok(units.into_iter().map(|(path, _)| path).collect())
+            }
+            "macos" => {
+                let (path, plist) = self.launchd_plist();
+                std::fs::write(&path, plist)
+                    .with_context(|| format!("failed to write {path:?}"))?;
+                let path_str = path.display().to_string();
+                run(Command::new("launchctl"), &["load", "-w", &path_str])?;
+                Ok(vec![path])
+            }
+            "windows" => {
+                run(Command::new("schtasks"), &self.schtasks_args())?;
+                Ok(vec![PathBuf::from(self.task_name())])
+            }
+            os => anyhow::bail!("scheduling jobs is not supported on {os}"),
+        }
+    }
+}
+
+/// Runs `cmd` with `args` and fails if it does.
+fn run<S: AsRef<str> + AsRef<std::ffi::OsStr>>(cmd: Command, args: &[S]) -> anyhow::Result<()> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let status = crate::run_command(cmd, args, true, None)?.0;
+    anyhow::ensure!(status.success(), "{program} failed with {status}");
+    Ok(())
+}
+
+/// Quote `arg` for a systemd `ExecStart` line if needed.
+fn systemd_quote(arg: &str) -> Cow<'_, str> {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || "\"'\\;$%".contains(c)) {
+        return Cow::Borrowed(arg);
+    }
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "$$")
+        .replace('%', "%%");
+    Cow::Owned(format!("\"{escaped}\""))
+}
+
+/// Quote `arg` for a Windows command line if needed.
+fn windows_quote(arg: &str) -> Cow<'_, str> {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"') {
+        return Cow::Borrowed(arg);
+    }
+    Cow::Owned(format!("\"{}\"", arg.replace('"', "\\\"")))
+}
+
+/// Escape `s` for the text of an XML element.
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(interval: Interval) -> Job {
+        let args = ["--yes", "update", "--all"].map(String::from);
+        Job::new(
+            "mpm-update",
+            Path::new("/usr/local/bin/mpm"),
+            &args,
+            interval,
+        )
+    }
+
+    #[test]
+    fn test_job_name() {
+        let job = Job::new("nightly sync", Path::new("mpm"), &[], Interval::Daily);
+        assert_eq!(job.name, "nightly-sync");
+    }
+
+    #[test]
+    fn test_systemd_units() {
+        let units = job(Interval::Weekly).systemd_units();
+        let paths: Vec<_> = units.iter().map(|(p, _)| p.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from("/etc/systemd/system/mpm-update.service"),
+                PathBuf::from("/etc/systemd/system/mpm-update.timer"),
+            ]
+        );
+        assert!(units[0]
+            .1
+            .contains("\nExecStart=/usr/local/bin/mpm --yes update --all\n"));
+        assert!(units[0]
+            .1
+            .contains("\nDescription=mpm --yes update --all\n"));
+        assert!(units[1].1.contains("\nOnCalendar=Mon *-*-* 03:00:00\n"));
+        assert!(units[1].1.contains("\nWantedBy=timers.target\n"));
+    }
+
+    #[test]
+    fn test_launchd_plist() {
+        let (path, plist) = job(Interval::Daily).launchd_plist();
+        assert_eq!(
+            path,
+            PathBuf::from("/Library/LaunchDaemons/com.subconsciouscompute.mpm-update.plist")
+        );
+        assert!(plist.contains("<string>com.subconsciouscompute.mpm-update</string>"));
+        assert!(
+            plist.contains("<string>/usr/local/bin/mpm</string>\n        <string>--yes</string>")
+        );
+        assert!(plist.contains("<key>Hour</key>\n        <integer>3</integer>"));
+        assert!(!plist.contains("Weekday"));
+    }
+
+    #[test]
+    fn test_schtasks_args() {
+        let args = ["update", "--all"].map(String::from);
+        let job = Job::new(
+            "mpm-update",
+            Path::new(r"C:\Program Files\mpm\mpm.exe"),
+            &args,
+            Interval::Hourly,
+        );
+        assert_eq!(
+            job.schtasks_args(),
+            [
+                "/Create",
+                "/TN",
+                r"mpm\mpm-update",
+                "/TR",
+                r#""C:\Program Files\mpm\mpm.exe" update --all"#,
+                "/SC",
+                "HOURLY",
+                "/RU",
+                "SYSTEM",
+                "/F",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(systemd_quote("--all"), "--all");
+        assert_eq!(systemd_quote("a b"), "\"a b\"");
+        assert_eq!(systemd_quote("100%"), "\"100%%\"");
+        assert_eq!(windows_quote("a\"b"), "\"a\\\"b\"");
+        assert_eq!(xml_escape("a&b<c>"), "a&amp;b&lt;c&gt;");
+    }
+
+    #[test]
+    fn test_emit() {
+        let emitted = job(Interval::Daily).emit("linux").unwrap();
+        assert!(emitted.starts_with("# /etc/systemd/system/mpm-update.service\n# Generated"));
+        assert!(emitted.contains("\n# /etc/systemd/system/mpm-update.timer\n"));
+        let emitted = job(Interval::Daily).emit("windows").unwrap();
+        assert!(emitted.starts_with("schtasks /Create /TN mpm\\mpm-update /TR "));
+        assert!(job(Interval::Daily).emit("freebsd").is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! Updating the `mpm` binary itself from its GitHub releases, see
+//! `mpm self-update`.
+//!
+//! The asset matching the host is picked the same way as for
+//! [`crate::managers::github::GithubReleases`]. It is only installed if the
+//! release publishes its SHA-256 checksum, either as `<asset>.sha256` or in a
+//! `SHA256SUMS` or `checksums.txt` file, and the download matches it. If the
+//! release also signs that file (`<file>.sig` or `<file>.asc`), the
+//! signature must be made by a trusted key, see
+//! [`crate::Package::set_signature`]. Everything is downloaded to a private
+//! directory, so that nobody can swap the archive after it was verified.
+//!
+//! The new binary is written next to the running one and renamed over it,
+//! so an interrupted update never leaves a broken `mpm` behind. An mpm
+//! installed by a package manager is left to that package manager.
+
+use std::{cmp::Ordering, path::Path};
+
+use anyhow::Context;
+use strum::IntoEnumIterator;
+
+use crate::{
+    error::Error,
+    managers::github::{extract_binary, select_asset, GithubReleases, Release, ReleaseAsset},
+    AvailablePackageManager, MetaPackageManager, Package, PackageManager,
+};
+
+/// Repository whose releases ship mpm.
+pub const REPO: &str = "SubconsciousCompute/meta-package-manager";
+
+/// Version of the running mpm.
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Outcome of `mpm self-update`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SelfUpdate {
+    /// Version that was running.
+    pub current: String,
+    /// Version of the latest release.
+    pub latest: String,
+    /// Whether the latest release was installed.
+    pub updated: bool,
+}
+
+impl SelfUpdate {
+    /// Whether the latest release is newer than the running version.
+    pub fn is_available(&self) -> bool {
+        crate::print::cmp_versions(&self.latest, &self.current) == Ordering::Greater
+    }
+}
+
+/// Looks up the latest release and, unless `check_only`, installs it over
+/// the running executable if it is newer.
+pub fn self_update(check_only: bool) -> anyhow::Result<SelfUpdate> {
+    let release = GithubReleases::fetch_release(REPO, None)?;
+    let mut outcome = SelfUpdate {
+        current: CURRENT_VERSION.to_string(),
+        latest: release.tag_name.trim_start_matches('v').to_string(),
+        updated: false,
+    };
+    if check_only || !outcome.is_available() {
+        return Ok(outcome);
+    }
+    let exe = std::env::current_exe().context("failed to locate the running mpm")?;
+    if let Some(pkg) = installed_by(&exe) {
+        anyhow::bail!(
+            "{} was installed by {} (package {}), update it with `mpm --manager {} update {}`",
+            exe.display(),
+            pkg.package_manager(),
+            pkg.name(),
+            pkg.package_manager(),
+            pkg.name()
+        );
+    }
+    install(&release, &exe)?;
+    outcome.updated = true;
+    Ok(outcome)
+}
+
+/// Downloads the asset of `release` for this host, verifies its checksum and
+/// replaces `exe` with the binary in it.
+fn install(release: &Release, exe: &Path) -> anyhow::Result<()> {
+    let (os, arch) = (std::env::consts::OS, std::env::consts::ARCH);
+    let asset = select_asset(release, os, arch)
+        .with_context(|| format!("no asset of mpm {} matches {os}/{arch}", release.tag_name))?;
+    let sums = checksum_asset(release, &asset.name).with_context(|| {
+        format!(
+            "mpm {} publishes no checksum of {}",
+            release.tag_name, asset.name
+        )
+    })?;
+
+    let dir = tempfile::Builder::new().prefix("mpm-").tempdir()?;
+    let mut sums_download: Package = sums.browser_download_url.parse()?;
+    match signature_asset(release, &sums.name) {
+        Some(sig) => sums_download.set_signature(sig.browser_download_url.parse()?),
+        None => tracing::warn!(
+            "mpm {} does not sign {}, only verifying the checksum",
+            release.tag_name,
+            sums.name
+        ),
+    }
+    let sums_path =
+        sums_download.make_available_on_disk(Some(&dir.path().join(&sums.name)), true)?;
+    let sums = std::fs::read_to_string(sums_path)?;
+    let expected = expected_checksum(&sums, &asset.name)
+        .with_context(|| format!("no checksum of {} in {}", asset.name, REPO))?;
+
+    // verified while downloading, see `Package::make_available_on_disk`
+    let mut download: Package = asset.browser_download_url.parse()?;
+    download.set_sha256(&expected);
+    let archive = download.make_available_on_disk(Some(&dir.path().join(&asset.name)), true)?;
+
+    // staged in the same directory so that renaming it is atomic
+    let dir = exe.parent().context("invalid path of the running mpm")?;
+    let staged = dir.join(format!(".mpm-{}.new", release.tag_name));
+    let binary_name = format!("mpm{}", std::env::consts::EXE_SUFFIX);
+    if let Err(e) = extract_binary(&archive, &binary_name, &staged) {
+        let _ = std::fs::remove_file(&staged);
+        return Err(match e.downcast_ref::<std::io::Error>() {
+            Some(io) if io.kind() == std::io::ErrorKind::PermissionDenied => {
+                Error::PermissionDenied(format!("can not write to {}", dir.display())).into()
+            }
+            _ => e,
+        });
+    }
+    replace(&staged, exe)
+}
+
+/// Atomically moves `new` over the executable `exe`.
+#[cfg(not(windows))]
+fn replace(new: &Path, exe: &Path) -> anyhow::Result<()> {
+    std::fs::rename(new, exe).with_context(|| format!("failed to replace {}", exe.display()))
+}
+
+/// The running executable can not be overwritten on Windows, but renamed.
+#[cfg(windows)]
+fn replace(new: &Path, exe: &Path) -> anyhow::Result<()> {
+    let old = exe.with_extension("old.exe");
+    let _ = std::fs::remove_file(&old);
+    std::fs::rename(exe, &old).with_context(|| format!("failed to move {}", exe.display()))?;
+    if let Err(e) = std::fs::rename(new, exe) {
+        std::fs::rename(&old, exe)?;
+        return Err(e).with_context(|| format!("failed to replace {}", exe.display()));
+    }
+    Ok(())
+}
+
+/// Asset of `release` with the checksum of the asset `name`.
+fn checksum_asset<'a>(release: &'a Release, name: &str) -> Option<&'a ReleaseAsset> {
+    let own = [format!("{name}.sha256"), format!("{name}.sha256sum")];
+    release
+        .assets
+        .iter()
+        .find(|a| own.contains(&a.name))
+        .or_else(|| {
+            release.assets.iter().find(|a| {
+                let lower = a.name.to_lowercase();
+                let signature = lower.ends_with(".sig") || lower.ends_with(".asc");
+                (lower.starts_with("sha256sums") && !signature) || lower == "checksums.txt"
+            })
+        })
+}
+
+/// Asset of `release` with the detached signature of the asset `name`.
+fn signature_asset<'a>(release: &'a Release, name: &str) -> Option<&'a ReleaseAsset> {
+    let names = [format!("{name}.sig"), format!("{name}.asc")];
+    release.assets.iter().find(|a| names.contains(&a.name))
+}
+
+/// Package of the package manager that installed the executable `exe`, which
+/// must be updated with that package manager instead.
+fn installed_by(exe: &Path) -> Option<Package> {
+    AvailablePackageManager::iter()
+        .map(MetaPackageManager::new)
+        .filter(|mpm| mpm.is_available())
+        .find_map(|mpm| mpm.owner(exe).ok())
+}
+
+/// SHA-256 checksum of `name` in the contents of a checksum file: either
+/// just the checksum, or lines of `<checksum>  <file name>` as written by
+/// `sha256sum`.
+fn expected_checksum(sums: &str, name: &str) -> Option<String> {
+    sums.lines()
+        .find_map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [hash] => Some(hash),
+                [hash, file] if file.trim_start_matches('*') == name => Some(hash),
+                _ => None,
+            },
+        )
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(name: &str) -> ReleaseAsset {
+        ReleaseAsset {
+            name: name.to_string(),
+            browser_download_url: format!("https://example.com/{name}"),
+        }
+    }
+
+    #[test]
+    fn test_checksum_asset() {
+        let release = Release {
+            tag_name: "v0.8.0".to_string(),
+            assets: vec![
+                asset("mpm-x86_64-unknown-linux-gnu.tar.gz"),
+                asset("SHA256SUMS"),
+                asset("mpm-x86_64-unknown-linux-gnu.tar.gz.sha256"),
+            ],
+        };
+        let name = "mpm-x86_64-unknown-linux-gnu.tar.gz";
+        assert_eq!(
+            checksum_asset(&release, name).unwrap().name,
+            "mpm-x86_64-unknown-linux-gnu.tar.gz.sha256"
+        );
+        let name = "mpm-aarch64-apple-darwin.tar.gz";
+        assert_eq!(checksum_asset(&release, name).unwrap().name, "SHA256SUMS");
+
+        let release = Release {
+            tag_name: "v0.8.0".to_string(),
+            assets: vec![asset(name)],
+        };
+        assert!(checksum_asset(&release, name).is_none());
+    }
+
+    #[test]
+    fn test_signature_asset() {
+        let release = Release {
+            tag_name: "v0.8.0".to_string(),
+            assets: vec![
+                asset("SHA256SUMS.asc"),
+                asset("SHA256SUMS"),
+                asset("mpm-x86_64-unknown-linux-gnu.tar.gz"),
+            ],
+        };
+        let name = "mpm-x86_64-unknown-linux-gnu.tar.gz";
+        let sums = checksum_asset(&release, name).unwrap();
+        assert_eq!(sums.name, "SHA256SUMS");
+        assert_eq!(
+            signature_asset(&release, &sums.name).unwrap().name,
+            "SHA256SUMS.asc"
+        );
+        assert!(signature_asset(&release, name).is_none());
+    }
+
+    #[test]
+    fn test_expected_checksum() {
+        let sums = "1111  mpm-aarch64-apple-darwin.tar.gz\n\
+                    2222 *mpm-x86_64-unknown-linux-gnu.tar.gz\n";
+        assert_eq!(
+            expected_checksum(sums, "mpm-x86_64-unknown-linux-gnu.tar.gz").as_deref(),
+            Some("2222")
+        );
+        assert_eq!(expected_checksum(sums, "mpm.zip"), None);
+        assert_eq!(
+            expected_checksum("3333\n", "mpm.zip").as_deref(),
+            Some("3333")
+        );
+    }
+
+    #[test]
+    fn test_is_available() {
+        let update = |latest: &str| SelfUpdate {
+            current: "0.7.5".to_string(),
+            latest: latest.to_string(),
+            updated: false,
+        };
+        assert!(update("0.7.10").is_available());
+        assert!(!update("0.7.5").is_available());
+        assert!(!update("0.6.0").is_available());
+    }
+}
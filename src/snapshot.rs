@@ -0,0 +1,217 @@
+//! Named snapshots of the installed packages.
+//!
+//! `mpm snapshot save <name>` records the installed packages of all available
+//! package managers with their versions, in the format of a
+//! [``Lockfile``], under `snapshots` in the user's data directory, e.g.
+//! `~/.local/share/mpm/snapshots/<name>.lock` on Linux. `mpm snapshot restore
+//! <name>` reconciles the system back to it: missing packages and other
+//! versions are installed, packages that were not installed are removed.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Context;
+
+use crate::{
+    lockfile::Lockfile, AvailablePackageManager, MetaPackageManager, Package, PackageManager,
+};
+
+/// Directory of named snapshots.
+#[derive(Debug, Clone)]
+pub struct Snapshots {
+    dir: PathBuf,
+}
+
+impl Snapshots {
+    /// Create a store that keeps the snapshots in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Create a store in the default data directory of this user.
+    pub fn try_default() -> anyhow::Result<Self> {
+        let dirs = directories::ProjectDirs::from("", "", "mpm")
+            .context("failed to determine the data directory")?;
+        Ok(Self::new(dirs.data_dir().join("snapshots")))
+    }
+
+    /// Directory the snapshots are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of the snapshot called `name`.
+    pub fn path(&self, name: &str) -> anyhow::Result<PathBuf> {
+        anyhow::ensure!(
+            !name.is_empty() && !name.contains(['/', '\\']) && !name.starts_with('.'),
+            "invalid snapshot name {name:?}"
+        );
+        Ok(self.dir.join(format!("{name}.lock")))
+    }
+
+    /// Store a snapshot, replacing one of the same name.
+    pub fn save(&self, name: &str, snapshot: &Lockfile) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create snapshot directory {:?}", self.dir))?;
+        snapshot.write(&self.path(name)?)
+    }
+
+    /// Read the snapshot called `name`.
+    pub fn load(&self, name: &str) -> anyhow::Result<Lockfile> {
+        let path = self.path(name)?;
+        anyhow::ensure!(path.is_file(), "no snapshot called {name:?}");
+        Lockfile::from_file(&path)
+    }
+
+    /// Names of the stored snapshots, sorted.
+    pub fn list(&self) -> Vec<String> {
+        crate::common::files_with_extension(&self.dir, "lock")
+            .iter()
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect()
+    }
+}
+
+/// Changes that bring the installed packages back to a snapshot.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct SnapshotDiff {
+    /// Packages to install, in the version of the snapshot. Includes
+    /// installed packages whose version differs.
+    pub install: Vec<Package>,
+    /// Installed packages that are not in the snapshot.
+    pub remove: Vec<Package>,
+}
+
+impl SnapshotDiff {
+    /// Whether the installed packages match the snapshot.
+    pub fn is_empty(&self) -> bool {
+        self.install.is_empty() && self.remove.is_empty()
+    }
+}
+
+/// Changes that bring the `installed` packages of `manager` back to the
+/// `locked` versions of a snapshot. Installed packages without a version only
+/// need to be present.
+pub fn diff(
+    manager: &str,
+    locked: &BTreeMap<String, String>,
+    installed: &[Package],
+) -> SnapshotDiff {
+    let drift = crate::lockfile::drift(manager, locked, installed);
+    let mut install = drift.missing;
+    install.extend(
+        drift
+            .mismatched
+            .iter()
+            .map(|m| Package::new(&m.name, m.manager.clone(), Some(&m.locked))),
+    );
+    install.sort_by(|a, b| a.name().cmp(b.name()));
+    SnapshotDiff {
+        install,
+        remove: drift.extra,
+    }
+}
+
+/// What restoring a snapshot changed and what failed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct RestoreReport {
+    /// The changes that were made, or with `dry_run` would be made.
+    pub diff: SnapshotDiff,
+    /// Changes that failed, with the reason.
+    pub failed: Vec<String>,
+}
+
+/// Reconcile the package managers of `snapshot` with it. With `dry_run`
+/// only the changes are computed.
+///
+/// Fails if a package manager of the snapshot is not available. Failing
+/// changes do not stop the others and are reported instead.
+pub fn restore(
+    snapshot: &Lockfile,
+    dry_run: bool,
+    interactive: bool,
+) -> anyhow::Result<RestoreReport> {
+    let mut report = RestoreReport::default();
+    for (manager, locked) in &snapshot.packages {
+        let available = AvailablePackageManager::from_str(manager)
+            .map_err(|_| anyhow::anyhow!("unknown package manager {manager:?} in the snapshot"))?;
+        let mpm = MetaPackageManager::new_if_available(available)?;
+        let diff = diff(&mpm.pkg_manager_name(), locked, &mpm.list_installed());
+        for pkg in diff.install {
+            if dry_run || mpm.install(pkg.clone(), interactive).success() {
+                report.diff.install.push(pkg);
+            } else {
+                report.failed.push(format!(
+                    "install {} {}",
+                    pkg.name(),
+                    pkg.version().unwrap_or_default()
+                ));
+            }
+        }
+        for pkg in diff.remove {
+            if dry_run || mpm.uninstall(pkg.name(), interactive).success() {
+                report.diff.remove.push(pkg);
+            } else {
+                report.failed.push(format!("uninstall {}", pkg.name()));
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let locked = BTreeMap::from([
+            ("curl".to_string(), "7.88.1".to_string()),
+            ("hello".to_string(), "2.10-3".to_string()),
+            ("htop".to_string(), "3.2.2".to_string()),
+        ]);
+        let installed = [
+            Package::new("curl", "apt".to_string(), Some("7.88.1")),
+            Package::new("hello", "apt".to_string(), Some("2.10-4")),
+            Package::new("tmux", "apt".to_string(), Some("3.3a")),
+        ];
+        let diff = diff("apt", &locked, &installed);
+        assert_eq!(
+            diff.install,
+            [
+                Package::new("hello", "apt".to_string(), Some("2.10-3")),
+                Package::new("htop", "apt".to_string(), Some("3.2.2")),
+            ]
+        );
+        assert_eq!(
+            diff.remove,
+            [Package::new("tmux", "apt".to_string(), Some("3.3a"))]
+        );
+
+        let installed = [
+            Package::new("curl", "apt".to_string(), None),
+            Package::new("hello", "apt".to_string(), Some("2.10-3")),
+            Package::new("htop", "apt".to_string(), Some("3.2.2")),
+        ];
+        assert!(super::diff("apt", &locked, &installed).is_empty());
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshots = Snapshots::new(dir.path().join("snapshots"));
+        assert!(snapshots.list().is_empty());
+        assert!(snapshots.load("base").is_err());
+        assert!(snapshots.path("../base").is_err());
+
+        let snapshot =
+            Lockfile::from_packages([Package::new("curl", "apt".to_string(), Some("7.88.1"))]);
+        snapshots.save("base", &snapshot).unwrap();
+        snapshots.save("other", &Lockfile::default()).unwrap();
+        assert_eq!(snapshots.list(), ["base", "other"]);
+        assert_eq!(snapshots.load("base").unwrap(), snapshot);
+    }
+}
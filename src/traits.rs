@@ -1,4 +1,11 @@
-use crate::{Cmd, Operation, Package, PkgFormat};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::{
+    Cmd, DependencyGraph, InstallAction, InstallPlan, Operation, Package, PackageKind,
+    PackageMetadata, PkgFormat, Progress, ProgressCounter, Repository, Unsupported,
+};
 
 /// Trait for defining package panager commands in one place
 ///
@@ -12,6 +19,15 @@ pub trait PackageManagerCommands {
     /// 'dnf', constructed with [``std::process::Command::new``].
     fn cmd(&self) -> std::process::Command;
 
+    /// Command to run for `cmd` if it differs from
+    /// [``PackageManagerCommands::cmd``], for package managers whose
+    /// operations are separate executables like `xbps-install` and
+    /// `xbps-remove`. The default implementation always uses
+    /// [``PackageManagerCommands::cmd``].
+    fn cmd_for(&self, _cmd: Cmd) -> Option<std::process::Command> {
+        None
+    }
+
     /// Returns the appropriate command/s for the given supported command type.
     /// Check [``crate::common::Cmd``] enum to see all supported commands.
     fn get_cmds(&self, cmd: Cmd, pkg: Option<&Package>) -> Vec<String>;
@@ -25,9 +41,39 @@ pub trait PackageManagerCommands {
         vec![]
     }
 
+    /// Flags that answer the package manager's confirmation prompts for
+    /// `cmd`, added by [``PackageManagerCommands::consolidated``] unless
+    /// disabled with [``crate::common::set_assume_yes``]. None by default.
+    fn yes_flags(&self, _cmd: Cmd) -> Vec<String> {
+        vec![]
+    }
+
+    /// Flags that make `cmd` only simulate its changes, added by
+    /// [``PackageManagerCommands::consolidated``] in dry-run mode (see
+    /// [``crate::common::set_dry_run``]). Commands without such flags are
+    /// printed instead of run, which is what the default implementation does.
+    fn dry_run_flags(&self, _cmd: Cmd) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Whether a simulation (see [``PackageManagerCommands::dry_run_flags``])
+    /// that exited with `status` and printed `output` found no problems. The
+    /// default checks `status`; simulations that always end by declining the
+    /// transaction, like dnf's `--assumeno`, look at the output instead.
+    fn simulation_succeeded(&self, status: std::process::ExitStatus, _output: &[String]) -> bool {
+        status.success()
+    }
+
+    /// Whether simulations need elevated privileges, like `dnf --assumeno`.
+    /// `false` by default.
+    fn simulation_needs_sudo(&self) -> bool {
+        false
+    }
+
     /// Retreives defined commands and flags for the given
     /// [``crate::common::Cmd``] type and returns a Vec of args in the
-    /// order: `[commands..., user-args..., flags...]`
+    /// order: `[commands..., yes flags..., flags..., dry-run flags...,
+    /// user-args...]`
     ///
     /// The appropriate commands and flags are determined with the help of the
     /// enum [``crate::common::Cmd``] For finer control, a general purpose
@@ -40,7 +86,13 @@ pub trait PackageManagerCommands {
         args: &[S],
     ) -> Vec<String> {
         let mut commands = self.get_cmds(cmd, pkg);
+        if crate::common::assume_yes() {
+            commands.append(&mut self.yes_flags(cmd));
+        }
         commands.append(&mut self.get_flags(cmd));
+        if crate::common::is_dry_run() {
+            commands.extend(self.dry_run_flags(cmd).into_iter().flatten());
+        }
         commands.append(&mut args.iter().map(|x| x.as_ref().to_string()).collect());
         commands
     }
@@ -54,9 +106,9 @@ pub trait PackageManagerCommands {
     /// [``verified::Verified``] or manually ensuring that the
     /// [``PackageManagerCommands::cmd``] is valid.
     fn exec_cmds(&self, cmds: &[String]) -> std::process::Output {
-        self.ensure_sudo();
-        tracing::info!("Executing {:?} with args {:?}", self.cmd(), cmds);
-        self.cmd()
+        let mut command = self.elevated(self.cmd());
+        tracing::info!("Executing {command:?} with args {cmds:?}");
+        command
             .args(cmds)
             .output()
             .expect("command executed without a prior check")
@@ -70,15 +122,62 @@ pub trait PackageManagerCommands {
     /// not found in path. This can be avoided by using
     /// [``verified::Verified``] or manually ensuring that the
     /// [``PackageManagerCommands::cmd``] is valid.
+    ///
+    /// In dry-run mode the command is printed instead.
     fn exec_cmds_status<S: AsRef<str> + std::fmt::Debug + std::convert::AsRef<std::ffi::OsStr>>(
         &self,
         cmds: &[S],
         interactive: Option<bool>,
     ) -> std::process::ExitStatus {
-        self.ensure_sudo();
-        tracing::debug!("Executing {:?} with args {:?}", self.cmd(), cmds);
+        if crate::common::is_dry_run() {
+            return crate::common::exec_dry_run(self.cmd(), cmds, None, |s, _| s.success());
+        }
+        let command = self.elevated(self.cmd());
+        tracing::debug!("Executing {command:?} with args {cmds:?}");
         let res =
-            crate::run_command(self.cmd(), cmds, true, interactive).expect("failed to run command");
+            crate::run_command(command, cmds, true, interactive).expect("failed to run command");
+        res.0
+    }
+
+    /// Like [``PackageManagerCommands::exec_cmds``], but runs the command
+    /// for `cmd` (see [``PackageManagerCommands::cmd_for``]).
+    fn exec_cmd(&self, cmd: Cmd, cmds: &[String]) -> std::process::Output {
+        let Some(command) = self.cmd_for(cmd) else {
+            return self.exec_cmds(cmds);
+        };
+        let mut command = self.elevated(command);
+        tracing::info!("Executing {command:?} with args {cmds:?}");
+        command
+            .args(cmds)
+            .output()
+            .expect("command executed without a prior check")
+    }
+
+    /// Like [``PackageManagerCommands::exec_cmds_status``], but runs the
+    /// command for `cmd` (see [``PackageManagerCommands::cmd_for``]).
+    fn exec_cmd_status<S: AsRef<str> + std::fmt::Debug + std::convert::AsRef<std::ffi::OsStr>>(
+        &self,
+        cmd: Cmd,
+        cmds: &[S],
+        interactive: Option<bool>,
+    ) -> std::process::ExitStatus {
+        if crate::common::is_dry_run() {
+            let mut command = self.cmd_for(cmd).unwrap_or_else(|| self.cmd());
+            let flags = self.dry_run_flags(cmd);
+            if self.simulation_needs_sudo() && crate::common::is_simulated(cmds, flags.as_deref()) {
+                command = self.elevated(command);
+            }
+            return crate::common::exec_dry_run(command, cmds, flags.as_deref(), |s, out| {
+                self.simulation_succeeded(s, out)
+            });
+        }
+        let Some(command) = self.cmd_for(cmd) else {
+            return self.exec_cmds_status(cmds, interactive);
+        };
+        let command = self.elevated(command);
+        tracing::debug!("Executing {command:?} with args {cmds:?}");
+        let res =
+            crate::run_command(command, cmds, true, interactive).expect("failed to run command");
         res.0
     }
 
@@ -91,19 +190,63 @@ pub trait PackageManagerCommands {
     /// [``verified::Verified``] or manually ensuring that the
     /// [``PackageManagerCommands::cmd``] is valid.
     fn exec_cmds_spawn(&self, cmds: &[String]) -> std::process::Child {
-        self.ensure_sudo();
-        tracing::info!("Executing {:?} with args {:?}", self.cmd(), cmds);
-        self.cmd()
+        let mut command = self.elevated(self.cmd());
+        tracing::info!("Executing {command:?} with args {cmds:?}");
+        command
             .args(cmds)
             .spawn()
             .expect("command executed without a prior check")
     }
 
-    /// Ensure that we are in sudo mode.
+    /// Whether this package manager has to run with elevated privileges.
+    ///
+    /// Package managers that install into the user's home directory should
+    /// return `false` so that they are never run as root.
+    fn needs_sudo(&self) -> bool {
+        true
+    }
+
+    /// `command` of this package manager, run with `sudo` if it needs
+    /// elevated privileges (see [``PackageManagerCommands::needs_sudo``])
+    /// that mpm does not have. Unlike
+    /// [``PackageManagerCommands::ensure_sudo``], mpm keeps running as the
+    /// user, so other package managers of the same invocation are not run as
+    /// root.
+    fn elevated(&self, command: std::process::Command) -> std::process::Command {
+        #[cfg(target_os = "linux")]
+        if self.needs_sudo() && matches!(sudo::check(), sudo::RunningAs::User) {
+            let mut sudo = std::process::Command::new("sudo");
+            // sudo resets the environment, e.g. the proxy of `--env`
+            let envs: Vec<_> = command
+                .get_envs()
+                .filter_map(|(key, value)| {
+                    let mut env = key.to_os_string();
+                    env.push("=");
+                    env.push(value?);
+                    Some(env)
+                })
+                .collect();
+            if !envs.is_empty() {
+                sudo.arg("env").args(envs);
+            }
+            sudo.arg(command.get_program()).args(command.get_args());
+            if let Some(dir) = command.get_current_dir() {
+                sudo.current_dir(dir);
+            }
+            return sudo;
+        }
+        command
+    }
+
+    /// Restart mpm as root, for package managers that change files of the
+    /// system directly, e.g. to add a repository. Commands are elevated
+    /// with [``PackageManagerCommands::elevated``] instead.
     fn ensure_sudo(&self) {
         #[cfg(target_os = "linux")]
-        if let Err(e) = sudo::with_env(&["CARGO_", "MPM_LOG", "RUST_LOG"]) {
-            tracing::warn!("Failed to elevate to sudo: {e}.");
+        if self.needs_sudo() {
+            if let Err(e) = sudo::with_env(&["CARGO_", "MPM_LOG", "RUST_LOG"]) {
+                tracing::warn!("Failed to elevate to sudo: {e}.");
+            }
         }
     }
 
@@ -114,6 +257,18 @@ pub trait PackageManagerCommands {
             Ok(output) => output.status.success(),
         }
     }
+
+    /// Version of the package manager: the first line it prints for
+    /// `--version`, or `None` if that fails.
+    fn version(&self) -> Option<String> {
+        let output = self.cmd().arg("--version").output().ok()?;
+        output.status.success().then_some(())?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(String::from)
+    }
 }
 
 /// Primary interface for implementing a package manager
@@ -135,6 +290,15 @@ pub trait PackageManager: PackageManagerCommands + std::fmt::Debug + std::fmt::D
     /// Return the list of supported package extensions.
     fn supported_pkg_formats(&self) -> Vec<PkgFormat>;
 
+    /// The supported package formats that can be installed on a host with the
+    /// given architecture, named like [`std::env::consts::ARCH`].
+    fn supported_pkg_formats_for(&self, arch: &str) -> Vec<PkgFormat> {
+        self.supported_pkg_formats()
+            .into_iter()
+            .filter(|fmt| fmt.is_supported_on(arch))
+            .collect()
+    }
+
     /// Get a formatted string of the package that can be passed into package
     /// manager's cli.
     ///
@@ -170,43 +334,216 @@ pub trait PackageManager: PackageManagerCommands + std::fmt::Debug + std::fmt::D
     /// Parses output, generally from stdout, to a Vec of Packages.
     ///
     /// The default implementation uses [``PackageManager::parse_pkg``] for
-    /// parsing each line into a [`Package`].
+    /// parsing each line into a [`Package`], see
+    /// [``PackageManager::parse_output_lenient``].
     fn parse_output(&self, out: &[u8]) -> Vec<Package> {
+        self.parse_output_lenient(out).0
+    }
+
+    /// Parses output like [``PackageManager::parse_output``] and also returns
+    /// the number of non-empty lines that [``PackageManager::parse_pkg``]
+    /// could not parse.
+    ///
+    /// Each dropped line is logged at `TRACE` level and the number of dropped
+    /// lines at `DEBUG` level, which helps to find gaps in the parsers.
+    fn parse_output_lenient(&self, out: &[u8]) -> (Vec<Package>, usize) {
         let outstr = String::from_utf8_lossy(out);
-        outstr
+        let mut unparsed = 0;
+        let pkgs = outstr
             .lines()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
             .filter_map(|s| {
-                let ts = s.trim();
-                if !ts.is_empty() {
-                    self.parse_pkg(ts)
-                } else {
-                    None
+                let pkg = self.parse_pkg(s);
+                if pkg.is_none() {
+                    tracing::trace!("{}: dropped unparsed line {s:?}", self.pkg_manager_name());
+                    unparsed += 1;
                 }
+                pkg
             })
-            .collect()
+            .collect();
+        if unparsed > 0 {
+            tracing::debug!("{}: {unparsed} line(s) not parsed", self.pkg_manager_name());
+        }
+        (pkgs, unparsed)
+    }
+
+    /// Whether the native search command interprets the query as a regular
+    /// expression, e.g. `apt search`.
+    fn search_uses_regex(&self) -> bool {
+        false
+    }
+
+    /// Returns the query to pass to the native search command.
+    ///
+    /// For package managers that search by regular expression (see
+    /// [``PackageManager::search_uses_regex``]) metacharacters are escaped so
+    /// that queries like `c++` are matched literally.
+    fn search_query(&self, query: &str) -> String {
+        if self.search_uses_regex() {
+            crate::common::escape_regex(query)
+        } else {
+            query.to_string()
+        }
     }
 
     /// General package search
     fn search(&self, query: &str) -> Vec<Package> {
-        let cmds = self.consolidated(Cmd::Search, None, &[query.to_string()]);
-        let out = self.exec_cmds(&cmds);
+        self.search_with_args(query, &[])
+    }
+
+    /// Like [``PackageManager::search``], with `args` passed to the search
+    /// command before the query, e.g. those of
+    /// [``PackageManager::search_exact_args``]. Package managers that search
+    /// differently override this method rather than `search`.
+    fn search_with_args(&self, query: &str, args: &[String]) -> Vec<Package> {
+        let mut args = args.to_vec();
+        args.push(self.search_query(query));
+        let cmds = self.consolidated(Cmd::Search, None, &args);
+        let out = self.exec_cmd(Cmd::Search, &cmds);
         self.parse_output(&out.stdout)
     }
 
+    /// Like [``PackageManager::search``], but fails if the search command
+    /// does, e.g. because the package database is locked, rather than
+    /// returning no results. Package managers that override
+    /// [``PackageManager::search_with_args``] override this method as well.
+    fn try_search(&self, query: &str) -> anyhow::Result<Vec<Package>> {
+        let cmds = self.consolidated(Cmd::Search, None, &[self.search_query(query)]);
+        let out = self.exec_cmd(Cmd::Search, &cmds);
+        let program = self.cmd_for(Cmd::Search).unwrap_or_else(|| self.cmd());
+        ensure_search_succeeded(program.get_program(), &out)?;
+        Ok(self.parse_output(&out.stdout))
+    }
+
+    /// Native flags limiting the number of search results, if the package
+    /// manager has any.
+    fn search_limit_args(&self, _limit: usize) -> Vec<String> {
+        vec![]
+    }
+
+    /// Like [``PackageManager::search``], but returns at most `limit`
+    /// results.
+    ///
+    /// The limit is passed to the package manager where it supports one (see
+    /// [``PackageManager::search_limit_args``]). Otherwise its output is
+    /// parsed as it is streamed, and the search is stopped once `limit`
+    /// packages were found.
+    fn search_limited(&self, query: &str, limit: usize) -> anyhow::Result<Vec<Package>> {
+        let mut args = vec![self.search_query(query)];
+        args.extend(self.search_limit_args(limit));
+        let cmds = self.consolidated(Cmd::Search, None, &args);
+        let mut command = self.elevated(self.cmd_for(Cmd::Search).unwrap_or_else(|| self.cmd()));
+        command.args(&cmds);
+        search_streaming(self, command, limit)
+    }
+
+    /// Native flags making the search command only match packages named
+    /// exactly like the query, if the package manager has any.
+    fn search_exact_args(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Like [``PackageManager::search``], but only returns the packages
+    /// named `query`, ignoring case.
+    ///
+    /// The native flags of [``PackageManager::search_exact_args``] are
+    /// passed where the package manager has them, and the results are
+    /// filtered by name either way.
+    fn search_exact(&self, query: &str) -> Vec<Package> {
+        let mut pkgs = self.search_with_args(query, &self.search_exact_args());
+        pkgs.retain(|p| p.name().eq_ignore_ascii_case(query));
+        pkgs
+    }
+
+    /// Like [``PackageManager::search``], but marks the results that are
+    /// already installed (see [``Package::installed``]).
+    fn search_with_installed(&self, query: &str) -> Vec<Package> {
+        let mut pkgs = self.search(query);
+        self.mark_installed(&mut pkgs);
+        pkgs
+    }
+
+    /// Mark the given packages as installed or not by cross-referencing them
+    /// against [``PackageManager::list_installed``].
+    fn mark_installed(&self, pkgs: &mut [Package]) {
+        let installed: std::collections::HashSet<String> = self
+            .list_installed()
+            .into_iter()
+            .map(|p| p.name().to_string())
+            .collect();
+        for pkg in pkgs {
+            pkg.set_installed(installed.contains(pkg.name()));
+        }
+    }
+
     /// Sync package manaager repositories
     fn sync(&self) -> std::process::ExitStatus {
         tracing::debug!("Syncing...");
-        self.exec_cmds_status(&self.consolidated::<&str>(Cmd::Sync, None, &[]), None)
+        self.exec_cmd_status(
+            Cmd::Sync,
+            &self.consolidated::<&str>(Cmd::Sync, None, &[]),
+            None,
+        )
     }
 
     /// Update/upgrade all packages
     fn update_all(&self, interactive: bool) -> std::process::ExitStatus {
-        self.exec_cmds_status(
+        self.exec_cmd_status(
+            Cmd::UpdateAll,
             &self.consolidated::<&str>(Cmd::UpdateAll, None, &[]),
             Some(interactive),
         )
     }
 
+    /// Parse a line of output into a [`Progress`] event.
+    ///
+    /// The default implementation doesn't recognize any progress output.
+    fn parse_progress(&self, _line: &str) -> Option<Progress> {
+        None
+    }
+
+    /// Like [``PackageManager::update_all``], but calls `on_progress` with
+    /// the number of packages done and the total number of packages as the
+    /// upgrade progresses.
+    ///
+    /// Depends on [``PackageManager::parse_progress``]; for package managers
+    /// that don't implement it, `on_progress` is never called.
+    fn update_all_with_progress<F: FnMut(usize, usize)>(
+        &self,
+        mut on_progress: F,
+    ) -> std::process::ExitStatus {
+        let cmds = self.consolidated::<&str>(Cmd::UpdateAll, None, &[]);
+        // simulated or printed without progress
+        if crate::common::is_dry_run() {
+            return self.exec_cmd_status(Cmd::UpdateAll, &cmds, None);
+        }
+        let command = self.elevated(self.cmd_for(Cmd::UpdateAll).unwrap_or_else(|| self.cmd()));
+        tracing::debug!("Executing {command:?} with args {cmds:?}");
+        let mut counter = ProgressCounter::default();
+        crate::run_command_with(command, &cmds, |line| {
+            tracing::debug!(">> {line}");
+            if let Some((done, total)) = self.parse_progress(line).and_then(|p| counter.update(p)) {
+                on_progress(done, total);
+            }
+        })
+        .expect("failed to run command")
+    }
+
+    /// Download all available updates without installing them, to apply
+    /// them later with [``PackageManager::apply_downloaded_updates``]. The
+    /// default implementation returns [`Unsupported`].
+    fn download_updates(&self) -> anyhow::Result<std::process::ExitStatus> {
+        Err(Unsupported::new(self.pkg_manager_name(), "downloading updates").into())
+    }
+
+    /// Install updates previously downloaded with
+    /// [``PackageManager::download_updates``] without downloading anything.
+    /// The default implementation returns [`Unsupported`].
+    fn apply_downloaded_updates(&self) -> anyhow::Result<std::process::ExitStatus> {
+        Err(Unsupported::new(self.pkg_manager_name(), "applying downloaded updates").into())
+    }
+
     /// Install a single package
     ///
     /// For multi-package operations, see
@@ -221,9 +558,133 @@ pub trait PackageManager: PackageManagerCommands + std::fmt::Debug + std::fmt::D
         self.execute_pkg_command(&mut pkg, Operation::Install, interactive)
     }
 
+    /// Install all of the given packages in a single invocation of the
+    /// package manager, which resolves their dependencies together and takes
+    /// its lock only once, see [``PackageManager::execute_pkgs_command``].
+    /// Package managers that cannot install several packages at once install
+    /// them one by one with [`install_each`].
+    fn install_many(&self, pkgs: &[Package], interactive: bool) -> std::process::ExitStatus {
+        self.execute_pkgs_command(pkgs, Operation::Install, interactive)
+    }
+
+    /// Arguments of the invocations of the package manager that install the
+    /// given packages, see [`consolidated_pkgs`].
+    fn consolidated_install(&self, pkgs: &[Package]) -> Vec<Vec<String>> {
+        consolidated_pkgs(self, Cmd::Install, pkgs, |pkg| {
+            self.reformat_for_command(pkg)
+        })
+    }
+
+    /// Whether [``PackageManager::install_many``] installs all of the
+    /// packages in a single transaction that the package manager rolls back
+    /// as a whole if any of them fails. `false` by default.
+    fn installs_atomically(&self) -> bool {
+        false
+    }
+
+    /// Install all of the given packages or none of them, with
+    /// [``PackageManager::install_many``].
+    ///
+    /// Unless the package manager rolls the install back itself (see
+    /// [``PackageManager::installs_atomically``]), the packages that were not
+    /// installed before are uninstalled again when it fails.
+    fn install_transactional(&self, pkgs: &[Package]) -> anyhow::Result<()> {
+        if self.installs_atomically() {
+            anyhow::ensure!(
+                self.install_many(pkgs, false).success(),
+                "Failed to install packages, transaction rolled back"
+            );
+            return Ok(());
+        }
+        let before = installed_names(self);
+        if !self.install_many(pkgs, false).success() {
+            rollback_install(self, pkgs, &before);
+            anyhow::bail!("Failed to install packages, rolled back the installed ones");
+        }
+        Ok(())
+    }
+
+    /// Reinstall a single package, e.g. to restore missing or corrupt files.
+    /// The default implementation returns [`Unsupported`].
+    fn reinstall(
+        &self,
+        _pkg: &Package,
+        _interactive: bool,
+    ) -> anyhow::Result<std::process::ExitStatus> {
+        Err(Unsupported::new(self.pkg_manager_name(), "reinstalling packages").into())
+    }
+
+    /// Check that the files of an installed package are present and
+    /// unmodified. The default implementation returns [`Unsupported`].
+    fn verify_integrity(&self, _pkg: &Package) -> anyhow::Result<bool> {
+        Err(Unsupported::new(self.pkg_manager_name(), "verifying package integrity").into())
+    }
+
+    /// Files of the installed package `pkg` that are modified or missing,
+    /// empty if the package is intact. Fails if `pkg` is not installed. The
+    /// default implementation returns [`Unsupported`].
+    fn verify_files(&self, _pkg: &Package) -> anyhow::Result<Vec<std::path::PathBuf>> {
+        Err(Unsupported::new(self.pkg_manager_name(), "verifying package files").into())
+    }
+
+    /// Verify all installed packages and return the files that are modified
+    /// or missing, grouped by package. Modified files are logged as soon as
+    /// they are found since this can take a while. The default
+    /// implementation returns [`Unsupported`].
+    fn verify_all(&self) -> anyhow::Result<Vec<(Package, Vec<std::path::PathBuf>)>> {
+        Err(Unsupported::new(self.pkg_manager_name(), "verifying all packages").into())
+    }
+
+    /// The packages installing `pkg` would install, upgrade and remove,
+    /// without installing it. The default implementation returns
+    /// [`Unsupported`].
+    fn install_plan(&self, _pkg: &Package) -> anyhow::Result<InstallPlan> {
+        Err(Unsupported::new(self.pkg_manager_name(), "planning installs").into())
+    }
+
+    /// Estimated number of bytes an install of the package downloads or
+    /// occupies on disk, whichever is larger, without installing it.
+    ///
+    /// Returns `None` if the size cannot be determined. The default
+    /// implementation always returns `None`.
+    fn estimate_operation_size(&self, _pkg: &Package) -> Option<u64> {
+        None
+    }
+
+    /// Install a package unless the requested version is already installed
+    /// and intact; reinstall it if its files are missing or corrupt.
+    ///
+    /// Packages are assumed intact when
+    /// [``PackageManager::verify_integrity``] is not supported.
+    fn install_if_missing(
+        &self,
+        pkg: &Package,
+        interactive: bool,
+    ) -> anyhow::Result<InstallAction> {
+        let name = crate::managers::normalize_pkg_name(pkg.name());
+        let installed = self
+            .list_installed()
+            .into_iter()
+            .find(|p| crate::managers::normalize_pkg_name(p.name()) == name);
+        let action = InstallAction::decide(installed.as_ref(), pkg, || {
+            self.verify_integrity(pkg).unwrap_or_else(|e| {
+                tracing::debug!("Assuming {} is intact: {e}", pkg.name());
+                true
+            })
+        });
+        let status = match action {
+            InstallAction::Skip => return Ok(action),
+            InstallAction::Reinstall => self.reinstall(pkg, interactive)?,
+            InstallAction::Install => self.install(pkg.clone(), interactive),
+        };
+        anyhow::ensure!(status.success(), "Failed to install {}", pkg.name());
+        Ok(action)
+    }
+
     /// Uninstall a single package
     ///
-    /// For multi-package operations, see
+    /// This does not check [``PackageManager::is_protected``], see
+    /// [``PackageManager::try_uninstall``]. For multi-package operations, see
     /// [``PackageManager::execute_pkg_command``]
     fn uninstall<P: Into<Package> + Clone + std::fmt::Debug>(
         &self,
@@ -234,6 +695,32 @@ pub trait PackageManager: PackageManagerCommands + std::fmt::Debug + std::fmt::D
         self.execute_pkg_command(&mut pkg, Operation::Uninstall, interactive)
     }
 
+    /// Returns `true` if the package is essential to the system (e.g. apt's
+    /// `Essential: yes`) and removing it could leave the system unusable.
+    ///
+    /// The default implementation treats every package as unprotected.
+    fn is_protected(&self, _pkg: &Package) -> bool {
+        false
+    }
+
+    /// Uninstall a single package, refusing to remove protected packages
+    /// unless `force` is set.
+    fn try_uninstall<P: Into<Package> + Clone + std::fmt::Debug>(
+        &self,
+        pkg: P,
+        interactive: bool,
+        force: bool,
+    ) -> anyhow::Result<std::process::ExitStatus> {
+        let pkg = pkg.into();
+        anyhow::ensure!(
+            force || !self.is_protected(&pkg),
+            "{} is protected by {}, refusing to uninstall it without --force",
+            pkg.name(),
+            self.pkg_manager_name()
+        );
+        Ok(self.uninstall(pkg, interactive))
+    }
+
     /// Update a single package
     ///
     /// For multi-package operations, see
@@ -249,13 +736,16 @@ pub trait PackageManager: PackageManagerCommands + std::fmt::Debug + std::fmt::D
 
     /// List installed packages
     fn list_installed(&self) -> Vec<Package> {
-        let out = self.exec_cmds(&self.consolidated::<&str>(Cmd::List, None, &[]));
+        let out = self.exec_cmd(Cmd::List, &self.consolidated::<&str>(Cmd::List, None, &[]));
         self.parse_output(&out.stdout)
     }
 
     /// List outdated packages
     fn list_outdated(&self) -> Vec<Package> {
-        let out = self.exec_cmds(&self.consolidated::<&str>(Cmd::Outdated, None, &[]));
+        let out = self.exec_cmd(
+            Cmd::Outdated,
+            &self.consolidated::<&str>(Cmd::Outdated, None, &[]),
+        );
         self.parse_output(&out.stdout)
     }
     /// Execute package manager command.
@@ -266,18 +756,223 @@ pub trait PackageManager: PackageManagerCommands + std::fmt::Debug + std::fmt::D
         interactive: bool,
     ) -> std::process::ExitStatus {
         tracing::debug!("> Operation {op:?} on {pkg:?}...");
+        self.execute_pkgs_command(std::slice::from_ref(pkg), op, interactive)
+    }
+
+    /// Run the operation on all of the given packages with as few
+    /// invocations of the package manager as possible, see
+    /// [`consolidated_pkgs`], stopping at the first that fails.
+    fn execute_pkgs_command(
+        &self,
+        pkgs: &[Package],
+        op: Operation,
+        interactive: bool,
+    ) -> std::process::ExitStatus {
         let command = match op {
             Operation::Install => Cmd::Install,
             Operation::Uninstall => Cmd::Uninstall,
             Operation::Update => Cmd::Update,
         };
+        let invocations =
+            consolidated_pkgs(self, command, pkgs, |pkg| self.reformat_for_command(pkg));
+        for cmds in invocations {
+            tracing::debug!(">> {pkgs:?} -> {cmds:?}");
+            let s = self.exec_cmd_status(command, &cmds, Some(interactive));
+            if !s.success() {
+                return s;
+            }
+        }
+        crate::common::synthetic_exit_status(true)
+    }
+
+    /// Call `on_pkg` for every package available in the repositories, as soon
+    /// as it is read from the package manager's output. The default
+    /// implementation returns [`Unsupported`].
+    fn list_available_with<F: FnMut(Package)>(&self, _on_pkg: F) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "listing available packages").into())
+    }
+
+    /// List all packages available in the repositories. The list can be
+    /// large, see [``PackageManager::list_available_with``] to stream it.
+    fn list_available(&self) -> anyhow::Result<Vec<Package>> {
+        let mut pkgs = vec![];
+        self.list_available_with(|pkg| pkgs.push(pkg))?;
+        Ok(pkgs)
+    }
+
+    /// Remove downloaded packages and cached metadata with [`Cmd::Clean`].
+    /// Returns [`Unsupported`] if the package manager has no such command.
+    fn clean(&self) -> anyhow::Result<std::process::ExitStatus> {
+        if self.get_cmds(Cmd::Clean, None).is_empty() {
+            return Err(Unsupported::new(self.pkg_manager_name(), "cleaning the cache").into());
+        }
+        Ok(self.exec_cmd_status(
+            Cmd::Clean,
+            &self.consolidated::<&str>(Cmd::Clean, None, &[]),
+            None,
+        ))
+    }
+
+    /// Remove dependencies that no installed package needs anymore with
+    /// [`Cmd::Autoremove`]. Returns [`Unsupported`] if the package manager
+    /// has no such command.
+    fn autoremove(&self, interactive: bool) -> anyhow::Result<std::process::ExitStatus> {
+        if self.get_cmds(Cmd::Autoremove, None).is_empty() {
+            return Err(
+                Unsupported::new(self.pkg_manager_name(), "removing unneeded packages").into(),
+            );
+        }
+        Ok(self.exec_cmd_status(
+            Cmd::Autoremove,
+            &self.consolidated::<&str>(Cmd::Autoremove, None, &[]),
+            Some(interactive),
+        ))
+    }
+
+    /// Operations from the package manager's own transaction history, oldest
+    /// first. The default implementation returns [`Unsupported`].
+    fn history(&self) -> anyhow::Result<Vec<crate::history::HistoryEntry>> {
+        Err(Unsupported::new(self.pkg_manager_name(), "reading the transaction history").into())
+    }
+
+    /// Id of the last transaction of the package manager, recorded in the
+    /// history so that [`PackageManager::undo_transaction`] can undo it later.
+    /// None by default.
+    fn last_transaction(&self) -> Option<String> {
+        None
+    }
+
+    /// Undo the transaction with the id from
+    /// [`PackageManager::last_transaction`], restoring the previous versions
+    /// of updated packages. The default implementation returns
+    /// [`Unsupported`].
+    fn undo_transaction(
+        &self,
+        _id: &str,
+        _interactive: bool,
+    ) -> anyhow::Result<std::process::ExitStatus> {
+        Err(Unsupported::new(self.pkg_manager_name(), "undoing transactions").into())
+    }
+
+    /// Hold the package at its installed version, so that updating it or all
+    /// packages leaves it alone until it is unpinned. The default
+    /// implementation returns [`Unsupported`].
+    fn pin(&self, _pkg: &Package) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "pinning packages").into())
+    }
+
+    /// Release a package held with [`PackageManager::pin`]. The default
+    /// implementation returns [`Unsupported`].
+    fn unpin(&self, _pkg: &Package) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "pinning packages").into())
+    }
+
+    /// Packages held with [`PackageManager::pin`]. The default
+    /// implementation returns [`Unsupported`].
+    fn list_pinned(&self) -> anyhow::Result<Vec<Package>> {
+        Err(Unsupported::new(self.pkg_manager_name(), "pinning packages").into())
+    }
+
+    /// Directories that [`PackageManager::clean`] empties, used to report
+    /// the space it would free. The default implementation returns none.
+    fn cache_dirs(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    /// Lock files the package manager holds while it changes the system,
+    /// checked by `mpm doctor`. The default implementation returns none.
+    fn lock_files(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    /// Directories the package manager installs executables into that have
+    /// to be on `PATH`, checked by `mpm doctor`. The default implementation
+    /// returns none, as system package managers install into `/usr/bin`.
+    fn bin_dirs(&self) -> Vec<PathBuf> {
+        vec![]
+    }
 
-        let fmt = self.reformat_for_command(pkg);
-        tracing::debug!(">> {pkg:?} -> {fmt}");
+    /// Package that installed the file at `path`, or [`crate::NotOwned`]
+    /// if no package owns it. The default implementation returns
+    /// [`Unsupported`].
+    fn owner(&self, _path: &Path) -> anyhow::Result<Package> {
+        Err(Unsupported::new(self.pkg_manager_name(), "querying file ownership").into())
+    }
+
+    /// Files installed by the package. The default implementation returns
+    /// [`Unsupported`].
+    fn files(&self, _pkg: &Package) -> anyhow::Result<Vec<PathBuf>> {
+        Err(Unsupported::new(self.pkg_manager_name(), "listing package files").into())
+    }
+
+    /// Dependencies of the package, recursively if the package manager
+    /// reports them at once. The default implementation returns
+    /// [`Unsupported`].
+    fn dependencies(&self, _pkg: &Package) -> anyhow::Result<DependencyGraph> {
+        Err(Unsupported::new(self.pkg_manager_name(), "querying dependencies").into())
+    }
+
+    /// Description, homepage, license, size and versions of the package. The
+    /// default implementation returns [`Unsupported`].
+    fn info(&self, _pkg: &Package) -> anyhow::Result<PackageMetadata> {
+        Err(Unsupported::new(self.pkg_manager_name(), "showing package information").into())
+    }
+
+    /// Whether packages of `kind` can be installed, removed and updated. Only
+    /// regular packages by default.
+    fn supports_kind(&self, kind: PackageKind) -> bool {
+        kind == PackageKind::Package
+    }
+
+    /// Patches that are needed by the installed packages, or all patches if
+    /// `all`. The default implementation returns [`Unsupported`].
+    fn list_patches(&self, _all: bool) -> anyhow::Result<Vec<Package>> {
+        Err(Unsupported::new(self.pkg_manager_name(), "listing patches").into())
+    }
+
+    /// Packages that depend on the given package. The default implementation
+    /// returns [`Unsupported`].
+    fn reverse_dependencies(&self, _pkg: &Package) -> anyhow::Result<Vec<Package>> {
+        Err(Unsupported::new(self.pkg_manager_name(), "querying reverse dependencies").into())
+    }
+
+    /// Value of the package manager's configuration option `key`, or `None`
+    /// if it is not set. The default implementation returns [`Unsupported`].
+    fn get_option(&self, _key: &str) -> anyhow::Result<Option<String>> {
+        Err(Unsupported::new(self.pkg_manager_name(), "reading configuration options").into())
+    }
 
-        let cmds = self.consolidated(command, Some(pkg), &[fmt.clone()]);
-        tracing::debug!(">> {pkg} -> {fmt} -> {cmds:?}");
-        self.exec_cmds_status(&cmds, Some(interactive))
+    /// Set the package manager's configuration option `key` to `value`
+    /// persistently. The default implementation returns [`Unsupported`].
+    fn set_option(&self, _key: &str, _value: &str) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "setting configuration options").into())
+    }
+
+    /// Mirrors the package manager downloads packages from. The default
+    /// implementation returns [`Unsupported`].
+    fn list_mirrors(&self) -> anyhow::Result<Vec<url::Url>> {
+        Err(Unsupported::new(self.pkg_manager_name(), "listing mirrors").into())
+    }
+
+    /// Switch the primary mirror to the scheme, host and port of `url`. The
+    /// default implementation returns [`Unsupported`].
+    fn set_mirror(&self, _url: &url::Url) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "setting the mirror").into())
+    }
+
+    /// Verify the signature of a package file before installing it.
+    ///
+    /// Returns `Ok(false)` if the package is unsigned or its signature is
+    /// invalid. The default implementation returns [`Unsupported`].
+    fn verify_package_signature(&self, _path: &Path) -> anyhow::Result<bool> {
+        Err(Unsupported::new(self.pkg_manager_name(), "signature verification").into())
+    }
+
+    /// Recover a broken package database, e.g. finish configuring packages
+    /// left half-configured by an interrupted operation. The default
+    /// implementation returns [`Unsupported`].
+    fn fix_broken(&self) -> anyhow::Result<std::process::ExitStatus> {
+        Err(Unsupported::new(self.pkg_manager_name(), "fixing broken packages").into())
     }
 
     /// Add third-party repository to the package manager's repository list
@@ -287,8 +982,205 @@ pub trait PackageManager: PackageManagerCommands + std::fmt::Debug + std::fmt::D
     /// `std::process::ExitStatus`.
     fn add_repo(&self, repo: &Vec<String>) -> anyhow::Result<()> {
         let cmds = self.consolidated(Cmd::AddRepo, None, repo);
-        let s = self.exec_cmds_status(&cmds, None);
+        let s = self.exec_cmd_status(Cmd::AddRepo, &cmds, None);
         anyhow::ensure!(s.success(), "Error adding repo");
         Ok(())
     }
+
+    /// Repositories the package manager installs packages from, including
+    /// disabled ones. The default implementation returns [`Unsupported`].
+    fn list_repos(&self) -> anyhow::Result<Vec<Repository>> {
+        Err(Unsupported::new(self.pkg_manager_name(), "listing repositories").into())
+    }
+
+    /// Remove the repository `name`, see [`Repository::name`]. The default
+    /// implementation returns [`Unsupported`].
+    fn remove_repo(&self, _name: &str) -> anyhow::Result<()> {
+        Err(Unsupported::new(self.pkg_manager_name(), "removing repositories").into())
+    }
+}
+
+/// Run a search `command` and parse its stdout line by line using
+/// [``PackageManager::parse_pkg``], killing it once `limit` packages were
+/// parsed. Fails if the search fails for another reason than finding
+/// nothing (see [``crate::error::not_found``]).
+pub(crate) fn search_streaming<P: PackageManager + ?Sized>(
+    mpm: &P,
+    mut command: std::process::Command,
+    limit: usize,
+) -> anyhow::Result<Vec<Package>> {
+    use std::io::BufRead;
+
+    if limit == 0 {
+        return Ok(vec![]);
+    }
+    tracing::info!("Executing {command:?} for at most {limit} results");
+    let mut child = command
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run {:?}", command.get_program()))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let stderr_reader = std::thread::spawn(move || {
+        std::io::BufReader::new(stderr)
+            .lines()
+            .map_while(Result::ok)
+            .collect::<Vec<_>>()
+    });
+
+    let mut pkgs = Vec::new();
+    for line in std::io::BufReader::new(stdout).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(pkg) = mpm.parse_pkg(line) {
+            pkgs.push(pkg);
+            if pkgs.len() == limit {
+                break;
+            }
+        }
+    }
+    if pkgs.len() == limit {
+        // Stop the search if it is still producing results. It may have
+        // exited already, so a failing kill is not an error.
+        let _ = child.kill();
+        child.wait()?;
+        return Ok(pkgs);
+    }
+    let status = child.wait()?;
+    let stderr = stderr_reader.join().unwrap_or_default();
+    check_search_status(command.get_program(), status, &stderr)?;
+    Ok(pkgs)
+}
+
+/// Fail if the search by `program` exited with `status`, unless it printed
+/// to `stderr` that nothing was found.
+fn check_search_status(
+    program: &std::ffi::OsStr,
+    status: std::process::ExitStatus,
+    stderr: &[String],
+) -> anyhow::Result<()> {
+    if !status.success() && !crate::error::not_found(stderr) {
+        anyhow::bail!(
+            "{program:?} failed with {status}: {}",
+            stderr.last().map_or("", |l| l.trim())
+        );
+    }
+    Ok(())
+}
+
+/// Fail if the search whose output is `out` failed, see
+/// [``PackageManager::try_search``].
+pub(crate) fn ensure_search_succeeded(
+    program: &std::ffi::OsStr,
+    out: &std::process::Output,
+) -> anyhow::Result<()> {
+    let stderr: Vec<String> = String::from_utf8_lossy(&out.stderr)
+        .lines()
+        .map(String::from)
+        .collect();
+    check_search_status(program, out.status, &stderr)
+}
+
+/// Names of the packages currently installed by the package manager.
+pub(crate) fn installed_names<P: PackageManager + ?Sized>(
+    mpm: &P,
+) -> std::collections::HashSet<String> {
+    mpm.list_installed()
+        .into_iter()
+        .map(|p| p.name().to_string())
+        .collect()
+}
+
+/// Arguments that run `cmd` with `args` like
+/// [``PackageManagerCommands::consolidated``], but with the subcommand
+/// `subcommand` instead of that of `cmd`, e.g. `reinstall` with the flags of
+/// [``Cmd::Install``].
+pub(crate) fn consolidated_as<P, S>(
+    mpm: &P,
+    subcommand: &[&str],
+    cmd: Cmd,
+    pkg: Option<&Package>,
+    args: &[S],
+) -> Vec<String>
+where
+    P: PackageManagerCommands + ?Sized,
+    S: AsRef<str>,
+{
+    let skip = mpm.get_cmds(cmd, pkg).len();
+    subcommand
+        .iter()
+        .map(|s| s.to_string())
+        .chain(mpm.consolidated(cmd, pkg, args).into_iter().skip(skip))
+        .collect()
+}
+
+/// Arguments of the invocations of `mpm` that run `cmd` on `pkgs`, each
+/// package given as `format` returns it. Packages share an invocation
+/// unless their command differs, e.g. by the flag flatpak needs for their
+/// kind; invocations are in the order their first package was given.
+pub fn consolidated_pkgs<P, F>(
+    mpm: &P,
+    cmd: Cmd,
+    pkgs: &[Package],
+    mut format: F,
+) -> Vec<Vec<String>>
+where
+    P: PackageManager + ?Sized,
+    F: FnMut(&mut Package) -> String,
+{
+    let mut invocations: Vec<(Vec<String>, Vec<String>)> = vec![];
+    for pkg in pkgs {
+        let mut pkg = pkg.clone();
+        let arg = format(&mut pkg);
+        let cmds = mpm.consolidated::<&str>(cmd, Some(&pkg), &[]);
+        match invocations.iter_mut().find(|(c, _)| *c == cmds) {
+            Some((_, args)) => args.push(arg),
+            None => invocations.push((cmds, vec![arg])),
+        }
+    }
+    invocations
+        .into_iter()
+        .map(|(mut cmds, args)| {
+            cmds.extend(args);
+            cmds
+        })
+        .collect()
+}
+
+/// Install `pkgs` one by one with [`PackageManager::install`], stopping at
+/// the first one that fails and returning its status.
+pub fn install_each<P: PackageManager + ?Sized>(
+    mpm: &P,
+    pkgs: &[Package],
+    interactive: bool,
+) -> std::process::ExitStatus {
+    for pkg in pkgs {
+        let s = mpm.install(pkg.clone(), interactive);
+        if !s.success() {
+            return s;
+        }
+    }
+    crate::common::synthetic_exit_status(true)
+}
+
+/// Uninstall the packages of a failed install that were not installed before
+/// it, i.e. are missing from `before`.
+pub(crate) fn rollback_install<P: PackageManager + ?Sized>(
+    mpm: &P,
+    pkgs: &[Package],
+    before: &std::collections::HashSet<String>,
+) {
+    let after = installed_names(mpm);
+    for pkg in pkgs {
+        if after.contains(pkg.name()) && !before.contains(pkg.name()) {
+            tracing::info!("Rolling back installation of {}", pkg.name());
+            if !mpm.uninstall(pkg.clone(), false).success() {
+                tracing::warn!("Failed to roll back installation of {}", pkg.name());
+            }
+        }
+    }
 }